@@ -30,9 +30,9 @@ use jj_lib::local_working_copy::LocalWorkingCopy;
 use jj_lib::op_store::OperationId;
 use jj_lib::op_store::WorkspaceId;
 use jj_lib::repo::ReadonlyRepo;
-use jj_lib::repo_path::RepoPathBuf;
 use jj_lib::settings::UserSettings;
 use jj_lib::signing::Signer;
+use jj_lib::sparse::SparsePattern;
 use jj_lib::store::Store;
 use jj_lib::working_copy::CheckoutError;
 use jj_lib::working_copy::CheckoutOptions;
@@ -165,7 +165,7 @@ impl WorkingCopy for ConflictsWorkingCopy {
         self.inner.tree_id()
     }
 
-    fn sparse_patterns(&self) -> Result<&[RepoPathBuf], WorkingCopyStateError> {
+    fn sparse_patterns(&self) -> Result<&[SparsePattern], WorkingCopyStateError> {
         self.inner.sparse_patterns()
     }
 
@@ -275,13 +275,13 @@ impl LockedWorkingCopy for LockedConflictsWorkingCopy {
         self.inner.recover(commit)
     }
 
-    fn sparse_patterns(&self) -> Result<&[RepoPathBuf], WorkingCopyStateError> {
+    fn sparse_patterns(&self) -> Result<&[SparsePattern], WorkingCopyStateError> {
         self.inner.sparse_patterns()
     }
 
     fn set_sparse_patterns(
         &mut self,
-        new_sparse_patterns: Vec<RepoPathBuf>,
+        new_sparse_patterns: Vec<SparsePattern>,
         options: &CheckoutOptions,
     ) -> Result<CheckoutStats, CheckoutError> {
         self.inner.set_sparse_patterns(new_sparse_patterns, options)
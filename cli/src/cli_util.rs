@@ -56,6 +56,7 @@ use jj_lib::backend::ChangeId;
 use jj_lib::backend::CommitId;
 use jj_lib::backend::MergedTreeId;
 use jj_lib::backend::TreeValue;
+use jj_lib::case_collisions::CaseCollision;
 use jj_lib::commit::Commit;
 use jj_lib::config::ConfigGetError;
 use jj_lib::config::ConfigGetResultExt as _;
@@ -65,12 +66,19 @@ use jj_lib::config::ConfigNamePathBuf;
 use jj_lib::config::ConfigSource;
 use jj_lib::config::StackedConfig;
 use jj_lib::conflicts::ConflictMarkerStyle;
+use jj_lib::content_filter::ContentFilter;
+use jj_lib::content_filter::ContentFilters;
 use jj_lib::fileset;
 use jj_lib::fileset::FilesetDiagnostics;
 use jj_lib::fileset::FilesetExpression;
+use jj_lib::fsmonitor::FsmonitorSettings;
 use jj_lib::gitignore::GitIgnoreError;
 use jj_lib::gitignore::GitIgnoreFile;
 use jj_lib::id_prefix::IdPrefixContext;
+use jj_lib::ignore_resolver::IgnoreResolver;
+use jj_lib::local_working_copy::LockedLocalWorkingCopy;
+use jj_lib::local_working_copy::TreeStateFormat;
+use jj_lib::matchers::DifferenceMatcher;
 use jj_lib::matchers::Matcher;
 use jj_lib::merge::MergedTreeValue;
 use jj_lib::merged_tree::MergedTree;
@@ -139,6 +147,7 @@ use tracing_chrome::ChromeLayerBuilder;
 use tracing_subscriber::prelude::*;
 
 use crate::command_error::cli_error;
+use crate::command_error::config_error;
 use crate::command_error::config_error_with_message;
 use crate::command_error::handle_command_result;
 use crate::command_error::internal_error;
@@ -152,6 +161,7 @@ use crate::commit_templater::CommitTemplateLanguageExtension;
 use crate::complete;
 use crate::config::config_from_environment;
 use crate::config::parse_config_args;
+use crate::config::CommandNameAndArgs;
 use crate::config::ConfigArgKind;
 use crate::config::ConfigEnv;
 use crate::config::RawConfig;
@@ -774,6 +784,7 @@ pub struct WorkspaceCommandEnvironment {
     immutable_heads_expression: Rc<UserRevsetExpression>,
     short_prefixes_expression: Option<Rc<UserRevsetExpression>>,
     conflict_marker_style: ConflictMarkerStyle,
+    content_filters: ContentFilters,
 }
 
 impl WorkspaceCommandEnvironment {
@@ -796,9 +807,11 @@ impl WorkspaceCommandEnvironment {
             immutable_heads_expression: RevsetExpression::root(),
             short_prefixes_expression: None,
             conflict_marker_style: settings.get("ui.conflict-marker-style")?,
+            content_filters: ContentFilters::default(),
         };
         env.immutable_heads_expression = env.load_immutable_heads_expression(ui)?;
         env.short_prefixes_expression = env.load_short_prefixes_expression(ui)?;
+        env.content_filters = env.load_content_filters(ui)?;
         Ok(env)
     }
 
@@ -858,6 +871,11 @@ impl WorkspaceCommandEnvironment {
         self.conflict_marker_style
     }
 
+    /// Filters to run on file contents when snapshotting and checking out.
+    pub fn content_filters(&self) -> &ContentFilters {
+        &self.content_filters
+    }
+
     fn load_immutable_heads_expression(
         &self,
         ui: &Ui,
@@ -897,6 +915,60 @@ impl WorkspaceCommandEnvironment {
         }
     }
 
+    fn load_content_filters(&self, ui: &Ui) -> Result<ContentFilters, CommandError> {
+        #[derive(serde::Deserialize)]
+        struct RawContentFilter {
+            files: String,
+            clean: Option<CommandNameAndArgs>,
+            smudge: Option<CommandNameAndArgs>,
+        }
+
+        let to_args = |command: CommandNameAndArgs| {
+            let (name, args) = command.split_name_and_args();
+            [name.into_owned()]
+                .into_iter()
+                .chain(args.iter().cloned())
+                .collect()
+        };
+        let filters = self
+            .settings
+            .table_keys("working-copy.filters")
+            // Sort keys so the filters are run in deterministic order.
+            .sorted()
+            .map(|name| -> Result<_, CommandError> {
+                let filter: RawContentFilter =
+                    self.settings.get(["working-copy", "filters", name])?;
+                let mut diagnostics = FilesetDiagnostics::new();
+                let files = fileset::parse(
+                    &mut diagnostics,
+                    &filter.files,
+                    &RepoPathUiConverter::Fs {
+                        cwd: "".into(),
+                        base: "".into(),
+                    },
+                )
+                .map_err(|err| {
+                    config_error_with_message(
+                        format!("Invalid `working-copy.filters.{name}.files`"),
+                        err,
+                    )
+                })?;
+                print_parse_diagnostics(
+                    ui,
+                    &format!("In `working-copy.filters.{name}`"),
+                    &diagnostics,
+                )?;
+                Ok(ContentFilter {
+                    name: name.to_owned(),
+                    files,
+                    clean: filter.clean.map(to_args),
+                    smudge: filter.smudge.map(to_args),
+                })
+            })
+            .try_collect()?;
+        Ok(ContentFilters::new(filters))
+    }
+
     fn find_immutable_commit<'a>(
         &self,
         repo: &dyn Repo,
@@ -985,6 +1057,8 @@ pub struct WorkspaceCommandHelper {
     op_summary_template_text: String,
     may_update_working_copy: bool,
     working_copy_shared_with_git: bool,
+    // Case collisions added by the last snapshot, which have been warned about.
+    snapshot_case_collisions: Vec<CaseCollision>,
 }
 
 enum SnapshotWorkingCopyError {
@@ -1033,6 +1107,7 @@ impl WorkspaceCommandHelper {
             op_summary_template_text,
             may_update_working_copy,
             working_copy_shared_with_git,
+            snapshot_case_collisions: vec![],
         };
         // Parse commit_summary template early to report error before starting
         // mutable operation.
@@ -1214,6 +1289,7 @@ impl WorkspaceCommandHelper {
     pub fn checkout_options(&self) -> CheckoutOptions {
         CheckoutOptions {
             conflict_marker_style: self.env.conflict_marker_style(),
+            content_filters: self.env.content_filters().clone(),
         }
     }
 
@@ -1291,6 +1367,12 @@ to the current parents may contain changes from multiple commits.
         self.working_copy_shared_with_git
     }
 
+    /// Paths only differing in case which were added to the working copy by
+    /// the snapshot of this command.
+    pub fn snapshot_case_collisions(&self) -> &[CaseCollision] {
+        &self.snapshot_case_collisions
+    }
+
     pub fn format_file_path(&self, file: &RepoPath) -> String {
         self.path_converter().format_file_path(file)
     }
@@ -1333,8 +1415,26 @@ to the current parents may contain changes from multiple commits.
     }
 
     pub fn auto_tracking_matcher(&self, ui: &Ui) -> Result<Box<dyn Matcher>, CommandError> {
+        self.snapshot_fileset_matcher(ui, "snapshot.auto-track")
+    }
+
+    /// Matches untracked paths that should be warned about on every snapshot.
+    pub fn untracked_warning_matcher(&self, ui: &Ui) -> Result<Box<dyn Matcher>, CommandError> {
+        self.snapshot_fileset_matcher(ui, "snapshot.warn-untracked")
+    }
+
+    /// Matches untracked paths that shouldn't be reported at all.
+    pub fn untracked_ignore_matcher(&self, ui: &Ui) -> Result<Box<dyn Matcher>, CommandError> {
+        self.snapshot_fileset_matcher(ui, "snapshot.ignore-untracked")
+    }
+
+    fn snapshot_fileset_matcher(
+        &self,
+        ui: &Ui,
+        key: &'static str,
+    ) -> Result<Box<dyn Matcher>, CommandError> {
         let mut diagnostics = FilesetDiagnostics::new();
-        let pattern = self.settings().get_string("snapshot.auto-track")?;
+        let pattern = self.settings().get_string(key)?;
         let expression = fileset::parse(
             &mut diagnostics,
             &pattern,
@@ -1343,7 +1443,7 @@ to the current parents may contain changes from multiple commits.
                 base: "".into(),
             },
         )?;
-        print_parse_diagnostics(ui, "In `snapshot.auto-track`", &diagnostics)?;
+        print_parse_diagnostics(ui, &format!("In `{key}`"), &diagnostics)?;
         Ok(expression.to_matcher())
     }
 
@@ -1352,7 +1452,17 @@ to the current parents may contain changes from multiple commits.
         start_tracking_matcher: &'a dyn Matcher,
     ) -> Result<SnapshotOptions<'a>, CommandError> {
         let base_ignores = self.base_ignores()?;
-        let fsmonitor_settings = self.settings().fsmonitor_settings()?;
+        let mut fsmonitor_settings = self.settings().fsmonitor_settings()?;
+        if let FsmonitorSettings::Builtin(config) = &mut fsmonitor_settings {
+            // The daemon is run by `jj debug fsmonitor run`.
+            config.daemon_command = vec![
+                env::current_exe()?.into(),
+                "debug".into(),
+                "fsmonitor".into(),
+                "run".into(),
+                "--repository".into(),
+            ];
+        }
         let HumanByteSize(mut max_new_file_size) = self
             .settings()
             .get_value_with("snapshot.max-new-file-size", TryInto::try_into)?;
@@ -1367,6 +1477,7 @@ to the current parents may contain changes from multiple commits.
             start_tracking_matcher,
             max_new_file_size,
             conflict_marker_style,
+            content_filters: self.env.content_filters().clone(),
         })
     }
 
@@ -1374,52 +1485,14 @@ to the current parents may contain changes from multiple commits.
         self.env.path_converter()
     }
 
-    #[cfg(not(feature = "git"))]
-    pub fn base_ignores(&self) -> Result<Arc<GitIgnoreFile>, GitIgnoreError> {
-        Ok(GitIgnoreFile::empty())
+    /// Loads the ignore patterns that apply to the workspace.
+    #[instrument(skip_all)]
+    pub fn ignore_resolver(&self) -> Result<IgnoreResolver, GitIgnoreError> {
+        IgnoreResolver::load(self.workspace_root(), self.repo().store())
     }
 
-    #[cfg(feature = "git")]
-    #[instrument(skip_all)]
     pub fn base_ignores(&self) -> Result<Arc<GitIgnoreFile>, GitIgnoreError> {
-        let get_excludes_file_path = |config: &gix::config::File| -> Option<PathBuf> {
-            // TODO: maybe use path() and interpolate(), which can process non-utf-8
-            // path on Unix.
-            if let Some(value) = config.string("core.excludesFile") {
-                let path = str::from_utf8(&value)
-                    .ok()
-                    .map(jj_lib::file_util::expand_home_path)?;
-                // The configured path is usually absolute, but if it's relative,
-                // the "git" command would read the file at the work-tree directory.
-                Some(self.workspace_root().join(path))
-            } else {
-                xdg_config_home().ok().map(|x| x.join("git").join("ignore"))
-            }
-        };
-
-        fn xdg_config_home() -> Result<PathBuf, std::env::VarError> {
-            if let Ok(x) = std::env::var("XDG_CONFIG_HOME") {
-                if !x.is_empty() {
-                    return Ok(PathBuf::from(x));
-                }
-            }
-            std::env::var("HOME").map(|x| Path::new(&x).join(".config"))
-        }
-
-        let mut git_ignores = GitIgnoreFile::empty();
-        if let Ok(git_backend) = jj_lib::git::get_git_backend(self.repo().store()) {
-            let git_repo = git_backend.git_repo();
-            if let Some(excludes_file_path) = get_excludes_file_path(&git_repo.config_snapshot()) {
-                git_ignores = git_ignores.chain_with_file("", excludes_file_path)?;
-            }
-            git_ignores = git_ignores
-                .chain_with_file("", git_backend.git_repo_path().join("info").join("exclude"))?;
-        } else if let Ok(git_config) = gix::config::File::from_globals() {
-            if let Some(excludes_file_path) = get_excludes_file_path(&git_config) {
-                git_ignores = git_ignores.chain_with_file("", excludes_file_path)?;
-            }
-        }
-        Ok(git_ignores)
+        Ok(self.ignore_resolver()?.base_ignores().clone())
     }
 
     /// Creates textual diff renderer of the specified `formats`.
@@ -1845,9 +1918,19 @@ to the current parents may contain changes from multiple commits.
         let auto_tracking_matcher = self
             .auto_tracking_matcher(ui)
             .map_err(snapshot_command_error)?;
+        let untracked_warning_matcher = self
+            .untracked_warning_matcher(ui)
+            .map_err(snapshot_command_error)?;
+        let untracked_ignore_matcher = self
+            .untracked_ignore_matcher(ui)
+            .map_err(snapshot_command_error)?;
         let options = self
             .snapshot_options_with_start_tracking_matcher(&auto_tracking_matcher)
             .map_err(snapshot_command_error)?;
+        let tree_state_format: TreeStateFormat = self
+            .settings()
+            .get("working-copy.tree-state-format")
+            .map_err(snapshot_command_error)?;
 
         // Compare working-copy tree and operation with repo's, and reload as needed.
         let mut locked_ws = self
@@ -1907,6 +1990,17 @@ See https://jj-vcs.github.io/jj/latest/working-copy/#stale-working-copy \
                 Err(e) => return Err(snapshot_command_error(e)),
             };
         self.user_repo = ReadonlyUserRepo::new(repo);
+        // Migrate the local working copy to the configured format. The state is
+        // saved in the new format when the working copy is finished below.
+        if let Some(locked_wc) = locked_ws
+            .locked_wc()
+            .as_any_mut()
+            .downcast_mut::<LockedLocalWorkingCopy>()
+        {
+            locked_wc
+                .set_tree_state_format(tree_state_format)
+                .map_err(snapshot_command_error)?;
+        }
         let (new_tree_id, stats) = {
             let mut options = options;
             let progress = crate::progress::snapshot_progress(ui);
@@ -1917,6 +2011,7 @@ See https://jj-vcs.github.io/jj/latest/working-copy/#stale-working-copy \
                 .map_err(snapshot_command_error)?
         };
         if new_tree_id != *wc_commit.tree_id() {
+            self.snapshot_case_collisions = stats.case_collisions.clone();
             let mut tx =
                 start_repo_transaction(&self.user_repo.repo, self.env.command.string_args());
             tx.set_is_snapshot(true);
@@ -1959,6 +2054,17 @@ See https://jj-vcs.github.io/jj/latest/working-copy/#stale-working-copy \
             .map_err(snapshot_command_error)?;
         print_snapshot_stats(ui, &stats, &self.env.path_converter)
             .map_err(snapshot_command_error)?;
+        let warning_matcher =
+            DifferenceMatcher::new(&untracked_warning_matcher, &untracked_ignore_matcher);
+        print_untracked_paths_warning(ui, &stats, &self.env.path_converter, &warning_matcher)
+            .map_err(snapshot_command_error)?;
+        print_case_collisions_warning(
+            ui,
+            "The working copy",
+            &self.snapshot_case_collisions,
+            &self.env.path_converter,
+        )
+        .map_err(snapshot_command_error)?;
         Ok(stats)
     }
 
@@ -2492,6 +2598,8 @@ jj git init --colocate",
             err @ (StoreLoadError::ReadError { .. } | StoreLoadError::Backend(_)),
         ) => internal_error_with_message("The repository appears broken or inaccessible", err),
         WorkspaceLoadError::StoreLoadError(StoreLoadError::Signing(err)) => user_error(err),
+        WorkspaceLoadError::StoreLoadError(StoreLoadError::MergeDrivers(err)) => config_error(err),
+        WorkspaceLoadError::StoreLoadError(StoreLoadError::MergeOptions(err)) => config_error(err),
         WorkspaceLoadError::WorkingCopyState(err) => internal_error(err),
         WorkspaceLoadError::NonUnicodePath | WorkspaceLoadError::Path(_) => user_error(err),
     }
@@ -2712,6 +2820,66 @@ pub fn print_snapshot_stats(
     Ok(())
 }
 
+/// Warns about new paths that weren't tracked because they don't match
+/// `snapshot.auto-track`, if they match the `matcher`.
+pub fn print_untracked_paths_warning(
+    ui: &Ui,
+    stats: &SnapshotStats,
+    path_converter: &RepoPathUiConverter,
+    matcher: &dyn Matcher,
+) -> io::Result<()> {
+    let mut untracked_paths = stats
+        .untracked_paths
+        .iter()
+        .filter(|(path, reason)| {
+            matches!(reason, UntrackedReason::FileNotAutoTracked) && matcher.matches(path)
+        })
+        .peekable();
+    if untracked_paths.peek().is_none() {
+        return Ok(());
+    }
+    writeln!(ui.warning_default(), "Some new files are not tracked:")?;
+    let mut formatter = ui.stderr_formatter();
+    for (path, _) in untracked_paths {
+        let ui_path = path_converter.format_file_path(path);
+        writeln!(formatter, "  {ui_path}")?;
+    }
+    drop(formatter);
+    writeln!(
+        ui.hint_default(),
+        "Run `jj file track` to track them, or add them to `snapshot.ignore-untracked` to \
+         silence this warning."
+    )?;
+    Ok(())
+}
+
+/// Warns about `collisions` of paths added by `subject` (e.g. "The commit").
+pub fn print_case_collisions_warning(
+    ui: &Ui,
+    subject: &str,
+    collisions: &[CaseCollision],
+    path_converter: &RepoPathUiConverter,
+) -> io::Result<()> {
+    if collisions.is_empty() {
+        return Ok(());
+    }
+    for paths in collisions {
+        writeln!(
+            ui.warning_default(),
+            "{subject} adds paths which only differ in case: {}",
+            paths
+                .iter()
+                .map(|path| path_converter.format_file_path(path))
+                .join(" ")
+        )?;
+    }
+    writeln!(
+        ui.hint_default(),
+        "These paths can't be checked out together on case-insensitive filesystems."
+    )?;
+    Ok(())
+}
+
 pub fn print_checkout_stats(
     ui: &Ui,
     stats: CheckoutStats,
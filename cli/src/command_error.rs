@@ -350,6 +350,8 @@ impl From<WorkspaceInitError> for CommandError {
                 internal_error_with_message("Failed to access the repository", err)
             }
             WorkspaceInitError::SignInit(err) => user_error(err),
+            WorkspaceInitError::MergeDrivers(err) => config_error(err),
+            WorkspaceInitError::MergeOptions(err) => config_error(err),
         }
     }
 }
@@ -482,6 +484,7 @@ mod git {
     use jj_lib::git::GitFetchError;
     use jj_lib::git::GitFetchPrepareError;
     use jj_lib::git::GitImportError;
+    use jj_lib::git::GitLfsTransferError;
     use jj_lib::git::GitPushError;
     use jj_lib::git::GitRemoteManagementError;
     use jj_lib::git::UnexpectedGitBackendError;
@@ -575,6 +578,18 @@ jj currently does not support partial clones. To use jj with this repository, tr
         }
     }
 
+    impl From<GitLfsTransferError> for CommandError {
+        fn from(err: GitLfsTransferError) -> Self {
+            match err {
+                GitLfsTransferError::NoSuchRemote(_) => user_error(err),
+                GitLfsTransferError::Lfs(_) => user_error(err),
+                GitLfsTransferError::Revset(err) => err.into(),
+                GitLfsTransferError::InternalGitError(err) => map_git2_error(err),
+                GitLfsTransferError::UnexpectedBackend(_) => user_error(err),
+            }
+        }
+    }
+
     impl From<GitRemoteManagementError> for CommandError {
         fn from(err: GitRemoteManagementError) -> Self {
             user_error(err)
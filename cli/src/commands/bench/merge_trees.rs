@@ -0,0 +1,62 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::run_bench;
+use super::CriterionArgs;
+use crate::cli_util::CommandHelper;
+use crate::cli_util::RevisionArg;
+use crate::command_error::CommandError;
+use crate::ui::Ui;
+
+/// Merges the trees like rebasing a revision onto a destination would
+///
+/// The tree of the destination is merged with the tree of the revision, using
+/// the tree of the revision's parents as base.
+#[derive(clap::Args, Clone, Debug)]
+pub struct BenchMergeTreesArgs {
+    /// The revision whose changes to merge
+    #[arg(long, short)]
+    revision: RevisionArg,
+    /// The revision to merge the changes into
+    #[arg(long, short)]
+    destination: RevisionArg,
+    #[command(flatten)]
+    criterion: CriterionArgs,
+}
+
+pub fn cmd_bench_merge_trees(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &BenchMergeTreesArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper(ui)?;
+    let commit = workspace_command.resolve_single_rev(ui, &args.revision)?;
+    let destination = workspace_command.resolve_single_rev(ui, &args.destination)?;
+    let repo = workspace_command.repo();
+    let base_tree = commit.parent_tree(repo.as_ref())?;
+    let tree = commit.tree()?;
+    let destination_tree = destination.tree()?;
+    let routine = || {
+        destination_tree
+            .merge(&base_tree, &tree)
+            .map(|merged| merged.id())
+    };
+    run_bench(
+        ui,
+        &format!("merge-trees-{}-{}", args.revision, args.destination),
+        &args.criterion,
+        routine,
+    )?;
+    Ok(())
+}
@@ -14,6 +14,7 @@
 
 mod common_ancestors;
 mod is_ancestor;
+mod merge_trees;
 mod resolve_prefix;
 mod revset;
 
@@ -28,6 +29,8 @@ use self::common_ancestors::cmd_bench_common_ancestors;
 use self::common_ancestors::BenchCommonAncestorsArgs;
 use self::is_ancestor::cmd_bench_is_ancestor;
 use self::is_ancestor::BenchIsAncestorArgs;
+use self::merge_trees::cmd_bench_merge_trees;
+use self::merge_trees::BenchMergeTreesArgs;
 use self::resolve_prefix::cmd_bench_resolve_prefix;
 use self::resolve_prefix::BenchResolvePrefixArgs;
 use self::revset::cmd_bench_revset;
@@ -42,6 +45,7 @@ use crate::ui::Ui;
 pub enum BenchCommand {
     CommonAncestors(BenchCommonAncestorsArgs),
     IsAncestor(BenchIsAncestorArgs),
+    MergeTrees(BenchMergeTreesArgs),
     ResolvePrefix(BenchResolvePrefixArgs),
     Revset(BenchRevsetArgs),
 }
@@ -54,6 +58,7 @@ pub(crate) fn cmd_bench(
     match subcommand {
         BenchCommand::CommonAncestors(args) => cmd_bench_common_ancestors(ui, command, args),
         BenchCommand::IsAncestor(args) => cmd_bench_is_ancestor(ui, command, args),
+        BenchCommand::MergeTrees(args) => cmd_bench_merge_trees(ui, command, args),
         BenchCommand::ResolvePrefix(args) => cmd_bench_resolve_prefix(ui, command, args),
         BenchCommand::Revset(args) => cmd_bench_revset(ui, command, args),
     }
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use std::collections::HashSet;
+use std::rc::Rc;
 
 use clap_complete::ArgValueCandidates;
 use itertools::Itertools;
@@ -26,6 +27,8 @@ use crate::command_error::CommandError;
 use crate::commit_templater::CommitTemplateLanguage;
 use crate::commit_templater::RefName;
 use crate::complete;
+use crate::formatter::FormatRecorder;
+use crate::text_util;
 use crate::ui::Ui;
 
 /// List bookmarks and their targets
@@ -160,8 +163,14 @@ pub fn cmd_bookmark_list(
             .labeled("bookmark_list")
     };
 
-    ui.request_pager();
-    let mut formatter = ui.stdout_formatter();
+    // Rows are buffered so that columns can be aligned across bookmarks.
+    let mut rows = vec![];
+    let mut format_row = |ref_name: &Rc<RefName>| -> Result<(), CommandError> {
+        let mut recorder = FormatRecorder::new();
+        template.format(ref_name, &mut recorder)?;
+        rows.push(recorder);
+        Ok(())
+    };
 
     let mut found_deleted_local_bookmark = false;
     let mut found_deleted_tracking_local_bookmark = false;
@@ -197,12 +206,12 @@ pub fn cmd_bookmark_list(
                 local_target.clone(),
                 remote_refs.iter().map(|&(_, remote_ref)| remote_ref),
             );
-            template.format(&ref_name, formatter.as_mut())?;
+            format_row(&ref_name)?;
         }
 
         for &(remote, remote_ref) in &tracking_remote_refs {
             let ref_name = RefName::remote(name, remote, remote_ref.clone(), local_target);
-            template.format(&ref_name, formatter.as_mut())?;
+            format_row(&ref_name)?;
         }
 
         if local_target.is_absent() && !tracking_remote_refs.is_empty() {
@@ -215,11 +224,14 @@ pub fn cmd_bookmark_list(
         if !args.tracked && (args.all_remotes || args.remotes.is_some()) {
             for &(remote, remote_ref) in &untracked_remote_refs {
                 let ref_name = RefName::remote_only(name, remote, remote_ref.target.clone());
-                template.format(&ref_name, formatter.as_mut())?;
+                format_row(&ref_name)?;
             }
         }
     }
 
+    ui.request_pager();
+    let mut formatter = ui.stdout_formatter();
+    text_util::write_table(formatter.as_mut(), &rows, ui.table_width())?;
     drop(formatter);
 
     #[cfg(feature = "git")]
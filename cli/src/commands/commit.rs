@@ -14,10 +14,14 @@
 
 use clap_complete::ArgValueCompleter;
 use jj_lib::backend::Signature;
+use jj_lib::case_collisions::find_case_collisions;
+use jj_lib::matchers::EverythingMatcher;
 use jj_lib::object_id::ObjectId;
 use jj_lib::repo::Repo;
+use pollster::FutureExt as _;
 use tracing::instrument;
 
+use crate::cli_util::print_case_collisions_warning;
 use crate::cli_util::CommandHelper;
 use crate::command_error::user_error;
 use crate::command_error::CommandError;
@@ -115,6 +119,13 @@ new working-copy commit.
             args.paths.join(" ")
         )?;
     }
+    let new_tree = tx.repo().store().get_root_tree(&tree_id)?;
+    let mut collisions =
+        find_case_collisions(&base_tree, &new_tree, &EverythingMatcher).block_on()?;
+    // Collisions which were just snapshotted have already been warned about.
+    let helper = tx.base_workspace_helper();
+    collisions.retain(|paths| !helper.snapshot_case_collisions().contains(paths));
+    print_case_collisions_warning(ui, "The commit", &collisions, helper.path_converter())?;
 
     let mut commit_builder = tx.repo_mut().rewrite_commit(&commit).detach();
     commit_builder.set_tree_id(tree_id);
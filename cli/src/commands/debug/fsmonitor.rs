@@ -0,0 +1,115 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Debug;
+#[cfg(all(unix, feature = "fsmonitor-daemon"))]
+use std::io::Write as _;
+
+use clap::Subcommand;
+#[cfg(all(unix, feature = "fsmonitor-daemon"))]
+use jj_lib::fsmonitor::builtin;
+#[cfg(all(unix, feature = "fsmonitor-daemon"))]
+use jj_lib::fsmonitor::FsmonitorSettings;
+
+#[cfg(all(unix, feature = "fsmonitor-daemon"))]
+use super::check_local_disk_wc;
+use crate::cli_util::CommandHelper;
+#[cfg(not(all(unix, feature = "fsmonitor-daemon")))]
+use crate::command_error::user_error;
+#[cfg(all(unix, feature = "fsmonitor-daemon"))]
+use crate::command_error::user_error_with_message;
+use crate::command_error::CommandError;
+use crate::ui::Ui;
+
+/// Manage the built-in filesystem monitor daemon
+#[derive(Subcommand, Clone, Debug)]
+pub enum DebugFsmonitorCommand {
+    /// Show whether the daemon is running for the current workspace
+    Status,
+    /// Stop the daemon for the current workspace
+    Stop,
+    /// Run the daemon for the current workspace in the foreground
+    ///
+    /// This is how `jj` starts the daemon in the background when
+    /// `core.fsmonitor = "builtin"`.
+    Run,
+}
+
+#[cfg(all(unix, feature = "fsmonitor-daemon"))]
+pub fn cmd_debug_fsmonitor(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    subcommand: &DebugFsmonitorCommand,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    let wc = check_local_disk_wc(workspace_command.working_copy().as_any())?;
+    let socket_path = builtin::socket_path(wc.state_path());
+    match subcommand {
+        DebugFsmonitorCommand::Status => {
+            match workspace_command.settings().fsmonitor_settings()? {
+                FsmonitorSettings::Builtin(_) => writeln!(
+                    ui.stdout(),
+                    "The built-in filesystem monitor is enabled via `core.fsmonitor`."
+                )?,
+                _ => writeln!(
+                    ui.stdout(),
+                    r#"The built-in filesystem monitor is disabled. Set `core.fsmonitor="builtin"` to enable."#
+                )?,
+            }
+            match builtin::Client::connect(&socket_path) {
+                Ok(mut client) => {
+                    let status = client.status().map_err(|err| {
+                        user_error_with_message("Failed to query the daemon", err)
+                    })?;
+                    writeln!(ui.stdout(), "The daemon is running (pid {}).", status.pid)?;
+                    writeln!(
+                        ui.stdout(),
+                        "Paths changed since the daemon started: {}",
+                        status.changed_path_count
+                    )?;
+                    if let Some(err) = &status.watch_error {
+                        writeln!(ui.stdout(), "Failed to watch the working copy: {err}")?;
+                    }
+                }
+                Err(_) => writeln!(ui.stdout(), "The daemon is not running.")?,
+            }
+        }
+        DebugFsmonitorCommand::Stop => match builtin::Client::connect(&socket_path) {
+            Ok(mut client) => {
+                client
+                    .stop()
+                    .map_err(|err| user_error_with_message("Failed to stop the daemon", err))?;
+                writeln!(ui.status(), "Stopped the filesystem monitor daemon")?;
+            }
+            Err(_) => writeln!(ui.status(), "The filesystem monitor daemon is not running")?,
+        },
+        DebugFsmonitorCommand::Run => {
+            builtin::run(workspace_command.workspace_root(), wc.state_path())
+                .map_err(|err| user_error_with_message("Failed to run the daemon", err))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(all(unix, feature = "fsmonitor-daemon")))]
+pub fn cmd_debug_fsmonitor(
+    _ui: &mut Ui,
+    _command: &CommandHelper,
+    _subcommand: &DebugFsmonitorCommand,
+) -> Result<(), CommandError> {
+    Err(user_error(
+        "Cannot use the built-in filesystem monitor because jj was not compiled with the \
+         `fsmonitor-daemon` feature, or the platform isn't supported",
+    ))
+}
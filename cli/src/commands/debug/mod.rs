@@ -14,6 +14,7 @@
 
 mod copy_detection;
 mod fileset;
+mod fsmonitor;
 mod index;
 mod local_working_copy;
 mod operation;
@@ -35,6 +36,8 @@ use self::copy_detection::cmd_debug_copy_detection;
 use self::copy_detection::CopyDetectionArgs;
 use self::fileset::cmd_debug_fileset;
 use self::fileset::DebugFilesetArgs;
+use self::fsmonitor::cmd_debug_fsmonitor;
+use self::fsmonitor::DebugFsmonitorCommand;
 use self::index::cmd_debug_index;
 use self::index::DebugIndexArgs;
 use self::local_working_copy::cmd_debug_local_working_copy;
@@ -66,6 +69,8 @@ use crate::ui::Ui;
 pub enum DebugCommand {
     CopyDetection(CopyDetectionArgs),
     Fileset(DebugFilesetArgs),
+    #[command(subcommand)]
+    Fsmonitor(DebugFsmonitorCommand),
     Index(DebugIndexArgs),
     LocalWorkingCopy(DebugLocalWorkingCopyArgs),
     #[command(visible_alias = "view")]
@@ -87,6 +92,7 @@ pub fn cmd_debug(
 ) -> Result<(), CommandError> {
     match subcommand {
         DebugCommand::Fileset(args) => cmd_debug_fileset(ui, command, args),
+        DebugCommand::Fsmonitor(args) => cmd_debug_fsmonitor(ui, command, args),
         DebugCommand::Index(args) => cmd_debug_index(ui, command, args),
         DebugCommand::LocalWorkingCopy(args) => cmd_debug_local_working_copy(ui, command, args),
         DebugCommand::Operation(args) => cmd_debug_operation(ui, command, args),
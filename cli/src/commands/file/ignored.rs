@@ -0,0 +1,103 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write as _;
+
+use itertools::Itertools as _;
+use jj_lib::gitignore::GitIgnoreMatch;
+use jj_lib::repo_path::RepoPathBuf;
+use tracing::instrument;
+
+use crate::cli_util::CommandHelper;
+use crate::cli_util::WorkspaceCommandHelper;
+use crate::command_error::CommandError;
+use crate::ui::Ui;
+
+/// Explain whether paths in the working copy are ignored
+///
+/// For each path, prints the ignore file and line of the pattern that decides
+/// whether the path is ignored. Patterns are read from the global Git excludes
+/// file, `.git/info/exclude`, and the `.gitignore` and `.jjignore` files in
+/// the working copy.
+///
+/// Ignore patterns only prevent new files from being tracked. Files that are
+/// already tracked stay tracked even if they match a pattern.
+#[derive(clap::Args, Clone, Debug)]
+pub(crate) struct FileIgnoredArgs {
+    /// Paths to check
+    #[arg(required = true, value_hint = clap::ValueHint::AnyPath)]
+    paths: Vec<String>,
+}
+
+#[instrument(skip_all)]
+pub(crate) fn cmd_file_ignored(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: &FileIgnoredArgs,
+) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    let repo_paths: Vec<RepoPathBuf> = args
+        .paths
+        .iter()
+        .map(|path| workspace_command.parse_file_path(path))
+        .try_collect()?;
+    let ignore_resolver = workspace_command.ignore_resolver()?;
+    let mut formatter = ui.stdout_formatter();
+    for repo_path in &repo_paths {
+        let disk_path = repo_path.to_fs_path_unchecked(workspace_command.workspace_root());
+        let is_dir = disk_path
+            .symlink_metadata()
+            .is_ok_and(|metadata| metadata.is_dir());
+        let ui_path = workspace_command.format_file_path(repo_path);
+        match ignore_resolver.explain(repo_path, is_dir)? {
+            Some(ignore_match) => {
+                let source = format_source(&workspace_command, &ignore_match);
+                let GitIgnoreMatch {
+                    line_number,
+                    pattern,
+                    is_ignored,
+                    ..
+                } = &ignore_match;
+                if *is_ignored {
+                    writeln!(
+                        formatter,
+                        "{ui_path}: ignored by {source}:{line_number} ({pattern})"
+                    )?;
+                } else {
+                    writeln!(
+                        formatter,
+                        "{ui_path}: not ignored, re-included by {source}:{line_number} ({pattern})"
+                    )?;
+                }
+            }
+            None => writeln!(formatter, "{ui_path}: not ignored")?,
+        }
+    }
+    Ok(())
+}
+
+/// Formats the ignore file path relative to the current directory if it's in
+/// the working copy.
+fn format_source(
+    workspace_command: &WorkspaceCommandHelper,
+    ignore_match: &GitIgnoreMatch,
+) -> String {
+    ignore_match
+        .source
+        .strip_prefix(workspace_command.workspace_root())
+        .ok()
+        .and_then(|path| RepoPathBuf::from_relative_path(path).ok())
+        .map(|path| workspace_command.format_file_path(&path))
+        .unwrap_or_else(|| ignore_match.source.display().to_string())
+}
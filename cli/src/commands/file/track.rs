@@ -14,6 +14,10 @@
 
 use std::io::Write;
 
+use jj_lib::matchers::EverythingMatcher;
+use jj_lib::matchers::FilesMatcher;
+use jj_lib::matchers::Matcher;
+use jj_lib::working_copy::UntrackedReason;
 use tracing::instrument;
 
 use crate::cli_util::print_snapshot_stats;
@@ -33,8 +37,19 @@ use crate::ui::Ui;
 #[derive(clap::Args, Clone, Debug)]
 pub(crate) struct FileTrackArgs {
     /// Paths to track
-    #[arg(required = true, value_name = "FILESETS", value_hint = clap::ValueHint::AnyPath)]
+    #[arg(
+        required_unless_present = "interactive",
+        value_name = "FILESETS",
+        value_hint = clap::ValueHint::AnyPath
+    )]
     paths: Vec<String>,
+    /// Interactively choose which untracked files to track
+    ///
+    /// You are asked about each new file that isn't tracked, and that matches
+    /// the given paths if any. Files matching `snapshot.ignore-untracked` are
+    /// skipped.
+    #[arg(long, short)]
+    interactive: bool,
 }
 
 #[instrument(skip_all)]
@@ -43,10 +58,39 @@ pub(crate) fn cmd_file_track(
     command: &CommandHelper,
     args: &FileTrackArgs,
 ) -> Result<(), CommandError> {
-    let mut workspace_command = command.workspace_helper(ui)?;
-    let matcher = workspace_command
-        .parse_file_patterns(ui, &args.paths)?
-        .to_matcher();
+    let (mut workspace_command, snapshot_stats) = command.workspace_helper_with_stats(ui)?;
+    let matcher = if args.interactive {
+        let candidate_matcher = if args.paths.is_empty() {
+            Box::new(EverythingMatcher)
+        } else {
+            workspace_command
+                .parse_file_patterns(ui, &args.paths)?
+                .to_matcher()
+        };
+        let ignore_matcher = workspace_command.untracked_ignore_matcher(ui)?;
+        let mut selected_paths = vec![];
+        for (path, reason) in &snapshot_stats.untracked_paths {
+            if !matches!(reason, UntrackedReason::FileNotAutoTracked)
+                || !candidate_matcher.matches(path)
+                || ignore_matcher.matches(path)
+            {
+                continue;
+            }
+            let ui_path = workspace_command.format_file_path(path);
+            if ui.prompt_yes_no(&format!("Track {ui_path}?"), Some(false))? {
+                selected_paths.push(path);
+            }
+        }
+        if selected_paths.is_empty() {
+            writeln!(ui.status(), "No files selected to track")?;
+            return Ok(());
+        }
+        Box::new(FilesMatcher::new(selected_paths))
+    } else {
+        workspace_command
+            .parse_file_patterns(ui, &args.paths)?
+            .to_matcher()
+    };
     let options = workspace_command.snapshot_options_with_start_tracking_matcher(&matcher)?;
 
     let mut tx = workspace_command.start_transaction().into_inner();
@@ -33,8 +33,9 @@ use crate::ui::Ui;
 pub(crate) struct FileUntrackArgs {
     /// Paths to untrack. They must already be ignored.
     ///
-    /// The paths could be ignored via a .gitignore or .git/info/exclude (in
-    /// colocated repos).
+    /// The paths could be ignored via a .gitignore, .jjignore, or
+    /// .git/info/exclude. Use `jj file ignored` to check whether a path is
+    /// ignored.
     #[arg(
         required = true,
         value_name = "FILESETS",
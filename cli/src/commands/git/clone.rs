@@ -33,6 +33,7 @@ use crate::command_error::user_error_with_message;
 use crate::command_error::CommandError;
 use crate::commands::git::maybe_add_gitignore;
 use crate::git_util::absolute_git_url;
+use crate::git_util::fetch_git_lfs_objects;
 use crate::git_util::get_git_repo;
 use crate::git_util::print_git_import_stats;
 use crate::git_util::with_remote_git_callbacks;
@@ -231,6 +232,12 @@ fn fetch_new_remote(
         with_remote_git_callbacks(ui, |cb| git_fetch.get_default_branch(remote_name, cb))?;
     let import_stats = git_fetch.import_refs()?;
     print_git_import_stats(ui, fetch_tx.repo(), &import_stats, true)?;
+    fetch_git_lfs_objects(
+        ui,
+        fetch_tx.repo(),
+        &import_stats,
+        &[remote_name.to_owned()],
+    )?;
     fetch_tx.finish(ui, "fetch from git remote into empty repo")?;
     Ok(default_branch)
 }
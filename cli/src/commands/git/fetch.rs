@@ -26,6 +26,7 @@ use crate::cli_util::WorkspaceCommandTransaction;
 use crate::command_error::CommandError;
 use crate::commands::git::get_single_remote;
 use crate::complete;
+use crate::git_util::fetch_git_lfs_objects;
 use crate::git_util::print_git_import_stats;
 use crate::git_util::with_remote_git_callbacks;
 use crate::ui::Ui;
@@ -130,6 +131,7 @@ fn do_git_fetch(
     }
     let import_stats = git_fetch.import_refs()?;
     print_git_import_stats(ui, tx.repo(), &import_stats, true)?;
+    fetch_git_lfs_objects(ui, tx.repo(), &import_stats, remotes)?;
     warn_if_branches_not_found(
         ui,
         tx,
@@ -365,6 +365,7 @@ pub fn cmd_git_push(
         return Ok(());
     }
 
+    push_git_lfs_objects(ui, &tx, &remote, &bookmark_updates)?;
     let targets = GitBranchPushTargets {
         branch_updates: bookmark_updates,
     };
@@ -376,6 +377,31 @@ pub fn cmd_git_push(
     Ok(())
 }
 
+/// Copies Git LFS objects added by the commits to be pushed.
+fn push_git_lfs_objects(
+    ui: &Ui,
+    tx: &WorkspaceCommandTransaction,
+    remote: &str,
+    bookmark_updates: &[(String, BookmarkPushUpdate)],
+) -> Result<(), CommandError> {
+    let repo = tx.repo();
+    let new_heads = bookmark_updates
+        .iter()
+        .filter_map(|(_, update)| update.new_target.clone())
+        .collect_vec();
+    let old_heads = repo
+        .view()
+        .remote_bookmarks(remote)
+        .flat_map(|(_, old_head)| old_head.target.added_ids())
+        .cloned()
+        .collect_vec();
+    let count = git::push_lfs_objects(repo, remote, &old_heads, &new_heads)?;
+    if count > 0 {
+        writeln!(ui.status(), "Pushed {count} LFS objects to {remote}")?;
+    }
+    Ok(())
+}
+
 /// Validates that the commits that will be pushed are ready (have authorship
 /// information, are not conflicted, etc.).
 ///
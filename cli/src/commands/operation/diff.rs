@@ -17,7 +17,6 @@ use std::convert::Infallible;
 use std::sync::Arc;
 
 use clap_complete::ArgValueCandidates;
-use indexmap::IndexMap;
 use itertools::Itertools;
 use jj_lib::backend::ChangeId;
 use jj_lib::backend::CommitId;
@@ -33,8 +32,6 @@ use jj_lib::refs::diff_named_ref_targets;
 use jj_lib::refs::diff_named_remote_refs;
 use jj_lib::repo::ReadonlyRepo;
 use jj_lib::repo::Repo;
-use jj_lib::revset;
-use jj_lib::revset::RevsetIteratorExt as _;
 
 use crate::cli_util::CommandHelper;
 use crate::cli_util::LogContentFormat;
@@ -47,6 +44,8 @@ use crate::diff_util::DiffRenderer;
 use crate::formatter::Formatter;
 use crate::graphlog::get_graphlog;
 use crate::graphlog::GraphStyle;
+use crate::op_diff_util::compute_operation_commits_diff;
+use crate::op_diff_util::ModifiedChange;
 use crate::templater::TemplateRenderer;
 use crate::ui::Ui;
 
@@ -486,59 +485,6 @@ fn get_parent_changes(
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-struct ModifiedChange {
-    added_commits: Vec<Commit>,
-    removed_commits: Vec<Commit>,
-}
-
-/// Compute the changes in commits between two operations, returned as a
-/// `HashMap` from `ChangeId` to a `ModifiedChange` struct containing the added
-/// and removed commits for the change ID.
-fn compute_operation_commits_diff(
-    repo: &dyn Repo,
-    from_repo: &ReadonlyRepo,
-    to_repo: &ReadonlyRepo,
-) -> Result<IndexMap<ChangeId, ModifiedChange>, CommandError> {
-    let mut changes: IndexMap<ChangeId, ModifiedChange> = IndexMap::new();
-
-    let from_heads = from_repo.view().heads().iter().cloned().collect_vec();
-    let to_heads = to_repo.view().heads().iter().cloned().collect_vec();
-
-    // Find newly added commits in `to_repo` which were not present in
-    // `from_repo`.
-    for commit in revset::walk_revs(repo, &to_heads, &from_heads)?
-        .iter()
-        .commits(repo.store())
-    {
-        let commit = commit?;
-        let modified_change = changes
-            .entry(commit.change_id().clone())
-            .or_insert_with(|| ModifiedChange {
-                added_commits: vec![],
-                removed_commits: vec![],
-            });
-        modified_change.added_commits.push(commit);
-    }
-
-    // Find commits which were hidden in `to_repo`.
-    for commit in revset::walk_revs(repo, &from_heads, &to_heads)?
-        .iter()
-        .commits(repo.store())
-    {
-        let commit = commit?;
-        let modified_change = changes
-            .entry(commit.change_id().clone())
-            .or_insert_with(|| ModifiedChange {
-                added_commits: vec![],
-                removed_commits: vec![],
-            });
-        modified_change.removed_commits.push(commit);
-    }
-
-    Ok(changes)
-}
-
 /// Displays the diffs of a modified change. The output differs based on the
 /// commits added and removed for the change.
 /// If there is a single added and removed commit, the diff is shown between the
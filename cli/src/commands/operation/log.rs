@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::io;
 use std::slice;
 
 use clap_complete::ArgValueCandidates;
@@ -21,6 +22,7 @@ use jj_lib::config::ConfigGetResultExt as _;
 use jj_lib::graph::reverse_graph;
 use jj_lib::graph::GraphEdge;
 use jj_lib::op_store::OpStoreError;
+use jj_lib::op_store::OperationId;
 use jj_lib::op_walk;
 use jj_lib::operation::Operation;
 use jj_lib::repo::RepoLoader;
@@ -37,10 +39,12 @@ use crate::complete;
 use crate::diff_util::diff_formats_for_log;
 use crate::diff_util::DiffFormatArgs;
 use crate::diff_util::DiffRenderer;
+use crate::formatter::FormatRecorder;
 use crate::formatter::Formatter;
 use crate::graphlog::get_graphlog;
 use crate::graphlog::GraphStyle;
 use crate::operation_templater::OperationTemplateLanguage;
+use crate::text_util;
 use crate::ui::Ui;
 
 /// Show the operation log
@@ -208,6 +212,17 @@ fn do_op_log(
     let formatter = formatter.as_mut();
     let iter =
         op_walk::walk_ancestors(slice::from_ref(current_op)).take(args.limit.unwrap_or(usize::MAX));
+    let render_row = |op: &Operation| -> io::Result<FormatRecorder> {
+        let mut recorder = FormatRecorder::new();
+        template.format(op, &mut recorder)?;
+        Ok(recorder)
+    };
+    // Operations are written as soon as they are rendered until the template
+    // emits table cells. From then on, the rows are buffered so that the
+    // columns can be aligned across operations. Rows without cells aren't
+    // affected by the alignment, so the rows written earlier don't change.
+    let mut table_nodes = vec![];
+    let mut table_rows = vec![];
 
     if !args.no_graph {
         let mut raw_output = formatter.raw()?;
@@ -223,27 +238,48 @@ fn do_op_log(
         } else {
             Box::new(iter)
         };
-        for node in iter_nodes {
-            let (op, edges) = node?;
+        let mut write_node = |op: &Operation,
+                              edges: &[GraphEdge<OperationId>],
+                              write_row: &dyn Fn(&mut dyn Formatter, usize) -> io::Result<()>|
+         -> Result<(), CommandError> {
             let mut buffer = vec![];
-            let within_graph = with_content_format.sub_width(graph.width(op.id(), &edges));
+            let graph_width = graph.width(op.id(), edges);
+            let within_graph = with_content_format.sub_width(graph_width);
+            let table_width = ui.table_width().saturating_sub(graph_width);
             within_graph.write(ui.new_formatter(&mut buffer).as_mut(), |formatter| {
-                template.format(&op, formatter)
+                write_row(formatter, table_width)
             })?;
             if !buffer.ends_with(b"\n") {
                 buffer.push(b'\n');
             }
             if let Some(show) = &maybe_show_op_diff {
                 let mut formatter = ui.new_formatter(&mut buffer);
-                show(ui, formatter.as_mut(), &op, &within_graph)?;
+                show(ui, formatter.as_mut(), op, &within_graph)?;
             }
-            let node_symbol = format_template(ui, &op, &op_node_template);
+            let node_symbol = format_template(ui, op, &op_node_template);
             graph.add_node(
                 op.id(),
-                &edges,
+                edges,
                 &node_symbol,
                 &String::from_utf8_lossy(&buffer),
             )?;
+            Ok(())
+        };
+        for node in iter_nodes {
+            let (op, edges) = node?;
+            let row = render_row(&op)?;
+            if table_rows.is_empty() && !row.has_cells() {
+                write_node(&op, &edges, &|formatter, _| row.replay(formatter))?;
+            } else {
+                table_nodes.push((op, edges));
+                table_rows.push(row);
+            }
+        }
+        let table = text_util::Table::new(&table_rows);
+        for (index, (op, edges)) in table_nodes.iter().enumerate() {
+            write_node(op, edges, &|formatter, width| {
+                table.write_row(formatter, index, width)
+            })?;
         }
     } else {
         let iter: Box<dyn Iterator<Item = _>> = if args.reversed {
@@ -251,13 +287,32 @@ fn do_op_log(
         } else {
             Box::new(iter)
         };
+        let mut write_op = |op: &Operation,
+                            write_row: &dyn Fn(&mut dyn Formatter) -> io::Result<()>|
+         -> Result<(), CommandError> {
+            with_content_format.write(formatter, write_row)?;
+            if let Some(show) = &maybe_show_op_diff {
+                show(ui, formatter, op, &with_content_format)?;
+            }
+            Ok(())
+        };
         for op in iter {
             let op = op?;
-            with_content_format.write(formatter, |formatter| template.format(&op, formatter))?;
-            if let Some(show) = &maybe_show_op_diff {
-                show(ui, formatter, &op, &with_content_format)?;
+            let row = render_row(&op)?;
+            if table_rows.is_empty() && !row.has_cells() {
+                write_op(&op, &|formatter| row.replay(formatter))?;
+            } else {
+                table_nodes.push((op, vec![]));
+                table_rows.push(row);
             }
         }
+        let table = text_util::Table::new(&table_rows);
+        let table_width = ui.table_width();
+        for (index, (op, _)) in table_nodes.iter().enumerate() {
+            write_op(op, &|formatter| {
+                table.write_row(formatter, index, table_width)
+            })?;
+        }
     }
 
     Ok(())
@@ -30,11 +30,12 @@ use crate::ui::Ui;
 
 /// Resolve conflicted files with an external merge tool
 ///
-/// Only conflicts that can be resolved with a 3-way merge are supported. See
-/// docs for merge tool configuration instructions. External merge tools will be
-/// invoked for each conflicted file one-by-one until all conflicts are
-/// resolved. To stop resolving conflicts, exit the merge tool without making
-/// any changes.
+/// External merge tools only support conflicts that can be resolved with a
+/// 3-way merge. The built-in merge tool (`:builtin`) also supports conflicts
+/// with more sides. See docs for merge tool configuration instructions.
+/// External merge tools will be invoked for each conflicted file one-by-one
+/// until all conflicts are resolved. To stop resolving conflicts, exit the
+/// merge tool without making any changes.
 ///
 /// Note that conflicts can also be resolved without using this command. You may
 /// edit the conflict markers in the conflicted file directly with a text
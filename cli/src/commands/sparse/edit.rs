@@ -13,15 +13,13 @@
 // limitations under the License.
 
 use std::fmt::Write as _;
-use std::path::Path;
 
 use itertools::Itertools;
-use jj_lib::repo_path::RepoPathBuf;
+use jj_lib::sparse::SparsePattern;
 use tracing::instrument;
 
 use super::update_sparse_patterns_with;
 use crate::cli_util::CommandHelper;
-use crate::command_error::internal_error;
 use crate::command_error::user_error_with_message;
 use crate::command_error::CommandError;
 use crate::description_util::TextEditor;
@@ -49,19 +47,11 @@ pub fn cmd_sparse_edit(
 
 fn edit_sparse(
     editor: &TextEditor,
-    sparse: &[RepoPathBuf],
-) -> Result<Vec<RepoPathBuf>, CommandError> {
+    sparse: &[SparsePattern],
+) -> Result<Vec<SparsePattern>, CommandError> {
     let mut content = String::new();
-    for sparse_path in sparse {
-        // Invalid path shouldn't block editing. Edited paths will be validated.
-        let workspace_relative_sparse_path = sparse_path.to_fs_path_unchecked(Path::new(""));
-        let path_string = workspace_relative_sparse_path.to_str().ok_or_else(|| {
-            internal_error(format!(
-                "Stored sparse path is not valid utf-8: {}",
-                workspace_relative_sparse_path.display()
-            ))
-        })?;
-        writeln!(&mut content, "{path_string}").unwrap();
+    for pattern in sparse {
+        writeln!(&mut content, "{pattern}").unwrap();
     }
 
     let content = editor
@@ -74,7 +64,7 @@ fn edit_sparse(
         .map(|line| line.trim())
         .filter(|line| !line.is_empty())
         .map(|line| {
-            RepoPathBuf::from_relative_path(line).map_err(|err| {
+            SparsePattern::parse(line).map_err(|err| {
                 user_error_with_message(format!("Failed to parse sparse pattern: {line}"), err)
             })
         })
@@ -18,7 +18,7 @@ mod reset;
 mod set;
 
 use clap::Subcommand;
-use jj_lib::repo_path::RepoPathBuf;
+use jj_lib::sparse::SparsePattern;
 use tracing::instrument;
 
 use self::edit::cmd_sparse_edit;
@@ -63,7 +63,7 @@ pub(crate) fn cmd_sparse(
 fn update_sparse_patterns_with(
     ui: &mut Ui,
     workspace_command: &mut WorkspaceCommandHelper,
-    f: impl FnOnce(&mut Ui, &[RepoPathBuf]) -> Result<Vec<RepoPathBuf>, CommandError>,
+    f: impl FnOnce(&mut Ui, &[SparsePattern]) -> Result<Vec<SparsePattern>, CommandError>,
 ) -> Result<(), CommandError> {
     let checkout_options = workspace_command.checkout_options();
     let (mut locked_ws, wc_commit) = workspace_command.start_working_copy_mutation()?;
@@ -15,7 +15,7 @@
 use std::collections::HashSet;
 
 use itertools::Itertools as _;
-use jj_lib::repo_path::RepoPathBuf;
+use jj_lib::sparse::SparsePattern;
 use tracing::instrument;
 
 use super::update_sparse_patterns_with;
@@ -28,23 +28,21 @@ use crate::ui::Ui;
 /// For example, if all you need is the `README.md` and the `lib/`
 /// directory, use `jj sparse set --clear --add README.md --add lib`.
 /// If you no longer need the `lib` directory, use `jj sparse set --remove lib`.
+///
+/// Patterns are [filesets] relative to the workspace root. A pattern starting
+/// with `~` excludes paths from the other patterns, so `jj sparse set --clear
+/// --add lib --add '~lib/testdata'` checks out `lib` without its test data.
+///
+/// [filesets]:
+///     https://jj-vcs.github.io/jj/latest/filesets/
 #[derive(clap::Args, Clone, Debug)]
 pub struct SparseSetArgs {
     /// Patterns to add to the working copy
-    #[arg(
-        long,
-        value_hint = clap::ValueHint::AnyPath,
-        value_parser = |s: &str| RepoPathBuf::from_relative_path(s),
-    )]
-    add: Vec<RepoPathBuf>,
+    #[arg(long, value_hint = clap::ValueHint::AnyPath)]
+    add: Vec<String>,
     /// Patterns to remove from the working copy
-    #[arg(
-        long,
-        conflicts_with = "clear",
-        value_hint = clap::ValueHint::AnyPath,
-        value_parser = |s: &str| RepoPathBuf::from_relative_path(s),
-    )]
-    remove: Vec<RepoPathBuf>,
+    #[arg(long, conflicts_with = "clear", value_hint = clap::ValueHint::AnyPath)]
+    remove: Vec<String>,
     /// Include no files in the working copy (combine with --add)
     #[arg(long)]
     clear: bool,
@@ -56,18 +54,26 @@ pub fn cmd_sparse_set(
     command: &CommandHelper,
     args: &SparseSetArgs,
 ) -> Result<(), CommandError> {
+    let add_patterns: Vec<_> = args
+        .add
+        .iter()
+        .map(|text| SparsePattern::parse(text))
+        .try_collect()?;
+    let remove_patterns: Vec<_> = args
+        .remove
+        .iter()
+        .map(|text| SparsePattern::parse(text))
+        .try_collect()?;
     let mut workspace_command = command.workspace_helper(ui)?;
     update_sparse_patterns_with(ui, &mut workspace_command, |_ui, old_patterns| {
         let mut new_patterns = HashSet::new();
         if !args.clear {
             new_patterns.extend(old_patterns.iter().cloned());
-            for path in &args.remove {
-                new_patterns.remove(path);
+            for pattern in &remove_patterns {
+                new_patterns.remove(pattern);
             }
         }
-        for path in &args.add {
-            new_patterns.insert(path.to_owned());
-        }
+        new_patterns.extend(add_patterns);
         Ok(new_patterns.into_iter().sorted_unstable().collect())
     })
 }
@@ -19,6 +19,8 @@ use jj_lib::copies::CopyRecords;
 use jj_lib::repo::Repo;
 use jj_lib::revset::RevsetExpression;
 use jj_lib::revset::RevsetFilterPredicate;
+use jj_lib::settings::HumanByteSize;
+use jj_lib::working_copy::UntrackedReason;
 use tracing::instrument;
 
 use crate::cli_util::print_conflicted_paths;
@@ -34,6 +36,8 @@ use crate::ui::Ui;
 ///
 ///  * The working copy commit and its (first) parent, and a summary of the
 ///    changes between them
+///  * New files which aren't tracked, and the reason why. Files matching
+///    `snapshot.ignore-untracked` aren't listed.
 ///  * [Conflicted bookmarks]
 ///
 /// [Conflicted bookmarks]:
@@ -70,7 +74,13 @@ pub(crate) fn cmd_status(
         let tree = wc_commit.tree()?;
 
         let wc_has_changes = tree.id() != parent_tree.id();
-        let wc_has_untracked = !snapshot_stats.untracked_paths.is_empty();
+        let untracked_ignore_matcher = workspace_command.untracked_ignore_matcher(ui)?;
+        let untracked_paths = snapshot_stats
+            .untracked_paths
+            .iter()
+            .filter(|(path, _)| !untracked_ignore_matcher.matches(path))
+            .collect_vec();
+        let wc_has_untracked = !untracked_paths.is_empty();
         if !wc_has_changes && !wc_has_untracked {
             writeln!(formatter, "The working copy is clean")?;
         } else {
@@ -99,9 +109,15 @@ pub(crate) fn cmd_status(
                 // when using watchman. See https://github.com/jj-vcs/jj/commit/168c7979feab40d58f49fe19683975697a7bc089 for details.
                 writeln!(formatter, "Untracked paths:")?;
                 formatter.with_label("diff", |formatter| {
-                    for path in snapshot_stats.untracked_paths.keys() {
+                    for (path, reason) in untracked_paths {
                         let ui_path = workspace_command.path_converter().format_file_path(path);
-                        writeln!(formatter.labeled("untracked"), "? {ui_path}")?;
+                        let reason = match reason {
+                            UntrackedReason::FileTooLarge { size, .. } => {
+                                format!("too large: {}", HumanByteSize(*size))
+                            }
+                            UntrackedReason::FileNotAutoTracked => "not auto-tracked".to_owned(),
+                        };
+                        writeln!(formatter.labeled("untracked"), "? {ui_path} ({reason})")?;
                     }
                     io::Result::Ok(())
                 })?;
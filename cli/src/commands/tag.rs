@@ -20,6 +20,8 @@ use crate::command_error::CommandError;
 use crate::commit_templater::CommitTemplateLanguage;
 use crate::commit_templater::RefName;
 use crate::complete;
+use crate::formatter::FormatRecorder;
+use crate::text_util;
 use crate::ui::Ui;
 
 /// Manage tags.
@@ -85,16 +87,21 @@ fn cmd_tag_list(
             .labeled("tag_list")
     };
 
-    ui.request_pager();
-    let mut formatter = ui.stdout_formatter();
-
+    // Rows are buffered so that columns can be aligned across tags.
+    let mut rows = vec![];
     for (name, target) in view.tags() {
         if !args.names.is_empty() && !args.names.iter().any(|pattern| pattern.matches(name)) {
             continue;
         }
         let ref_name = RefName::local_only(name, target.clone());
-        template.format(&ref_name, formatter.as_mut())?;
+        let mut recorder = FormatRecorder::new();
+        template.format(&ref_name, &mut recorder)?;
+        rows.push(recorder);
     }
 
+    ui.request_pager();
+    let mut formatter = ui.stdout_formatter();
+    text_util::write_table(formatter.as_mut(), &rows, ui.table_width())?;
+
     Ok(())
 }
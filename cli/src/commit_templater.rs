@@ -17,8 +17,11 @@ use std::cmp::max;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::io;
+use std::num::TryFromIntError;
+use std::ops::Range;
 use std::rc::Rc;
 
+use bstr::ByteSlice as _;
 use futures::stream::BoxStream;
 use futures::StreamExt as _;
 use futures::TryStreamExt as _;
@@ -26,6 +29,7 @@ use itertools::Itertools as _;
 use jj_lib::backend::BackendResult;
 use jj_lib::backend::ChangeId;
 use jj_lib::backend::CommitId;
+use jj_lib::backend::Timestamp;
 use jj_lib::backend::TreeValue;
 use jj_lib::commit::Commit;
 use jj_lib::conflicts::ConflictMarkerStyle;
@@ -66,9 +70,12 @@ use pollster::FutureExt as _;
 
 use crate::diff_util;
 use crate::diff_util::DiffStats;
+use crate::diff_util::FileDiffHunk;
+use crate::diff_util::FileDiffLineType;
 use crate::formatter::Formatter;
 use crate::revset_util;
 use crate::template_builder;
+use crate::template_builder::memoize_property;
 use crate::template_builder::merge_fn_map;
 use crate::template_builder::BuildContext;
 use crate::template_builder::CoreTemplateBuildFnTable;
@@ -83,6 +90,7 @@ use crate::template_parser::TemplateDiagnostics;
 use crate::template_parser::TemplateParseError;
 use crate::template_parser::TemplateParseResult;
 use crate::templater;
+use crate::templater::MemoScope;
 use crate::templater::PlainTextFormattedProperty;
 use crate::templater::SizeHint;
 use crate::templater::Template;
@@ -297,6 +305,22 @@ impl<'repo> TemplateLanguage<'repo> for CommitTemplateLanguage<'repo> {
                     Self::wrap_tree_diff_entry_list,
                 )
             }
+            CommitTemplatePropertyKind::DiffHunk(property) => {
+                let table = &self.build_fn_table.diff_hunk_methods;
+                let build = template_parser::lookup_method(type_name, table, function)?;
+                build(self, diagnostics, build_ctx, property, function)
+            }
+            CommitTemplatePropertyKind::DiffHunkList(property) => {
+                template_builder::build_unformattable_list_method(
+                    self,
+                    diagnostics,
+                    build_ctx,
+                    property,
+                    function,
+                    Self::wrap_diff_hunk,
+                    Self::wrap_diff_hunk_list,
+                )
+            }
             CommitTemplatePropertyKind::TreeEntry(property) => {
                 let table = &self.build_fn_table.tree_entry_methods;
                 let build = template_parser::lookup_method(type_name, table, function)?;
@@ -424,6 +448,18 @@ impl<'repo> CommitTemplateLanguage<'repo> {
         CommitTemplatePropertyKind::TreeDiffEntryList(Box::new(property))
     }
 
+    pub fn wrap_diff_hunk(
+        property: impl TemplateProperty<Output = FileDiffHunk> + 'repo,
+    ) -> CommitTemplatePropertyKind<'repo> {
+        CommitTemplatePropertyKind::DiffHunk(Box::new(property))
+    }
+
+    pub fn wrap_diff_hunk_list(
+        property: impl TemplateProperty<Output = Vec<FileDiffHunk>> + 'repo,
+    ) -> CommitTemplatePropertyKind<'repo> {
+        CommitTemplatePropertyKind::DiffHunkList(Box::new(property))
+    }
+
     pub fn wrap_tree_entry(
         property: impl TemplateProperty<Output = TreeEntry> + 'repo,
     ) -> CommitTemplatePropertyKind<'repo> {
@@ -458,6 +494,8 @@ pub enum CommitTemplatePropertyKind<'repo> {
     TreeDiff(Box<dyn TemplateProperty<Output = TreeDiff> + 'repo>),
     TreeDiffEntry(Box<dyn TemplateProperty<Output = TreeDiffEntry> + 'repo>),
     TreeDiffEntryList(Box<dyn TemplateProperty<Output = Vec<TreeDiffEntry>> + 'repo>),
+    DiffHunk(Box<dyn TemplateProperty<Output = FileDiffHunk> + 'repo>),
+    DiffHunkList(Box<dyn TemplateProperty<Output = Vec<FileDiffHunk>> + 'repo>),
     TreeEntry(Box<dyn TemplateProperty<Output = TreeEntry> + 'repo>),
     DiffStats(Box<dyn TemplateProperty<Output = DiffStatsFormatted<'repo>> + 'repo>),
     CryptographicSignatureOpt(
@@ -482,6 +520,8 @@ impl<'repo> IntoTemplateProperty<'repo> for CommitTemplatePropertyKind<'repo> {
             CommitTemplatePropertyKind::TreeDiff(_) => "TreeDiff",
             CommitTemplatePropertyKind::TreeDiffEntry(_) => "TreeDiffEntry",
             CommitTemplatePropertyKind::TreeDiffEntryList(_) => "List<TreeDiffEntry>",
+            CommitTemplatePropertyKind::DiffHunk(_) => "DiffHunk",
+            CommitTemplatePropertyKind::DiffHunkList(_) => "List<DiffHunk>",
             CommitTemplatePropertyKind::TreeEntry(_) => "TreeEntry",
             CommitTemplatePropertyKind::DiffStats(_) => "DiffStats",
             CommitTemplatePropertyKind::CryptographicSignatureOpt(_) => {
@@ -520,6 +560,10 @@ impl<'repo> IntoTemplateProperty<'repo> for CommitTemplatePropertyKind<'repo> {
             CommitTemplatePropertyKind::TreeDiffEntryList(property) => {
                 Some(Box::new(property.map(|l| !l.is_empty())))
             }
+            CommitTemplatePropertyKind::DiffHunk(_) => None,
+            CommitTemplatePropertyKind::DiffHunkList(property) => {
+                Some(Box::new(property.map(|l| !l.is_empty())))
+            }
             CommitTemplatePropertyKind::TreeEntry(_) => None,
             CommitTemplatePropertyKind::DiffStats(_) => None,
             CommitTemplatePropertyKind::CryptographicSignatureOpt(property) => {
@@ -535,6 +579,13 @@ impl<'repo> IntoTemplateProperty<'repo> for CommitTemplatePropertyKind<'repo> {
         }
     }
 
+    fn try_into_timestamp(self) -> Option<Box<dyn TemplateProperty<Output = Timestamp> + 'repo>> {
+        match self {
+            CommitTemplatePropertyKind::Core(property) => property.try_into_timestamp(),
+            _ => None,
+        }
+    }
+
     fn try_into_plain_text(self) -> Option<Box<dyn TemplateProperty<Output = String> + 'repo>> {
         match self {
             CommitTemplatePropertyKind::Core(property) => property.try_into_plain_text(),
@@ -565,6 +616,8 @@ impl<'repo> IntoTemplateProperty<'repo> for CommitTemplatePropertyKind<'repo> {
             CommitTemplatePropertyKind::TreeDiff(_) => None,
             CommitTemplatePropertyKind::TreeDiffEntry(_) => None,
             CommitTemplatePropertyKind::TreeDiffEntryList(_) => None,
+            CommitTemplatePropertyKind::DiffHunk(_) => None,
+            CommitTemplatePropertyKind::DiffHunkList(_) => None,
             CommitTemplatePropertyKind::TreeEntry(_) => None,
             CommitTemplatePropertyKind::DiffStats(property) => Some(property.into_template()),
             CommitTemplatePropertyKind::CryptographicSignatureOpt(_) => None,
@@ -590,6 +643,8 @@ impl<'repo> IntoTemplateProperty<'repo> for CommitTemplatePropertyKind<'repo> {
             (CommitTemplatePropertyKind::TreeDiff(_), _) => None,
             (CommitTemplatePropertyKind::TreeDiffEntry(_), _) => None,
             (CommitTemplatePropertyKind::TreeDiffEntryList(_), _) => None,
+            (CommitTemplatePropertyKind::DiffHunk(_), _) => None,
+            (CommitTemplatePropertyKind::DiffHunkList(_), _) => None,
             (CommitTemplatePropertyKind::TreeEntry(_), _) => None,
             (CommitTemplatePropertyKind::DiffStats(_), _) => None,
             (CommitTemplatePropertyKind::CryptographicSignatureOpt(_), _) => None,
@@ -618,11 +673,59 @@ impl<'repo> IntoTemplateProperty<'repo> for CommitTemplatePropertyKind<'repo> {
             (CommitTemplatePropertyKind::TreeDiff(_), _) => None,
             (CommitTemplatePropertyKind::TreeDiffEntry(_), _) => None,
             (CommitTemplatePropertyKind::TreeDiffEntryList(_), _) => None,
+            (CommitTemplatePropertyKind::DiffHunk(_), _) => None,
+            (CommitTemplatePropertyKind::DiffHunkList(_), _) => None,
             (CommitTemplatePropertyKind::TreeEntry(_), _) => None,
             (CommitTemplatePropertyKind::DiffStats(_), _) => None,
             (CommitTemplatePropertyKind::CryptographicSignatureOpt(_), _) => None,
         }
     }
+
+    fn try_into_memoized(self, scope: &MemoScope) -> Option<Box<dyn Fn() -> Self + 'repo>> {
+        type Kind<'repo> = CommitTemplatePropertyKind<'repo>;
+        match self {
+            Kind::Core(property) => {
+                let make = property.try_into_memoized(scope)?;
+                Some(Box::new(move || Kind::Core(make())))
+            }
+            Kind::Commit(property) => Some(memoize_property(property, scope, Kind::Commit)),
+            Kind::CommitOpt(property) => Some(memoize_property(property, scope, Kind::CommitOpt)),
+            Kind::CommitList(property) => Some(memoize_property(property, scope, Kind::CommitList)),
+            Kind::RefName(property) => Some(memoize_property(property, scope, Kind::RefName)),
+            Kind::RefNameOpt(property) => Some(memoize_property(property, scope, Kind::RefNameOpt)),
+            Kind::RefNameList(property) => {
+                Some(memoize_property(property, scope, Kind::RefNameList))
+            }
+            Kind::RepoPath(property) => Some(memoize_property(property, scope, Kind::RepoPath)),
+            Kind::RepoPathOpt(property) => {
+                Some(memoize_property(property, scope, Kind::RepoPathOpt))
+            }
+            Kind::CommitOrChangeId(property) => {
+                Some(memoize_property(property, scope, Kind::CommitOrChangeId))
+            }
+            Kind::ShortestIdPrefix(property) => {
+                Some(memoize_property(property, scope, Kind::ShortestIdPrefix))
+            }
+            Kind::TreeDiff(property) => Some(memoize_property(property, scope, Kind::TreeDiff)),
+            Kind::TreeDiffEntry(property) => {
+                Some(memoize_property(property, scope, Kind::TreeDiffEntry))
+            }
+            Kind::TreeDiffEntryList(property) => {
+                Some(memoize_property(property, scope, Kind::TreeDiffEntryList))
+            }
+            Kind::DiffHunk(property) => Some(memoize_property(property, scope, Kind::DiffHunk)),
+            Kind::DiffHunkList(property) => {
+                Some(memoize_property(property, scope, Kind::DiffHunkList))
+            }
+            Kind::TreeEntry(property) => Some(memoize_property(property, scope, Kind::TreeEntry)),
+            Kind::DiffStats(property) => Some(memoize_property(property, scope, Kind::DiffStats)),
+            Kind::CryptographicSignatureOpt(property) => Some(memoize_property(
+                property,
+                scope,
+                Kind::CryptographicSignatureOpt,
+            )),
+        }
+    }
 }
 
 /// Table of functions that translate method call node of self type `T`.
@@ -639,6 +742,7 @@ pub struct CommitTemplateBuildFnTable<'repo> {
     pub shortest_id_prefix_methods: CommitTemplateBuildMethodFnMap<'repo, ShortestIdPrefix>,
     pub tree_diff_methods: CommitTemplateBuildMethodFnMap<'repo, TreeDiff>,
     pub tree_diff_entry_methods: CommitTemplateBuildMethodFnMap<'repo, TreeDiffEntry>,
+    pub diff_hunk_methods: CommitTemplateBuildMethodFnMap<'repo, FileDiffHunk>,
     pub tree_entry_methods: CommitTemplateBuildMethodFnMap<'repo, TreeEntry>,
     pub diff_stats_methods: CommitTemplateBuildMethodFnMap<'repo, DiffStats>,
     pub cryptographic_signature_methods:
@@ -657,6 +761,7 @@ impl<'repo> CommitTemplateBuildFnTable<'repo> {
             shortest_id_prefix_methods: builtin_shortest_id_prefix_methods(),
             tree_diff_methods: builtin_tree_diff_methods(),
             tree_diff_entry_methods: builtin_tree_diff_entry_methods(),
+            diff_hunk_methods: builtin_diff_hunk_methods(),
             tree_entry_methods: builtin_tree_entry_methods(),
             diff_stats_methods: builtin_diff_stats_methods(),
             cryptographic_signature_methods: builtin_cryptographic_signature_methods(),
@@ -673,6 +778,7 @@ impl<'repo> CommitTemplateBuildFnTable<'repo> {
             shortest_id_prefix_methods: HashMap::new(),
             tree_diff_methods: HashMap::new(),
             tree_diff_entry_methods: HashMap::new(),
+            diff_hunk_methods: HashMap::new(),
             tree_entry_methods: HashMap::new(),
             diff_stats_methods: HashMap::new(),
             cryptographic_signature_methods: HashMap::new(),
@@ -689,6 +795,7 @@ impl<'repo> CommitTemplateBuildFnTable<'repo> {
             shortest_id_prefix_methods,
             tree_diff_methods,
             tree_diff_entry_methods,
+            diff_hunk_methods,
             tree_entry_methods,
             diff_stats_methods,
             cryptographic_signature_methods,
@@ -708,6 +815,7 @@ impl<'repo> CommitTemplateBuildFnTable<'repo> {
         );
         merge_fn_map(&mut self.tree_diff_methods, tree_diff_methods);
         merge_fn_map(&mut self.tree_diff_entry_methods, tree_diff_entry_methods);
+        merge_fn_map(&mut self.diff_hunk_methods, diff_hunk_methods);
         merge_fn_map(&mut self.tree_entry_methods, tree_entry_methods);
         merge_fn_map(&mut self.diff_stats_methods, diff_stats_methods);
         merge_fn_map(
@@ -1606,6 +1714,26 @@ fn builtin_commit_or_change_id_methods<'repo>(
             Ok(L::wrap_string(out_property))
         },
     );
+    map.insert(
+        "url",
+        |language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let url_template = language
+                .settings()
+                .get_string("hyperlinks.commit-url")
+                .map_err(|err| {
+                    TemplateParseError::expression("Failed to get config value", function.name_span)
+                        .with_source(err)
+                })?;
+            let out_property = self_property.map(move |id| match id {
+                CommitOrChangeId::Commit(id) if !url_template.is_empty() => {
+                    url_template.replace("$commit_id", &id.hex())
+                }
+                _ => String::new(),
+            });
+            Ok(L::wrap_string(out_property))
+        },
+    );
     map.insert(
         "shortest",
         |language, diagnostics, build_ctx, self_property, function| {
@@ -1644,6 +1772,7 @@ fn builtin_commit_or_change_id_methods<'repo>(
     map
 }
 
+#[derive(Clone, Debug)]
 pub struct ShortestIdPrefix {
     pub prefix: String,
     pub rest: String,
@@ -1714,7 +1843,7 @@ fn builtin_shortest_id_prefix_methods<'repo>(
 }
 
 /// Pair of trees to be diffed.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TreeDiff {
     from_tree: MergedTree,
     to_tree: MergedTree,
@@ -2013,9 +2142,128 @@ fn builtin_tree_diff_entry_methods<'repo>() -> CommitTemplateBuildMethodFnMap<'r
             Ok(L::wrap_tree_entry(out_property))
         },
     );
+    map.insert(
+        "hunks",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let ([], [context_node]) = function.expect_arguments()?;
+            let context_property = context_node
+                .map(|node| {
+                    template_builder::expect_usize_expression(
+                        language,
+                        diagnostics,
+                        build_ctx,
+                        node,
+                    )
+                })
+                .transpose()?;
+            let options = diff_util::UnifiedDiffOptions::from_settings(language.settings())
+                .map_err(|err| {
+                    let message = "Failed to load diff settings";
+                    TemplateParseError::expression(message, function.name_span).with_source(err)
+                })?;
+            let store = language.repo.store();
+            let conflict_marker_style = language.conflict_marker_style;
+            let out_property =
+                (self_property, context_property).and_then(move |(entry, context)| {
+                    let mut options = options.clone();
+                    if let Some(context) = context {
+                        options.context = context;
+                    }
+                    let hunks = diff_util::file_diff_hunks(
+                        store,
+                        &entry.path,
+                        entry.source_value,
+                        entry.target_value,
+                        &options,
+                        conflict_marker_style,
+                    )
+                    .block_on()?;
+                    Ok(hunks)
+                });
+            Ok(L::wrap_diff_hunk_list(out_property))
+        },
+    );
     map
 }
 
+fn builtin_diff_hunk_methods<'repo>() -> CommitTemplateBuildMethodFnMap<'repo, FileDiffHunk> {
+    type L<'repo> = CommitTemplateLanguage<'repo>;
+    // Not using maplit::hashmap!{} or custom declarative macro here because
+    // code completion inside macro is quite restricted.
+    let mut map = CommitTemplateBuildMethodFnMap::<FileDiffHunk>::new();
+    map.insert(
+        "left_start",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property =
+                self_property.and_then(|hunk| Ok(to_line_number(&hunk.left_line_range)?));
+            Ok(L::wrap_integer(out_property))
+        },
+    );
+    map.insert(
+        "left_len",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property =
+                self_property.and_then(|hunk| Ok(i64::try_from(hunk.left_line_range.len())?));
+            Ok(L::wrap_integer(out_property))
+        },
+    );
+    map.insert(
+        "right_start",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property =
+                self_property.and_then(|hunk| Ok(to_line_number(&hunk.right_line_range)?));
+            Ok(L::wrap_integer(out_property))
+        },
+    );
+    map.insert(
+        "right_len",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property =
+                self_property.and_then(|hunk| Ok(i64::try_from(hunk.right_line_range.len())?));
+            Ok(L::wrap_integer(out_property))
+        },
+    );
+    map.insert(
+        "removed_lines",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|hunk| {
+                hunk.lines_of_type(FileDiffLineType::Removed)
+                    .map(|line| line.to_str_lossy().into_owned())
+                    .collect()
+            });
+            Ok(L::wrap_string_list(out_property))
+        },
+    );
+    map.insert(
+        "added_lines",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|hunk| {
+                hunk.lines_of_type(FileDiffLineType::Added)
+                    .map(|line| line.to_str_lossy().into_owned())
+                    .collect()
+            });
+            Ok(L::wrap_string_list(out_property))
+        },
+    );
+    map
+}
+
+/// Converts 0-based line range to 1-based line number as in unified diff
+/// header. The line number of an empty range is the preceding line.
+fn to_line_number(range: &Range<usize>) -> Result<i64, TryFromIntError> {
+    if range.is_empty() {
+        i64::try_from(range.start)
+    } else {
+        i64::try_from(range.start + 1)
+    }
+}
+
 /// [`MergedTree`] entry.
 #[derive(Clone, Debug)]
 pub struct TreeEntry {
@@ -2127,7 +2375,7 @@ fn builtin_diff_stats_methods<'repo>() -> CommitTemplateBuildMethodFnMap<'repo,
     map
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct CryptographicSignature {
     commit: Commit,
 }
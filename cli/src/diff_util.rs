@@ -22,6 +22,7 @@ use std::path::Path;
 use std::path::PathBuf;
 
 use bstr::BStr;
+use bstr::BString;
 use futures::executor::block_on_stream;
 use futures::stream::BoxStream;
 use futures::StreamExt as _;
@@ -36,6 +37,7 @@ use jj_lib::commit::Commit;
 use jj_lib::config::ConfigGetError;
 use jj_lib::config::ConfigGetResultExt as _;
 use jj_lib::conflicts::materialize_merge_result_to_bytes;
+use jj_lib::conflicts::materialize_tree_value;
 use jj_lib::conflicts::materialized_diff_stream;
 use jj_lib::conflicts::ConflictMarkerStyle;
 use jj_lib::conflicts::MaterializedTreeDiffEntry;
@@ -1438,6 +1440,85 @@ where
     (left_lines, right_lines)
 }
 
+/// Line-based diff hunk between two file contents.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FileDiffHunk {
+    /// 0-based range of the lines in the left (old) content.
+    pub left_line_range: Range<usize>,
+    /// 0-based range of the lines in the right (new) content.
+    pub right_line_range: Range<usize>,
+    /// Context, removed, and added lines without line terminators.
+    pub lines: Vec<(FileDiffLineType, BString)>,
+}
+
+/// Type of [`FileDiffHunk`] line.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FileDiffLineType {
+    Context,
+    Removed,
+    Added,
+}
+
+impl FileDiffHunk {
+    /// Iterates over the lines of the given `line_type`.
+    pub fn lines_of_type(&self, line_type: FileDiffLineType) -> impl Iterator<Item = &BStr> {
+        self.lines
+            .iter()
+            .filter(move |(ty, _)| *ty == line_type)
+            .map(|(_, line)| line.as_ref())
+    }
+}
+
+/// Calculates line-based diff hunks between the `source` and `target` values.
+///
+/// Binary files produce no hunks.
+pub async fn file_diff_hunks(
+    store: &Store,
+    path: &CopiesTreeDiffEntryPath,
+    source: MergedTreeValue,
+    target: MergedTreeValue,
+    options: &UnifiedDiffOptions,
+    conflict_marker_style: ConflictMarkerStyle,
+) -> BackendResult<Vec<FileDiffHunk>> {
+    let left_value = materialize_tree_value(store, path.source(), source).await?;
+    let right_value = materialize_tree_value(store, path.target(), target).await?;
+    let left_content = diff_content(path.source(), left_value, conflict_marker_style)?;
+    let right_content = diff_content(path.target(), right_value, conflict_marker_style)?;
+    if left_content.is_binary || right_content.is_binary {
+        return Ok(vec![]);
+    }
+    let hunks = unified_diff_hunks(&left_content.contents, &right_content.contents, options)
+        .into_iter()
+        .map(|hunk| {
+            let lines = hunk
+                .lines
+                .iter()
+                .map(|(line_type, tokens)| {
+                    let line_type = match line_type {
+                        DiffLineType::Context => FileDiffLineType::Context,
+                        DiffLineType::Removed => FileDiffLineType::Removed,
+                        DiffLineType::Added => FileDiffLineType::Added,
+                    };
+                    let mut line = BString::default();
+                    for (_, content) in tokens {
+                        line.extend_from_slice(content);
+                    }
+                    if line.ends_with(b"\n") {
+                        line.pop();
+                    }
+                    (line_type, line)
+                })
+                .collect();
+            FileDiffHunk {
+                left_line_range: hunk.left_line_range,
+                right_line_range: hunk.right_line_range,
+                lines,
+            }
+        })
+        .collect();
+    Ok(hunks)
+}
+
 fn show_unified_diff_hunks(
     formatter: &mut dyn Formatter,
     left_content: &[u8],
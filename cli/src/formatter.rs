@@ -44,6 +44,18 @@ pub trait Formatter: Write {
     fn push_label(&mut self, label: &str) -> io::Result<()>;
 
     fn pop_label(&mut self) -> io::Result<()>;
+
+    /// Starts a hyperlink to the given `url`. Text written until the matching
+    /// `pop_hyperlink()` becomes the link text.
+    fn push_hyperlink(&mut self, url: &str) -> io::Result<()>;
+
+    fn pop_hyperlink(&mut self) -> io::Result<()>;
+
+    /// Marks the end of a table cell. Formatters that don't lay out tables
+    /// separate cells by a single space.
+    fn end_cell(&mut self) -> io::Result<()> {
+        self.write_all(b" ")
+    }
 }
 
 impl dyn Formatter + '_ {
@@ -143,6 +155,7 @@ type Rules = Vec<(Vec<String>, Style)>;
 #[derive(Clone, Debug)]
 pub struct FormatterFactory {
     kind: FormatterFactoryKind,
+    hyperlinks: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -155,18 +168,33 @@ enum FormatterFactoryKind {
 impl FormatterFactory {
     pub fn plain_text() -> Self {
         let kind = FormatterFactoryKind::PlainText;
-        FormatterFactory { kind }
+        FormatterFactory {
+            kind,
+            hyperlinks: false,
+        }
     }
 
     pub fn sanitized() -> Self {
         let kind = FormatterFactoryKind::Sanitized;
-        FormatterFactory { kind }
+        FormatterFactory {
+            kind,
+            hyperlinks: false,
+        }
     }
 
     pub fn color(config: &StackedConfig, debug: bool) -> Result<Self, ConfigGetError> {
         let rules = Arc::new(rules_from_config(config)?);
         let kind = FormatterFactoryKind::Color { rules, debug };
-        Ok(FormatterFactory { kind })
+        Ok(FormatterFactory {
+            kind,
+            hyperlinks: false,
+        })
+    }
+
+    /// Sets whether the formatters emit hyperlinks.
+    pub fn with_hyperlinks(mut self, hyperlinks: bool) -> Self {
+        self.hyperlinks = hyperlinks;
+        self
     }
 
     pub fn new_formatter<'output, W: Write + 'output>(
@@ -174,11 +202,15 @@ impl FormatterFactory {
         output: W,
     ) -> Box<dyn Formatter + 'output> {
         match &self.kind {
-            FormatterFactoryKind::PlainText => Box::new(PlainTextFormatter::new(output)),
-            FormatterFactoryKind::Sanitized => Box::new(SanitizingFormatter::new(output)),
-            FormatterFactoryKind::Color { rules, debug } => {
-                Box::new(ColorFormatter::new(output, rules.clone(), *debug))
+            FormatterFactoryKind::PlainText => {
+                Box::new(PlainTextFormatter::new(output).with_hyperlinks(self.hyperlinks))
             }
+            FormatterFactoryKind::Sanitized => {
+                Box::new(SanitizingFormatter::new(output).with_hyperlinks(self.hyperlinks))
+            }
+            FormatterFactoryKind::Color { rules, debug } => Box::new(
+                ColorFormatter::new(output, rules.clone(), *debug).with_hyperlinks(self.hyperlinks),
+            ),
         }
     }
 
@@ -189,17 +221,34 @@ impl FormatterFactory {
 
 pub struct PlainTextFormatter<W> {
     output: W,
+    hyperlinks: Hyperlinks,
 }
 
 impl<W> PlainTextFormatter<W> {
     pub fn new(output: W) -> PlainTextFormatter<W> {
-        Self { output }
+        Self {
+            output,
+            hyperlinks: Hyperlinks::new(false),
+        }
+    }
+
+    /// Sets whether to emit hyperlinks. Disabled by default.
+    pub fn with_hyperlinks(mut self, enabled: bool) -> Self {
+        self.hyperlinks = Hyperlinks::new(enabled);
+        self
     }
 }
 
 impl<W: Write> Write for PlainTextFormatter<W> {
     fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
-        self.output.write(data)
+        if self.hyperlinks.is_inactive() {
+            return self.output.write(data);
+        }
+        self.hyperlinks
+            .write_lines(&mut self.output, data, |output, line| {
+                output.write_all(line)
+            })?;
+        Ok(data.len())
     }
 
     fn flush(&mut self) -> Result<(), Error> {
@@ -219,21 +268,42 @@ impl<W: Write> Formatter for PlainTextFormatter<W> {
     fn pop_label(&mut self) -> io::Result<()> {
         Ok(())
     }
+
+    fn push_hyperlink(&mut self, url: &str) -> io::Result<()> {
+        self.hyperlinks.push(url);
+        Ok(())
+    }
+
+    fn pop_hyperlink(&mut self) -> io::Result<()> {
+        self.hyperlinks.pop();
+        self.hyperlinks.write_update(&mut self.output)
+    }
 }
 
 pub struct SanitizingFormatter<W> {
     output: W,
+    hyperlinks: Hyperlinks,
 }
 
 impl<W> SanitizingFormatter<W> {
     pub fn new(output: W) -> SanitizingFormatter<W> {
-        Self { output }
+        Self {
+            output,
+            hyperlinks: Hyperlinks::new(false),
+        }
+    }
+
+    /// Sets whether to emit hyperlinks. Disabled by default.
+    pub fn with_hyperlinks(mut self, enabled: bool) -> Self {
+        self.hyperlinks = Hyperlinks::new(enabled);
+        self
     }
 }
 
 impl<W: Write> Write for SanitizingFormatter<W> {
     fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
-        write_sanitized(&mut self.output, data)?;
+        self.hyperlinks
+            .write_lines(&mut self.output, data, write_sanitized)?;
         Ok(data.len())
     }
 
@@ -254,6 +324,99 @@ impl<W: Write> Formatter for SanitizingFormatter<W> {
     fn pop_label(&mut self) -> io::Result<()> {
         Ok(())
     }
+
+    fn push_hyperlink(&mut self, url: &str) -> io::Result<()> {
+        self.hyperlinks.push(url);
+        Ok(())
+    }
+
+    fn pop_hyperlink(&mut self) -> io::Result<()> {
+        self.hyperlinks.pop();
+        self.hyperlinks.write_update(&mut self.output)
+    }
+}
+
+/// Stack of hyperlinks applied to the output of a formatter.
+#[derive(Clone, Debug)]
+struct Hyperlinks {
+    /// Whether to emit hyperlinks at all.
+    enabled: bool,
+    /// The stack of currently applied hyperlink URLs. The innermost one is
+    /// emitted.
+    urls: Vec<String>,
+    /// The hyperlink URL we last opened in the output.
+    current: Option<String>,
+}
+
+impl Hyperlinks {
+    fn new(enabled: bool) -> Self {
+        Hyperlinks {
+            enabled,
+            urls: vec![],
+            current: None,
+        }
+    }
+
+    /// Returns true if no hyperlink is applied nor opened in the output.
+    fn is_inactive(&self) -> bool {
+        self.urls.is_empty() && self.current.is_none()
+    }
+
+    fn push(&mut self, url: &str) {
+        if self.enabled {
+            self.urls.push(url.to_owned());
+        }
+    }
+
+    fn pop(&mut self) {
+        self.urls.pop();
+    }
+
+    fn clear(&mut self) {
+        self.urls.clear();
+    }
+
+    /// Opens or terminates hyperlink in the output to match the stack.
+    fn write_update(&mut self, output: &mut impl Write) -> io::Result<()> {
+        let new_url = self.urls.last();
+        if new_url == self.current.as_ref() {
+            return Ok(());
+        }
+        if self.current.is_some() {
+            write_hyperlink_escape(output, "")?;
+        }
+        if let Some(url) = new_url {
+            write_hyperlink_escape(output, url)?;
+        }
+        self.current = new_url.cloned();
+        Ok(())
+    }
+
+    /// Writes `data` line by line. The hyperlink is terminated at the end of
+    /// each line, and reopened at the start of the next line.
+    fn write_lines<W: Write>(
+        &mut self,
+        output: &mut W,
+        data: &[u8],
+        mut write_line: impl FnMut(&mut W, &[u8]) -> io::Result<()>,
+    ) -> io::Result<()> {
+        if self.is_inactive() {
+            return write_line(output, data);
+        }
+        for line in data.split_inclusive(|b| *b == b'\n') {
+            self.write_update(output)?;
+            if let Some(line) = line.strip_suffix(b"\n") {
+                write_line(output, line)?;
+                let urls = mem::take(&mut self.urls);
+                self.write_update(output)?;
+                self.urls = urls;
+                output.write_all(b"\n")?;
+            } else {
+                write_line(output, line)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize)]
@@ -291,6 +454,7 @@ pub struct ColorFormatter<W: Write> {
     /// The debug string (space-separated labels) we last wrote to the output.
     /// Initialize to None to turn debug strings off.
     current_debug: Option<String>,
+    hyperlinks: Hyperlinks,
 }
 
 impl<W: Write> ColorFormatter<W> {
@@ -302,9 +466,16 @@ impl<W: Write> ColorFormatter<W> {
             cached_styles: HashMap::new(),
             current_style: Style::default(),
             current_debug: debug.then(String::new),
+            hyperlinks: Hyperlinks::new(true),
         }
     }
 
+    /// Sets whether to emit hyperlinks. Enabled by default.
+    pub fn with_hyperlinks(mut self, enabled: bool) -> Self {
+        self.hyperlinks = Hyperlinks::new(enabled);
+        self
+    }
+
     pub fn for_config(
         output: W,
         config: &StackedConfig,
@@ -416,10 +587,17 @@ impl<W: Write> ColorFormatter<W> {
             }
             self.current_debug = Some(d);
         }
-        Ok(())
+        self.hyperlinks.write_update(&mut self.output)
     }
 }
 
+/// Writes OSC 8 escape sequence. An empty `url` terminates the hyperlink.
+fn write_hyperlink_escape(output: &mut impl Write, url: &str) -> io::Result<()> {
+    // Control characters would terminate the escape sequence early.
+    let url: String = url.chars().filter(|c| !c.is_control()).collect();
+    write!(output, "\x1b]8;;{url}\x1b\\")
+}
+
 fn rules_from_config(config: &StackedConfig) -> Result<Rules, ConfigGetError> {
     config
         .table_keys("colors")
@@ -537,9 +715,11 @@ impl<W: Write> Write for ColorFormatter<W> {
                 self.write_new_style()?;
                 write_sanitized(&mut self.output, &line[..line.len() - 1])?;
                 let labels = mem::take(&mut self.labels);
+                let urls = mem::take(&mut self.hyperlinks.urls);
                 self.write_new_style()?;
                 self.output.write_all(b"\n")?;
                 self.labels = labels;
+                self.hyperlinks.urls = urls;
             } else {
                 self.write_new_style()?;
                 write_sanitized(&mut self.output, line)?;
@@ -572,6 +752,17 @@ impl<W: Write> Formatter for ColorFormatter<W> {
         }
         Ok(())
     }
+
+    fn push_hyperlink(&mut self, url: &str) -> io::Result<()> {
+        self.hyperlinks.push(url);
+        Ok(())
+    }
+
+    fn pop_hyperlink(&mut self) -> io::Result<()> {
+        self.hyperlinks.pop();
+        // Terminate the link immediately so the trailing text isn't linked.
+        self.hyperlinks.write_update(&mut self.output)
+    }
 }
 
 impl<W: Write> Drop for ColorFormatter<W> {
@@ -579,6 +770,7 @@ impl<W: Write> Drop for ColorFormatter<W> {
         // If a `ColorFormatter` was dropped without popping all labels first (perhaps
         // because of an error), let's still try to reset any currently active style.
         self.labels.clear();
+        self.hyperlinks.clear();
         self.write_new_style().ok();
     }
 }
@@ -600,6 +792,9 @@ pub struct FormatRecorder {
 enum FormatOp {
     PushLabel(String),
     PopLabel,
+    PushHyperlink(String),
+    PopHyperlink,
+    EndCell,
     RawEscapeSequence(Vec<u8>),
 }
 
@@ -648,6 +843,9 @@ impl FormatRecorder {
             match op {
                 FormatOp::PushLabel(label) => formatter.push_label(label)?,
                 FormatOp::PopLabel => formatter.pop_label()?,
+                FormatOp::PushHyperlink(url) => formatter.push_hyperlink(url)?,
+                FormatOp::PopHyperlink => formatter.pop_hyperlink()?,
+                FormatOp::EndCell => formatter.end_cell()?,
                 FormatOp::RawEscapeSequence(raw_escape_sequence) => {
                     formatter.raw()?.write_all(raw_escape_sequence)?;
                 }
@@ -655,6 +853,65 @@ impl FormatRecorder {
         }
         flush_data(formatter, self.data.len())
     }
+
+    /// Returns true if the recorded content contains
+    /// [`Formatter::end_cell()`] boundaries.
+    pub fn has_cells(&self) -> bool {
+        self.ops.iter().any(|(_, op)| *op == FormatOp::EndCell)
+    }
+
+    /// Splits the recorded content at [`Formatter::end_cell()`] boundaries.
+    ///
+    /// Labels and hyperlinks spanning multiple cells are closed at the end of
+    /// each cell, and reopened at the start of the next cell.
+    pub fn split_cells(&self) -> Vec<FormatRecorder> {
+        let mut cells = vec![];
+        let mut cell = FormatRecorder::new();
+        let mut open_ops: Vec<&FormatOp> = vec![];
+        let mut last_pos = 0;
+        for (pos, op) in &self.ops {
+            cell.data.extend_from_slice(&self.data[last_pos..*pos]);
+            last_pos = *pos;
+            match op {
+                FormatOp::PushLabel(_) | FormatOp::PushHyperlink(_) => open_ops.push(op),
+                FormatOp::PopLabel => {
+                    let pos = open_ops
+                        .iter()
+                        .rposition(|op| matches!(op, FormatOp::PushLabel(_)));
+                    if let Some(pos) = pos {
+                        open_ops.remove(pos);
+                    }
+                }
+                FormatOp::PopHyperlink => {
+                    let pos = open_ops
+                        .iter()
+                        .rposition(|op| matches!(op, FormatOp::PushHyperlink(_)));
+                    if let Some(pos) = pos {
+                        open_ops.remove(pos);
+                    }
+                }
+                FormatOp::EndCell => {
+                    for op in open_ops.iter().rev() {
+                        match op {
+                            FormatOp::PushLabel(_) => cell.push_op(FormatOp::PopLabel),
+                            FormatOp::PushHyperlink(_) => cell.push_op(FormatOp::PopHyperlink),
+                            _ => unreachable!(),
+                        }
+                    }
+                    cells.push(mem::take(&mut cell));
+                    for &op in &open_ops {
+                        cell.push_op(op.clone());
+                    }
+                    continue;
+                }
+                FormatOp::RawEscapeSequence(_) => {}
+            }
+            cell.push_op(op.clone());
+        }
+        cell.data.extend_from_slice(&self.data[last_pos..]);
+        cells.push(cell);
+        cells
+    }
 }
 
 impl Write for FormatRecorder {
@@ -695,6 +952,21 @@ impl Formatter for FormatRecorder {
         self.push_op(FormatOp::PopLabel);
         Ok(())
     }
+
+    fn push_hyperlink(&mut self, url: &str) -> io::Result<()> {
+        self.push_op(FormatOp::PushHyperlink(url.to_owned()));
+        Ok(())
+    }
+
+    fn pop_hyperlink(&mut self) -> io::Result<()> {
+        self.push_op(FormatOp::PopHyperlink);
+        Ok(())
+    }
+
+    fn end_cell(&mut self) -> io::Result<()> {
+        self.push_op(FormatOp::EndCell);
+        Ok(())
+    }
 }
 
 fn write_sanitized(output: &mut impl Write, buf: &[u8]) -> Result<(), Error> {
@@ -1219,6 +1491,94 @@ mod tests {
         insta::assert_snapshot!(String::from_utf8(output).unwrap(), @"[38;5;2m<<outer inner:: inside >>[39m");
     }
 
+    #[test]
+    fn test_color_formatter_hyperlink() {
+        let config = config_from_string(r#" colors.inner = "red" "#);
+        let mut output: Vec<u8> = vec![];
+        let mut formatter = ColorFormatter::for_config(&mut output, &config, false).unwrap();
+        write!(formatter, " before ").unwrap();
+        formatter.push_hyperlink("https://example.com/").unwrap();
+        formatter.push_label("inner").unwrap();
+        write!(formatter, " link ").unwrap();
+        formatter.pop_label().unwrap();
+        formatter.pop_hyperlink().unwrap();
+        write!(formatter, " after ").unwrap();
+        drop(formatter);
+        insta::assert_snapshot!(String::from_utf8(output).unwrap(), @r" before [38;5;1m]8;;https://example.com/\ link [39m]8;;\ after");
+    }
+
+    #[test]
+    fn test_color_formatter_hyperlink_multiline() {
+        // The hyperlink should be terminated at each newline, and control
+        // characters in URL should be removed.
+        let config = config_from_string("");
+        let mut output: Vec<u8> = vec![];
+        let mut formatter = ColorFormatter::for_config(&mut output, &config, false).unwrap();
+        formatter
+            .push_hyperlink("https://\x1bexample.com/")
+            .unwrap();
+        write!(formatter, " line1 \n line2 ").unwrap();
+        formatter.pop_hyperlink().unwrap();
+        drop(formatter);
+        insta::assert_snapshot!(String::from_utf8(output).unwrap(), @r"
+        ]8;;https://example.com/\ line1 ]8;;\
+        ]8;;https://example.com/\ line2 ]8;;\
+        ");
+    }
+
+    #[test]
+    fn test_color_formatter_hyperlink_empty() {
+        // Nothing should be emitted if the link text is empty.
+        let config = config_from_string("");
+        let mut output: Vec<u8> = vec![];
+        let mut formatter = ColorFormatter::for_config(&mut output, &config, false).unwrap();
+        formatter.push_hyperlink("https://example.com/").unwrap();
+        formatter.pop_hyperlink().unwrap();
+        drop(formatter);
+        insta::assert_snapshot!(String::from_utf8(output).unwrap(), @"");
+    }
+
+    #[test]
+    fn test_plaintext_formatter_hyperlink() {
+        let mut output: Vec<u8> = vec![];
+        let mut formatter = PlainTextFormatter::new(&mut output);
+        formatter.push_hyperlink("https://example.com/").unwrap();
+        write!(formatter, " link ").unwrap();
+        formatter.pop_hyperlink().unwrap();
+        insta::assert_snapshot!(String::from_utf8(output).unwrap(), @" link ");
+    }
+
+    #[test]
+    fn test_plaintext_formatter_hyperlink_enabled() {
+        let mut output: Vec<u8> = vec![];
+        let mut formatter = PlainTextFormatter::new(&mut output).with_hyperlinks(true);
+        write!(formatter, " before ").unwrap();
+        formatter.push_hyperlink("https://example.com/").unwrap();
+        write!(formatter, " line1 \n line2 ").unwrap();
+        formatter.pop_hyperlink().unwrap();
+        write!(formatter, " after ").unwrap();
+        insta::assert_snapshot!(String::from_utf8(output).unwrap(), @r"
+         before ]8;;https://example.com/\ line1 ]8;;\
+        ]8;;https://example.com/\ line2 ]8;;\ after
+        ");
+    }
+
+    #[test]
+    fn test_color_formatter_hyperlink_disabled() {
+        let config = config_from_string(r#" colors.inner = "red" "#);
+        let mut output: Vec<u8> = vec![];
+        let mut formatter = ColorFormatter::for_config(&mut output, &config, false)
+            .unwrap()
+            .with_hyperlinks(false);
+        formatter.push_hyperlink("https://example.com/").unwrap();
+        formatter.push_label("inner").unwrap();
+        write!(formatter, " link ").unwrap();
+        formatter.pop_label().unwrap();
+        formatter.pop_hyperlink().unwrap();
+        drop(formatter);
+        insta::assert_snapshot!(String::from_utf8(output).unwrap(), @"[38;5;1m link [39m");
+    }
+
     #[test]
     fn test_heading_labeled_writer() {
         let config = config_from_string(
@@ -1301,6 +1661,66 @@ mod tests {
             @"<< outer1 >>[38;5;1m<< inner1  inner2 >>[39m<< outer2 >>");
     }
 
+    #[test]
+    fn test_format_recorder_hyperlink() {
+        let mut recorder = FormatRecorder::new();
+        write!(recorder, " outer1 ").unwrap();
+        recorder.push_hyperlink("https://example.com/").unwrap();
+        write!(recorder, " link ").unwrap();
+        recorder.pop_hyperlink().unwrap();
+        write!(recorder, " outer2 ").unwrap();
+
+        // Replayed output should be hyperlinked.
+        let config = config_from_string("");
+        let mut output: Vec<u8> = vec![];
+        let mut formatter = ColorFormatter::for_config(&mut output, &config, false).unwrap();
+        recorder.replay(&mut formatter).unwrap();
+        drop(formatter);
+        insta::assert_snapshot!(String::from_utf8(output).unwrap(), @r" outer1 ]8;;https://example.com/\ link ]8;;\ outer2");
+    }
+
+    #[test]
+    fn test_format_recorder_split_cells() {
+        let mut recorder = FormatRecorder::new();
+        recorder.push_label("outer").unwrap();
+        write!(recorder, "a").unwrap();
+        recorder.end_cell().unwrap();
+        recorder.push_label("inner").unwrap();
+        write!(recorder, "b").unwrap();
+        recorder.end_cell().unwrap();
+        write!(recorder, "c").unwrap();
+        recorder.pop_label().unwrap();
+        write!(recorder, "d").unwrap();
+        recorder.pop_label().unwrap();
+
+        // Labels spanning multiple cells should be reopened in each cell.
+        let config = config_from_string("");
+        let cells = recorder.split_cells();
+        let outputs = cells
+            .iter()
+            .map(|cell| {
+                let mut output: Vec<u8> = vec![];
+                let mut formatter = ColorFormatter::for_config(&mut output, &config, true).unwrap();
+                cell.replay(&mut formatter).unwrap();
+                drop(formatter);
+                String::from_utf8(output).unwrap()
+            })
+            .collect_vec();
+        insta::assert_debug_snapshot!(outputs, @r#"
+        [
+            "<<outer::a>>",
+            "<<outer inner::b>>",
+            "<<outer inner::c>><<outer::d>>",
+        ]
+        "#);
+
+        // Cells are separated by a space if replayed to non-table formatter.
+        let mut output: Vec<u8> = vec![];
+        let mut formatter = PlainTextFormatter::new(&mut output);
+        recorder.replay(&mut formatter).unwrap();
+        insta::assert_snapshot!(String::from_utf8(output).unwrap(), @"a b cd");
+    }
+
     #[test]
     fn test_raw_format_recorder() {
         // Note: similar to test_format_recorder above
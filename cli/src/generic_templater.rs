@@ -15,6 +15,7 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
 
+use jj_lib::backend::Timestamp;
 use jj_lib::settings::UserSettings;
 
 use crate::template_builder;
@@ -27,6 +28,7 @@ use crate::template_parser;
 use crate::template_parser::FunctionCallNode;
 use crate::template_parser::TemplateDiagnostics;
 use crate::template_parser::TemplateParseResult;
+use crate::templater::MemoScope;
 use crate::templater::Template;
 use crate::templater::TemplateProperty;
 
@@ -165,6 +167,13 @@ impl<'a, C: 'a> IntoTemplateProperty<'a> for GenericTemplatePropertyKind<'a, C>
         }
     }
 
+    fn try_into_timestamp(self) -> Option<Box<dyn TemplateProperty<Output = Timestamp> + 'a>> {
+        match self {
+            GenericTemplatePropertyKind::Core(property) => property.try_into_timestamp(),
+            GenericTemplatePropertyKind::Self_(_) => None,
+        }
+    }
+
     fn try_into_plain_text(self) -> Option<Box<dyn TemplateProperty<Output = String> + 'a>> {
         match self {
             GenericTemplatePropertyKind::Core(property) => property.try_into_plain_text(),
@@ -201,6 +210,17 @@ impl<'a, C: 'a> IntoTemplateProperty<'a> for GenericTemplatePropertyKind<'a, C>
             (GenericTemplatePropertyKind::Self_(_), _) => None,
         }
     }
+
+    fn try_into_memoized(self, scope: &MemoScope) -> Option<Box<dyn Fn() -> Self + 'a>> {
+        match self {
+            GenericTemplatePropertyKind::Core(property) => {
+                let make = property.try_into_memoized(scope)?;
+                Some(Box::new(move || GenericTemplatePropertyKind::Core(make())))
+            }
+            // The context type isn't necessarily cloneable.
+            GenericTemplatePropertyKind::Self_(_) => None,
+        }
+    }
 }
 
 /// Function that translates keyword (or 0-ary method call node of the self type
@@ -14,6 +14,7 @@
 
 //! Git utilities shared by various commands.
 
+use std::collections::HashMap;
 use std::error;
 use std::io;
 use std::io::Read;
@@ -284,6 +285,55 @@ pub fn with_remote_git_callbacks<T>(ui: &Ui, f: impl FnOnce(git::RemoteCallbacks
     result
 }
 
+/// Copies Git LFS objects added by the commits fetched from the `remotes`.
+///
+/// The commits are selected in the same way as `jj git push`, i.e. the commits
+/// reachable from the new remote bookmarks, but not from the old ones.
+pub fn fetch_git_lfs_objects(
+    ui: &Ui,
+    repo: &dyn Repo,
+    stats: &GitImportStats,
+    remotes: &[String],
+) -> Result<(), CommandError> {
+    for remote_name in remotes {
+        let changed_refs = stats
+            .changed_remote_refs
+            .iter()
+            .filter_map(|(ref_name, change)| match ref_name {
+                RefName::RemoteBranch { branch, remote } if remote == remote_name => {
+                    Some((branch.as_str(), change))
+                }
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+        if changed_refs.is_empty() {
+            continue;
+        }
+        let new_heads = changed_refs
+            .values()
+            .flat_map(|(_, new_target)| new_target.added_ids())
+            .cloned()
+            .collect_vec();
+        let old_heads = repo
+            .view()
+            .remote_bookmarks(remote_name)
+            .flat_map(|(branch, remote_ref)| match changed_refs.get(branch) {
+                Some((old_remote_ref, _)) => old_remote_ref.target.added_ids(),
+                None => remote_ref.target.added_ids(),
+            })
+            .cloned()
+            .collect_vec();
+        let count = git::fetch_lfs_objects(repo, remote_name, &old_heads, &new_heads)?;
+        if count > 0 {
+            writeln!(
+                ui.status(),
+                "Fetched {count} LFS objects from {remote_name}"
+            )?;
+        }
+    }
+    Ok(())
+}
+
 pub fn print_git_import_stats(
     ui: &Ui,
     repo: &dyn Repo,
@@ -2,6 +2,8 @@ use std::borrow::Cow;
 use std::path::Path;
 use std::sync::Arc;
 
+use bstr::BString;
+use bstr::ByteVec as _;
 use futures::StreamExt;
 use futures::TryFutureExt;
 use futures::TryStreamExt;
@@ -11,8 +13,11 @@ use jj_lib::backend::BackendResult;
 use jj_lib::backend::FileId;
 use jj_lib::backend::MergedTreeId;
 use jj_lib::backend::TreeValue;
+use jj_lib::conflicts::choose_materialized_conflict_marker_len;
 use jj_lib::conflicts::materialize_merge_result_to_bytes;
+use jj_lib::conflicts::materialize_merge_result_to_bytes_with_marker_len;
 use jj_lib::conflicts::materialize_tree_value;
+use jj_lib::conflicts::update_from_content;
 use jj_lib::conflicts::ConflictMarkerStyle;
 use jj_lib::conflicts::MaterializedTreeValue;
 use jj_lib::diff::Diff;
@@ -558,13 +563,69 @@ pub fn edit_diff_builtin(
     Ok(tree_id)
 }
 
+/// Hunks of a conflicted file shown in the built-in merge editor.
+struct MergeFileHunks {
+    hunks: Vec<Merge<BString>>,
+    /// Index of the hunk which each section of the file belongs to.
+    section_hunks: Vec<usize>,
+}
+
+impl MergeFileHunks {
+    /// Returns the contents of the file with the selected lines of each
+    /// conflicting hunk, and whether any hunk is still conflicted.
+    ///
+    /// Conflicting hunks without selected lines are materialized as conflicts.
+    /// Returns `None` if no lines were selected in any conflicting hunk.
+    fn to_selected_contents(
+        &self,
+        sections: &[scm_record::Section],
+        conflict_marker_style: ConflictMarkerStyle,
+        conflict_marker_len: usize,
+    ) -> Option<(BString, bool)> {
+        let mut selected_hunks: Vec<Option<BString>> = vec![None; self.hunks.len()];
+        for (section, &hunk_index) in sections.iter().zip(&self.section_hunks) {
+            if let scm_record::Section::Changed { lines } = section {
+                for line in lines.iter().filter(|line| line.is_checked) {
+                    selected_hunks[hunk_index]
+                        .get_or_insert_with(BString::default)
+                        .push_str(line.line.as_bytes());
+                }
+            }
+        }
+
+        let mut contents = BString::default();
+        let mut num_selected = 0;
+        let mut num_conflicts = 0;
+        for (hunk, selected) in self.hunks.iter().zip(selected_hunks) {
+            if let Some(resolved) = hunk.as_resolved() {
+                contents.push_str(resolved);
+            } else if let Some(selected) = selected {
+                contents.push_str(selected);
+                num_selected += 1;
+            } else {
+                contents.push_str(materialize_merge_result_to_bytes_with_marker_len(
+                    hunk,
+                    conflict_marker_style,
+                    conflict_marker_len,
+                ));
+                num_conflicts += 1;
+            }
+        }
+        if num_selected == 0 && num_conflicts > 0 {
+            return None;
+        }
+        Some((contents, num_conflicts > 0))
+    }
+}
+
 fn make_merge_sections(
     merge_result: MergeResult,
-) -> Result<Vec<scm_record::Section<'static>>, BuiltinToolError> {
+) -> Result<(Vec<scm_record::Section<'static>>, MergeFileHunks), BuiltinToolError> {
     let mut sections = Vec::new();
-    match merge_result {
+    let mut section_hunks = Vec::new();
+    let hunks = match merge_result {
         MergeResult::Resolved(buf) => {
-            let contents = buf_to_file_contents(None, buf.into());
+            let contents = buf_to_file_contents(None, buf.clone().into());
             let section = match contents {
                 FileContents::Absent => None,
                 FileContents::Text {
@@ -585,65 +646,76 @@ fn make_merge_sections(
             };
             if let Some(section) = section {
                 sections.push(section);
+                section_hunks.push(0);
             }
+            vec![Merge::resolved(buf)]
         }
         MergeResult::Conflict(hunks) => {
-            for hunk in hunks {
-                let section = match hunk.into_resolved() {
-                    Ok(contents) => {
-                        let contents = std::str::from_utf8(&contents).map_err(|err| {
-                            BuiltinToolError::DecodeUtf8 {
-                                source: err,
-                                item: "unchanged hunk",
-                            }
-                        })?;
-                        scm_record::Section::Unchanged {
-                            lines: contents
-                                .split_inclusive('\n')
-                                .map(|line| Cow::Owned(line.to_owned()))
-                                .collect(),
+            for (hunk_index, hunk) in hunks.iter().enumerate() {
+                if let Some(contents) = hunk.as_resolved() {
+                    let contents = std::str::from_utf8(contents).map_err(|err| {
+                        BuiltinToolError::DecodeUtf8 {
+                            source: err,
+                            item: "unchanged hunk",
                         }
+                    })?;
+                    sections.push(scm_record::Section::Unchanged {
+                        lines: contents
+                            .split_inclusive('\n')
+                            .map(|line| Cow::Owned(line.to_owned()))
+                            .collect(),
+                    });
+                    section_hunks.push(hunk_index);
+                    continue;
+                }
+                // Each side and base of the conflict is a separate section
+                // following a label, so that a side can be picked as a whole.
+                // The labels are left out of the result.
+                for (term_index, contents) in hunk.iter().enumerate() {
+                    let contents = std::str::from_utf8(contents).map_err(|err| {
+                        BuiltinToolError::DecodeUtf8 {
+                            source: err,
+                            item: "conflicting hunk",
+                        }
+                    })?;
+                    let (marker, term) = if term_index % 2 == 0 {
+                        ("+++++++", "side")
+                    } else {
+                        ("-------", "base")
+                    };
+                    let number = term_index / 2 + 1;
+                    let empty = if contents.is_empty() { " (empty)" } else { "" };
+                    sections.push(scm_record::Section::Unchanged {
+                        lines: vec![Cow::Owned(format!(
+                            "{marker} Contents of {term} #{number}{empty}\n"
+                        ))],
+                    });
+                    section_hunks.push(hunk_index);
+                    let lines = make_section_changed_lines(contents, scm_record::ChangeType::Added);
+                    if !lines.is_empty() {
+                        sections.push(scm_record::Section::Changed { lines });
+                        section_hunks.push(hunk_index);
                     }
-                    Err(merge) => {
-                        let lines: Vec<scm_record::SectionChangedLine> = merge
-                            .iter()
-                            .zip(
-                                [
-                                    scm_record::ChangeType::Added,
-                                    scm_record::ChangeType::Removed,
-                                ]
-                                .into_iter()
-                                .cycle(),
-                            )
-                            .map(|(contents, change_type)| -> Result<_, BuiltinToolError> {
-                                let contents = std::str::from_utf8(contents).map_err(|err| {
-                                    BuiltinToolError::DecodeUtf8 {
-                                        source: err,
-                                        item: "conflicting hunk",
-                                    }
-                                })?;
-                                let changed_lines =
-                                    make_section_changed_lines(contents, change_type);
-                                Ok(changed_lines)
-                            })
-                            .flatten_ok()
-                            .try_collect()?;
-                        scm_record::Section::Changed { lines }
-                    }
-                };
-                sections.push(section);
+                }
             }
+            hunks
         }
-    }
-    Ok(sections)
+    };
+    Ok((
+        sections,
+        MergeFileHunks {
+            hunks,
+            section_hunks,
+        },
+    ))
 }
 
 fn make_merge_file(
     merge_tool_file: &MergeToolFile,
-) -> Result<scm_record::File<'static>, BuiltinToolError> {
+) -> Result<(scm_record::File<'static>, MergeFileHunks), BuiltinToolError> {
     let merge_result = files::merge(&merge_tool_file.content);
-    let sections = make_merge_sections(merge_result)?;
-    Ok(scm_record::File {
+    let (sections, hunks) = make_merge_sections(merge_result)?;
+    let file = scm_record::File {
         old_path: None,
         // Path for displaying purposes, not for file access.
         path: Cow::Owned(
@@ -653,33 +725,90 @@ fn make_merge_file(
         ),
         file_mode: None,
         sections,
-    })
+    };
+    Ok((file, hunks))
+}
+
+/// Writes the selected lines of the conflicted `files` to a new tree.
+///
+/// Files in which no lines of the conflicting hunks were selected keep their
+/// conflicts.
+fn apply_merge_builtin(
+    tree: &MergedTree,
+    merge_tool_files: &[MergeToolFile],
+    files: &[scm_record::File],
+    file_hunks: &[MergeFileHunks],
+    conflict_marker_style: ConflictMarkerStyle,
+) -> BackendResult<MergedTreeId> {
+    let store = tree.store();
+    let mut tree_builder = MergedTreeBuilder::new(tree.id());
+    for ((merge_tool_file, file), hunks) in merge_tool_files.iter().zip(files).zip(file_hunks) {
+        let conflict_marker_len = choose_materialized_conflict_marker_len(&merge_tool_file.content);
+        let Some((contents, has_conflicts)) =
+            hunks.to_selected_contents(&file.sections, conflict_marker_style, conflict_marker_len)
+        else {
+            continue;
+        };
+        let path = &merge_tool_file.repo_path;
+        let new_file_ids = if has_conflicts {
+            update_from_content(
+                &merge_tool_file.file_merge,
+                store,
+                path,
+                &contents,
+                conflict_marker_style,
+                conflict_marker_len,
+            )
+            .block_on()?
+        } else {
+            let file_id = store
+                .write_file(path, &mut contents.as_slice())
+                .block_on()?;
+            Merge::normal(file_id)
+        };
+        tree_builder.set_or_remove(path.clone(), merge_tool_file.new_tree_value(new_file_ids));
+    }
+    tree_builder.write_tree(store)
 }
 
 pub fn edit_merge_builtin(
     tree: &MergedTree,
     merge_tool_files: &[MergeToolFile],
+    conflict_marker_style: ConflictMarkerStyle,
 ) -> Result<MergedTreeId, BuiltinToolError> {
-    let mut input = scm_record::helpers::CrosstermInput;
+    edit_merge_builtin_with_input(
+        tree,
+        merge_tool_files,
+        conflict_marker_style,
+        &mut scm_record::helpers::CrosstermInput,
+    )
+}
+
+fn edit_merge_builtin_with_input(
+    tree: &MergedTree,
+    merge_tool_files: &[MergeToolFile],
+    conflict_marker_style: ConflictMarkerStyle,
+    input: &mut dyn scm_record::RecordInput,
+) -> Result<MergedTreeId, BuiltinToolError> {
+    let (files, file_hunks): (Vec<_>, Vec<_>) = merge_tool_files
+        .iter()
+        .map(make_merge_file)
+        .process_results(|iter| iter.unzip())?;
     let recorder = scm_record::Recorder::new(
         scm_record::RecordState {
             is_read_only: false,
-            files: merge_tool_files.iter().map(make_merge_file).try_collect()?,
+            files,
             commits: Default::default(),
         },
-        &mut input,
+        input,
     );
     let state = recorder.run()?;
-
-    apply_diff_builtin(
-        tree.store(),
+    apply_merge_builtin(
         tree,
-        tree,
-        merge_tool_files
-            .iter()
-            .map(|file| file.repo_path.clone())
-            .collect_vec(),
+        merge_tool_files,
         &state.files,
+        &file_hunks,
+        conflict_marker_style,
     )
     .map_err(BuiltinToolError::BackendError)
 }
@@ -1102,9 +1231,14 @@ mod tests {
             .block_on()
             .unwrap();
         let merge_result = files::merge(&content);
-        let sections = make_merge_sections(merge_result).unwrap();
-        insta::assert_debug_snapshot!(sections, @r###"
+        let (sections, _hunks) = make_merge_sections(merge_result).unwrap();
+        insta::assert_debug_snapshot!(sections, @r#"
         [
+            Unchanged {
+                lines: [
+                    "+++++++ Contents of side #1\n",
+                ],
+            },
             Changed {
                 lines: [
                     SectionChangedLine {
@@ -1112,11 +1246,29 @@ mod tests {
                         change_type: Added,
                         line: "left 1\n",
                     },
+                ],
+            },
+            Unchanged {
+                lines: [
+                    "------- Contents of base #1\n",
+                ],
+            },
+            Changed {
+                lines: [
                     SectionChangedLine {
                         is_checked: false,
-                        change_type: Removed,
+                        change_type: Added,
                         line: "base 1\n",
                     },
+                ],
+            },
+            Unchanged {
+                lines: [
+                    "+++++++ Contents of side #2\n",
+                ],
+            },
+            Changed {
+                lines: [
                     SectionChangedLine {
                         is_checked: false,
                         change_type: Added,
@@ -1131,6 +1283,11 @@ mod tests {
                     "base 4\n",
                 ],
             },
+            Unchanged {
+                lines: [
+                    "+++++++ Contents of side #1\n",
+                ],
+            },
             Changed {
                 lines: [
                     SectionChangedLine {
@@ -1138,11 +1295,29 @@ mod tests {
                         change_type: Added,
                         line: "left 5\n",
                     },
+                ],
+            },
+            Unchanged {
+                lines: [
+                    "------- Contents of base #1\n",
+                ],
+            },
+            Changed {
+                lines: [
                     SectionChangedLine {
                         is_checked: false,
-                        change_type: Removed,
+                        change_type: Added,
                         line: "base 5\n",
                     },
+                ],
+            },
+            Unchanged {
+                lines: [
+                    "+++++++ Contents of side #2\n",
+                ],
+            },
+            Changed {
+                lines: [
                     SectionChangedLine {
                         is_checked: false,
                         change_type: Added,
@@ -1151,6 +1326,194 @@ mod tests {
                 ],
             },
         ]
-        "###);
+        "#);
+    }
+
+    #[test]
+    fn test_edit_merge_builtin_n_way() {
+        let test_repo = TestRepo::init();
+        let store = test_repo.repo.store();
+
+        let path = RepoPath::from_internal_string("file");
+        let create_tree =
+            |content| testutils::create_single_tree(&test_repo.repo, &[(path, content)]);
+        let base_tree = create_tree("line 1\nbase\nline 3\nbase\n");
+        let tree = MergedTree::new(Merge::from_removes_adds(
+            vec![base_tree.clone(), base_tree],
+            vec![
+                create_tree("line 1\nside 1\nline 3\nside 1\n"),
+                create_tree("line 1\nside 2\nline 3\nside 2\n"),
+                create_tree("line 1\nside 3a\nside 3b\nline 3\nside 3\n"),
+            ],
+        ));
+        let merge_tool_files = [MergeToolFile::from_tree_and_path(&tree, path).unwrap()];
+        let (mut file, hunks) = make_merge_file(&merge_tool_files[0]).unwrap();
+        // Each side and base is a separate section following a label
+        insta::assert_debug_snapshot!(&file.sections[..6], @r#"
+        [
+            Unchanged {
+                lines: [
+                    "line 1\n",
+                ],
+            },
+            Unchanged {
+                lines: [
+                    "+++++++ Contents of side #1\n",
+                ],
+            },
+            Changed {
+                lines: [
+                    SectionChangedLine {
+                        is_checked: false,
+                        change_type: Added,
+                        line: "side 1\n",
+                    },
+                ],
+            },
+            Unchanged {
+                lines: [
+                    "------- Contents of base #1\n",
+                ],
+            },
+            Changed {
+                lines: [
+                    SectionChangedLine {
+                        is_checked: false,
+                        change_type: Added,
+                        line: "base\n",
+                    },
+                ],
+            },
+            Unchanged {
+                lines: [
+                    "+++++++ Contents of side #2\n",
+                ],
+            },
+        ]
+        "#);
+
+        let resolve = |file: &scm_record::File| {
+            let tree_id = apply_merge_builtin(
+                &tree,
+                &merge_tool_files,
+                std::slice::from_ref(file),
+                std::slice::from_ref(&hunks),
+                ConflictMarkerStyle::Diff,
+            )
+            .unwrap();
+            store.get_root_tree(&tree_id).unwrap()
+        };
+        let read_file = |tree: &MergedTree| {
+            let value = tree.path_value(path).unwrap();
+            let Ok(Some(TreeValue::File { id, .. })) = value.into_resolved() else {
+                panic!("file should be resolved");
+            };
+            String::from_utf8(testutils::read_file(store, path, &id)).unwrap()
+        };
+        // Returns the lines of the changed section at `index`
+        fn changed_lines<'a>(
+            file: &'a mut scm_record::File<'static>,
+            index: usize,
+        ) -> &'a mut [scm_record::SectionChangedLine<'static>] {
+            file.sections
+                .iter_mut()
+                .filter_map(|section| match section {
+                    scm_record::Section::Changed { lines } => Some(lines),
+                    _ => None,
+                })
+                .nth(index)
+                .unwrap()
+        }
+
+        // Nothing selected keeps the conflict
+        assert_eq!(resolve(&file).id(), tree.id());
+
+        // Pick the second side in the first hunk, and one line of the third
+        // side. The second hunk is still conflicted.
+        for line in changed_lines(&mut file, 2) {
+            line.is_checked = true;
+        }
+        changed_lines(&mut file, 4)[1].is_checked = true;
+        let partial_tree = resolve(&file);
+        let conflict = partial_tree.path_value(path).unwrap();
+        assert!(!conflict.is_resolved());
+        let merge_tool_file = MergeToolFile::from_tree_and_path(&partial_tree, path).unwrap();
+        insta::assert_snapshot!(
+            materialize_merge_result_to_bytes(&merge_tool_file.content, ConflictMarkerStyle::Diff),
+            @r"
+        line 1
+        side 2
+        side 3b
+        line 3
+        <<<<<<< Conflict 1 of 1
+        %%%%%%% Changes from base #1 to side #1
+        -base
+        +side 1
+        %%%%%%% Changes from base #2 to side #2
+        -base
+        +side 2
+        +++++++ Contents of side #3
+        side 3
+        >>>>>>> Conflict 1 of 1 ends
+        ");
+
+        // Pick the first side in the second hunk
+        for line in changed_lines(&mut file, 5) {
+            line.is_checked = true;
+        }
+        insta::assert_snapshot!(read_file(&resolve(&file)), @r"
+        line 1
+        side 2
+        side 3b
+        line 3
+        side 1
+        ");
+    }
+
+    #[test]
+    fn test_edit_merge_builtin_input() {
+        let test_repo = TestRepo::init();
+        let store = test_repo.repo.store();
+
+        let path = RepoPath::from_internal_string("file");
+        let create_tree =
+            |content| testutils::create_single_tree(&test_repo.repo, &[(path, content)]);
+        let base_tree = create_tree("base\n");
+        let tree = MergedTree::new(Merge::from_removes_adds(
+            vec![base_tree.clone(), base_tree],
+            vec![create_tree("a\n"), create_tree("b\n"), create_tree("c\n")],
+        ));
+        let merge_tool_files = [MergeToolFile::from_tree_and_path(&tree, path).unwrap()];
+        let edit = |events: Vec<scm_record::Event>| {
+            let mut input = scm_record::helpers::TestingInput::new(80, 24, events);
+            let tree_id = edit_merge_builtin_with_input(
+                &tree,
+                &merge_tool_files,
+                ConflictMarkerStyle::Diff,
+                &mut input,
+            )
+            .unwrap();
+            store.get_root_tree(&tree_id).unwrap()
+        };
+
+        // Accepting without selecting anything keeps the conflict
+        assert_eq!(edit(vec![scm_record::Event::QuitAccept]).id(), tree.id());
+
+        // Pick the second side
+        let merged_tree = edit(vec![
+            scm_record::Event::ExpandItem,
+            scm_record::Event::FocusNext,
+            scm_record::Event::FocusNext,
+            scm_record::Event::FocusNext,
+            scm_record::Event::FocusNext,
+            scm_record::Event::FocusNext,
+            scm_record::Event::ToggleItem,
+            scm_record::Event::QuitAccept,
+        ]);
+        let value = merged_tree.path_value(path).unwrap();
+        let Ok(Some(TreeValue::File { id, .. })) = value.into_resolved() else {
+            panic!("file should be resolved");
+        };
+        assert_eq!(testutils::read_file(store, path, &id), b"b\n");
     }
 }
@@ -9,6 +9,7 @@ use std::sync::Arc;
 use futures::StreamExt;
 use jj_lib::backend::MergedTreeId;
 use jj_lib::conflicts::ConflictMarkerStyle;
+use jj_lib::content_filter::ContentFilters;
 use jj_lib::fsmonitor::FsmonitorSettings;
 use jj_lib::gitignore::GitIgnoreFile;
 use jj_lib::local_working_copy::TreeState;
@@ -17,7 +18,7 @@ use jj_lib::matchers::EverythingMatcher;
 use jj_lib::matchers::Matcher;
 use jj_lib::merged_tree::MergedTree;
 use jj_lib::merged_tree::TreeDiffEntry;
-use jj_lib::repo_path::RepoPathBuf;
+use jj_lib::sparse::SparsePattern;
 use jj_lib::store::Store;
 use jj_lib::working_copy::CheckoutError;
 use jj_lib::working_copy::CheckoutOptions;
@@ -85,7 +86,7 @@ fn check_out(
     wc_dir: PathBuf,
     state_dir: PathBuf,
     tree: &MergedTree,
-    sparse_patterns: Vec<RepoPathBuf>,
+    sparse_patterns: Vec<SparsePattern>,
     options: &CheckoutOptions,
 ) -> Result<TreeState, DiffCheckoutError> {
     std::fs::create_dir(&wc_dir).map_err(DiffCheckoutError::SetUpDir)?;
@@ -142,7 +143,7 @@ pub(crate) fn check_out_trees(
 ) -> Result<DiffWorkingCopies, DiffCheckoutError> {
     let changed_files: Vec<_> = left_tree
         .diff_stream(right_tree, matcher)
-        .map(|TreeDiffEntry { path, .. }| path)
+        .map(|TreeDiffEntry { path, .. }| SparsePattern::from_prefix(path))
         .collect()
         .block_on();
 
@@ -299,6 +300,7 @@ diff editing in mind and be a little inaccurate.
             start_tracking_matcher: &EverythingMatcher,
             max_new_file_size: u64::MAX,
             conflict_marker_style,
+            content_filters: ContentFilters::default(),
         })?;
         Ok(output_tree_state.current_tree_id().clone())
     }
@@ -9,12 +9,12 @@ use std::sync::Arc;
 use bstr::BString;
 use itertools::Itertools;
 use jj_lib::backend::MergedTreeId;
-use jj_lib::backend::TreeValue;
 use jj_lib::conflicts;
 use jj_lib::conflicts::choose_materialized_conflict_marker_len;
 use jj_lib::conflicts::materialize_merge_result_to_bytes_with_marker_len;
 use jj_lib::conflicts::ConflictMarkerStyle;
 use jj_lib::conflicts::MIN_CONFLICT_MARKER_LEN;
+use jj_lib::content_filter::ContentFilters;
 use jj_lib::gitignore::GitIgnoreFile;
 use jj_lib::matchers::Matcher;
 use jj_lib::merge::Merge;
@@ -183,7 +183,7 @@ fn run_mergetool_external_single_file(
 ) -> Result<(), ConflictResolveError> {
     let MergeToolFile {
         repo_path,
-        conflict,
+        conflict: _,
         file_merge,
         content,
     } = merge_tool_file;
@@ -304,18 +304,7 @@ fn run_mergetool_external_single_file(
         ));
     }
 
-    let new_tree_value = match new_file_ids.into_resolved() {
-        Ok(new_file_id) => Merge::normal(TreeValue::File {
-            id: new_file_id.unwrap(),
-            executable: conflict
-                .to_executable_merge()
-                .as_ref()
-                .and_then(Merge::resolve_trivial)
-                .copied()
-                .unwrap_or_default(),
-        }),
-        Err(new_file_ids) => conflict.with_new_file_ids(&new_file_ids),
-    };
+    let new_tree_value = merge_tool_file.new_tree_value(new_file_ids);
     tree_builder.set_or_remove(repo_path.to_owned(), new_tree_value);
     Ok(())
 }
@@ -379,6 +368,7 @@ pub fn edit_diff_external(
         .unwrap_or(default_conflict_marker_style);
     let options = CheckoutOptions {
         conflict_marker_style,
+        content_filters: ContentFilters::default(),
     };
 
     let got_output_field = find_all_variables(&editor.edit_args).contains(&"output");
@@ -427,6 +417,7 @@ pub fn generate_diff(
         .unwrap_or(default_conflict_marker_style);
     let options = CheckoutOptions {
         conflict_marker_style,
+        content_filters: ContentFilters::default(),
     };
     let store = left_tree.store();
     let diff_wc = check_out_trees(store, left_tree, right_tree, matcher, None, &options)?;
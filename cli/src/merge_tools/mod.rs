@@ -22,6 +22,7 @@ use bstr::BString;
 use itertools::Itertools;
 use jj_lib::backend::FileId;
 use jj_lib::backend::MergedTreeId;
+use jj_lib::backend::TreeValue;
 use jj_lib::config::ConfigGetError;
 use jj_lib::config::ConfigGetResultExt as _;
 use jj_lib::config::ConfigNamePathBuf;
@@ -96,13 +97,18 @@ pub enum ConflictResolveError {
          supported. Conflict summary for {0:?}:\n{1}"
     )]
     NotNormalFiles(RepoPathBuf, String),
-    #[error("The conflict at {path:?} has {sides} sides. At most 2 sides are supported.")]
+    #[error(
+        "The conflict at {path:?} has {sides} sides. At most 2 sides are supported by external \
+         merge tools."
+    )]
     ConflictTooComplicated { path: RepoPathBuf, sides: usize },
     #[error(
         "The output file is either unchanged or empty after the editor quit (run with --debug to \
          see the exact invocation)."
     )]
     EmptyOrUnchanged,
+    #[error("No lines of the conflicts were selected in the built-in merge editor")]
+    NothingSelected,
     #[error(transparent)]
     Backend(#[from] jj_lib::backend::BackendError),
     #[error(transparent)]
@@ -302,13 +308,6 @@ impl MergeToolFile {
             ConflictResolveError::NotNormalFiles(repo_path.to_owned(), summary)
         })?;
         let simplified_file_merge = file_merge.clone().simplify();
-        // We only support conflicts with 2 sides (3-way conflicts)
-        if simplified_file_merge.num_sides() > 2 {
-            return Err(ConflictResolveError::ConflictTooComplicated {
-                path: repo_path.to_owned(),
-                sides: simplified_file_merge.num_sides(),
-            });
-        };
         let content =
             extract_as_single_hunk(&simplified_file_merge, tree.store(), repo_path).block_on()?;
         Ok(MergeToolFile {
@@ -318,6 +317,24 @@ impl MergeToolFile {
             content,
         })
     }
+
+    /// Returns the tree value of the file with the `new_file_ids` as contents,
+    /// keeping the executable bits of the conflict.
+    fn new_tree_value(&self, new_file_ids: Merge<Option<FileId>>) -> MergedTreeValue {
+        match new_file_ids.into_resolved() {
+            Ok(new_file_id) => Merge::normal(TreeValue::File {
+                id: new_file_id.unwrap(),
+                executable: self
+                    .conflict
+                    .to_executable_merge()
+                    .as_ref()
+                    .and_then(Merge::resolve_trivial)
+                    .copied()
+                    .unwrap_or_default(),
+            }),
+            Err(new_file_ids) => self.conflict.with_new_file_ids(&new_file_ids),
+        }
+    }
 }
 
 /// Configured 3-way merge editor.
@@ -391,17 +408,35 @@ impl MergeEditor {
 
         match &self.tool {
             MergeTool::Builtin => {
-                let tree_id = edit_merge_builtin(tree, &merge_tool_files).map_err(Box::new)?;
+                let tree_id =
+                    edit_merge_builtin(tree, &merge_tool_files, self.conflict_marker_style)
+                        .map_err(Box::new)?;
+                if tree_id == tree.id() {
+                    return Err(ConflictResolveError::NothingSelected);
+                }
                 Ok((tree_id, None))
             }
-            MergeTool::External(editor) => external::run_mergetool_external(
-                ui,
-                &self.path_converter,
-                editor,
-                tree,
-                &merge_tool_files,
-                self.conflict_marker_style,
-            ),
+            MergeTool::External(editor) => {
+                // External tools only support conflicts with 2 sides (3-way
+                // conflicts)
+                if let Some(file) = merge_tool_files
+                    .iter()
+                    .find(|file| file.content.num_sides() > 2)
+                {
+                    return Err(ConflictResolveError::ConflictTooComplicated {
+                        path: file.repo_path.clone(),
+                        sides: file.content.num_sides(),
+                    });
+                }
+                external::run_mergetool_external(
+                    ui,
+                    &self.path_converter,
+                    editor,
+                    tree,
+                    &merge_tool_files,
+                    self.conflict_marker_style,
+                )
+            }
         }
     }
 }
@@ -0,0 +1,95 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Utilities to compute differences between operations.
+
+use indexmap::IndexMap;
+use itertools::Itertools as _;
+use jj_lib::backend::ChangeId;
+use jj_lib::commit::Commit;
+use jj_lib::repo::ReadonlyRepo;
+use jj_lib::repo::Repo;
+use jj_lib::revset;
+use jj_lib::revset::RevsetEvaluationError;
+use jj_lib::revset::RevsetIteratorExt as _;
+
+/// Commits added and removed for a change ID between two operations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModifiedChange {
+    pub added_commits: Vec<Commit>,
+    pub removed_commits: Vec<Commit>,
+}
+
+impl ModifiedChange {
+    /// Returns true if the change was rewritten, i.e. some of its commits were
+    /// replaced by new commits.
+    pub fn is_rewritten(&self) -> bool {
+        !self.added_commits.is_empty() && !self.removed_commits.is_empty()
+    }
+
+    /// Returns true if all commits of the change were hidden.
+    pub fn is_abandoned(&self) -> bool {
+        self.added_commits.is_empty() && !self.removed_commits.is_empty()
+    }
+}
+
+/// Compute the changes in commits between two operations, returned as a
+/// `HashMap` from `ChangeId` to a `ModifiedChange` struct containing the added
+/// and removed commits for the change ID.
+///
+/// `repo` should have an index containing the commits of both `from_repo` and
+/// `to_repo`.
+pub fn compute_operation_commits_diff(
+    repo: &dyn Repo,
+    from_repo: &ReadonlyRepo,
+    to_repo: &ReadonlyRepo,
+) -> Result<IndexMap<ChangeId, ModifiedChange>, RevsetEvaluationError> {
+    let mut changes: IndexMap<ChangeId, ModifiedChange> = IndexMap::new();
+
+    let from_heads = from_repo.view().heads().iter().cloned().collect_vec();
+    let to_heads = to_repo.view().heads().iter().cloned().collect_vec();
+
+    // Find newly added commits in `to_repo` which were not present in
+    // `from_repo`.
+    for commit in revset::walk_revs(repo, &to_heads, &from_heads)?
+        .iter()
+        .commits(repo.store())
+    {
+        let commit = commit?;
+        let modified_change = changes
+            .entry(commit.change_id().clone())
+            .or_insert_with(|| ModifiedChange {
+                added_commits: vec![],
+                removed_commits: vec![],
+            });
+        modified_change.added_commits.push(commit);
+    }
+
+    // Find commits which were hidden in `to_repo`.
+    for commit in revset::walk_revs(repo, &from_heads, &to_heads)?
+        .iter()
+        .commits(repo.store())
+    {
+        let commit = commit?;
+        let modified_change = changes
+            .entry(commit.change_id().clone())
+            .or_insert_with(|| ModifiedChange {
+                added_commits: vec![],
+                removed_commits: vec![],
+            });
+        modified_change.removed_commits.push(commit);
+    }
+
+    Ok(changes)
+}
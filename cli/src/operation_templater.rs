@@ -13,19 +13,31 @@
 // limitations under the License.
 
 use std::any::Any;
+use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::io;
+use std::rc::Rc;
 
+use indexmap::IndexMap;
 use itertools::Itertools as _;
+use jj_lib::backend::ChangeId;
+use jj_lib::backend::Timestamp;
 use jj_lib::extensions_map::ExtensionsMap;
 use jj_lib::object_id::ObjectId;
 use jj_lib::op_store::OperationId;
+use jj_lib::op_store::RefTarget;
 use jj_lib::operation::Operation;
+use jj_lib::refs::diff_named_ref_targets;
 use jj_lib::repo::RepoLoader;
 use jj_lib::settings::UserSettings;
+use jj_lib::view::View;
+use once_cell::unsync::OnceCell;
 
+use crate::op_diff_util::compute_operation_commits_diff;
+use crate::op_diff_util::ModifiedChange;
 use crate::template_builder;
+use crate::template_builder::memoize_property;
 use crate::template_builder::merge_fn_map;
 use crate::template_builder::BuildContext;
 use crate::template_builder::CoreTemplateBuildFnTable;
@@ -37,10 +49,12 @@ use crate::template_parser;
 use crate::template_parser::FunctionCallNode;
 use crate::template_parser::TemplateDiagnostics;
 use crate::template_parser::TemplateParseResult;
+use crate::templater::MemoScope;
 use crate::templater::PlainTextFormattedProperty;
 use crate::templater::Template;
 use crate::templater::TemplateFormatter;
 use crate::templater::TemplateProperty;
+use crate::templater::TemplatePropertyError;
 use crate::templater::TemplatePropertyExt as _;
 use crate::templater::TimestampRange;
 
@@ -54,6 +68,7 @@ pub struct OperationTemplateLanguage {
     repo_loader: RepoLoader,
     current_op_id: Option<OperationId>,
     build_fn_table: OperationTemplateBuildFnTable,
+    op_diff_cache: OperationDiffCache,
     cache_extensions: ExtensionsMap,
 }
 
@@ -80,6 +95,7 @@ impl OperationTemplateLanguage {
             repo_loader: repo_loader.clone(),
             current_op_id: current_op_id.cloned(),
             build_fn_table,
+            op_diff_cache: OperationDiffCache::default(),
             cache_extensions,
         }
     }
@@ -179,6 +195,13 @@ impl IntoTemplateProperty<'static> for OperationTemplatePropertyKind {
         }
     }
 
+    fn try_into_timestamp(self) -> Option<Box<dyn TemplateProperty<Output = Timestamp>>> {
+        match self {
+            OperationTemplatePropertyKind::Core(property) => property.try_into_timestamp(),
+            _ => None,
+        }
+    }
+
     fn try_into_plain_text(self) -> Option<Box<dyn TemplateProperty<Output = String>>> {
         match self {
             OperationTemplatePropertyKind::Core(property) => property.try_into_plain_text(),
@@ -220,6 +243,20 @@ impl IntoTemplateProperty<'static> for OperationTemplatePropertyKind {
             (OperationTemplatePropertyKind::OperationId(_), _) => None,
         }
     }
+
+    fn try_into_memoized(self, scope: &MemoScope) -> Option<Box<dyn Fn() -> Self>> {
+        type Kind = OperationTemplatePropertyKind;
+        match self {
+            Kind::Core(property) => {
+                let make = property.try_into_memoized(scope)?;
+                Some(Box::new(move || Kind::Core(make())))
+            }
+            Kind::Operation(property) => Some(memoize_property(property, scope, Kind::Operation)),
+            Kind::OperationId(property) => {
+                Some(memoize_property(property, scope, Kind::OperationId))
+            }
+        }
+    }
 }
 
 /// Table of functions that translate method call node of self type `T`.
@@ -348,9 +385,250 @@ fn builtin_operation_methods() -> OperationTemplateBuildMethodFnMap<Operation> {
             Ok(L::wrap_boolean(out_property))
         },
     );
+    map.insert(
+        "added_bookmarks",
+        |language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = build_changed_refs_property(
+                language,
+                self_property,
+                local_bookmarks,
+                RefChangeKind::Added,
+            );
+            Ok(L::wrap_string_list(out_property))
+        },
+    );
+    map.insert(
+        "removed_bookmarks",
+        |language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = build_changed_refs_property(
+                language,
+                self_property,
+                local_bookmarks,
+                RefChangeKind::Removed,
+            );
+            Ok(L::wrap_string_list(out_property))
+        },
+    );
+    map.insert(
+        "moved_bookmarks",
+        |language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = build_changed_refs_property(
+                language,
+                self_property,
+                local_bookmarks,
+                RefChangeKind::Moved,
+            );
+            Ok(L::wrap_string_list(out_property))
+        },
+    );
+    map.insert(
+        "added_tags",
+        |language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property =
+                build_changed_refs_property(language, self_property, tags, RefChangeKind::Added);
+            Ok(L::wrap_string_list(out_property))
+        },
+    );
+    map.insert(
+        "removed_tags",
+        |language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property =
+                build_changed_refs_property(language, self_property, tags, RefChangeKind::Removed);
+            Ok(L::wrap_string_list(out_property))
+        },
+    );
+    map.insert(
+        "moved_tags",
+        |language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property =
+                build_changed_refs_property(language, self_property, tags, RefChangeKind::Moved);
+            Ok(L::wrap_string_list(out_property))
+        },
+    );
+    map.insert(
+        "rewritten_count",
+        |language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property =
+                build_modified_commits_count_property(language, self_property, |change| {
+                    change.is_rewritten()
+                });
+            Ok(L::wrap_integer(out_property))
+        },
+    );
+    map.insert(
+        "abandoned_count",
+        |language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property =
+                build_modified_commits_count_property(language, self_property, |change| {
+                    change.is_abandoned()
+                });
+            Ok(L::wrap_integer(out_property))
+        },
+    );
+    map.insert(
+        "workspaces",
+        |language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let repo_loader = language.repo_loader.clone();
+            let op_diff_cache = language.op_diff_cache.clone();
+            let out_property = self_property.and_then(move |op| {
+                let op_diff = op_diff_cache.get(&repo_loader, &op)?;
+                let (from_view, to_view) = op_diff.views()?;
+                let from_ids = from_view.wc_commit_ids();
+                let to_ids = to_view.wc_commit_ids();
+                let names = itertools::chain(from_ids.keys(), to_ids.keys())
+                    .filter(|id| from_ids.get(*id) != to_ids.get(*id))
+                    .map(|id| id.as_str().to_owned())
+                    .sorted()
+                    .dedup()
+                    .collect();
+                Ok(names)
+            });
+            Ok(L::wrap_string_list(out_property))
+        },
+    );
     map
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum RefChangeKind {
+    Added,
+    Removed,
+    Moved,
+}
+
+fn local_bookmarks(view: &View) -> Vec<(&str, &RefTarget)> {
+    view.local_bookmarks().collect()
+}
+
+fn tags(view: &View) -> Vec<(&str, &RefTarget)> {
+    view.tags()
+        .iter()
+        .map(|(name, target)| (name.as_str(), target))
+        .collect()
+}
+
+/// Difference between an operation and its parent, which is computed lazily.
+struct OperationDiff {
+    from_op: Operation,
+    to_op: Operation,
+    views: OnceCell<(View, View)>,
+    commit_changes: OnceCell<IndexMap<ChangeId, ModifiedChange>>,
+}
+
+impl OperationDiff {
+    fn views(&self) -> Result<&(View, View), TemplatePropertyError> {
+        self.views
+            .get_or_try_init(|| Ok((self.from_op.view()?, self.to_op.view()?)))
+    }
+
+    fn commit_changes(
+        &self,
+        repo_loader: &RepoLoader,
+    ) -> Result<&IndexMap<ChangeId, ModifiedChange>, TemplatePropertyError> {
+        self.commit_changes.get_or_try_init(|| {
+            let from_repo = repo_loader.load_at(&self.from_op)?;
+            let to_repo = repo_loader.load_at(&self.to_op)?;
+            // Merge index from `from_repo` to `to_repo`, so commits in
+            // `from_repo` are accessible.
+            let mut tx = to_repo.start_transaction();
+            tx.repo_mut().merge_index(&from_repo);
+            Ok(compute_operation_commits_diff(
+                tx.repo(),
+                &from_repo,
+                &to_repo,
+            )?)
+        })
+    }
+}
+
+/// Remembers the difference of the last evaluated operation so it can be
+/// shared by the operation methods.
+///
+/// Operations are usually evaluated one by one, so only the last one is kept.
+#[derive(Clone, Default)]
+struct OperationDiffCache {
+    last: Rc<RefCell<Option<Rc<OperationDiff>>>>,
+}
+
+impl OperationDiffCache {
+    fn get(
+        &self,
+        repo_loader: &RepoLoader,
+        op: &Operation,
+    ) -> Result<Rc<OperationDiff>, TemplatePropertyError> {
+        if let Some(op_diff) = &*self.last.borrow() {
+            if op_diff.to_op.id() == op.id() {
+                return Ok(op_diff.clone());
+            }
+        }
+        // If the operation has multiple parents, they are merged in the same
+        // way as `jj op diff`.
+        let parent_ops: Vec<_> = op.parents().try_collect()?;
+        let op_diff = Rc::new(OperationDiff {
+            from_op: repo_loader.merge_operations(parent_ops, None)?,
+            to_op: op.clone(),
+            views: OnceCell::new(),
+            commit_changes: OnceCell::new(),
+        });
+        *self.last.borrow_mut() = Some(op_diff.clone());
+        Ok(op_diff)
+    }
+}
+
+fn build_changed_refs_property(
+    language: &OperationTemplateLanguage,
+    self_property: impl TemplateProperty<Output = Operation> + 'static,
+    get_refs: fn(&View) -> Vec<(&str, &RefTarget)>,
+    kind: RefChangeKind,
+) -> impl TemplateProperty<Output = Vec<String>> + 'static {
+    let repo_loader = language.repo_loader.clone();
+    let op_diff_cache = language.op_diff_cache.clone();
+    self_property.and_then(move |op| {
+        let op_diff = op_diff_cache.get(&repo_loader, &op)?;
+        let (from_view, to_view) = op_diff.views()?;
+        let names = diff_named_ref_targets(get_refs(from_view), get_refs(to_view))
+            .filter(|(_, (from_target, to_target))| {
+                let change_kind = match (from_target.is_present(), to_target.is_present()) {
+                    (false, _) => RefChangeKind::Added,
+                    (true, false) => RefChangeKind::Removed,
+                    (true, true) => RefChangeKind::Moved,
+                };
+                change_kind == kind
+            })
+            .map(|(name, _)| name.to_owned())
+            .collect();
+        Ok(names)
+    })
+}
+
+fn build_modified_commits_count_property(
+    language: &OperationTemplateLanguage,
+    self_property: impl TemplateProperty<Output = Operation> + 'static,
+    predicate: fn(&ModifiedChange) -> bool,
+) -> impl TemplateProperty<Output = i64> + 'static {
+    let repo_loader = language.repo_loader.clone();
+    let op_diff_cache = language.op_diff_cache.clone();
+    self_property.and_then(move |op| {
+        let op_diff = op_diff_cache.get(&repo_loader, &op)?;
+        let count = op_diff
+            .commit_changes(&repo_loader)?
+            .values()
+            .filter(|change| predicate(change))
+            .map(|change| change.removed_commits.len())
+            .sum::<usize>();
+        Ok(i64::try_from(count)?)
+    })
+}
+
 impl Template for OperationId {
     fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
         write!(formatter, "{}", self.hex())
@@ -16,7 +16,9 @@ use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::io;
 use std::iter;
+use std::rc::Rc;
 
+use chrono::TimeDelta;
 use itertools::Itertools as _;
 use jj_lib::backend::Signature;
 use jj_lib::backend::Timestamp;
@@ -25,6 +27,9 @@ use jj_lib::config::ConfigValue;
 use jj_lib::dsl_util::AliasExpandError as _;
 use jj_lib::settings::UserSettings;
 use jj_lib::time_util::DatePattern;
+use jj_lib::trailer;
+use jj_lib::trailer::Trailer;
+use regex::Regex;
 use serde::de::IntoDeserializer as _;
 use serde::Deserialize;
 
@@ -43,13 +48,20 @@ use crate::template_parser::TemplateParseErrorKind;
 use crate::template_parser::TemplateParseResult;
 use crate::template_parser::UnaryOp;
 use crate::templater::CoalesceTemplate;
+use crate::templater::ColumnsTemplate;
 use crate::templater::ConcatTemplate;
 use crate::templater::ConditionalTemplate;
 use crate::templater::Email;
+use crate::templater::HyperlinkTemplate;
 use crate::templater::LabelTemplate;
+use crate::templater::LetTemplate;
 use crate::templater::ListPropertyTemplate;
 use crate::templater::ListTemplate;
 use crate::templater::Literal;
+use crate::templater::MemoScope;
+use crate::templater::MemoizedProperty;
+use crate::templater::MemoizedTemplate;
+use crate::templater::PatternLinkTemplate;
 use crate::templater::PlainTextFormattedProperty;
 use crate::templater::PropertyPlaceholder;
 use crate::templater::RawEscapeSequenceTemplate;
@@ -88,6 +100,11 @@ pub trait TemplateLanguage<'a> {
     fn wrap_timestamp_range(
         property: impl TemplateProperty<Output = TimestampRange> + 'a,
     ) -> Self::Property;
+    fn wrap_duration(property: impl TemplateProperty<Output = TimeDelta> + 'a) -> Self::Property;
+    fn wrap_trailer(property: impl TemplateProperty<Output = Trailer> + 'a) -> Self::Property;
+    fn wrap_trailer_list(
+        property: impl TemplateProperty<Output = Vec<Trailer>> + 'a,
+    ) -> Self::Property;
 
     fn wrap_template(template: Box<dyn Template + 'a>) -> Self::Property;
     fn wrap_list_template(template: Box<dyn ListTemplate + 'a>) -> Self::Property;
@@ -136,6 +153,9 @@ macro_rules! impl_core_wrap_property_fns {
                 wrap_size_hint($crate::templater::SizeHint) => SizeHint,
                 wrap_timestamp(jj_lib::backend::Timestamp) => Timestamp,
                 wrap_timestamp_range($crate::templater::TimestampRange) => TimestampRange,
+                wrap_duration(chrono::TimeDelta) => Duration,
+                wrap_trailer(jj_lib::trailer::Trailer) => Trailer,
+                wrap_trailer_list(Vec<jj_lib::trailer::Trailer>) => TrailerList,
             }
         );
         fn wrap_template(
@@ -176,6 +196,7 @@ pub trait IntoTemplateProperty<'a> {
 
     fn try_into_boolean(self) -> Option<Box<dyn TemplateProperty<Output = bool> + 'a>>;
     fn try_into_integer(self) -> Option<Box<dyn TemplateProperty<Output = i64> + 'a>>;
+    fn try_into_timestamp(self) -> Option<Box<dyn TemplateProperty<Output = Timestamp> + 'a>>;
 
     fn try_into_plain_text(self) -> Option<Box<dyn TemplateProperty<Output = String> + 'a>>;
     fn try_into_template(self) -> Option<Box<dyn Template + 'a>>;
@@ -186,6 +207,10 @@ pub trait IntoTemplateProperty<'a> {
     /// Transforms into a property that will evaluate to an [`Ordering`].
     fn try_into_cmp(self, other: Self)
         -> Option<Box<dyn TemplateProperty<Output = Ordering> + 'a>>;
+
+    /// Transforms into a function that creates copies of the property. The
+    /// copies share the value evaluated at most once within the `scope`.
+    fn try_into_memoized(self, scope: &MemoScope) -> Option<Box<dyn Fn() -> Self + 'a>>;
 }
 
 pub enum CoreTemplatePropertyKind<'a> {
@@ -200,6 +225,9 @@ pub enum CoreTemplatePropertyKind<'a> {
     SizeHint(Box<dyn TemplateProperty<Output = SizeHint> + 'a>),
     Timestamp(Box<dyn TemplateProperty<Output = Timestamp> + 'a>),
     TimestampRange(Box<dyn TemplateProperty<Output = TimestampRange> + 'a>),
+    Duration(Box<dyn TemplateProperty<Output = TimeDelta> + 'a>),
+    Trailer(Box<dyn TemplateProperty<Output = Trailer> + 'a>),
+    TrailerList(Box<dyn TemplateProperty<Output = Vec<Trailer>> + 'a>),
 
     // Both TemplateProperty and Template can represent a value to be evaluated
     // dynamically, which suggests that `Box<dyn Template + 'a>` could be
@@ -229,6 +257,9 @@ impl<'a> IntoTemplateProperty<'a> for CoreTemplatePropertyKind<'a> {
             CoreTemplatePropertyKind::SizeHint(_) => "SizeHint",
             CoreTemplatePropertyKind::Timestamp(_) => "Timestamp",
             CoreTemplatePropertyKind::TimestampRange(_) => "TimestampRange",
+            CoreTemplatePropertyKind::Duration(_) => "Duration",
+            CoreTemplatePropertyKind::Trailer(_) => "Trailer",
+            CoreTemplatePropertyKind::TrailerList(_) => "List<Trailer>",
             CoreTemplatePropertyKind::Template(_) => "Template",
             CoreTemplatePropertyKind::ListTemplate(_) => "ListTemplate",
         }
@@ -255,6 +286,11 @@ impl<'a> IntoTemplateProperty<'a> for CoreTemplatePropertyKind<'a> {
             CoreTemplatePropertyKind::SizeHint(_) => None,
             CoreTemplatePropertyKind::Timestamp(_) => None,
             CoreTemplatePropertyKind::TimestampRange(_) => None,
+            CoreTemplatePropertyKind::Duration(_) => None,
+            CoreTemplatePropertyKind::Trailer(_) => None,
+            CoreTemplatePropertyKind::TrailerList(property) => {
+                Some(Box::new(property.map(|l| !l.is_empty())))
+            }
             // Template types could also be evaluated to boolean, but it's less likely
             // to apply label() or .map() and use the result as conditional. It's also
             // unclear whether ListTemplate should behave as a "list" or a "template".
@@ -273,6 +309,13 @@ impl<'a> IntoTemplateProperty<'a> for CoreTemplatePropertyKind<'a> {
         }
     }
 
+    fn try_into_timestamp(self) -> Option<Box<dyn TemplateProperty<Output = Timestamp> + 'a>> {
+        match self {
+            CoreTemplatePropertyKind::Timestamp(property) => Some(property),
+            _ => None,
+        }
+    }
+
     fn try_into_plain_text(self) -> Option<Box<dyn TemplateProperty<Output = String> + 'a>> {
         match self {
             CoreTemplatePropertyKind::String(property) => Some(property),
@@ -296,6 +339,9 @@ impl<'a> IntoTemplateProperty<'a> for CoreTemplatePropertyKind<'a> {
             CoreTemplatePropertyKind::SizeHint(_) => None,
             CoreTemplatePropertyKind::Timestamp(property) => Some(property.into_template()),
             CoreTemplatePropertyKind::TimestampRange(property) => Some(property.into_template()),
+            CoreTemplatePropertyKind::Duration(property) => Some(property.into_template()),
+            CoreTemplatePropertyKind::Trailer(property) => Some(property.into_template()),
+            CoreTemplatePropertyKind::TrailerList(property) => Some(property.into_template()),
             CoreTemplatePropertyKind::Template(template) => Some(template),
             CoreTemplatePropertyKind::ListTemplate(template) => Some(template.into_template()),
         }
@@ -321,6 +367,18 @@ impl<'a> IntoTemplateProperty<'a> for CoreTemplatePropertyKind<'a> {
             (CoreTemplatePropertyKind::Email(lhs), CoreTemplatePropertyKind::String(rhs)) => {
                 Some(Box::new((lhs, rhs).map(|(l, r)| l.0 == r)))
             }
+            (
+                CoreTemplatePropertyKind::Timestamp(lhs),
+                CoreTemplatePropertyKind::Timestamp(rhs),
+            ) => {
+                // Compare instants regardless of the time zones.
+                Some(Box::new(
+                    (lhs, rhs).map(|(l, r)| l.timestamp == r.timestamp),
+                ))
+            }
+            (CoreTemplatePropertyKind::Duration(lhs), CoreTemplatePropertyKind::Duration(rhs)) => {
+                Some(Box::new((lhs, rhs).map(|(l, r)| l == r)))
+            }
             (CoreTemplatePropertyKind::String(_), _) => None,
             (CoreTemplatePropertyKind::StringList(_), _) => None,
             (CoreTemplatePropertyKind::Boolean(_), _) => None,
@@ -332,6 +390,9 @@ impl<'a> IntoTemplateProperty<'a> for CoreTemplatePropertyKind<'a> {
             (CoreTemplatePropertyKind::SizeHint(_), _) => None,
             (CoreTemplatePropertyKind::Timestamp(_), _) => None,
             (CoreTemplatePropertyKind::TimestampRange(_), _) => None,
+            (CoreTemplatePropertyKind::Duration(_), _) => None,
+            (CoreTemplatePropertyKind::Trailer(_), _) => None,
+            (CoreTemplatePropertyKind::TrailerList(_), _) => None,
             (CoreTemplatePropertyKind::Template(_), _) => None,
             (CoreTemplatePropertyKind::ListTemplate(_), _) => None,
         }
@@ -345,6 +406,18 @@ impl<'a> IntoTemplateProperty<'a> for CoreTemplatePropertyKind<'a> {
             (CoreTemplatePropertyKind::Integer(lhs), CoreTemplatePropertyKind::Integer(rhs)) => {
                 Some(Box::new((lhs, rhs).map(|(l, r)| l.cmp(&r))))
             }
+            (CoreTemplatePropertyKind::String(lhs), CoreTemplatePropertyKind::String(rhs)) => {
+                Some(Box::new((lhs, rhs).map(|(l, r)| l.cmp(&r))))
+            }
+            (
+                CoreTemplatePropertyKind::Timestamp(lhs),
+                CoreTemplatePropertyKind::Timestamp(rhs),
+            ) => Some(Box::new(
+                (lhs, rhs).map(|(l, r)| l.timestamp.0.cmp(&r.timestamp.0)),
+            )),
+            (CoreTemplatePropertyKind::Duration(lhs), CoreTemplatePropertyKind::Duration(rhs)) => {
+                Some(Box::new((lhs, rhs).map(|(l, r)| l.cmp(&r))))
+            }
             (CoreTemplatePropertyKind::String(_), _) => None,
             (CoreTemplatePropertyKind::StringList(_), _) => None,
             (CoreTemplatePropertyKind::Boolean(_), _) => None,
@@ -356,10 +429,60 @@ impl<'a> IntoTemplateProperty<'a> for CoreTemplatePropertyKind<'a> {
             (CoreTemplatePropertyKind::SizeHint(_), _) => None,
             (CoreTemplatePropertyKind::Timestamp(_), _) => None,
             (CoreTemplatePropertyKind::TimestampRange(_), _) => None,
+            (CoreTemplatePropertyKind::Duration(_), _) => None,
+            (CoreTemplatePropertyKind::Trailer(_), _) => None,
+            (CoreTemplatePropertyKind::TrailerList(_), _) => None,
             (CoreTemplatePropertyKind::Template(_), _) => None,
             (CoreTemplatePropertyKind::ListTemplate(_), _) => None,
         }
     }
+
+    fn try_into_memoized(self, scope: &MemoScope) -> Option<Box<dyn Fn() -> Self + 'a>> {
+        type Kind<'a> = CoreTemplatePropertyKind<'a>;
+        match self {
+            Kind::String(property) => Some(memoize_property(property, scope, Kind::String)),
+            Kind::StringList(property) => Some(memoize_property(property, scope, Kind::StringList)),
+            Kind::Boolean(property) => Some(memoize_property(property, scope, Kind::Boolean)),
+            Kind::Integer(property) => Some(memoize_property(property, scope, Kind::Integer)),
+            Kind::IntegerOpt(property) => Some(memoize_property(property, scope, Kind::IntegerOpt)),
+            Kind::ConfigValue(property) => {
+                Some(memoize_property(property, scope, Kind::ConfigValue))
+            }
+            Kind::Signature(property) => Some(memoize_property(property, scope, Kind::Signature)),
+            Kind::Email(property) => Some(memoize_property(property, scope, Kind::Email)),
+            Kind::SizeHint(property) => Some(memoize_property(property, scope, Kind::SizeHint)),
+            Kind::Timestamp(property) => Some(memoize_property(property, scope, Kind::Timestamp)),
+            Kind::TimestampRange(property) => {
+                Some(memoize_property(property, scope, Kind::TimestampRange))
+            }
+            Kind::Duration(property) => Some(memoize_property(property, scope, Kind::Duration)),
+            Kind::Trailer(property) => Some(memoize_property(property, scope, Kind::Trailer)),
+            Kind::TrailerList(property) => {
+                Some(memoize_property(property, scope, Kind::TrailerList))
+            }
+            // Templates are rendered once, and the recorded output is replayed
+            // where it is referenced.
+            Kind::Template(template) => {
+                let template = Rc::new(MemoizedTemplate::new(template, scope.clone()));
+                Some(Box::new(move || Kind::Template(Box::new(template.clone()))))
+            }
+            Kind::ListTemplate(_) => None,
+        }
+    }
+}
+
+/// Creates a function that wraps copies of the memoized `property`.
+pub fn memoize_property<'a, O, P>(
+    property: Box<dyn TemplateProperty<Output = O> + 'a>,
+    scope: &MemoScope,
+    wrap: fn(Box<dyn TemplateProperty<Output = O> + 'a>) -> P,
+) -> Box<dyn Fn() -> P + 'a>
+where
+    O: Clone + 'a,
+    P: 'a,
+{
+    let property = MemoizedProperty::new(property, scope.clone());
+    Box::new(move || wrap(Box::new(property.clone())))
 }
 
 /// Function that translates global function call node.
@@ -405,6 +528,8 @@ pub struct CoreTemplateBuildFnTable<'a, L: TemplateLanguage<'a> + ?Sized> {
     pub size_hint_methods: TemplateBuildMethodFnMap<'a, L, SizeHint>,
     pub timestamp_methods: TemplateBuildMethodFnMap<'a, L, Timestamp>,
     pub timestamp_range_methods: TemplateBuildMethodFnMap<'a, L, TimestampRange>,
+    pub duration_methods: TemplateBuildMethodFnMap<'a, L, TimeDelta>,
+    pub trailer_methods: TemplateBuildMethodFnMap<'a, L, Trailer>,
 }
 
 pub fn merge_fn_map<'s, F>(base: &mut HashMap<&'s str, F>, extension: HashMap<&'s str, F>) {
@@ -429,6 +554,8 @@ impl<'a, L: TemplateLanguage<'a> + ?Sized> CoreTemplateBuildFnTable<'a, L> {
             size_hint_methods: builtin_size_hint_methods(),
             timestamp_methods: builtin_timestamp_methods(),
             timestamp_range_methods: builtin_timestamp_range_methods(),
+            duration_methods: builtin_duration_methods(),
+            trailer_methods: builtin_trailer_methods(),
         }
     }
 
@@ -444,6 +571,8 @@ impl<'a, L: TemplateLanguage<'a> + ?Sized> CoreTemplateBuildFnTable<'a, L> {
             size_hint_methods: HashMap::new(),
             timestamp_methods: HashMap::new(),
             timestamp_range_methods: HashMap::new(),
+            duration_methods: HashMap::new(),
+            trailer_methods: HashMap::new(),
         }
     }
 
@@ -459,6 +588,8 @@ impl<'a, L: TemplateLanguage<'a> + ?Sized> CoreTemplateBuildFnTable<'a, L> {
             size_hint_methods,
             timestamp_methods,
             timestamp_range_methods,
+            duration_methods,
+            trailer_methods,
         } = extension;
 
         merge_fn_map(&mut self.functions, functions);
@@ -471,6 +602,8 @@ impl<'a, L: TemplateLanguage<'a> + ?Sized> CoreTemplateBuildFnTable<'a, L> {
         merge_fn_map(&mut self.size_hint_methods, size_hint_methods);
         merge_fn_map(&mut self.timestamp_methods, timestamp_methods);
         merge_fn_map(&mut self.timestamp_range_methods, timestamp_range_methods);
+        merge_fn_map(&mut self.duration_methods, duration_methods);
+        merge_fn_map(&mut self.trailer_methods, trailer_methods);
     }
 
     /// Translates the function call node `function` by using this symbol table.
@@ -568,6 +701,20 @@ impl<'a, L: TemplateLanguage<'a> + ?Sized> CoreTemplateBuildFnTable<'a, L> {
                 let build = template_parser::lookup_method(type_name, table, function)?;
                 build(language, diagnostics, build_ctx, property, function)
             }
+            CoreTemplatePropertyKind::Duration(property) => {
+                let table = &self.duration_methods;
+                let build = template_parser::lookup_method(type_name, table, function)?;
+                build(language, diagnostics, build_ctx, property, function)
+            }
+            CoreTemplatePropertyKind::Trailer(property) => {
+                let table = &self.trailer_methods;
+                let build = template_parser::lookup_method(type_name, table, function)?;
+                build(language, diagnostics, build_ctx, property, function)
+            }
+            CoreTemplatePropertyKind::TrailerList(property) => {
+                // TODO: migrate to table?
+                build_trailer_list_method(language, diagnostics, build_ctx, property, function)
+            }
             CoreTemplatePropertyKind::Template(_) => {
                 // TODO: migrate to table?
                 Err(TemplateParseError::no_such_method(type_name, function))
@@ -611,6 +758,10 @@ impl<'a, P: IntoTemplateProperty<'a>> Expression<P> {
         self.property.try_into_integer()
     }
 
+    pub fn try_into_timestamp(self) -> Option<Box<dyn TemplateProperty<Output = Timestamp> + 'a>> {
+        self.property.try_into_timestamp()
+    }
+
     pub fn try_into_plain_text(self) -> Option<Box<dyn TemplateProperty<Output = String> + 'a>> {
         self.property.try_into_plain_text()
     }
@@ -634,6 +785,12 @@ impl<'a, P: IntoTemplateProperty<'a>> Expression<P> {
     ) -> Option<Box<dyn TemplateProperty<Output = Ordering> + 'a>> {
         self.property.try_into_cmp(other.property)
     }
+
+    pub fn try_into_memoized(self, scope: &MemoScope) -> Option<Box<dyn Fn() -> P + 'a>> {
+        // Labels aren't inherited. A variable is unlabeled no matter if the
+        // value expression is a keyword.
+        self.property.try_into_memoized(scope)
+    }
 }
 
 pub struct BuildContext<'i, P> {
@@ -764,6 +921,30 @@ fn build_binary_operation<'a, L: TemplateLanguage<'a> + ?Sized>(
                 _ => unreachable!(),
             }
         }
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => {
+            let lhs = expect_integer_expression(language, diagnostics, build_ctx, lhs_node)?;
+            let rhs = expect_integer_expression(language, diagnostics, build_ctx, rhs_node)?;
+            let build = |op: fn(i64, i64) -> Option<i64>, msg: fn(i64) -> &'static str| {
+                (lhs, rhs).and_then(move |(l, r)| {
+                    op(l, r).ok_or_else(|| TemplatePropertyError(msg(r).into()))
+                })
+            };
+            let out = match op {
+                BinaryOp::Add => build(i64::checked_add, |_| "Attempt to add with overflow"),
+                BinaryOp::Sub => build(i64::checked_sub, |_| "Attempt to subtract with overflow"),
+                BinaryOp::Mul => build(i64::checked_mul, |_| "Attempt to multiply with overflow"),
+                BinaryOp::Div => build(i64::checked_div, |r| match r {
+                    0 => "Attempt to divide by zero",
+                    _ => "Attempt to divide with overflow",
+                }),
+                BinaryOp::Rem => build(i64::checked_rem, |r| match r {
+                    0 => "Attempt to calculate the remainder with a divisor of zero",
+                    _ => "Attempt to calculate the remainder with overflow",
+                }),
+                _ => unreachable!(),
+            };
+            Ok(L::wrap_integer(out))
+        }
     }
 }
 
@@ -879,6 +1060,43 @@ fn builtin_string_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
             Ok(L::wrap_string_list(out_property))
         },
     );
+    map.insert(
+        "trailers",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|s| trailer::parse_description_trailers(&s));
+            Ok(L::wrap_trailer_list(out_property))
+        },
+    );
+    map.insert(
+        "link_tickets",
+        |language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let get_config = |name: &'static str| {
+                language.settings().get_string(name).map_err(|err| {
+                    TemplateParseError::expression("Failed to get config value", function.name_span)
+                        .with_source(err)
+                })
+            };
+            let pattern = get_config("hyperlinks.ticket-pattern")?;
+            let url = get_config("hyperlinks.ticket-url")?;
+            if pattern.is_empty() || url.is_empty() {
+                return Ok(L::wrap_template(self_property.into_template()));
+            }
+            let pattern = Regex::new(&pattern).map_err(|err| {
+                TemplateParseError::expression(
+                    "Invalid `hyperlinks.ticket-pattern`",
+                    function.name_span,
+                )
+                .with_source(err)
+            })?;
+            Ok(L::wrap_template(Box::new(PatternLinkTemplate::new(
+                self_property,
+                pattern,
+                url,
+            ))))
+        },
+    );
     map.insert(
         "upper",
         |_language, _diagnostics, _build_ctx, self_property, function| {
@@ -1169,6 +1387,37 @@ fn builtin_timestamp_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
         },
     );
     map.insert("before", map["after"]);
+    map.insert(
+        "since",
+        |language, diagnostics, build_ctx, self_property, function| {
+            let [other_node] = function.expect_exact_arguments()?;
+            let other_property =
+                expect_timestamp_expression(language, diagnostics, build_ctx, other_node)?;
+            let out_property = (self_property, other_property).and_then(|(timestamp, other)| {
+                let millis = timestamp.timestamp.0.checked_sub(other.timestamp.0);
+                millis
+                    .and_then(TimeDelta::try_milliseconds)
+                    .ok_or_else(|| TemplatePropertyError("Duration out of range".into()))
+            });
+            Ok(L::wrap_duration(out_property))
+        },
+    );
+    map.insert(
+        "in_tz",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            let [tz_node] = function.expect_exact_arguments()?;
+            let tz = template_parser::expect_string_literal_with(tz_node, |tz, span| {
+                tz.parse::<chrono_tz::Tz>().map_err(|err| {
+                    TemplateParseError::expression("Invalid time zone", span).with_source(err)
+                })
+            })?;
+            let out_property = self_property.and_then(move |mut timestamp| {
+                timestamp.tz_offset = time_util::tz_offset_at(&timestamp, &tz)?;
+                Ok(timestamp)
+            });
+            Ok(L::wrap_timestamp(out_property))
+        },
+    );
     map
 }
 
@@ -1204,6 +1453,106 @@ fn builtin_timestamp_range_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
     map
 }
 
+fn builtin_duration_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
+) -> TemplateBuildMethodFnMap<'a, L, TimeDelta> {
+    // Not using maplit::hashmap!{} or custom declarative macro here because
+    // code completion inside macro is quite restricted.
+    let mut map = TemplateBuildMethodFnMap::<L, TimeDelta>::new();
+    map.insert(
+        "seconds",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|duration| duration.num_seconds());
+            Ok(L::wrap_integer(out_property))
+        },
+    );
+    map.insert(
+        "minutes",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|duration| duration.num_minutes());
+            Ok(L::wrap_integer(out_property))
+        },
+    );
+    map.insert(
+        "hours",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|duration| duration.num_hours());
+            Ok(L::wrap_integer(out_property))
+        },
+    );
+    map.insert(
+        "days",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|duration| duration.num_days());
+            Ok(L::wrap_integer(out_property))
+        },
+    );
+    map
+}
+
+fn builtin_trailer_methods<'a, L: TemplateLanguage<'a> + ?Sized>(
+) -> TemplateBuildMethodFnMap<'a, L, Trailer> {
+    // Not using maplit::hashmap!{} or custom declarative macro here because
+    // code completion inside macro is quite restricted.
+    let mut map = TemplateBuildMethodFnMap::<L, Trailer>::new();
+    map.insert(
+        "key",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|trailer| trailer.key);
+            Ok(L::wrap_string(out_property))
+        },
+    );
+    map.insert(
+        "value",
+        |_language, _diagnostics, _build_ctx, self_property, function| {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|trailer| trailer.value);
+            Ok(L::wrap_string(out_property))
+        },
+    );
+    map
+}
+
+fn build_trailer_list_method<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: Box<dyn TemplateProperty<Output = Vec<Trailer>> + 'a>,
+    function: &FunctionCallNode,
+) -> TemplateParseResult<L::Property> {
+    let property = match function.name {
+        "get" => {
+            // Trailer keys are case-insensitive. All values are returned since
+            // the same key may appear more than once.
+            let [key_node] = function.expect_exact_arguments()?;
+            let key_property =
+                expect_plain_text_expression(language, diagnostics, build_ctx, key_node)?;
+            let out_property = (self_property, key_property).map(|(trailers, key)| {
+                trailers
+                    .into_iter()
+                    .filter(|trailer| trailer.key.eq_ignore_ascii_case(&key))
+                    .map(|trailer| trailer.value)
+                    .collect()
+            });
+            L::wrap_string_list(out_property)
+        }
+        _ => build_formattable_list_method(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            L::wrap_trailer,
+            L::wrap_trailer_list,
+        )?,
+    };
+    Ok(property)
+}
+
 fn build_list_template_method<'a, L: TemplateLanguage<'a> + ?Sized>(
     language: &L,
     diagnostics: &mut TemplateDiagnostics,
@@ -1230,10 +1579,7 @@ pub fn build_formattable_list_method<'a, L, O>(
     build_ctx: &BuildContext<L::Property>,
     self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
     function: &FunctionCallNode,
-    // TODO: Generic L: WrapProperty<O> trait might be needed to support more
-    // list operations such as first()/slice(). For .map(), a simple callback
-    // works. For .filter(), redundant boxing is needed.
-    wrap_item: impl Fn(PropertyPlaceholder<O>) -> L::Property,
+    wrap_item: impl Fn(Box<dyn TemplateProperty<Output = O> + 'a>) -> L::Property,
     wrap_list: impl Fn(Box<dyn TemplateProperty<Output = Vec<O>> + 'a>) -> L::Property,
 ) -> TemplateParseResult<L::Property>
 where
@@ -1241,11 +1587,6 @@ where
     O: Template + Clone + 'a,
 {
     let property = match function.name {
-        "len" => {
-            function.expect_no_arguments()?;
-            let out_property = self_property.and_then(|items| Ok(items.len().try_into()?));
-            L::wrap_integer(out_property)
-        }
         "join" => {
             let [separator_node] = function.expect_exact_arguments()?;
             let separator =
@@ -1256,7 +1597,7 @@ where
                 });
             L::wrap_template(Box::new(template))
         }
-        "filter" => build_filter_operation(
+        _ => build_list_method(
             language,
             diagnostics,
             build_ctx,
@@ -1265,15 +1606,6 @@ where
             wrap_item,
             wrap_list,
         )?,
-        "map" => build_map_operation(
-            language,
-            diagnostics,
-            build_ctx,
-            self_property,
-            function,
-            wrap_item,
-        )?,
-        _ => return Err(TemplateParseError::no_such_method("List", function)),
     };
     Ok(property)
 }
@@ -1284,7 +1616,33 @@ pub fn build_unformattable_list_method<'a, L, O>(
     build_ctx: &BuildContext<L::Property>,
     self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
     function: &FunctionCallNode,
-    wrap_item: impl Fn(PropertyPlaceholder<O>) -> L::Property,
+    wrap_item: impl Fn(Box<dyn TemplateProperty<Output = O> + 'a>) -> L::Property,
+    wrap_list: impl Fn(Box<dyn TemplateProperty<Output = Vec<O>> + 'a>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    O: Clone + 'a,
+{
+    // No "join"
+    build_list_method(
+        language,
+        diagnostics,
+        build_ctx,
+        self_property,
+        function,
+        wrap_item,
+        wrap_list,
+    )
+}
+
+/// Builds method call expression common to printable and unprintable lists.
+fn build_list_method<'a, L, O>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
+    function: &FunctionCallNode,
+    wrap_item: impl Fn(Box<dyn TemplateProperty<Output = O> + 'a>) -> L::Property,
     wrap_list: impl Fn(Box<dyn TemplateProperty<Output = Vec<O>> + 'a>) -> L::Property,
 ) -> TemplateParseResult<L::Property>
 where
@@ -1297,7 +1655,34 @@ where
             let out_property = self_property.and_then(|items| Ok(items.len().try_into()?));
             L::wrap_integer(out_property)
         }
-        // No "join"
+        "first" => {
+            function.expect_no_arguments()?;
+            let out_property = self_property.and_then(|items| {
+                items
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| TemplatePropertyError("List is empty".into()))
+            });
+            wrap_item(Box::new(out_property))
+        }
+        "last" => {
+            function.expect_no_arguments()?;
+            let out_property = self_property.and_then(|items| {
+                items
+                    .into_iter()
+                    .next_back()
+                    .ok_or_else(|| TemplatePropertyError("List is empty".into()))
+            });
+            wrap_item(Box::new(out_property))
+        }
+        "reverse" => {
+            function.expect_no_arguments()?;
+            let out_property = self_property.map(|mut items| {
+                items.reverse();
+                items
+            });
+            wrap_list(Box::new(out_property))
+        }
         "filter" => build_filter_operation(
             language,
             diagnostics,
@@ -1315,6 +1700,40 @@ where
             function,
             wrap_item,
         )?,
+        "any" | "all" => build_any_all_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item,
+        )?,
+        "sum" => build_sum_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item,
+        )?,
+        "sort_by" => build_sort_by_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item,
+            wrap_list,
+        )?,
+        "unique" => build_unique_operation(
+            language,
+            diagnostics,
+            build_ctx,
+            self_property,
+            function,
+            wrap_item,
+            wrap_list,
+        )?,
         _ => return Err(TemplateParseError::no_such_method("List", function)),
     };
     Ok(property)
@@ -1330,7 +1749,7 @@ fn build_filter_operation<'a, L, O, P, B>(
     build_ctx: &BuildContext<L::Property>,
     self_property: P,
     function: &FunctionCallNode,
-    wrap_item: impl Fn(PropertyPlaceholder<O>) -> L::Property,
+    wrap_item: impl Fn(Box<dyn TemplateProperty<Output = O> + 'a>) -> L::Property,
     wrap_list: impl Fn(Box<dyn TemplateProperty<Output = B> + 'a>) -> L::Property,
 ) -> TemplateParseResult<L::Property>
 where
@@ -1346,7 +1765,7 @@ where
         build_lambda_expression(
             build_ctx,
             lambda,
-            &[&|| wrap_item(item_placeholder.clone())],
+            &[&|| wrap_item(Box::new(item_placeholder.clone()))],
             |build_ctx, body| expect_boolean_expression(language, diagnostics, build_ctx, body),
         )
     })?;
@@ -1375,7 +1794,7 @@ fn build_map_operation<'a, L, O, P>(
     build_ctx: &BuildContext<L::Property>,
     self_property: P,
     function: &FunctionCallNode,
-    wrap_item: impl Fn(PropertyPlaceholder<O>) -> L::Property,
+    wrap_item: impl Fn(Box<dyn TemplateProperty<Output = O> + 'a>) -> L::Property,
 ) -> TemplateParseResult<L::Property>
 where
     L: TemplateLanguage<'a> + ?Sized,
@@ -1389,7 +1808,7 @@ where
         build_lambda_expression(
             build_ctx,
             lambda,
-            &[&|| wrap_item(item_placeholder.clone())],
+            &[&|| wrap_item(Box::new(item_placeholder.clone()))],
             |build_ctx, body| expect_template_expression(language, diagnostics, build_ctx, body),
         )
     })?;
@@ -1403,6 +1822,217 @@ where
     Ok(L::wrap_list_template(Box::new(list_template)))
 }
 
+/// Builds expression that tests whether any or all items satisfy the
+/// predicate.
+fn build_any_all_operation<'a, L, O>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
+    function: &FunctionCallNode,
+    wrap_item: impl Fn(Box<dyn TemplateProperty<Output = O> + 'a>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    O: Clone + 'a,
+{
+    let [lambda_node] = function.expect_exact_arguments()?;
+    let item_placeholder = PropertyPlaceholder::new();
+    let item_predicate = template_parser::expect_lambda_with(lambda_node, |lambda, _span| {
+        build_lambda_expression(
+            build_ctx,
+            lambda,
+            &[&|| wrap_item(Box::new(item_placeholder.clone()))],
+            |build_ctx, body| expect_boolean_expression(language, diagnostics, build_ctx, body),
+        )
+    })?;
+    // any() stops at the first true, all() stops at the first false.
+    let stop_at = function.name == "any";
+    let out_property = self_property.and_then(move |items| {
+        for item in items {
+            let pred = item_placeholder.with_value(item, || item_predicate.extract())?;
+            if pred == stop_at {
+                return Ok(stop_at);
+            }
+        }
+        Ok(!stop_at)
+    });
+    Ok(L::wrap_boolean(out_property))
+}
+
+/// Builds expression that sums up the integer values computed from items.
+fn build_sum_operation<'a, L, O>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
+    function: &FunctionCallNode,
+    wrap_item: impl Fn(Box<dyn TemplateProperty<Output = O> + 'a>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    O: Clone + 'a,
+{
+    let [lambda_node] = function.expect_exact_arguments()?;
+    let item_placeholder = PropertyPlaceholder::new();
+    let item_value = template_parser::expect_lambda_with(lambda_node, |lambda, _span| {
+        build_lambda_expression(
+            build_ctx,
+            lambda,
+            &[&|| wrap_item(Box::new(item_placeholder.clone()))],
+            |build_ctx, body| expect_integer_expression(language, diagnostics, build_ctx, body),
+        )
+    })?;
+    let out_property = self_property.and_then(move |items| {
+        items.into_iter().try_fold(0_i64, |acc, item| {
+            let value = item_placeholder.with_value(item, || item_value.extract())?;
+            acc.checked_add(value)
+                .ok_or_else(|| TemplatePropertyError("Attempt to add with overflow".into()))
+        })
+    });
+    Ok(L::wrap_integer(out_property))
+}
+
+/// Builds expression that sorts items by the key computed from each item.
+///
+/// The sort is stable. Keys are compared by the same rules as the `<`
+/// operator.
+fn build_sort_by_operation<'a, L, O>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
+    function: &FunctionCallNode,
+    wrap_item: impl Fn(Box<dyn TemplateProperty<Output = O> + 'a>) -> L::Property,
+    wrap_list: impl Fn(Box<dyn TemplateProperty<Output = Vec<O>> + 'a>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    O: Clone + 'a,
+{
+    let [lambda_node] = function.expect_exact_arguments()?;
+    let lhs_placeholder = PropertyPlaceholder::new();
+    let rhs_placeholder = PropertyPlaceholder::new();
+    let key_cmp = template_parser::expect_lambda_with(lambda_node, |lambda, _span| {
+        // The key expression is instantiated for both sides of the comparison.
+        // Diagnostics are reported only once.
+        let lhs = build_lambda_expression(
+            build_ctx,
+            lambda,
+            &[&|| wrap_item(Box::new(lhs_placeholder.clone()))],
+            |build_ctx, body| build_expression(language, diagnostics, build_ctx, body),
+        )?;
+        let rhs = build_lambda_expression(
+            build_ctx,
+            lambda,
+            &[&|| wrap_item(Box::new(rhs_placeholder.clone()))],
+            |build_ctx, body| {
+                build_expression(language, &mut TemplateDiagnostics::new(), build_ctx, body)
+            },
+        )?;
+        let ty = lhs.type_name();
+        lhs.try_into_cmp(rhs).ok_or_else(|| {
+            let message = format!("Cannot sort by key of type `{ty}`");
+            TemplateParseError::expression(message, lambda.body.span)
+        })
+    })?;
+    let out_property = self_property.and_then(move |mut items| {
+        let mut first_err = None;
+        items.sort_by(|lhs, rhs| {
+            if first_err.is_some() {
+                return Ordering::Equal;
+            }
+            lhs_placeholder.set(lhs.clone());
+            rhs_placeholder.set(rhs.clone());
+            let result = key_cmp.extract();
+            lhs_placeholder.take();
+            rhs_placeholder.take();
+            result.unwrap_or_else(|err| {
+                first_err = Some(err);
+                Ordering::Equal
+            })
+        });
+        match first_err {
+            Some(err) => Err(err),
+            None => Ok(items),
+        }
+    });
+    Ok(wrap_list(Box::new(out_property)))
+}
+
+/// Builds expression that removes duplicated items, keeping the first
+/// occurrence.
+///
+/// Items (or keys computed from items) are compared by the `==` operator if
+/// supported, or by their plain text representation.
+fn build_unique_operation<'a, L, O>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    self_property: impl TemplateProperty<Output = Vec<O>> + 'a,
+    function: &FunctionCallNode,
+    wrap_item: impl Fn(Box<dyn TemplateProperty<Output = O> + 'a>) -> L::Property,
+    wrap_list: impl Fn(Box<dyn TemplateProperty<Output = Vec<O>> + 'a>) -> L::Property,
+) -> TemplateParseResult<L::Property>
+where
+    L: TemplateLanguage<'a> + ?Sized,
+    O: Clone + 'a,
+{
+    let ([], [key_node]) = function.expect_arguments()?;
+    let lhs_placeholder = PropertyPlaceholder::new();
+    let rhs_placeholder = PropertyPlaceholder::new();
+    let build_key = |diagnostics: &mut TemplateDiagnostics,
+                     placeholder: &PropertyPlaceholder<O>|
+     -> TemplateParseResult<Expression<L::Property>> {
+        let wrap_placeholder = || wrap_item(Box::new(placeholder.clone()));
+        let Some(key_node) = key_node else {
+            return Ok(Expression::unlabeled(wrap_placeholder()));
+        };
+        template_parser::expect_lambda_with(key_node, |lambda, _span| {
+            build_lambda_expression(
+                build_ctx,
+                lambda,
+                &[&wrap_placeholder],
+                |build_ctx, body| build_expression(language, diagnostics, build_ctx, body),
+            )
+        })
+    };
+    let lhs = build_key(diagnostics, &lhs_placeholder)?;
+    let rhs = build_key(&mut TemplateDiagnostics::new(), &rhs_placeholder)?;
+    let ty = lhs.type_name();
+    let key_eq = if let Some(key_eq) = lhs.try_into_eq(rhs) {
+        key_eq
+    } else {
+        let lhs = build_key(&mut TemplateDiagnostics::new(), &lhs_placeholder)?;
+        let rhs = build_key(&mut TemplateDiagnostics::new(), &rhs_placeholder)?;
+        let (Some(lhs), Some(rhs)) = (lhs.try_into_plain_text(), rhs.try_into_plain_text()) else {
+            let message = format!("Cannot compare items of type `{ty}`");
+            let span = key_node.map_or(function.args_span, |node| node.span);
+            return Err(TemplateParseError::expression(message, span));
+        };
+        Box::new((lhs, rhs).map(|(l, r)| l == r))
+    };
+    let out_property = self_property.and_then(move |items| {
+        let mut unique_items: Vec<O> = Vec::with_capacity(items.len());
+        for item in items {
+            let is_duplicate = lhs_placeholder.with_value(item.clone(), || {
+                // Linear search since items aren't hashable in general.
+                for unique_item in &unique_items {
+                    if rhs_placeholder.with_value(unique_item.clone(), || key_eq.extract())? {
+                        return Ok(true);
+                    }
+                }
+                Ok::<_, TemplatePropertyError>(false)
+            })?;
+            if !is_duplicate {
+                unique_items.push(item);
+            }
+        }
+        Ok(unique_items)
+    });
+    Ok(wrap_list(Box::new(out_property)))
+}
+
 /// Builds lambda expression to be evaluated with the provided arguments.
 /// `arg_fns` is usually an array of wrapped [`PropertyPlaceholder`]s.
 fn build_lambda_expression<'a, 'i, P: IntoTemplateProperty<'a>, T>(
@@ -1534,6 +2164,14 @@ fn builtin_functions<'a, L: TemplateLanguage<'a> + ?Sized>() -> TemplateBuildFun
             content, labels,
         ))))
     });
+    map.insert("hyperlink", |language, diagnostics, build_ctx, function| {
+        let [url_node, content_node] = function.expect_exact_arguments()?;
+        let url = expect_plain_text_expression(language, diagnostics, build_ctx, url_node)?;
+        let content = expect_template_expression(language, diagnostics, build_ctx, content_node)?;
+        Ok(L::wrap_template(Box::new(HyperlinkTemplate::new(
+            content, url,
+        ))))
+    });
     map.insert(
         "raw_escape_sequence",
         |language, diagnostics, build_ctx, function| {
@@ -1557,6 +2195,25 @@ fn builtin_functions<'a, L: TemplateLanguage<'a> + ?Sized>() -> TemplateBuildFun
         let template = ConditionalTemplate::new(condition, true_template, false_template);
         Ok(L::wrap_template(Box::new(template)))
     });
+    map.insert("let", |language, diagnostics, build_ctx, function| {
+        let [name_node, value_node, body_node] = function.expect_exact_arguments()?;
+        let name = template_parser::expect_identifier(name_node)?;
+        let value = build_expression(language, diagnostics, build_ctx, value_node)?;
+        let value_type = value.type_name();
+        let scope = MemoScope::new();
+        let make_value = value.try_into_memoized(&scope).ok_or_else(|| {
+            let message = format!("Expression of type `{value_type}` cannot be bound to a name");
+            TemplateParseError::expression(message, value_node.span)
+        })?;
+        let mut local_variables = build_ctx.local_variables.clone();
+        local_variables.insert(name, &*make_value);
+        let inner_build_ctx = BuildContext {
+            local_variables,
+            self_variable: build_ctx.self_variable,
+        };
+        let body = expect_template_expression(language, diagnostics, &inner_build_ctx, body_node)?;
+        Ok(L::wrap_template(Box::new(LetTemplate::new(scope, body))))
+    });
     map.insert("coalesce", |language, diagnostics, build_ctx, function| {
         let contents = function
             .args
@@ -1565,6 +2222,14 @@ fn builtin_functions<'a, L: TemplateLanguage<'a> + ?Sized>() -> TemplateBuildFun
             .try_collect()?;
         Ok(L::wrap_template(Box::new(CoalesceTemplate(contents))))
     });
+    map.insert("columns", |language, diagnostics, build_ctx, function| {
+        let contents = function
+            .args
+            .iter()
+            .map(|node| expect_template_expression(language, diagnostics, build_ctx, node))
+            .try_collect()?;
+        Ok(L::wrap_template(Box::new(ColumnsTemplate(contents))))
+    });
     map.insert("concat", |language, diagnostics, build_ctx, function| {
         let contents = function
             .args
@@ -1842,6 +2507,22 @@ pub fn expect_integer_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
     )
 }
 
+pub fn expect_timestamp_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
+    language: &L,
+    diagnostics: &mut TemplateDiagnostics,
+    build_ctx: &BuildContext<L::Property>,
+    node: &ExpressionNode,
+) -> TemplateParseResult<Box<dyn TemplateProperty<Output = Timestamp> + 'a>> {
+    expect_expression_of_type(
+        language,
+        diagnostics,
+        build_ctx,
+        node,
+        "Timestamp",
+        |expression| expression.try_into_timestamp(),
+    )
+}
+
 /// If the given expression `node` is of `Integer` type, converts it to `isize`.
 pub fn expect_isize_expression<'a, L: TemplateLanguage<'a> + ?Sized>(
     language: &L,
@@ -1933,9 +2614,12 @@ fn expect_expression_of_type<'a, L: TemplateLanguage<'a> + ?Sized, T>(
 
 #[cfg(test)]
 mod tests {
+    use std::cell::Cell;
     use std::iter;
 
     use jj_lib::backend::MillisSinceEpoch;
+    use jj_lib::config::ConfigLayer;
+    use jj_lib::config::ConfigSource;
     use jj_lib::config::StackedConfig;
 
     use super::*;
@@ -2075,7 +2759,7 @@ mod tests {
         1 | description ()
           |             ^---
           |
-          = expected <EOI>, `++`, `||`, `&&`, `==`, `!=`, `>=`, `>`, `<=`, or `<`
+          = expected <EOI>, `++`, `||`, `&&`, `==`, `!=`, `>=`, `>`, `<=`, `<`, `+`, `-`, `*`, `/`, or `%`
         "#);
 
         insta::assert_snapshot!(env.parse_err(r#"foo"#), @r"
@@ -2401,6 +3085,35 @@ mod tests {
         insta::assert_snapshot!(
             env.render_ok(r#"-i64_min"#),
             @"<Error: Attempt to negate with overflow>");
+
+        insta::assert_snapshot!(env.render_ok(r#"1 + 2 * 3"#), @"7");
+        insta::assert_snapshot!(env.render_ok(r#"(1 + 2) * 3"#), @"9");
+        insta::assert_snapshot!(env.render_ok(r#"1 - 2 - 3"#), @"-4");
+        insta::assert_snapshot!(env.render_ok(r#"7 / 2"#), @"3");
+        insta::assert_snapshot!(env.render_ok(r#"-7 / 2"#), @"-3");
+        insta::assert_snapshot!(env.render_ok(r#"7 % 3"#), @"1");
+        insta::assert_snapshot!(env.render_ok(r#"-7 % 3"#), @"-1");
+        insta::assert_snapshot!(env.render_ok(r#"1 -1"#), @"0");
+        insta::assert_snapshot!(env.render_ok(r#"some_i64 + 1"#), @"2");
+        insta::assert_snapshot!(env.render_ok(r#"none_i64 + 1"#), @"<Error: No Integer available>");
+        insta::assert_snapshot!(env.render_ok(r#"1 + 2 ++ "x""#), @"3x");
+        insta::assert_snapshot!(env.render_ok(r#"1 + 1 == 2"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"i64_min - 1"#), @"<Error: Attempt to subtract with overflow>");
+        insta::assert_snapshot!(env.render_ok(r#"i64_min + -1"#), @"<Error: Attempt to add with overflow>");
+        insta::assert_snapshot!(env.render_ok(r#"i64_min * -1"#), @"<Error: Attempt to multiply with overflow>");
+        insta::assert_snapshot!(env.render_ok(r#"i64_min / -1"#), @"<Error: Attempt to divide with overflow>");
+        insta::assert_snapshot!(env.render_ok(r#"1 / 0"#), @"<Error: Attempt to divide by zero>");
+        insta::assert_snapshot!(env.render_ok(r#"1 % 0"#), @"<Error: Attempt to calculate the remainder with a divisor of zero>");
+        insta::assert_snapshot!(env.render_ok(r#"i64_min % -1"#), @"<Error: Attempt to calculate the remainder with overflow>");
+
+        insta::assert_snapshot!(env.parse_err(r#"1 + "a""#), @r#"
+         --> 1:5
+          |
+        1 | 1 + "a"
+          |     ^-^
+          |
+          = Expected expression of type `Integer`, but actual type is `String`
+        "#);
     }
 
     #[test]
@@ -2415,6 +3128,10 @@ mod tests {
         insta::assert_snapshot!(env.render_ok(r#"2 <= 1"#), @"false");
         insta::assert_snapshot!(env.render_ok(r#"0 < 1"#), @"true");
         insta::assert_snapshot!(env.render_ok(r#"1 < 1"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"'a' < 'b'"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"'b' <= 'a'"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"'ab' > 'a'"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"'a' >= 'a'"#), @"true");
     }
 
     #[test]
@@ -2576,6 +3293,89 @@ mod tests {
         "#);
     }
 
+    #[test]
+    fn test_list_method_first_last_reverse() {
+        let env = TestTemplateEnv::new();
+
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().first()"#), @"a");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().last()"#), @"c");
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().first().upper()"#), @"A");
+        insta::assert_snapshot!(env.render_ok(r#""".lines().first()"#), @"<Error: List is empty>");
+        insta::assert_snapshot!(env.render_ok(r#""".lines().last()"#), @"<Error: List is empty>");
+
+        insta::assert_snapshot!(env.render_ok(r#""a\nb\nc".lines().reverse()"#), @"c b a");
+        insta::assert_snapshot!(env.render_ok(r#""".lines().reverse().len()"#), @"0");
+    }
+
+    #[test]
+    fn test_list_method_any_all_sum() {
+        let env = TestTemplateEnv::new();
+
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nbb\nc".lines().any(|s| s.len() == 2)"#),
+            @"true");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nbb\nc".lines().all(|s| s.len() == 2)"#),
+            @"false");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nbb\nc".lines().all(|s| s.len() >= 1)"#),
+            @"true");
+        insta::assert_snapshot!(env.render_ok(r#""".lines().any(|s| true)"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#""".lines().all(|s| false)"#), @"true");
+
+        insta::assert_snapshot!(env.render_ok(r#""a\nbb\nccc".lines().sum(|s| s.len())"#), @"6");
+        insta::assert_snapshot!(env.render_ok(r#""".lines().sum(|s| s.len())"#), @"0");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nb".lines().sum(|s| 9223372036854775807)"#),
+            @"<Error: Attempt to add with overflow>");
+
+        insta::assert_snapshot!(env.parse_err(r#""a".lines().sum(|s| s)"#), @r#"
+         --> 1:21
+          |
+        1 | "a".lines().sum(|s| s)
+          |                     ^
+          |
+          = Expected expression of type `Integer`, but actual type is `String`
+        "#);
+    }
+
+    #[test]
+    fn test_list_method_sort_by_unique() {
+        let env = TestTemplateEnv::new();
+
+        insta::assert_snapshot!(
+            env.render_ok(r#""b\nc\na".lines().sort_by(|s| s)"#),
+            @"a b c");
+        // Sort is stable
+        insta::assert_snapshot!(
+            env.render_ok(r#""bb\nc\naa\nd".lines().sort_by(|s| s.len())"#),
+            @"c d bb aa");
+        insta::assert_snapshot!(
+            env.render_ok(r#""bb\nc\naa\nd".lines().sort_by(|s| -s.len()).join(",")"#),
+            @"bb,aa,c,d");
+        insta::assert_snapshot!(
+            env.parse_err(r#""a".lines().sort_by(|s| s.len() == 1)"#),
+            @r#"
+         --> 1:25
+          |
+        1 | "a".lines().sort_by(|s| s.len() == 1)
+          |                         ^----------^
+          |
+          = Cannot sort by key of type `Boolean`
+        "#);
+
+        insta::assert_snapshot!(
+            env.render_ok(r#""b\na\nb\nc\na".lines().unique()"#),
+            @"b a c");
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nbb\nc\ndd".lines().unique(|s| s.len())"#),
+            @"a bb");
+        // Keys which can't be compared by == are compared as plain text
+        insta::assert_snapshot!(
+            env.render_ok(r#""a\nA\nb".lines().unique(|s| label("x", s.upper()))"#),
+            @"a b");
+    }
+
     #[test]
     fn test_string_method() {
         let mut env = TestTemplateEnv::new();
@@ -2701,6 +3501,47 @@ mod tests {
             @"<Error: invalid type: sequence, expected a boolean>");
     }
 
+    #[test]
+    fn test_trailer_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("description", || {
+            L::wrap_string(Literal(
+                "subject\n\nbody\n\nChangelog: Fix a bug\nSigned-off-by: Alice\nchangelog: Add a feature\n"
+                    .to_owned(),
+            ))
+        });
+        env.add_keyword("no_trailers", || {
+            L::wrap_string(Literal("subject\n\nbody\n".to_owned()))
+        });
+
+        insta::assert_snapshot!(env.render_ok("description.trailers()"), @"
+        Changelog: Fix a bug
+        Signed-off-by: Alice
+        changelog: Add a feature
+        ");
+        insta::assert_snapshot!(env.render_ok("description.trailers().len()"), @"3");
+        insta::assert_snapshot!(
+            env.render_ok(r#"description.trailers().map(|t| t.key() ++ "=" ++ t.value()).join(",")"#),
+            @"Changelog=Fix a bug,Signed-off-by=Alice,changelog=Add a feature");
+        insta::assert_snapshot!(
+            env.render_ok(r#"description.trailers().filter(|t| t.key() == "Signed-off-by")"#),
+            @"Signed-off-by: Alice");
+        insta::assert_snapshot!(
+            env.render_ok(r#"description.trailers().get("CHANGELOG").join("\n")"#),
+            @"
+        Fix a bug
+        Add a feature
+        ");
+        insta::assert_snapshot!(
+            env.render_ok(r#"if(description.trailers().get("Reviewed-by"), "reviewed", "not reviewed")"#),
+            @"not reviewed");
+
+        insta::assert_snapshot!(env.render_ok("no_trailers.trailers()"), @"");
+        insta::assert_snapshot!(
+            env.render_ok(r#"if(no_trailers.trailers(), "trailers", "no trailers")"#),
+            @"no trailers");
+    }
+
     #[test]
     fn test_signature() {
         let mut env = TestTemplateEnv::new();
@@ -2866,6 +3707,82 @@ mod tests {
         "#);
     }
 
+    #[test]
+    fn test_timestamp_since_method() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("t0", || L::wrap_timestamp(Literal(new_timestamp(0, 0))));
+        env.add_keyword("t1", || {
+            L::wrap_timestamp(Literal(new_timestamp(
+                (((3 * 24 + 2) * 60 + 30) * 60 + 15) * 1000,
+                60,
+            )))
+        });
+
+        insta::assert_snapshot!(env.render_ok(r#"t1.since(t0)"#), @"3 days");
+        insta::assert_snapshot!(env.render_ok(r#"t0.since(t1)"#), @"-3 days");
+        insta::assert_snapshot!(env.render_ok(r#"t0.since(t0)"#), @"0 milliseconds");
+        insta::assert_snapshot!(
+            env.render_ok(r#"separate(" ", t1.since(t0).days(), t1.since(t0).hours(),
+                t1.since(t0).minutes(), t1.since(t0).seconds())"#),
+            @"3 74 4470 268215");
+        insta::assert_snapshot!(env.render_ok(r#"t0.since(t1).days()"#), @"-3");
+        insta::assert_snapshot!(env.render_ok(r#"t1.since(t0) > t0.since(t1)"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"t1.since(t0) == t1.since(t0)"#), @"true");
+
+        // Invalid type
+        insta::assert_snapshot!(env.parse_err(r#"t0.since(0)"#), @"
+         --> 1:10
+          |
+        1 | t0.since(0)
+          |          ^
+          |
+          = Expected expression of type `Timestamp`, but actual type is `Integer`
+        ");
+    }
+
+    #[test]
+    fn test_timestamp_comparison() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("t0", || L::wrap_timestamp(Literal(new_timestamp(0, 0))));
+        env.add_keyword("t0_tz", || L::wrap_timestamp(Literal(new_timestamp(0, 60))));
+        env.add_keyword("t1", || L::wrap_timestamp(Literal(new_timestamp(1000, 0))));
+
+        insta::assert_snapshot!(env.render_ok(r#"t0 < t1"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"t0 >= t1"#), @"false");
+        insta::assert_snapshot!(env.render_ok(r#"t0 == t1"#), @"false");
+        // Time zone doesn't matter
+        insta::assert_snapshot!(env.render_ok(r#"t0 == t0_tz"#), @"true");
+        insta::assert_snapshot!(env.render_ok(r#"t0 <= t0_tz"#), @"true");
+    }
+
+    #[test]
+    fn test_timestamp_in_tz_method() {
+        let mut env = TestTemplateEnv::new();
+        // 2024-01-01 12:00:00 UTC
+        env.add_keyword("winter", || {
+            L::wrap_timestamp(Literal(new_timestamp(1704110400000, 0)))
+        });
+        // 2024-07-01 12:00:00 UTC
+        env.add_keyword("summer", || {
+            L::wrap_timestamp(Literal(new_timestamp(1719835200000, 0)))
+        });
+
+        insta::assert_snapshot!(env.render_ok(r#"winter.in_tz("Europe/Berlin")"#), @"2024-01-01 13:00:00.000 +01:00");
+        insta::assert_snapshot!(env.render_ok(r#"summer.in_tz("Europe/Berlin")"#), @"2024-07-01 14:00:00.000 +02:00");
+        insta::assert_snapshot!(
+            env.render_ok(r#"summer.in_tz("America/New_York").format("%H:%M %z")"#), @"08:00 -0400");
+        insta::assert_snapshot!(env.render_ok(r#"summer.in_tz("UTC")"#), @"2024-07-01 12:00:00.000 +00:00");
+
+        insta::assert_snapshot!(env.parse_err(r#"winter.in_tz("Mars/Olympus_Mons")"#), @r#"
+         --> 1:14
+          |
+        1 | winter.in_tz("Mars/Olympus_Mons")
+          |              ^-----------------^
+          |
+          = Invalid time zone
+        "#);
+    }
+
     #[test]
     fn test_fill_function() {
         let mut env = TestTemplateEnv::new();
@@ -3081,6 +3998,88 @@ mod tests {
             @"[38;5;1mtext[39m");
     }
 
+    #[test]
+    fn test_hyperlink_function() {
+        let mut env = TestTemplateEnv::new();
+        env.add_keyword("bad_string", || L::wrap_string(new_error_property("Bad")));
+        env.add_color("error", crossterm::style::Color::DarkRed);
+
+        insta::assert_snapshot!(
+            env.render_ok(r#"hyperlink("http://example.com", "Example")"#),
+            @r"]8;;http://example.com\Example]8;;\");
+
+        // Labels are applied to the link text
+        insta::assert_snapshot!(
+            env.render_ok(r#"hyperlink("http://example.com/" ++ "x", label("error", "text"))"#),
+            @r"[38;5;1m]8;;http://example.com/x\text[39m]8;;\");
+
+        // Empty text
+        insta::assert_snapshot!(env.render_ok(r#"hyperlink("http://example.com", "")"#), @"");
+
+        // Evaluation error
+        insta::assert_snapshot!(
+            env.render_ok(r#"hyperlink(bad_string, "text")"#),
+            @"[38;5;1m<Error: Bad>[39m");
+    }
+
+    #[test]
+    fn test_link_tickets_method() {
+        let mut config = StackedConfig::with_defaults();
+        config.add_layer(
+            ConfigLayer::parse(
+                ConfigSource::User,
+                r#"
+                hyperlinks.ticket-pattern = '#(\d+)'
+                hyperlinks.ticket-url = "https://example.com/issues/$1"
+                "#,
+            )
+            .unwrap(),
+        );
+        let env = TestTemplateEnv::with_config(config);
+
+        insta::assert_snapshot!(
+            env.render_ok(r#""fix #1 and #23".link_tickets()"#),
+            @r"fix ]8;;https://example.com/issues/1\#1]8;;\ and ]8;;https://example.com/issues/23\#23]8;;\");
+        insta::assert_snapshot!(env.render_ok(r#""no tickets".link_tickets()"#), @"no tickets");
+
+        // Disabled if the pattern is empty
+        let mut config = StackedConfig::with_defaults();
+        config.add_layer(
+            ConfigLayer::parse(
+                ConfigSource::User,
+                r#"
+                hyperlinks.ticket-pattern = ''
+                hyperlinks.ticket-url = "https://example.com/issues/$1"
+                "#,
+            )
+            .unwrap(),
+        );
+        let env = TestTemplateEnv::with_config(config);
+        insta::assert_snapshot!(env.render_ok(r#""fix #1".link_tickets()"#), @"fix #1");
+
+        // Invalid pattern
+        let mut config = StackedConfig::with_defaults();
+        config.add_layer(
+            ConfigLayer::parse(
+                ConfigSource::User,
+                r#"
+                hyperlinks.ticket-pattern = '('
+                hyperlinks.ticket-url = "https://example.com/issues/$1"
+                "#,
+            )
+            .unwrap(),
+        );
+        let env = TestTemplateEnv::with_config(config);
+        insta::assert_snapshot!(env.parse_err(r#""fix #1".link_tickets()"#), @r#"
+         --> 1:10
+          |
+        1 | "fix #1".link_tickets()
+          |          ^----------^
+          |
+          = Invalid `hyperlinks.ticket-pattern`
+        "#);
+    }
+
     #[test]
     fn test_raw_escape_sequence_function_strip_labels() {
         let mut env = TestTemplateEnv::new();
@@ -3122,6 +4121,86 @@ mod tests {
             @r#"]8;;http://example.com\Example]8;;\"#);
     }
 
+    #[test]
+    fn test_let_function() {
+        let mut env = TestTemplateEnv::new();
+        let counter = Rc::new(Cell::new(0));
+        env.add_keyword("count", {
+            let counter = counter.clone();
+            move || {
+                let counter = counter.clone();
+                L::wrap_integer(Literal(()).map(move |()| {
+                    counter.set(counter.get() + 1);
+                    counter.get()
+                }))
+            }
+        });
+        env.add_keyword("description", || L::wrap_string(Literal("foo".to_owned())));
+        env.add_color("description", crossterm::style::Color::Blue);
+
+        insta::assert_snapshot!(env.render_ok(r#"let(x, 1 + 2, x * x)"#), @"9");
+        insta::assert_snapshot!(env.render_ok(r#"let(x, "a", let(y, x ++ "b", x ++ y))"#), @"aab");
+        // Inner binding shadows the outer one
+        insta::assert_snapshot!(env.render_ok(r#"let(x, 1, let(x, 2, x))"#), @"2");
+        insta::assert_snapshot!(env.render_ok(r#"let(s, "a\nb", s.lines().map(|x| x ++ s.len()))"#), @"a3 b3");
+        // Templates are shared
+        insta::assert_snapshot!(
+            env.render_ok(r#"let(t, label("description", "x"), t ++ t)"#), @"[38;5;12mx[39m[38;5;12mx[39m");
+        // Keywords are labeled, but variables aren't
+        insta::assert_snapshot!(env.render_ok(r#"let(d, description, d)"#), @"foo");
+
+        // The value is evaluated once per rendering
+        insta::assert_snapshot!(env.render_ok(r#"let(c, count, c ++ c ++ c)"#), @"111");
+        insta::assert_snapshot!(env.render_ok(r#"let(c, count, c ++ c ++ c)"#), @"222");
+        insta::assert_snapshot!(env.render_ok(r#"count ++ count"#), @"34");
+        // Templates are also rendered once
+        insta::assert_snapshot!(env.render_ok(r#"let(t, "<" ++ count ++ ">", t ++ t)"#), @"<5><5>");
+        insta::assert_snapshot!(
+            env.render_ok(r#"let(t, label("description", count), t ++ t)"#),
+            @"[38;5;12m6[39m[38;5;12m6[39m");
+
+        insta::assert_snapshot!(env.parse_err(r#"let("x", 1, x)"#), @r#"
+         --> 1:5
+          |
+        1 | let("x", 1, x)
+          |     ^-^
+          |
+          = Expected identifier
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"let(x, 1, y)"#), @"
+         --> 1:11
+          |
+        1 | let(x, 1, y)
+          |           ^
+          |
+          = Keyword `y` doesn't exist
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"let(x, 1)"#), @"
+         --> 1:5
+          |
+        1 | let(x, 1)
+          |     ^--^
+          |
+          = Function `let`: Expected 3 arguments
+        ");
+        insta::assert_snapshot!(env.parse_err(r#"let(x, "a".lines().map(|s| s), x)"#), @r#"
+         --> 1:8
+          |
+        1 | let(x, "a".lines().map(|s| s), x)
+          |        ^--------------------^
+          |
+          = Expression of type `ListTemplate` cannot be bound to a name
+        "#);
+        insta::assert_snapshot!(env.parse_err(r#"let(x, 1, x) ++ x"#), @"
+         --> 1:17
+          |
+        1 | let(x, 1, x) ++ x
+          |                 ^
+          |
+          = Keyword `x` doesn't exist
+        ");
+    }
+
     #[test]
     fn test_coalesce_function() {
         let mut env = TestTemplateEnv::new();
@@ -3164,6 +4243,20 @@ mod tests {
             @"[38;5;3ma[39mb");
     }
 
+    #[test]
+    fn test_columns_function() {
+        let mut env = TestTemplateEnv::new();
+        env.add_color("error", crossterm::style::Color::DarkRed);
+
+        // Cells are separated by a space unless laid out as a table
+        insta::assert_snapshot!(env.render_ok(r#"columns()"#), @"");
+        insta::assert_snapshot!(env.render_ok(r#"columns("a")"#), @"a");
+        insta::assert_snapshot!(env.render_ok(r#"columns("a", "b")"#), @"a b");
+        insta::assert_snapshot!(env.render_ok(r#"columns("a", "", "b")"#), @"a  b");
+        insta::assert_snapshot!(
+            env.render_ok(r#"label("error", columns("a", "b"))"#), @"[38;5;1ma b[39m");
+    }
+
     #[test]
     fn test_separate_function() {
         let mut env = TestTemplateEnv::new();
@@ -80,6 +80,11 @@ impl Rule {
             Rule::gt_op => Some(">"),
             Rule::le_op => Some("<="),
             Rule::lt_op => Some("<"),
+            Rule::add_op => Some("+"),
+            Rule::sub_op => Some("-"),
+            Rule::mul_op => Some("*"),
+            Rule::div_op => Some("/"),
+            Rule::rem_op => Some("%"),
             Rule::logical_not_op => Some("!"),
             Rule::negate_op => Some("-"),
             Rule::prefix_ops => None,
@@ -392,6 +397,16 @@ pub enum BinaryOp {
     Le,
     /// `<`
     Lt,
+    /// `+`
+    Add,
+    /// `-`
+    Sub,
+    /// `*`
+    Mul,
+    /// `/`
+    Div,
+    /// `%`
+    Rem,
 }
 
 pub type ExpressionNode<'i> = dsl_util::ExpressionNode<'i, ExpressionKind<'i>>;
@@ -527,6 +542,10 @@ fn parse_expression_node(pair: Pair<Rule>) -> TemplateParseResult<ExpressionNode
                 | Op::infix(Rule::gt_op, Assoc::Left)
                 | Op::infix(Rule::le_op, Assoc::Left)
                 | Op::infix(Rule::lt_op, Assoc::Left))
+            .op(Op::infix(Rule::add_op, Assoc::Left) | Op::infix(Rule::sub_op, Assoc::Left))
+            .op(Op::infix(Rule::mul_op, Assoc::Left)
+                | Op::infix(Rule::div_op, Assoc::Left)
+                | Op::infix(Rule::rem_op, Assoc::Left))
             .op(Op::prefix(Rule::logical_not_op) | Op::prefix(Rule::negate_op))
     });
     PRATT
@@ -552,6 +571,11 @@ fn parse_expression_node(pair: Pair<Rule>) -> TemplateParseResult<ExpressionNode
                 Rule::gt_op => BinaryOp::Gt,
                 Rule::le_op => BinaryOp::Le,
                 Rule::lt_op => BinaryOp::Lt,
+                Rule::add_op => BinaryOp::Add,
+                Rule::sub_op => BinaryOp::Sub,
+                Rule::mul_op => BinaryOp::Mul,
+                Rule::div_op => BinaryOp::Div,
+                Rule::rem_op => BinaryOp::Rem,
                 r => panic!("unexpected infix operator rule {r:?}"),
             };
             let lhs = Box::new(lhs?);
@@ -669,6 +693,28 @@ pub fn expect_string_literal_with<'a, 'i, T>(
     }
 }
 
+/// Returns the name if the `node` is an identifier.
+pub fn expect_identifier<'i>(node: &ExpressionNode<'i>) -> TemplateParseResult<&'i str> {
+    match &node.kind {
+        ExpressionKind::Identifier(name) => Ok(name),
+        ExpressionKind::Boolean(_)
+        | ExpressionKind::Integer(_)
+        | ExpressionKind::String(_)
+        | ExpressionKind::Unary(..)
+        | ExpressionKind::Binary(..)
+        | ExpressionKind::Concat(_)
+        | ExpressionKind::FunctionCall(_)
+        | ExpressionKind::MethodCall(_)
+        | ExpressionKind::Lambda(_) => Err(TemplateParseError::expression(
+            "Expected identifier",
+            node.span,
+        )),
+        ExpressionKind::AliasExpanded(id, subst) => {
+            expect_identifier(subst).map_err(|e| e.within_alias_expansion(*id, node.span))
+        }
+    }
+}
+
 /// Applies the given function if the `node` is a lambda.
 pub fn expect_lambda_with<'a, 'i, T>(
     node: &'a ExpressionNode<'i>,
@@ -889,6 +935,14 @@ mod tests {
                 "((!(x.f()) < (!(x.f()))) == ((!(x.f())) >= (!(x.f())))) || ((!(g())) != (!(g())))"
             ),
         );
+        assert_eq!(
+            parse_normalized("-x + y * z - -w % v"),
+            parse_normalized("((-x) + (y * z)) - ((-w) % v)"),
+        );
+        assert_eq!(
+            parse_normalized("x + y < z / w"),
+            parse_normalized("(x + y) < (z / w)"),
+        );
         assert_eq!(
             parse_normalized("x.f() || y == y || z"),
             parse_normalized("((x.f()) || (y == y)) || z"),
@@ -920,6 +974,11 @@ mod tests {
             parse_normalized(r"x != y ++ z"),
             parse_normalized(r"(x != y) ++ z"),
         );
+        assert_eq!(
+            parse_normalized(r"x + y ++ z"),
+            parse_normalized(r"(x + y) ++ z"),
+        );
+        assert_eq!(parse_normalized(r"x ++ -y"), parse_normalized(r"x ++ (-y)"),);
 
         // Expression span
         assert_eq!(parse_template(" ! x ").unwrap().span.as_str(), "! x");
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::Cell;
 use std::cell::RefCell;
 use std::error;
 use std::fmt;
@@ -20,9 +21,12 @@ use std::io::Write;
 use std::iter;
 use std::rc::Rc;
 
+use chrono::TimeDelta;
 use jj_lib::backend::Signature;
 use jj_lib::backend::Timestamp;
 use jj_lib::config::ConfigValue;
+use jj_lib::trailer::Trailer;
+use regex::Regex;
 
 use crate::formatter::FormatRecorder;
 use crate::formatter::Formatter;
@@ -61,6 +65,12 @@ impl<T: Template + ?Sized> Template for Box<T> {
     }
 }
 
+impl<T: Template + ?Sized> Template for Rc<T> {
+    fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
+        <T as Template>::format(self, formatter)
+    }
+}
+
 // All optional printable types should be printable, and it's unlikely to
 // implement different formatting per type.
 impl<T: Template> Template for Option<T> {
@@ -159,6 +169,12 @@ impl TimestampRange {
     }
 }
 
+impl Template for TimeDelta {
+    fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
+        write!(formatter, "{}", time_util::format_signed_duration(*self))
+    }
+}
+
 impl Template for TimestampRange {
     fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
         self.start.format(formatter)?;
@@ -174,6 +190,18 @@ impl Template for Vec<String> {
     }
 }
 
+impl Template for Trailer {
+    fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
+        write!(formatter, "{}: {}", self.key, self.value)
+    }
+}
+
+impl Template for Vec<Trailer> {
+    fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
+        format_joined(formatter, self, "\n")
+    }
+}
+
 impl Template for bool {
     fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
         let repr = if *self { "true" } else { "false" };
@@ -215,6 +243,95 @@ where
     }
 }
 
+/// Renders the content as a terminal hyperlink to the given URL.
+///
+/// The URL is ignored if it is empty, or if the formatter doesn't emit
+/// hyperlinks.
+pub struct HyperlinkTemplate<T, U> {
+    content: T,
+    url: U,
+}
+
+impl<T, U> HyperlinkTemplate<T, U> {
+    pub fn new(content: T, url: U) -> Self
+    where
+        T: Template,
+        U: TemplateProperty<Output = String>,
+    {
+        HyperlinkTemplate { content, url }
+    }
+}
+
+impl<T, U> Template for HyperlinkTemplate<T, U>
+where
+    T: Template,
+    U: TemplateProperty<Output = String>,
+{
+    fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
+        let url = match self.url.extract() {
+            Ok(url) => url,
+            Err(err) => return formatter.handle_error(err),
+        };
+        if url.is_empty() {
+            return self.content.format(formatter);
+        }
+        formatter.push_hyperlink(&url)?;
+        self.content.format(formatter)?;
+        formatter.pop_hyperlink()
+    }
+}
+
+/// Renders the string property with the substrings matching a pattern
+/// hyperlinked.
+///
+/// The URL of each match is expanded from the `url` template, in which `$0`,
+/// `$1`, etc. refer to the capture groups of the match.
+pub struct PatternLinkTemplate<P> {
+    property: P,
+    pattern: Regex,
+    url: String,
+}
+
+impl<P> PatternLinkTemplate<P> {
+    pub fn new(property: P, pattern: Regex, url: String) -> Self
+    where
+        P: TemplateProperty<Output = String>,
+    {
+        PatternLinkTemplate {
+            property,
+            pattern,
+            url,
+        }
+    }
+}
+
+impl<P> Template for PatternLinkTemplate<P>
+where
+    P: TemplateProperty<Output = String>,
+{
+    fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
+        let text = match self.property.extract() {
+            Ok(text) => text,
+            Err(err) => return formatter.handle_error(err),
+        };
+        let mut last_end = 0;
+        for captures in self.pattern.captures_iter(&text) {
+            let matched = captures.get(0).unwrap();
+            if matched.is_empty() {
+                continue;
+            }
+            let mut url = String::new();
+            captures.expand(&self.url, &mut url);
+            write!(formatter, "{}", &text[last_end..matched.start()])?;
+            formatter.push_hyperlink(&url)?;
+            write!(formatter, "{}", matched.as_str())?;
+            formatter.pop_hyperlink()?;
+            last_end = matched.end();
+        }
+        write!(formatter, "{}", &text[last_end..])
+    }
+}
+
 pub struct RawEscapeSequenceTemplate<T>(pub T);
 
 impl<T: Template> Template for RawEscapeSequenceTemplate<T> {
@@ -253,6 +370,23 @@ impl<T: Template> Template for ConcatTemplate<T> {
     }
 }
 
+/// Renders contents as cells of a table row.
+///
+/// If the output isn't laid out as a table, cells are separated by a space.
+pub struct ColumnsTemplate<T>(pub Vec<T>);
+
+impl<T: Template> Template for ColumnsTemplate<T> {
+    fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
+        for (i, template) in self.0.iter().enumerate() {
+            if i > 0 {
+                formatter.end_cell()?;
+            }
+            template.format(formatter)?;
+        }
+        Ok(())
+    }
+}
+
 /// Renders the content to buffer, and transforms it without losing labels.
 pub struct ReformatTemplate<T, F> {
     content: T,
@@ -633,6 +767,132 @@ where
     }
 }
 
+/// Scope in which the values of [`MemoizedProperty`]s are shared.
+#[derive(Clone, Debug, Default)]
+pub struct MemoScope {
+    generation: Rc<Cell<u64>>,
+}
+
+impl MemoScope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Discards the values evaluated so far.
+    pub fn invalidate(&self) {
+        self.generation.set(self.generation.get().wrapping_add(1));
+    }
+}
+
+/// Property that evaluates the inner property at most once until the `scope`
+/// gets invalidated.
+pub struct MemoizedProperty<P: TemplateProperty> {
+    property: Rc<P>,
+    scope: MemoScope,
+    cache: Rc<RefCell<Option<P::Output>>>,
+    cache_generation: Rc<Cell<u64>>,
+}
+
+impl<P: TemplateProperty> MemoizedProperty<P> {
+    pub fn new(property: P, scope: MemoScope) -> Self {
+        MemoizedProperty {
+            property: Rc::new(property),
+            scope,
+            cache: Rc::new(RefCell::new(None)),
+            cache_generation: Rc::new(Cell::new(0)),
+        }
+    }
+}
+
+// #[derive(Clone)] would require `P: Clone`
+impl<P: TemplateProperty> Clone for MemoizedProperty<P> {
+    fn clone(&self) -> Self {
+        MemoizedProperty {
+            property: self.property.clone(),
+            scope: self.scope.clone(),
+            cache: self.cache.clone(),
+            cache_generation: self.cache_generation.clone(),
+        }
+    }
+}
+
+impl<P> TemplateProperty for MemoizedProperty<P>
+where
+    P: TemplateProperty,
+    P::Output: Clone,
+{
+    type Output = P::Output;
+
+    fn extract(&self) -> Result<Self::Output, TemplatePropertyError> {
+        let generation = self.scope.generation.get();
+        if self.cache_generation.get() == generation {
+            if let Some(value) = &*self.cache.borrow() {
+                return Ok(value.clone());
+            }
+        }
+        // Errors aren't cached. They will be reported again if the property
+        // is referenced multiple times.
+        let value = self.property.extract()?;
+        *self.cache.borrow_mut() = Some(value.clone());
+        self.cache_generation.set(generation);
+        Ok(value)
+    }
+}
+
+/// Template that renders the inner template at most once until the `scope`
+/// gets invalidated, and replays the recorded output.
+pub struct MemoizedTemplate<T> {
+    template: T,
+    scope: MemoScope,
+    cache: RefCell<Option<(u64, FormatRecorder)>>,
+}
+
+impl<T: Template> MemoizedTemplate<T> {
+    pub fn new(template: T, scope: MemoScope) -> Self {
+        MemoizedTemplate {
+            template,
+            scope,
+            cache: RefCell::new(None),
+        }
+    }
+}
+
+impl<T: Template> Template for MemoizedTemplate<T> {
+    fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
+        let generation = self.scope.generation.get();
+        if let Some((cache_generation, recorder)) = &*self.cache.borrow() {
+            if *cache_generation == generation {
+                return recorder.replay(formatter.as_mut());
+            }
+        }
+        let rewrap = formatter.rewrap_fn();
+        let mut recorder = FormatRecorder::new();
+        self.template.format(&mut rewrap(&mut recorder))?;
+        recorder.replay(formatter.as_mut())?;
+        *self.cache.borrow_mut() = Some((generation, recorder));
+        Ok(())
+    }
+}
+
+/// Template that renders the `content` with fresh values of the `scope`.
+pub struct LetTemplate<T> {
+    scope: MemoScope,
+    content: T,
+}
+
+impl<T: Template> LetTemplate<T> {
+    pub fn new(scope: MemoScope, content: T) -> Self {
+        LetTemplate { scope, content }
+    }
+}
+
+impl<T: Template> Template for LetTemplate<T> {
+    fn format(&self, formatter: &mut TemplateFormatter) -> io::Result<()> {
+        self.scope.invalidate();
+        self.content.format(formatter)
+    }
+}
+
 /// Property which will be compiled into template once, and substituted later.
 #[derive(Clone, Debug)]
 pub struct PropertyPlaceholder<O> {
@@ -757,6 +1017,18 @@ impl<'a> TemplateFormatter<'a> {
         self.formatter.pop_label()
     }
 
+    pub fn push_hyperlink(&mut self, url: &str) -> io::Result<()> {
+        self.formatter.push_hyperlink(url)
+    }
+
+    pub fn pop_hyperlink(&mut self) -> io::Result<()> {
+        self.formatter.pop_hyperlink()
+    }
+
+    pub fn end_cell(&mut self) -> io::Result<()> {
+        self.formatter.end_cell()
+    }
+
     pub fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> io::Result<()> {
         self.formatter.write_fmt(args)
     }
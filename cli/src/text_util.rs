@@ -541,6 +541,141 @@ pub fn write_wrapped(
     })
 }
 
+/// Writes `rows` aligned in columns.
+///
+/// Each row is split into cells at [`Formatter::end_cell()`] boundaries, and
+/// the cells are padded to the widest cell of the same column. If the table
+/// doesn't fit in `max_width`, the widest columns are truncated. The last cell
+/// of each row isn't padded, and may be terminated by newline. Rows consisting
+/// of a single cell are written as is.
+pub fn write_table(
+    formatter: &mut dyn Formatter,
+    rows: &[FormatRecorder],
+    max_width: usize,
+) -> io::Result<()> {
+    let table = Table::new(rows);
+    for index in 0..rows.len() {
+        table.write_row(formatter, index, max_width)?;
+    }
+    Ok(())
+}
+
+/// Rows split into cells, which can be written one by one aligned in columns
+/// as [`write_table()`] does.
+///
+/// This is useful if each row has to be written separately, e.g. next to a
+/// graph.
+#[derive(Clone, Debug)]
+pub struct Table {
+    rows: Vec<Vec<FormatRecorder>>,
+    column_widths: Vec<usize>,
+}
+
+impl Table {
+    const SEPARATOR: &[u8] = b" ";
+
+    /// Splits `rows` into cells, and measures the width of each column.
+    pub fn new(rows: &[FormatRecorder]) -> Self {
+        let rows = rows
+            .iter()
+            .map(FormatRecorder::split_cells)
+            .collect::<Vec<_>>();
+        let mut column_widths: Vec<usize> = vec![];
+        for cells in rows.iter().filter(|cells| cells.len() > 1) {
+            for (i, cell) in cells.iter().enumerate() {
+                let width = cell_width(cell.data());
+                if let Some(column_width) = column_widths.get_mut(i) {
+                    *column_width = cmp::max(*column_width, width);
+                } else {
+                    column_widths.push(width);
+                }
+            }
+        }
+        Table {
+            rows,
+            column_widths,
+        }
+    }
+
+    /// Writes the row at `index`. The widest columns are truncated if the row
+    /// doesn't fit in `max_width`.
+    pub fn write_row(
+        &self,
+        formatter: &mut dyn Formatter,
+        index: usize,
+        max_width: usize,
+    ) -> io::Result<()> {
+        let cells = &self.rows[index];
+        if let [cell] = &cells[..] {
+            return cell.replay(formatter);
+        }
+        let mut column_widths = self.column_widths.clone();
+        let separators_width = column_widths.len().saturating_sub(1) * Self::SEPARATOR.len();
+        fit_column_widths(
+            &mut column_widths,
+            max_width.saturating_sub(separators_width),
+        );
+
+        let ellipsis = FormatRecorder::with_data("...");
+        for (i, (cell, &column_width)) in cells.iter().zip(&column_widths).enumerate() {
+            let is_last = i + 1 == cells.len();
+            if i > 0 {
+                formatter.write_all(Self::SEPARATOR)?;
+            }
+            let data = cell.data();
+            let is_single_line = data.trim_end_with(|c| c == '\n').find_byte(b'\n').is_none();
+            let width = if is_single_line && cell_width(data) > column_width {
+                let width = write_truncated_end(formatter, cell, &ellipsis, column_width)?;
+                if data.ends_with(b"\n") {
+                    writeln!(formatter)?;
+                }
+                width
+            } else {
+                cell.replay(formatter)?;
+                // Padding follows the last line of multi-line cell.
+                let last_line = data.lines().next_back().unwrap_or_default();
+                String::from_utf8_lossy(last_line).width()
+            };
+            if !is_last {
+                let fill_width = column_width.saturating_sub(width);
+                write!(formatter, "{:fill_width$}", "")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the display width of the widest line in the `data`.
+fn cell_width(data: &[u8]) -> usize {
+    data.lines()
+        .map(|line| String::from_utf8_lossy(line).width())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Shrinks the widest columns so the sum of the `widths` fits in
+/// `available_width`.
+fn fit_column_widths(widths: &mut [usize], available_width: usize) {
+    if widths.iter().sum::<usize>() <= available_width {
+        return;
+    }
+    let mut sorted_widths = widths.to_vec();
+    sorted_widths.sort_unstable();
+    let mut rest_width = available_width;
+    let mut max_column_width = 0;
+    for (i, &width) in sorted_widths.iter().enumerate() {
+        let num_columns = sorted_widths.len() - i;
+        if width * num_columns > rest_width {
+            max_column_width = rest_width / num_columns;
+            break;
+        }
+        rest_width -= width;
+    }
+    for width in widths {
+        *width = cmp::min(*width, max_column_width);
+    }
+}
+
 pub fn parse_author(author: &str) -> Result<(String, String), &'static str> {
     let re = regex::Regex::new(r"(?<name>.*?)\s*<(?<email>.+)>$").unwrap();
     let captures = re.captures(author).ok_or("Invalid author string")?;
@@ -586,6 +721,90 @@ mod tests {
         String::from_utf8(output).unwrap()
     }
 
+    #[test]
+    fn test_write_table() {
+        let row = |cells: &[&str]| {
+            let mut recorder = FormatRecorder::new();
+            for (i, cell) in cells.iter().enumerate() {
+                if i > 0 {
+                    recorder.end_cell().unwrap();
+                }
+                write!(recorder, "{cell}").unwrap();
+            }
+            recorder
+        };
+        let rows = [
+            row(&["a", "bbb", "c\n"]),
+            row(&["aaaa", "b", "ccc\n"]),
+            row(&["not a table row\n"]),
+            row(&["multi\nline", "b\n"]),
+        ];
+        insta::assert_snapshot!(
+            format_plain_text(|formatter| write_table(formatter, &rows, 80)),
+            @"
+        a     bbb c
+        aaaa  b   ccc
+        not a table row
+        multi
+        line  b
+        ");
+
+        // Widest columns are truncated
+        let rows = [
+            row(&["aaaaaaaaaa", "b", "cccccccccccccccccccc\n"]),
+            row(&["a", "bb", "c\n"]),
+        ];
+        insta::assert_snapshot!(
+            format_plain_text(|formatter| write_table(formatter, &rows, 20)),
+            @"
+        aaaaa... b  ccccc...
+        a        bb c
+        ");
+
+        // Rows written separately can be truncated to different widths
+        let table = Table::new(&rows);
+        insta::assert_snapshot!(
+            format_plain_text(|formatter| {
+                table.write_row(formatter, 0, 16)?;
+                table.write_row(formatter, 1, 20)
+            }),
+            @r"
+        aaa... b  ccc...
+        a        bb c
+        ");
+
+        // Labels are preserved
+        let mut recorder = FormatRecorder::new();
+        recorder.push_label("red").unwrap();
+        write!(recorder, "a").unwrap();
+        recorder.end_cell().unwrap();
+        write!(recorder, "b").unwrap();
+        recorder.pop_label().unwrap();
+        writeln!(recorder).unwrap();
+        let rows = [recorder, row(&["aaa", "b\n"])];
+        insta::assert_snapshot!(
+            format_colored(|formatter| write_table(formatter, &rows, 80)),
+            @"
+        [38;5;1ma[39m   [38;5;1mb[39m
+        aaa b
+        ");
+    }
+
+    #[test]
+    fn test_fit_column_widths() {
+        let fit = |widths: &[usize], available_width| {
+            let mut widths = widths.to_vec();
+            fit_column_widths(&mut widths, available_width);
+            widths
+        };
+        assert_eq!(fit(&[], 0), Vec::<usize>::new());
+        assert_eq!(fit(&[1, 2, 3], 6), vec![1, 2, 3]);
+        assert_eq!(fit(&[1, 2, 3], 5), vec![1, 2, 2]);
+        assert_eq!(fit(&[1, 2, 3], 4), vec![1, 1, 1]);
+        assert_eq!(fit(&[1, 10, 20], 21), vec![1, 10, 10]);
+        assert_eq!(fit(&[1, 10, 20], 0), vec![0, 0, 0]);
+    }
+
     #[test]
     fn test_elide_start() {
         // Empty string
@@ -2,6 +2,8 @@ use chrono::format::StrftimeItems;
 use chrono::DateTime;
 use chrono::FixedOffset;
 use chrono::LocalResult;
+use chrono::Offset as _;
+use chrono::TimeDelta;
 use chrono::TimeZone;
 use chrono::Utc;
 use jj_lib::backend::Timestamp;
@@ -95,3 +97,29 @@ pub fn format_duration(
         .map_err(|_: chrono::OutOfRangeError| TimestampOutOfRange)?;
     Ok(format.convert(duration))
 }
+
+/// Formats the signed `duration` in human-readable form such as "2 hours".
+pub fn format_signed_duration(duration: TimeDelta) -> String {
+    if duration.is_zero() {
+        return "0 milliseconds".to_owned();
+    }
+    let mut format = timeago::Formatter::new();
+    format.min_unit(timeago::TimeUnit::Milliseconds).ago("");
+    // abs() of TimeDelta never overflows, so to_std() should succeed.
+    let text = format.convert(duration.abs().to_std().unwrap());
+    if duration < TimeDelta::zero() {
+        format!("-{text}")
+    } else {
+        text
+    }
+}
+
+/// Returns the UTC offset in minutes of the time zone `tz` at the instant of
+/// the `timestamp`.
+pub fn tz_offset_at<Tz: TimeZone>(
+    timestamp: &Timestamp,
+    tz: &Tz,
+) -> Result<i32, TimestampOutOfRange> {
+    let datetime = datetime_from_timestamp(timestamp)?.with_timezone(tz);
+    Ok(datetime.offset().fix().local_minus_utc() / 60)
+}
@@ -248,15 +248,33 @@ fn prepare_formatter_factory(
         ColorChoice::Debug => (true, true),
         ColorChoice::Auto => (terminal, false),
     };
-    if color {
-        FormatterFactory::color(config, debug)
+    // Terminals which don't support OSC 8 are supposed to ignore it, but
+    // "dumb" terminals may print the escape sequences as is.
+    let hyperlinks = match config.get("ui.hyperlinks")? {
+        HyperlinkChoice::Always => true,
+        HyperlinkChoice::Never => false,
+        HyperlinkChoice::Auto => {
+            terminal && !matches!(env::var_os("TERM"), Some(term) if term == "dumb")
+        }
+    };
+    let factory = if color {
+        FormatterFactory::color(config, debug)?
     } else if terminal {
         // Sanitize ANSI escape codes if we're printing to a terminal. Doesn't
         // affect ANSI escape codes that originate from the formatter itself.
-        Ok(FormatterFactory::sanitized())
+        FormatterFactory::sanitized()
     } else {
-        Ok(FormatterFactory::plain_text())
-    }
+        FormatterFactory::plain_text()
+    };
+    Ok(factory.with_hyperlinks(hyperlinks))
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum HyperlinkChoice {
+    Always,
+    Never,
+    Auto,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, serde::Deserialize)]
@@ -650,6 +668,16 @@ impl Ui {
     pub fn term_width(&self) -> usize {
         term_width().unwrap_or(80).into()
     }
+
+    /// Maximum width of tables written to stdout. Tables aren't truncated if
+    /// stdout isn't a terminal.
+    pub fn table_width(&self) -> usize {
+        if io::stdout().is_terminal() {
+            self.term_width()
+        } else {
+            usize::MAX
+        }
+    }
 }
 
 #[derive(Debug)]
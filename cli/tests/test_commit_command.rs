@@ -395,6 +395,55 @@ fn test_commit_paths_warning() {
     "###);
 }
 
+#[test]
+fn test_commit_case_collision_warning() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "init", "repo"]);
+    let workspace_path = test_env.env_root().join("repo");
+
+    std::fs::write(workspace_path.join("readme"), "foo\n").unwrap();
+    std::fs::write(workspace_path.join("README"), "bar\n").unwrap();
+    if std::fs::read_to_string(workspace_path.join("readme")).unwrap() != "foo\n" {
+        eprintln!("Skipping test because the filesystem is case-insensitive");
+        return;
+    }
+    std::fs::create_dir(workspace_path.join("dir")).unwrap();
+    std::fs::write(workspace_path.join("dir").join("file"), "baz\n").unwrap();
+
+    // The snapshot warns about the new paths, so the commit doesn't
+    let (stdout, stderr) = test_env.jj_cmd_ok(&workspace_path, &["commit", "-m=first"]);
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r"
+    Warning: The working copy adds paths which only differ in case: README readme
+    Hint: These paths can't be checked out together on case-insensitive filesystems.
+    Working copy now at: rlvkpnrz a991523d (empty) (no description set)
+    Parent commit      : qpvuntsm fa1ff4e6 first
+    ");
+
+    // Paths snapshotted by an earlier command are warned about by the commit
+    std::fs::write(workspace_path.join("dir").join("FILE"), "qux\n").unwrap();
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&workspace_path, &["status"]);
+    insta::assert_snapshot!(stderr, @r"
+    Warning: The working copy adds paths which only differ in case: dir/FILE dir/file
+    Hint: These paths can't be checked out together on case-insensitive filesystems.
+    ");
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&workspace_path, &["commit", "-m=second"]);
+    insta::assert_snapshot!(stderr, @r"
+    Warning: The commit adds paths which only differ in case: dir/FILE dir/file
+    Hint: These paths can't be checked out together on case-insensitive filesystems.
+    Working copy now at: zsuskuln de45d009 (empty) (no description set)
+    Parent commit      : rlvkpnrz 94cb13b7 second
+    ");
+
+    // No warning if the collision was already there
+    std::fs::write(workspace_path.join("README"), "changed\n").unwrap();
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&workspace_path, &["commit", "-m=third"]);
+    insta::assert_snapshot!(stderr, @r"
+    Working copy now at: mzvwutvl 073726cf (empty) (no description set)
+    Parent commit      : zsuskuln 2c8e7b50 third
+    ");
+}
+
 #[test]
 fn test_commit_reset_author() {
     let test_env = TestEnvironment::default();
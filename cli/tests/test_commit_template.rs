@@ -54,6 +54,25 @@ fn test_log_parents() {
     ◆  P:
     ");
 
+    // List<Commit> supports other list operations
+    let template = r#"
+    if(parents, separate(" ",
+      parents.first().commit_id().short(),
+      parents.last().commit_id().short(),
+      parents.reverse().map(|c| c.commit_id().short()).join(","),
+      parents.any(|c| c.root()),
+    )) ++ "\n"
+    "#;
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "-T", template]);
+    insta::assert_snapshot!(stdout, @"
+    @    4db490c88528 230dd059e1b0 230dd059e1b0,4db490c88528 false
+    ├─╮
+    ○ │  230dd059e1b0 230dd059e1b0 230dd059e1b0 false
+    ├─╯
+    ○  000000000000 000000000000 000000000000 true
+    ◆
+    ");
+
     let template = r#"parents.map(|c| c.commit_id().shortest(4))"#;
     let stdout = test_env.jj_cmd_success(
         &repo_path,
@@ -626,6 +645,22 @@ fn test_log_bookmarks() {
     ◆
     "#);
 
+    let template = r#"
+    bookmarks.sort_by(|b| b.name()).unique(|b| b.name()).map(|b| b.name()).join(", ")
+    "#;
+    let output = test_env.jj_cmd_success(&workspace_root, &["log", "-T", template]);
+    insta::assert_snapshot!(output, @"
+    @  bookmark2, new-bookmark
+    ○  bookmark2, unchanged
+    │ ○  bookmark3
+    ├─╯
+    │ ○  bookmark3
+    ├─╯
+    │ ○  bookmark1
+    ├─╯
+    ◆
+    ");
+
     let template = r#"separate(" ", "L:", local_bookmarks, "R:", remote_bookmarks)"#;
     let output = test_env.jj_cmd_success(&workspace_root, &["log", "-T", template]);
     insta::assert_snapshot!(output, @r#"
@@ -1345,6 +1380,33 @@ fn test_log_diff_predefined_formats() {
     * empty len=0
     ");
 
+    // custom template with hunks()
+    let template = indoc! {r#"
+        diff.files().map(|e| e.hunks(0).map(|h| separate(" ",
+          e.path() ++ ":",
+          "-" ++ h.left_start() ++ "," ++ h.left_len(),
+          "+" ++ h.right_start() ++ "," ++ h.right_len(),
+          "removed=" ++ h.removed_lines().map(|l| "'" ++ l ++ "'"),
+          "added=" ++ h.added_lines().map(|l| "'" ++ l ++ "'"),
+        ) ++ "\n").join("")).join("")
+    "#};
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "--no-graph", "-r@", "-T", template]);
+    insta::assert_snapshot!(stdout, @"
+    file1: -2,0 +3,1 removed= added='c'
+    file2: -1,1 +1,2 removed='a' added='b' 'c'
+    ");
+
+    // first changed line of each file
+    let template = indoc! {r#"
+        diff.files().filter(|e| e.hunks()).map(|e|
+          e.path() ++ ":" ++ e.hunks(0).first().right_start() ++ "\n").join("")
+    "#};
+    let stdout = test_env.jj_cmd_success(&repo_path, &["log", "--no-graph", "-r@", "-T", template]);
+    insta::assert_snapshot!(stdout, @"
+    file1:3
+    file2:1
+    ");
+
     // custom diff stat template
     let template = indoc! {r#"
         concat(
@@ -535,7 +535,8 @@ fn test_config() {
 
     let stdout = test_env.jj_cmd_success(dir, &["--", "jj", "config", "get", "c"]);
     insta::assert_snapshot!(stdout, @r"
-    core.fsmonitor	Whether to use an external filesystem monitor, useful for large repos
+    core.builtin-fsmonitor.auto-start	Whether to start the built-in filesystem monitor daemon automatically when it isn't running.
+    core.fsmonitor	Whether to use a filesystem monitor, useful for large repos
     core.watchman.register_snapshot_trigger	Whether to use triggers to monitor for changes in the background.
     ");
 
@@ -543,14 +544,17 @@ fn test_config() {
     insta::assert_snapshot!(stdout, @r"
     colors	Mapping from jj formatter labels to colors
     core
-    core.fsmonitor	Whether to use an external filesystem monitor, useful for large repos
+    core.builtin-fsmonitor
+    core.builtin-fsmonitor.auto-start	Whether to start the built-in filesystem monitor daemon automatically when it isn't running.
+    core.fsmonitor	Whether to use a filesystem monitor, useful for large repos
     core.watchman
     core.watchman.register_snapshot_trigger	Whether to use triggers to monitor for changes in the background.
     ");
 
     let stdout = test_env.jj_cmd_success(dir, &["--", "jj", "log", "--config", "c"]);
     insta::assert_snapshot!(stdout, @r"
-    core.fsmonitor=	Whether to use an external filesystem monitor, useful for large repos
+    core.builtin-fsmonitor.auto-start=	Whether to start the built-in filesystem monitor daemon automatically when it isn't running.
+    core.fsmonitor=	Whether to use a filesystem monitor, useful for large repos
     core.watchman.register_snapshot_trigger=	Whether to use triggers to monitor for changes in the background.
     ");
 
@@ -128,7 +128,7 @@ fn test_debug_reindex() {
     );
     let (stdout, stderr) = test_env.jj_cmd_ok(&workspace_path, &["debug", "reindex"]);
     assert_snapshot!(stdout, @"");
-    insta::assert_snapshot!(stderr, @r###"
+    assert_snapshot!(stderr, @r###"
     Finished indexing 4 commits.
     "###);
     let stdout = test_env.jj_cmd_success(&workspace_path, &["debug", "index"]);
@@ -240,6 +240,47 @@ fn test_debug_operation_id() {
     );
 }
 
+#[cfg(all(unix, feature = "fsmonitor-daemon"))]
+#[test]
+fn test_debug_fsmonitor() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "init", "repo"]);
+    let workspace_path = test_env.env_root().join("repo");
+    let socket_path = workspace_path.join(".jj/working_copy/fsmonitor.sock");
+
+    let stdout = test_env.jj_cmd_success(&workspace_path, &["debug", "fsmonitor", "status"]);
+    assert_snapshot!(stdout, @r#"
+    The built-in filesystem monitor is disabled. Set `core.fsmonitor="builtin"` to enable.
+    The daemon is not running.
+    "#);
+    let (stdout, stderr) = test_env.jj_cmd_ok(&workspace_path, &["debug", "fsmonitor", "stop"]);
+    assert_snapshot!(stdout, @"");
+    assert_snapshot!(stderr, @"The filesystem monitor daemon is not running");
+
+    // The daemon is started on demand when taking a snapshot
+    test_env.add_config(r#"core.fsmonitor = "builtin""#);
+    test_env.jj_cmd_ok(&workspace_path, &["status"]);
+    for _ in 0..1000 {
+        if socket_path.exists() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    let stdout = test_env.jj_cmd_success(&workspace_path, &["debug", "fsmonitor", "status"]);
+    let stdout = Regex::new(r"pid \d+")
+        .unwrap()
+        .replace(&stdout, "pid [pid]");
+    assert_snapshot!(stdout, @"
+    The built-in filesystem monitor is enabled via `core.fsmonitor`.
+    The daemon is running (pid [pid]).
+    Paths changed since the daemon started: 0
+    ");
+
+    let (stdout, stderr) = test_env.jj_cmd_ok(&workspace_path, &["debug", "fsmonitor", "stop"]);
+    assert_snapshot!(stdout, @"");
+    assert_snapshot!(stderr, @"Stopped the filesystem monitor daemon");
+}
+
 fn filter_index_stats(text: &str) -> String {
     let regex = Regex::new(r"    Name: [0-9a-z]+").unwrap();
     regex.replace_all(text, "    Name: [hash]").to_string()
@@ -220,3 +220,37 @@ fn test_track_ignored() {
     file1
     "###);
 }
+
+#[test]
+fn test_track_interactive() {
+    let test_env = TestEnvironment::default();
+    test_env.add_config(r#"snapshot.auto-track = 'none()'"#);
+    test_env.add_config(r#"snapshot.ignore-untracked = 'glob:"*.log"'"#);
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "init", "repo"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("file1"), "initial").unwrap();
+    std::fs::write(repo_path.join("file2"), "initial").unwrap();
+    std::fs::write(repo_path.join("file3"), "initial").unwrap();
+    std::fs::write(repo_path.join("output.log"), "initial").unwrap();
+
+    // Only the answered files are tracked, and ignored files aren't asked about
+    let (stdout, stderr) =
+        test_env.jj_cmd_stdin_ok(&repo_path, &["file", "track", "-i"], "y\nn\ny\n");
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @"Track file1? (yN): Track file2? (yN): Track file3? (yN):");
+    let stdout = test_env.jj_cmd_success(&repo_path, &["file", "list"]);
+    insta::assert_snapshot!(stdout, @r"
+    file1
+    file3
+    ");
+
+    // Candidates can be restricted by paths
+    std::fs::write(repo_path.join("file4"), "initial").unwrap();
+    let (stdout, stderr) =
+        test_env.jj_cmd_stdin_ok(&repo_path, &["file", "track", "-i", "file2"], "n\n");
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r"
+    Track file2? (yN): No files selected to track
+    ");
+}
@@ -0,0 +1,183 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use crate::common::TestEnvironment;
+
+/// Returns the paths of all LFS objects in the `lfs_dir`.
+fn list_lfs_objects(lfs_dir: &Path) -> Vec<String> {
+    let objects_dir = lfs_dir.join("objects");
+    if !objects_dir.is_dir() {
+        return vec![];
+    }
+    let mut objects: Vec<_> = walkdir(&objects_dir)
+        .into_iter()
+        .map(|path| {
+            path.strip_prefix(&objects_dir)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .replace('\\', "/")
+        })
+        .collect();
+    objects.sort();
+    objects
+}
+
+fn walkdir(dir: &Path) -> Vec<std::path::PathBuf> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(walkdir(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+#[test]
+fn test_git_lfs_clone_push_fetch() {
+    let test_env = TestEnvironment::default();
+    test_env.add_config(r#"revset-aliases."immutable_heads()" = "none()""#);
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "init", "origin"]);
+    let origin_path = test_env.env_root().join("origin");
+    let origin_git_repo_path = origin_path
+        .join(".jj")
+        .join("repo")
+        .join("store")
+        .join("git");
+    let origin_lfs_dir = origin_git_repo_path.join("lfs");
+
+    std::fs::write(origin_path.join(".gitattributes"), "*.bin filter=lfs\n").unwrap();
+    std::fs::write(origin_path.join("file.bin"), "content 1\n").unwrap();
+    test_env.jj_cmd_ok(&origin_path, &["commit", "-m=add file"]);
+    test_env.jj_cmd_ok(&origin_path, &["bookmark", "create", "-r@-", "main"]);
+    test_env.jj_cmd_ok(&origin_path, &["git", "export"]);
+    let stdout = test_env.jj_cmd_success(&origin_path, &["file", "show", "-r@-", "file.bin"]);
+    insta::assert_snapshot!(stdout, @"
+    version https://git-lfs.github.com/spec/v1
+    oid sha256:59e709625682d8e5a571a2b11fa44b54c393869f3a49bb67bea1802fc6937972
+    size 10
+    ");
+    insta::assert_snapshot!(list_lfs_objects(&origin_lfs_dir).join("\n"), @"59/e7/59e709625682d8e5a571a2b11fa44b54c393869f3a49bb67bea1802fc6937972");
+
+    // Cloning fetches the LFS objects
+    let (_stdout, stderr) = test_env.jj_cmd_ok(
+        test_env.env_root(),
+        &[
+            "git",
+            "clone",
+            "--config=git.auto-local-bookmark=true",
+            origin_git_repo_path.to_str().unwrap(),
+            "local",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r#"
+    Fetching into new repo in "$TEST_ENV/local"
+    bookmark: main@origin [new] tracked
+    Fetched 1 LFS objects from origin
+    "#);
+    let local_path = test_env.env_root().join("local");
+    let local_lfs_dir = local_path
+        .join(".jj")
+        .join("repo")
+        .join("store")
+        .join("git")
+        .join("lfs");
+    assert_eq!(
+        list_lfs_objects(&local_lfs_dir),
+        list_lfs_objects(&origin_lfs_dir)
+    );
+    test_env.jj_cmd_ok(&local_path, &["new", "main"]);
+    assert_eq!(
+        std::fs::read_to_string(local_path.join("file.bin")).unwrap(),
+        "content 1\n"
+    );
+
+    // Pushing copies new LFS objects to the remote
+    std::fs::write(local_path.join("file.bin"), "content 2\n").unwrap();
+    test_env.jj_cmd_ok(&local_path, &["describe", "-m=update file"]);
+    test_env.jj_cmd_ok(&local_path, &["bookmark", "set", "main"]);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&local_path, &["git", "push"]);
+    insta::assert_snapshot!(stderr, @"
+    Changes to push to origin:
+      Move forward bookmark main from c2873422f4e4 to 5507811e6064
+    Pushed 1 LFS objects to origin
+    ");
+    assert_eq!(
+        list_lfs_objects(&origin_lfs_dir),
+        list_lfs_objects(&local_lfs_dir)
+    );
+    assert_eq!(list_lfs_objects(&origin_lfs_dir).len(), 2);
+
+    // Fetching copies new LFS objects from the remote
+    test_env.jj_cmd_ok(&origin_path, &["git", "import"]);
+    test_env.jj_cmd_ok(&origin_path, &["new", "main"]);
+    std::fs::write(origin_path.join("file.bin"), "content 3\n").unwrap();
+    test_env.jj_cmd_ok(&origin_path, &["commit", "-m=update file again"]);
+    test_env.jj_cmd_ok(&origin_path, &["bookmark", "set", "-r@-", "main"]);
+    test_env.jj_cmd_ok(&origin_path, &["git", "export"]);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&local_path, &["git", "fetch"]);
+    insta::assert_snapshot!(stderr, @"
+    bookmark: main@origin [updated] tracked
+    Fetched 1 LFS objects from origin
+    ");
+    assert_eq!(list_lfs_objects(&local_lfs_dir).len(), 3);
+    test_env.jj_cmd_ok(&local_path, &["new", "main"]);
+    assert_eq!(
+        std::fs::read_to_string(local_path.join("file.bin")).unwrap(),
+        "content 3\n"
+    );
+
+    // Objects added by intermediate commits are also pushed
+    std::fs::write(local_path.join("file.bin"), "content 4\n").unwrap();
+    test_env.jj_cmd_ok(&local_path, &["commit", "-m=update file 4"]);
+    std::fs::write(local_path.join("file.bin"), "content 5\n").unwrap();
+    test_env.jj_cmd_ok(&local_path, &["commit", "-m=update file 5"]);
+    test_env.jj_cmd_ok(&local_path, &["bookmark", "set", "-r@-", "main"]);
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&local_path, &["git", "push"]);
+    insta::assert_snapshot!(stderr, @"
+    Changes to push to origin:
+      Move forward bookmark main from 825f19ed9272 to 3745b7657ed1
+    Pushed 2 LFS objects to origin
+    ");
+    assert_eq!(list_lfs_objects(&origin_lfs_dir).len(), 5);
+
+    // Bookmarks aren't pushed if LFS objects are missing
+    let old_objects = list_lfs_objects(&local_lfs_dir);
+    std::fs::write(local_path.join("file.bin"), "content 6\n").unwrap();
+    test_env.jj_cmd_ok(&local_path, &["commit", "-m=update file 6"]);
+    test_env.jj_cmd_ok(&local_path, &["bookmark", "set", "-r@-", "main"]);
+    let new_objects = list_lfs_objects(&local_lfs_dir)
+        .into_iter()
+        .filter(|path| !old_objects.contains(path))
+        .collect::<Vec<_>>();
+    assert_eq!(new_objects.len(), 1);
+    std::fs::remove_file(local_lfs_dir.join("objects").join(&new_objects[0])).unwrap();
+    let stderr = test_env.jj_cmd_failure(&local_path, &["git", "push"]);
+    insta::assert_snapshot!(stderr, @r"
+    Changes to push to origin:
+      Move forward bookmark main from 3745b7657ed1 to ad2d52019a80
+    Error: LFS objects not found: c39a16d26d75caae308dff07a3eb38f680eef35c3ca7718e6cb51e4153168894
+    ");
+    assert_eq!(list_lfs_objects(&origin_lfs_dir).len(), 5);
+    let stdout = test_env.jj_cmd_success(&local_path, &["bookmark", "list", "main"]);
+    insta::assert_snapshot!(stdout, @r"
+    main: xtnwkqum ad2d5201 update file 6
+      @origin (behind by 1 commits): xznxytkn 3745b765 update file 5
+    ");
+}
@@ -140,3 +140,112 @@ fn test_gitignores_ignored_file_in_target_commit() {
     +contents in working copy
     "###);
 }
+
+#[test]
+fn test_gitignores_jjignore() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "init", "repo"]);
+    let workspace_root = test_env.env_root().join("repo");
+
+    // .jjignore takes precedence over .gitignore in the same directory, and
+    // files in subdirectories take precedence over their parents
+    std::fs::write(workspace_root.join(".gitignore"), "*.log\n").unwrap();
+    std::fs::write(workspace_root.join(".jjignore"), "!keep.log\n/build\n").unwrap();
+    std::fs::create_dir(workspace_root.join("sub")).unwrap();
+    std::fs::write(workspace_root.join("sub").join(".gitignore"), "!*.log\n").unwrap();
+
+    std::fs::write(workspace_root.join("a.log"), "").unwrap();
+    std::fs::write(workspace_root.join("keep.log"), "").unwrap();
+    std::fs::create_dir(workspace_root.join("build")).unwrap();
+    std::fs::write(workspace_root.join("build").join("out"), "").unwrap();
+    std::fs::write(workspace_root.join("sub").join("b.log"), "").unwrap();
+
+    let stdout = test_env.jj_cmd_success(&workspace_root, &["diff", "-s"]);
+    insta::assert_snapshot!(stdout.replace('\\', "/"), @"
+    A .gitignore
+    A .jjignore
+    A keep.log
+    A sub/.gitignore
+    A sub/b.log
+    ");
+}
+
+#[test]
+fn test_gitignores_info_exclude_in_non_colocated_workspace() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "init", "repo"]);
+    let workspace_root = test_env.env_root().join("repo");
+    // A Git repo at the workspace root that isn't backing the jj repo
+    git2::Repository::init(&workspace_root).unwrap();
+    std::fs::write(
+        workspace_root.join(".git").join("info").join("exclude"),
+        "ignored\n",
+    )
+    .unwrap();
+
+    std::fs::write(workspace_root.join("ignored"), "").unwrap();
+    std::fs::write(workspace_root.join("not-ignored"), "").unwrap();
+    let stdout = test_env.jj_cmd_success(&workspace_root, &["diff", "-s"]);
+    insta::assert_snapshot!(stdout, @"A not-ignored");
+}
+
+#[test]
+fn test_file_ignored() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "init", "--colocate", "repo"]);
+    let workspace_root = test_env.env_root().join("repo");
+
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(workspace_root.join(".git").join("config"))
+        .unwrap();
+    file.write_all(b"[core]\nexcludesFile=../my-ignores\n")
+        .unwrap();
+    drop(file);
+    std::fs::write(test_env.env_root().join("my-ignores"), "*.tmp\n").unwrap();
+    std::fs::write(
+        workspace_root.join(".git").join("info").join("exclude"),
+        "# local\n/local\n",
+    )
+    .unwrap();
+    std::fs::write(workspace_root.join(".gitignore"), "*.o\ntarget/\n").unwrap();
+    std::fs::create_dir(workspace_root.join("sub")).unwrap();
+    std::fs::write(workspace_root.join("sub").join(".jjignore"), "!keep.o\n").unwrap();
+    std::fs::create_dir(workspace_root.join("target")).unwrap();
+
+    let stdout = test_env.jj_cmd_success(
+        &workspace_root,
+        &[
+            "file",
+            "ignored",
+            "a.tmp",
+            "local",
+            "sub/local",
+            "sub/a.o",
+            "sub/keep.o",
+            "target",
+            "target/debug/out",
+            "src/lib.rs",
+        ],
+    );
+    insta::assert_snapshot!(stdout.replace('\\', "/"), @"
+    a.tmp: ignored by $TEST_ENV/repo/../my-ignores:1 (*.tmp)
+    local: ignored by .git/info/exclude:2 (/local)
+    sub/local: not ignored
+    sub/a.o: ignored by .gitignore:1 (*.o)
+    sub/keep.o: not ignored, re-included by sub/.jjignore:1 (!keep.o)
+    target: ignored by .gitignore:2 (target/)
+    target/debug/out: ignored by .gitignore:2 (target/)
+    src/lib.rs: not ignored
+    ");
+
+    // Paths are relative to the current directory
+    let stdout = test_env.jj_cmd_success(
+        &workspace_root.join("sub"),
+        &["file", "ignored", "a.o", "keep.o"],
+    );
+    insta::assert_snapshot!(stdout.replace('\\', "/"), @"
+    a.o: ignored by ../.gitignore:1 (*.o)
+    keep.o: not ignored, re-included by .jjignore:1 (!keep.o)
+    ");
+}
@@ -317,6 +317,27 @@ fn test_op_log_template() {
     ○  00000 false @ 1970-01-01 00:00:00.000 +00:00 1970-01-01 00:00:00.000 +00:00 less than a microsecond
     "#);
 
+    // Columns are aligned across operations
+    insta::assert_snapshot!(
+        render(r#"columns(id.short(5), user, description.first_line()) ++ "\n""#), @r#"
+    @  eac75 test-username@host.example.com add workspace 'default'
+    ○  00000 @
+    "#);
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "op",
+            "log",
+            "--no-graph",
+            "-T",
+            r#"columns(description.first_line(), id.short(5)) ++ "\n""#,
+        ],
+    );
+    insta::assert_snapshot!(stdout, @r#"
+    add workspace 'default' eac75
+                            00000
+    "#);
+
     // Negative length shouldn't cause panic.
     insta::assert_snapshot!(render(r#"id.short(-1) ++ "|""#), @r#"
     @  <Error: out of range integral type conversion attempted>|
@@ -341,6 +362,45 @@ fn test_op_log_template() {
     "#);
 }
 
+#[test]
+fn test_op_log_template_changes() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "init", "repo"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m", "a"]);
+    test_env.jj_cmd_ok(&repo_path, &["bookmark", "create", "-r@", "foo", "bar"]);
+    test_env.jj_cmd_ok(&repo_path, &["new", "-m", "b"]);
+    test_env.jj_cmd_ok(&repo_path, &["bookmark", "set", "-r@", "foo"]);
+    test_env.jj_cmd_ok(&repo_path, &["bookmark", "delete", "bar"]);
+    test_env.jj_cmd_ok(&repo_path, &["describe", "-r@-", "-m", "a2"]);
+    test_env.jj_cmd_ok(&repo_path, &["abandon", "@"]);
+
+    let template = r#"
+        separate(" ",
+          description.first_line(),
+          if(added_bookmarks, "added " ++ added_bookmarks.join(",")),
+          if(removed_bookmarks, "removed " ++ removed_bookmarks.join(",")),
+          if(moved_bookmarks, "moved " ++ moved_bookmarks.join(",")),
+          if(added_tags || removed_tags || moved_tags, "tags changed"),
+          if(rewritten_count > 0, "rewrote " ++ rewritten_count),
+          if(abandoned_count > 0, "abandoned " ++ abandoned_count),
+          if(workspaces, "in " ++ workspaces.join(",")),
+        ) ++ "\n"
+    "#;
+    let stdout = test_env.jj_cmd_success(&repo_path, &["op", "log", "-T", template]);
+    insta::assert_snapshot!(stdout, @"
+    @  abandon commit d73cb70de4cd02c6db1ad2e749cf07140ee29e25 removed foo abandoned 1 in default
+    ○  describe commit f19b941f58d28712c8ecbe19f13943d3b8d44df3 moved foo rewrote 2 in default
+    ○  delete bookmark bar removed bar
+    ○  point bookmark foo to commit 329d8f9d019c654d91db61554107eb66e4a771ab moved foo
+    ○  new empty commit in default
+    ○  create bookmark foo, bar pointing to commit f19b941f58d28712c8ecbe19f13943d3b8d44df3 added bar,foo
+    ○  new empty commit in default
+    ○  add workspace 'default' in default
+    ○
+    ");
+}
+
 #[test]
 fn test_op_log_builtin_templates() {
     let test_env = TestEnvironment::default();
@@ -2802,6 +2802,69 @@ fn test_rebase_skip_if_on_destination() {
     "###);
 }
 
+#[test]
+fn test_rebase_with_merge_driver() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "init", "repo"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.add_config(
+        r#"
+        [merge-drivers.changelog]
+        files = "CHANGELOG.md"
+        command = ":union"
+        "#,
+    );
+
+    let changelog_path = repo_path.join("CHANGELOG.md");
+    std::fs::write(&changelog_path, "# Changelog\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["bookmark", "create", "base"]);
+    test_env.jj_cmd_ok(&repo_path, &["new", "base", "-m", "a"]);
+    std::fs::write(&changelog_path, "# Changelog\n* a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["bookmark", "create", "a"]);
+    test_env.jj_cmd_ok(&repo_path, &["new", "base", "-m", "b"]);
+    std::fs::write(&changelog_path, "# Changelog\n* b\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["bookmark", "create", "b"]);
+
+    // The conflicting additions are both kept
+    test_env.jj_cmd_ok(&repo_path, &["rebase", "-r", "b", "-d", "a"]);
+    let stdout = test_env.jj_cmd_success(&repo_path, &["file", "show", "-r", "b", "CHANGELOG.md"]);
+    insta::assert_snapshot!(stdout, @r"
+    # Changelog
+    * a
+    * b
+    ");
+
+    // Invalid driver configuration is reported
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &["log", "--config=merge-drivers.changelog.command=':unknown'"],
+    );
+    insta::assert_snapshot!(stderr, @r"
+    Config error: Invalid `merge-drivers.changelog.command`: Unknown built-in merge driver `:unknown`
+    For help, see https://jj-vcs.github.io/jj/latest/config/.
+    ");
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &[
+            "log",
+            r#"--config=merge-drivers.changelog.command="sh -c 'cat""#,
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r"
+    Config error: Invalid `merge-drivers.changelog.command`: Command has invalid quoting
+    For help, see https://jj-vcs.github.io/jj/latest/config/.
+    ");
+
+    // Invalid merge options are reported
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["log", "--config=merge.hunk-level='char'"]);
+    insta::assert_snapshot!(stderr, @r"
+    Config error: Invalid type or value for merge.hunk-level
+    Caused by: unknown variant `char`, expected `line` or `word`
+
+    For help, see https://jj-vcs.github.io/jj/latest/config/.
+    ");
+}
+
 fn get_log_output(test_env: &TestEnvironment, repo_path: &Path) -> String {
     let template = "bookmarks ++ surround(': ', '', parents.map(|c| c.bookmarks()))";
     test_env.jj_cmd_success(repo_path, &["log", "-T", template])
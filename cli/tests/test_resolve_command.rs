@@ -650,7 +650,7 @@ fn test_baseless_conflict_input_files() {
 
 #[test]
 fn test_too_many_parents() {
-    let test_env = TestEnvironment::default();
+    let mut test_env = TestEnvironment::default();
     test_env.jj_cmd_ok(test_env.env_root(), &["git", "init", "repo"]);
     let repo_path = test_env.env_root().join("repo");
 
@@ -669,11 +669,12 @@ fn test_too_many_parents() {
     file    [38;5;1m3-sided[38;5;3m conflict[39m
     "###);
 
+    // External merge tools only support 3-way conflicts
+    test_env.set_up_fake_editor();
     let error = test_env.jj_cmd_failure(&repo_path, &["resolve"]);
     insta::assert_snapshot!(error, @r###"
-    Hint: Using default editor ':builtin'; run `jj config set --user ui.merge-editor :builtin` to disable this message.
     Error: Failed to resolve conflicts
-    Caused by: The conflict at "file" has 3 sides. At most 2 sides are supported.
+    Caused by: The conflict at "file" has 3 sides. At most 2 sides are supported by external merge tools.
     "###);
 }
 
@@ -62,12 +62,19 @@ fn test_sparse_manage_patterns() {
     std::fs::create_dir(&sub_dir).unwrap();
 
     // Not a workspace-relative path
-    let stderr = test_env.jj_cmd_cli_error(&sub_dir, &["sparse", "set", "--add=../file2"]);
-    insta::assert_snapshot!(stderr, @r###"
-    error: invalid value '../file2' for '--add <ADD>': Invalid component ".." in repo-relative path "../file2"
-
-    For more information, try '--help'.
-    "###);
+    let stderr = test_env.jj_cmd_failure(&sub_dir, &["sparse", "set", "--add=../file2"]);
+    insta::assert_snapshot!(stderr, @r#"
+    Error: Failed to parse fileset: Invalid file pattern
+    Caused by:
+    1:  --> 1:1
+      |
+    1 | ../file2
+      | ^------^
+      |
+      = Invalid file pattern
+    2: Path "../file2" is not in the repo "."
+    3: Invalid component ".." in repo-relative path "../file2"
+    "#);
 
     // Can `--add` a few files
     let (stdout, stderr) = test_env.jj_cmd_ok(
@@ -175,6 +182,81 @@ fn test_sparse_manage_patterns() {
     "###);
 }
 
+#[test]
+fn test_sparse_fileset_patterns() {
+    let mut test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "init", "repo"]);
+    let repo_path = test_env.env_root().join("repo");
+    let edit_script = test_env.set_up_fake_editor();
+
+    std::fs::create_dir_all(repo_path.join("src/testdata")).unwrap();
+    std::fs::create_dir(repo_path.join("docs")).unwrap();
+    std::fs::write(repo_path.join("README.md"), "contents").unwrap();
+    std::fs::write(repo_path.join("src/lib.rs"), "contents").unwrap();
+    std::fs::write(repo_path.join("src/testdata/big.bin"), "contents").unwrap();
+    std::fs::write(repo_path.join("docs/index.md"), "contents").unwrap();
+
+    // Can exclude a subdirectory and add files by glob
+    let (stdout, stderr) = test_env.jj_cmd_ok(
+        &repo_path,
+        &[
+            "sparse",
+            "set",
+            "--clear",
+            "--add=src",
+            "--add=~src/testdata",
+            "--add=glob:*.md",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @"Added 0 files, modified 0 files, removed 2 files");
+    let stdout = test_env.jj_cmd_success(&repo_path, &["sparse", "list"]);
+    insta::assert_snapshot!(stdout, @"
+    glob:*.md
+    src
+    ~src/testdata
+    ");
+    assert!(repo_path.join("README.md").exists());
+    assert!(repo_path.join("src/lib.rs").exists());
+    assert!(!repo_path.join("src/testdata/big.bin").exists());
+    assert!(!repo_path.join("docs/index.md").exists());
+
+    // Plain paths are normalized, so they can be removed by any spelling
+    let (_stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["sparse", "set", "--remove=root:src"]);
+    insta::assert_snapshot!(stderr, @"Added 0 files, modified 0 files, removed 1 files");
+    let stdout = test_env.jj_cmd_success(&repo_path, &["sparse", "list"]);
+    insta::assert_snapshot!(stdout, @"
+    glob:*.md
+    ~src/testdata
+    ");
+
+    // Invalid expression
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["sparse", "set", "--add=src |"]);
+    insta::assert_snapshot!(stderr, @"
+    Error: Failed to parse fileset: Syntax error
+    Caused by:  --> 1:6
+      |
+    1 | src |
+      |      ^---
+      |
+      = expected `~` or <primary>
+    Hint: See https://jj-vcs.github.io/jj/latest/filesets/ for filesets syntax, or for how to match file paths.
+    ");
+
+    // Edited patterns are validated
+    std::fs::write(&edit_script, "write\nsrc\nall(").unwrap();
+    let stderr = test_env.jj_cmd_failure(&repo_path, &["sparse", "edit"]);
+    insta::assert_snapshot!(stderr, @"
+    Error: Failed to parse sparse pattern: all(
+    Caused by:  --> 1:5
+      |
+    1 | all(
+      |     ^---
+      |
+      = expected <expression>
+    ");
+}
+
 #[test]
 fn test_sparse_editor_avoids_unc() {
     use std::path::PathBuf;
@@ -369,6 +369,54 @@ fn test_status_simplify_conflict_sides() {
     "###);
 }
 
+#[test]
+fn test_status_untracked_policies() {
+    let test_env = TestEnvironment::default();
+    test_env.add_config(
+        r#"
+        [snapshot]
+        auto-track = 'glob:"*.rs"'
+        warn-untracked = 'glob:"*.txt" | glob:"*.log"'
+        ignore-untracked = 'glob:"*.log"'
+        max-new-file-size = "10"
+        "#,
+    );
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "init", "repo"]);
+    let repo_path = test_env.env_root().join("repo");
+
+    std::fs::write(repo_path.join("main.rs"), "...").unwrap();
+    std::fs::write(repo_path.join("large.rs"), "0123456789abcdef").unwrap();
+    std::fs::write(repo_path.join("notes.txt"), "...").unwrap();
+    std::fs::write(repo_path.join("output.log"), "...").unwrap();
+    std::fs::write(repo_path.join("other"), "...").unwrap();
+
+    // Only notes.txt is warned about, and output.log isn't listed
+    let (stdout, stderr) = test_env.jj_cmd_ok(&repo_path, &["status"]);
+    insta::assert_snapshot!(stdout, @r"
+    Working copy changes:
+    A main.rs
+    Untracked paths:
+    ? large.rs (too large: 16.0B)
+    ? notes.txt (not auto-tracked)
+    ? other (not auto-tracked)
+    Working copy : qpvuntsm bc2772a6 (no description set)
+    Parent commit: zzzzzzzz 00000000 (empty) (no description set)
+    ");
+    insta::assert_snapshot!(stderr, @r"
+    Warning: Refused to snapshot some files:
+      large.rs: 16.0B (16 bytes); the maximum size allowed is 10.0B (10 bytes)
+    Hint: This is to prevent large files from being added by accident. You can fix this by:
+      - Adding the file to `.gitignore`
+      - Run `jj config set --repo snapshot.max-new-file-size 16`
+        This will increase the maximum file size allowed for new files, in this repository only.
+      - Run `jj --config snapshot.max-new-file-size=16 st`
+        This will increase the maximum file size allowed for new files, for this command only.
+    Warning: Some new files are not tracked:
+      notes.txt
+    Hint: Run `jj file track` to track them, or add them to `snapshot.ignore-untracked` to silence this warning.
+    ");
+}
+
 #[test]
 fn test_status_untracked_files() {
     let test_env = TestEnvironment::default();
@@ -386,10 +434,10 @@ fn test_status_untracked_files() {
     let stdout = test_env.jj_cmd_success(&repo_path, &["status"]);
     insta::assert_snapshot!(stdout.replace('\\', "/"), @r"
     Untracked paths:
-    ? always-untracked-file
-    ? initially-untracked-file
-    ? sub/always-untracked
-    ? sub/initially-untracked
+    ? always-untracked-file (not auto-tracked)
+    ? initially-untracked-file (not auto-tracked)
+    ? sub/always-untracked (not auto-tracked)
+    ? sub/initially-untracked (not auto-tracked)
     Working copy : qpvuntsm 230dd059 (empty) (no description set)
     Parent commit: zzzzzzzz 00000000 (empty) (no description set)
     ");
@@ -410,8 +458,8 @@ fn test_status_untracked_files() {
     A initially-untracked-file
     A sub/initially-untracked
     Untracked paths:
-    ? always-untracked-file
-    ? sub/always-untracked
+    ? always-untracked-file (not auto-tracked)
+    ? sub/always-untracked (not auto-tracked)
     Working copy : qpvuntsm 99798fcd (no description set)
     Parent commit: zzzzzzzz 00000000 (empty) (no description set)
     ");
@@ -421,8 +469,8 @@ fn test_status_untracked_files() {
     let stdout = test_env.jj_cmd_success(&repo_path, &["status"]);
     insta::assert_snapshot!(stdout.replace('\\', "/"), @r"
     Untracked paths:
-    ? always-untracked-file
-    ? sub/always-untracked
+    ? always-untracked-file (not auto-tracked)
+    ? sub/always-untracked (not auto-tracked)
     Working copy : mzvwutvl 30e53c74 (empty) (no description set)
     Parent commit: qpvuntsm 99798fcd (no description set)
     ");
@@ -442,10 +490,10 @@ fn test_status_untracked_files() {
     D initially-untracked-file
     D sub/initially-untracked
     Untracked paths:
-    ? always-untracked-file
-    ? initially-untracked-file
-    ? sub/always-untracked
-    ? sub/initially-untracked
+    ? always-untracked-file (not auto-tracked)
+    ? initially-untracked-file (not auto-tracked)
+    ? sub/always-untracked (not auto-tracked)
+    ? sub/initially-untracked (not auto-tracked)
     Working copy : mzvwutvl bb362aaf (no description set)
     Parent commit: qpvuntsm 99798fcd (no description set)
     ");
@@ -455,10 +503,10 @@ fn test_status_untracked_files() {
     let stdout = test_env.jj_cmd_success(&repo_path, &["status"]);
     insta::assert_snapshot!(stdout.replace('\\', "/"), @r"
     Untracked paths:
-    ? always-untracked-file
-    ? initially-untracked-file
-    ? sub/always-untracked
-    ? sub/initially-untracked
+    ? always-untracked-file (not auto-tracked)
+    ? initially-untracked-file (not auto-tracked)
+    ? sub/always-untracked (not auto-tracked)
+    ? sub/initially-untracked (not auto-tracked)
     Working copy : yostqsxw 8e8c02fe (empty) (no description set)
     Parent commit: mzvwutvl bb362aaf (no description set)
     ");
@@ -116,4 +116,20 @@ fn test_tag_list() {
     removed_targets:
     added_targets: commit2
     "###);
+
+    // Columns are aligned across tags
+    let template = r#"
+    columns(
+      name,
+      if(conflict, "(conflicted)", normal_target.commit_id().short()),
+      added_targets.map(|c| c.description().first_line()),
+    ) ++ "\n"
+    "#;
+    insta::assert_snapshot!(
+        test_env.jj_cmd_success(&repo_path, &["tag", "list", "-T", template]),
+        @"
+    conflicted_tag (conflicted) commit2 commit3
+    test_tag       caf975d0989a commit1
+    test_tag2      3db783e0fc37 commit2
+    ");
 }
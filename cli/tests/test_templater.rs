@@ -32,7 +32,7 @@ fn test_templater_parse_error() {
     1 | description ()
       |             ^---
       |
-      = expected <EOI>, `++`, `||`, `&&`, `==`, `!=`, `>=`, `>`, `<=`, or `<`
+      = expected <EOI>, `++`, `||`, `&&`, `==`, `!=`, `>=`, `>`, `<=`, `<`, `+`, `-`, `*`, `/`, or `%`
     "#);
 
     // Typo
@@ -522,6 +522,46 @@ fn test_templater_config_function() {
     ");
 }
 
+#[test]
+fn test_templater_hyperlinks() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "init", "repo"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.add_config(
+        r#"
+        hyperlinks.commit-url = "https://example.com/commit/$commit_id"
+        "#,
+    );
+    let render = |hyperlinks: &str, template: &str| {
+        test_env.jj_cmd_success(
+            &repo_path,
+            &[
+                "log",
+                "--color=never",
+                &format!("--config=ui.hyperlinks={hyperlinks}"),
+                "--no-graph",
+                "-r",
+                "root()",
+                "-T",
+                template,
+            ],
+        )
+    };
+
+    insta::assert_snapshot!(
+        render("never", "commit_id.url()"),
+        @"https://example.com/commit/0000000000000000000000000000000000000000");
+    insta::assert_snapshot!(render("never", "change_id.url()"), @"");
+
+    // Hyperlinks are emitted independently of color
+    insta::assert_snapshot!(
+        render("always", "hyperlink(commit_id.url(), commit_id.short())"),
+        @r"]8;;https://example.com/commit/0000000000000000000000000000000000000000\000000000000]8;;\");
+    insta::assert_snapshot!(
+        render("never", "hyperlink(commit_id.url(), commit_id.short())"),
+        @"000000000000");
+}
+
 fn get_template_output(
     test_env: &TestEnvironment,
     repo_path: &Path,
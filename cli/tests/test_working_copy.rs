@@ -264,6 +264,102 @@ fn test_snapshot_invalid_ignore_pattern() {
     "##);
 }
 
+#[cfg(unix)]
+#[test]
+fn test_snapshot_content_filters() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "init", "repo"]);
+    let repo_path = test_env.env_root().join("repo");
+    test_env.add_config(indoc! {r#"
+        [working-copy.filters.upper]
+        files = 'glob:"*.txt"'
+        clean = ["tr", "a-z", "A-Z"]
+        smudge = "tr A-Z a-z"
+    "#});
+
+    std::fs::write(repo_path.join("file.txt"), "abc\n").unwrap();
+    std::fs::write(repo_path.join("other"), "abc\n").unwrap();
+    let stdout = test_env.jj_cmd_success(&repo_path, &["file", "show", "file.txt", "other"]);
+    insta::assert_snapshot!(stdout, @r"
+    ABC
+    abc
+    ");
+
+    // The stored content is smudged when checked out
+    test_env.jj_cmd_ok(&repo_path, &["new", "root()"]);
+    assert!(!repo_path.join("file.txt").exists());
+    test_env.jj_cmd_ok(&repo_path, &["edit", "root()+ ~ @"]);
+    let content = std::fs::read_to_string(repo_path.join("file.txt")).unwrap();
+    insta::assert_snapshot!(content, @"abc");
+
+    // Failing filters abort the snapshot
+    std::fs::write(repo_path.join("file.txt"), "def\n").unwrap();
+    let stderr = test_env.jj_cmd_internal_error(
+        &repo_path,
+        &[
+            "st",
+            "--config=working-copy.filters.upper.clean=['sh', '-c', 'echo oops >&2; false']",
+        ],
+    );
+    insta::assert_snapshot!(stderr, @r"
+    Internal error: Failed to snapshot the working copy
+    Caused by:
+    1: Failed to filter file $TEST_ENV/repo/file.txt
+    2: The clean filter `upper` failed on file.txt (exit status: 1): oops
+    ");
+
+    // Invalid filesets are reported
+    let stderr = test_env.jj_cmd_failure(
+        &repo_path,
+        &["st", "--config=working-copy.filters.upper.files='foo('"],
+    );
+    insta::assert_snapshot!(stderr, @r"
+    Config error: Invalid `working-copy.filters.upper.files`
+    Caused by:  --> 1:5
+      |
+    1 | foo(
+      |     ^---
+      |
+      = expected <expression>
+    For help, see https://jj-vcs.github.io/jj/latest/config/.
+    ");
+}
+
+#[test]
+fn test_tree_state_format_migration() {
+    let test_env = TestEnvironment::default();
+    test_env.jj_cmd_ok(test_env.env_root(), &["git", "init", "repo"]);
+    let repo_path = test_env.env_root().join("repo");
+    let state_path = repo_path.join(".jj").join("working_copy");
+    std::fs::write(repo_path.join("file"), "a\n").unwrap();
+    test_env.jj_cmd_ok(&repo_path, &["st"]);
+    assert!(state_path.join("tree_state").exists());
+    assert!(!state_path.join("segmented_tree_state").exists());
+
+    // The tree state is migrated on the next snapshot
+    test_env.add_config("working-copy.tree-state-format = 'segmented'");
+    test_env.jj_cmd_ok(&repo_path, &["st"]);
+    assert!(!state_path.join("tree_state").exists());
+    assert!(state_path.join("segmented_tree_state").exists());
+
+    std::fs::write(repo_path.join("file"), "b\n").unwrap();
+    let stdout = test_env.jj_cmd_success(&repo_path, &["diff", "--summary"]);
+    insta::assert_snapshot!(stdout, @"A file");
+
+    // And back
+    let stdout = test_env.jj_cmd_success(
+        &repo_path,
+        &[
+            "diff",
+            "--summary",
+            "--config=working-copy.tree-state-format='proto'",
+        ],
+    );
+    insta::assert_snapshot!(stdout, @"A file");
+    assert!(state_path.join("tree_state").exists());
+    assert!(!state_path.join("segmented_tree_state").exists());
+}
+
 #[test]
 fn test_conflict_marker_length_stored_in_working_copy() {
     let test_env = TestEnvironment::default();
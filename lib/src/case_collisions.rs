@@ -0,0 +1,153 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Detection of paths which only differ in case, and therefore can't coexist
+//! on case-insensitive filesystems.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use futures::StreamExt as _;
+use itertools::Itertools as _;
+
+use crate::backend::BackendResult;
+use crate::matchers::Matcher;
+use crate::matchers::Visit;
+use crate::merged_tree::MergedTree;
+use crate::merged_tree::TreeDiffEntry;
+use crate::repo_path::RepoPath;
+use crate::repo_path::RepoPathBuf;
+use crate::repo_path::RepoPathComponent;
+use crate::repo_path::RepoPathComponentBuf;
+
+/// Group of paths in the same directory whose names only differ in case.
+pub type CaseCollision = Vec<RepoPathBuf>;
+
+/// Finds entries in `new_tree` which collide with other entries if the names
+/// are compared case-insensitively, and which were added since `old_tree`.
+///
+/// Only entries matching the `matcher` are considered. Pre-existing collisions
+/// are reported only if an entry was added to a colliding directory.
+pub async fn find_case_collisions(
+    old_tree: &MergedTree,
+    new_tree: &MergedTree,
+    matcher: &dyn Matcher,
+) -> BackendResult<Vec<CaseCollision>> {
+    let mut finder = CaseCollisionFinder::new();
+    let mut diff_stream = old_tree.diff_stream(new_tree, matcher);
+    while let Some(TreeDiffEntry { path, values }) = diff_stream.next().await {
+        let (before, after) = values?;
+        if before.is_absent() && after.is_present() {
+            finder.add_path(&path);
+        }
+    }
+    finder.finish(new_tree, matcher)
+}
+
+/// Collects added paths to find case collisions once the new tree is known.
+///
+/// This can be used instead of [`find_case_collisions()`] if the added paths
+/// are already enumerated by another pass over the diff.
+#[derive(Clone, Debug, Default)]
+pub struct CaseCollisionFinder {
+    /// Directories and the names in them which were added, including the
+    /// parent directories of the added files.
+    added_names: BTreeMap<RepoPathBuf, BTreeSet<RepoPathComponentBuf>>,
+}
+
+impl CaseCollisionFinder {
+    /// Creates a finder with no added paths.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that the file at `path` was added.
+    pub fn add_path(&mut self, mut path: &RepoPath) {
+        while let Some((dir, name)) = path.split() {
+            let names = self.added_names.entry(dir.to_owned()).or_default();
+            if !names.insert(name.to_owned()) {
+                break; // parents were recorded by a sibling
+            }
+            path = dir;
+        }
+    }
+
+    /// Finds entries in `new_tree` which collide with the added paths.
+    /// Only entries matching the `matcher` are considered.
+    pub fn finish(
+        self,
+        new_tree: &MergedTree,
+        matcher: &dyn Matcher,
+    ) -> BackendResult<Vec<CaseCollision>> {
+        let mut collisions = vec![];
+        for (dir, names) in self.added_names {
+            let Some(tree) = new_tree.sub_tree_recursive(&dir)? else {
+                continue;
+            };
+            let visible_names = tree.names().filter(|&name| {
+                let path = dir.join(name);
+                if tree.value(name).is_tree() {
+                    matcher.visit(&path) != Visit::Nothing
+                } else {
+                    matcher.matches(&path)
+                }
+            });
+            for group in find_case_colliding_names(visible_names) {
+                if group.iter().any(|&name| names.contains(name)) {
+                    collisions.push(group.iter().map(|&name| dir.join(name)).collect());
+                }
+            }
+        }
+        Ok(collisions)
+    }
+}
+
+/// Groups `names` which are equal if compared case-insensitively. Returns
+/// groups of two or more names, in sorted order.
+pub fn find_case_colliding_names<'a>(
+    names: impl IntoIterator<Item = &'a RepoPathComponent>,
+) -> Vec<Vec<&'a RepoPathComponent>> {
+    names
+        .into_iter()
+        .into_group_map_by(|name| name.as_internal_str().to_lowercase())
+        .into_iter()
+        .filter(|(_, group)| group.len() > 1)
+        .sorted_by(|(key1, _), (key2, _)| key1.cmp(key2))
+        .map(|(_, group)| group.into_iter().sorted().collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(names: &[&'static str]) -> Vec<Vec<&'static str>> {
+        let names = names.iter().map(|&name| RepoPathComponent::new(name));
+        find_case_colliding_names(names)
+            .into_iter()
+            .map(|group| group.iter().map(|name| name.as_internal_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_find_case_colliding_names() {
+        assert_eq!(find(&["a", "b", "c"]), Vec::<Vec<&str>>::new());
+        assert_eq!(
+            find(&["readme", "README", "src", "Readme", "SRC", "lib"]),
+            vec![vec!["README", "Readme", "readme"], vec!["SRC", "src"]]
+        );
+        // Non-ASCII letters are folded too
+        assert_eq!(find(&["ÄBC", "äbc", "abc"]), vec![vec!["ÄBC", "äbc"]]);
+    }
+}
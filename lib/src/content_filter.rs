@@ -0,0 +1,229 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Commands which transform file contents between the working copy and the
+//! store, like Git's `clean` and `smudge` filters.
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::fileset::FilesetExpression;
+use crate::matchers::Matcher;
+use crate::path_command::command_for_path;
+use crate::path_command::run_with_input;
+use crate::repo_path::RepoPath;
+use crate::repo_path::RepoPathBuf;
+
+/// Direction in which a filter transforms file contents.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContentFilterKind {
+    /// From the working copy to the store.
+    Clean,
+    /// From the store to the working copy.
+    Smudge,
+}
+
+impl fmt::Display for ContentFilterKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentFilterKind::Clean => write!(f, "clean"),
+            ContentFilterKind::Smudge => write!(f, "smudge"),
+        }
+    }
+}
+
+/// Error occurred while running a content filter.
+#[derive(Debug, Error)]
+pub enum ContentFilterError {
+    /// The command couldn't be started, or communicating with it failed.
+    #[error("Failed to run {kind} filter `{name}` on {path}", path = path.as_internal_file_string())]
+    Io {
+        /// Name of the filter.
+        name: String,
+        /// Direction of the filter.
+        kind: ContentFilterKind,
+        /// Path of the filtered file.
+        path: RepoPathBuf,
+        /// Underlying error.
+        #[source]
+        source: io::Error,
+    },
+    /// The command exited unsuccessfully.
+    #[error(
+        "The {kind} filter `{name}` failed on {path} ({status}){}",
+        format_stderr(stderr),
+        path = path.as_internal_file_string()
+    )]
+    Failed {
+        /// Name of the filter.
+        name: String,
+        /// Direction of the filter.
+        kind: ContentFilterKind,
+        /// Path of the filtered file.
+        path: RepoPathBuf,
+        /// Exit status of the command.
+        status: ExitStatus,
+        /// What the command printed to stderr.
+        stderr: String,
+    },
+}
+
+fn format_stderr(stderr: &str) -> String {
+    let stderr = stderr.trim_end();
+    if stderr.is_empty() {
+        String::new()
+    } else {
+        format!(": {stderr}")
+    }
+}
+
+/// Filter commands applied to files matching a fileset.
+#[derive(Clone, Debug)]
+pub struct ContentFilter {
+    /// Name of the filter, used in error messages.
+    pub name: String,
+    /// Files to which the filter applies.
+    pub files: FilesetExpression,
+    /// Command and arguments converting the working-copy content to the
+    /// stored content.
+    pub clean: Option<Vec<String>>,
+    /// Command and arguments converting the stored content to the
+    /// working-copy content.
+    pub smudge: Option<Vec<String>>,
+}
+
+impl ContentFilter {
+    fn command(&self, kind: ContentFilterKind) -> Option<&[String]> {
+        match kind {
+            ContentFilterKind::Clean => self.clean.as_deref(),
+            ContentFilterKind::Smudge => self.smudge.as_deref(),
+        }
+    }
+}
+
+/// Set of content filters configured for a working copy.
+///
+/// The commands read the content from stdin and write the filtered content to
+/// stdout. `$path` in the arguments is substituted with the repo-relative path
+/// of the file. If more than one filter applies to a file, the clean commands
+/// are run in order, and the smudge commands in reverse order.
+#[derive(Clone, Debug, Default)]
+pub struct ContentFilters {
+    filters: Arc<[ContentFilter]>,
+}
+
+impl ContentFilters {
+    /// Creates a set of content filters.
+    pub fn new(filters: Vec<ContentFilter>) -> Self {
+        ContentFilters {
+            filters: filters.into(),
+        }
+    }
+
+    /// Returns true if no filters are configured.
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// Prepares the filters to be run in the workspace at `workspace_root`.
+    pub fn runner(&self, workspace_root: &Path) -> ContentFilterRunner<'_> {
+        let filters = self
+            .filters
+            .iter()
+            .map(|filter| (filter, filter.files.to_matcher()))
+            .collect();
+        ContentFilterRunner {
+            workspace_root: workspace_root.to_owned(),
+            filters,
+        }
+    }
+}
+
+/// Runs content filters on the files of a workspace.
+#[derive(Debug)]
+pub struct ContentFilterRunner<'a> {
+    workspace_root: PathBuf,
+    filters: Vec<(&'a ContentFilter, Box<dyn Matcher>)>,
+}
+
+impl ContentFilterRunner<'_> {
+    /// Returns true if any filter of the `kind` applies to the `path`.
+    pub fn applies_to(&self, kind: ContentFilterKind, path: &RepoPath) -> bool {
+        self.filters
+            .iter()
+            .any(|(filter, matcher)| filter.command(kind).is_some() && matcher.matches(path))
+    }
+
+    /// Converts working-copy `content` of the `path` to the stored content.
+    pub fn clean(&self, path: &RepoPath, content: Vec<u8>) -> Result<Vec<u8>, ContentFilterError> {
+        let kind = ContentFilterKind::Clean;
+        self.filters
+            .iter()
+            .try_fold(content, |content, (filter, matcher)| {
+                self.run(filter, matcher.as_ref(), kind, path, content)
+            })
+    }
+
+    /// Converts stored `content` of the `path` to the working-copy content.
+    pub fn smudge(&self, path: &RepoPath, content: Vec<u8>) -> Result<Vec<u8>, ContentFilterError> {
+        let kind = ContentFilterKind::Smudge;
+        self.filters
+            .iter()
+            .rev()
+            .try_fold(content, |content, (filter, matcher)| {
+                self.run(filter, matcher.as_ref(), kind, path, content)
+            })
+    }
+
+    fn run(
+        &self,
+        filter: &ContentFilter,
+        matcher: &dyn Matcher,
+        kind: ContentFilterKind,
+        path: &RepoPath,
+        content: Vec<u8>,
+    ) -> Result<Vec<u8>, ContentFilterError> {
+        let Some(command) = filter.command(kind).filter(|args| !args.is_empty()) else {
+            return Ok(content);
+        };
+        if !matcher.matches(path) {
+            return Ok(content);
+        }
+        let io_error = |source| ContentFilterError::Io {
+            name: filter.name.clone(),
+            kind,
+            path: path.to_owned(),
+            source,
+        };
+        let mut command = command_for_path(command, path, str::to_owned);
+        command.current_dir(&self.workspace_root);
+        let output = run_with_input(&mut command, &content).map_err(io_error)?;
+        if !output.status.success() {
+            return Err(ContentFilterError::Failed {
+                name: filter.name.clone(),
+                kind,
+                path: path.to_owned(),
+                status: output.status,
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+        Ok(output.stdout)
+    }
+}
@@ -0,0 +1,252 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Line-ending conversion driven by the `text` and `eol` attributes.
+
+use std::borrow::Cow;
+
+use crate::gitattributes::GitAttributeState;
+use crate::gitattributes::GitAttributesCache;
+use crate::gitattributes::GitAttributesError;
+use crate::repo_path::RepoPath;
+
+/// Number of bytes to inspect when guessing whether content is binary, which
+/// matches Git.
+const BINARY_DETECTION_LEN: usize = 8000;
+
+/// Line ending to use in the working copy.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EolStyle {
+    /// `\n`
+    Lf,
+    /// `\r\n`
+    Crlf,
+}
+
+impl EolStyle {
+    /// Line ending native to the current platform.
+    pub fn native() -> Self {
+        if cfg!(windows) {
+            EolStyle::Crlf
+        } else {
+            EolStyle::Lf
+        }
+    }
+}
+
+/// How to convert line endings of a file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EolConversion {
+    /// The file is stored as is.
+    None,
+    /// The file is text. Line endings are normalized to LF in the store, and
+    /// converted to `eol` in the working copy.
+    Text {
+        /// Line ending in the working copy.
+        eol: EolStyle,
+    },
+    /// Same as `Text`, but files that look binary are stored as is.
+    Auto {
+        /// Line ending in the working copy.
+        eol: EolStyle,
+    },
+}
+
+impl EolConversion {
+    /// Determines the conversion from the `text` and `eol` attributes.
+    pub fn from_attributes(text: &GitAttributeState, eol: &GitAttributeState) -> Self {
+        let eol_style = match eol.as_value() {
+            Some("lf") => Some(EolStyle::Lf),
+            Some("crlf") => Some(EolStyle::Crlf),
+            _ => None,
+        };
+        match text {
+            GitAttributeState::Set => EolConversion::Text {
+                eol: eol_style.unwrap_or_else(EolStyle::native),
+            },
+            GitAttributeState::Unset => EolConversion::None,
+            GitAttributeState::Value(value) if value == "auto" => EolConversion::Auto {
+                eol: eol_style.unwrap_or_else(EolStyle::native),
+            },
+            // Setting "eol" implies "text".
+            GitAttributeState::Value(_) | GitAttributeState::Unspecified => match eol_style {
+                Some(eol) => EolConversion::Text { eol },
+                None => EolConversion::None,
+            },
+        }
+    }
+
+    /// Looks up the conversion for the file at `path`.
+    pub fn for_path(
+        attributes: &GitAttributesCache,
+        path: &RepoPath,
+    ) -> Result<Self, GitAttributesError> {
+        let text = attributes.get(path, "text")?;
+        let eol = attributes.get(path, "eol")?;
+        Ok(Self::from_attributes(&text, &eol))
+    }
+
+    /// Converts working-copy `content` to be written to the store.
+    pub fn convert_to_store<'a>(&self, content: &'a [u8]) -> Cow<'a, [u8]> {
+        match self {
+            EolConversion::None => Cow::Borrowed(content),
+            EolConversion::Auto { .. } if is_binary(content) => Cow::Borrowed(content),
+            EolConversion::Text { .. } | EolConversion::Auto { .. } => crlf_to_lf(content),
+        }
+    }
+
+    /// Converts stored `content` to be written to the working copy.
+    pub fn convert_to_working_copy<'a>(&self, content: &'a [u8]) -> Cow<'a, [u8]> {
+        match self {
+            EolConversion::None => Cow::Borrowed(content),
+            EolConversion::Auto { .. } if is_binary(content) => Cow::Borrowed(content),
+            EolConversion::Text { eol } | EolConversion::Auto { eol } => match eol {
+                EolStyle::Lf => Cow::Borrowed(content),
+                EolStyle::Crlf => lf_to_crlf(content),
+            },
+        }
+    }
+
+    /// Returns true if content may be altered when written to the store.
+    pub fn converts_to_store(&self) -> bool {
+        *self != EolConversion::None
+    }
+
+    /// Returns true if content may be altered when written to the working
+    /// copy.
+    pub fn converts_to_working_copy(&self) -> bool {
+        matches!(
+            self,
+            EolConversion::Text {
+                eol: EolStyle::Crlf
+            } | EolConversion::Auto {
+                eol: EolStyle::Crlf
+            }
+        )
+    }
+}
+
+fn is_binary(content: &[u8]) -> bool {
+    let len = content.len().min(BINARY_DETECTION_LEN);
+    content[..len].contains(&b'\0')
+}
+
+fn crlf_to_lf(content: &[u8]) -> Cow<'_, [u8]> {
+    if !content.windows(2).any(|w| w == b"\r\n") {
+        return Cow::Borrowed(content);
+    }
+    let mut output = Vec::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(pos) = rest.windows(2).position(|w| w == b"\r\n") {
+        output.extend_from_slice(&rest[..pos]);
+        output.push(b'\n');
+        rest = &rest[pos + 2..];
+    }
+    output.extend_from_slice(rest);
+    Cow::Owned(output)
+}
+
+fn lf_to_crlf(content: &[u8]) -> Cow<'_, [u8]> {
+    if !content.contains(&b'\n') {
+        return Cow::Borrowed(content);
+    }
+    let mut output = Vec::with_capacity(content.len() + content.len() / 16);
+    let mut prev = None;
+    for &b in content {
+        // Don't double existing CRLF
+        if b == b'\n' && prev != Some(b'\r') {
+            output.push(b'\r');
+        }
+        output.push(b);
+        prev = Some(b);
+    }
+    Cow::Owned(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(s: &str) -> GitAttributeState {
+        GitAttributeState::Value(s.to_owned())
+    }
+
+    #[test]
+    fn test_from_attributes() {
+        use GitAttributeState::*;
+        let native = EolStyle::native();
+        assert_eq!(
+            EolConversion::from_attributes(&Unspecified, &Unspecified),
+            EolConversion::None
+        );
+        assert_eq!(
+            EolConversion::from_attributes(&Set, &Unspecified),
+            EolConversion::Text { eol: native }
+        );
+        assert_eq!(
+            EolConversion::from_attributes(&Set, &value("crlf")),
+            EolConversion::Text {
+                eol: EolStyle::Crlf
+            }
+        );
+        assert_eq!(
+            EolConversion::from_attributes(&Unset, &value("crlf")),
+            EolConversion::None
+        );
+        assert_eq!(
+            EolConversion::from_attributes(&Unspecified, &value("lf")),
+            EolConversion::Text { eol: EolStyle::Lf }
+        );
+        assert_eq!(
+            EolConversion::from_attributes(&value("auto"), &Unspecified),
+            EolConversion::Auto { eol: native }
+        );
+        assert_eq!(
+            EolConversion::from_attributes(&Set, &value("bogus")),
+            EolConversion::Text { eol: native }
+        );
+    }
+
+    #[test]
+    fn test_convert_to_store() {
+        let text = EolConversion::Text { eol: EolStyle::Lf };
+        assert_eq!(*text.convert_to_store(b"a\r\nb\nc\r"), *b"a\nb\nc\r");
+        assert_eq!(*text.convert_to_store(b"a\r\n\0"), *b"a\n\0");
+        assert_matches::assert_matches!(text.convert_to_store(b"a\nb"), Cow::Borrowed(_));
+
+        let auto = EolConversion::Auto { eol: EolStyle::Lf };
+        assert_eq!(*auto.convert_to_store(b"a\r\nb"), *b"a\nb");
+        assert_eq!(*auto.convert_to_store(b"a\r\n\0"), *b"a\r\n\0");
+
+        assert_eq!(*EolConversion::None.convert_to_store(b"a\r\n"), *b"a\r\n");
+    }
+
+    #[test]
+    fn test_convert_to_working_copy() {
+        let crlf = EolConversion::Text {
+            eol: EolStyle::Crlf,
+        };
+        assert_eq!(*crlf.convert_to_working_copy(b"a\nb\r\nc"), *b"a\r\nb\r\nc");
+        assert_eq!(*crlf.convert_to_working_copy(b"\n\n"), *b"\r\n\r\n");
+
+        let lf = EolConversion::Text { eol: EolStyle::Lf };
+        assert_eq!(*lf.convert_to_working_copy(b"a\nb"), *b"a\nb");
+
+        let auto = EolConversion::Auto {
+            eol: EolStyle::Crlf,
+        };
+        assert_eq!(*auto.convert_to_working_copy(b"a\nb"), *b"a\r\nb");
+        assert_eq!(*auto.convert_to_working_copy(b"a\n\0"), *b"a\n\0");
+    }
+}
@@ -132,6 +132,17 @@ pub fn normalize_path(path: &Path) -> PathBuf {
     }
 }
 
+/// Returns true if file names in the directory `dir` appear to be
+/// case-insensitive. A temporary file is created to test that.
+pub fn check_case_insensitive_fs(dir: &Path) -> io::Result<bool> {
+    let test_file = tempfile::Builder::new()
+        .prefix(".jj-case-test-")
+        .tempfile_in(dir)?;
+    let orig_name = test_file.path().file_name().unwrap().to_str().unwrap();
+    let upper_name = orig_name.to_ascii_uppercase();
+    dir.join(upper_name).try_exists()
+}
+
 /// Like `NamedTempFile::persist()`, but doesn't try to overwrite the existing
 /// target on Windows.
 pub fn persist_content_addressed_temp_file<P: AsRef<Path>>(
@@ -23,10 +23,12 @@ use bstr::BStr;
 use bstr::BString;
 use itertools::Itertools;
 
+use crate::config::ConfigGetError;
 use crate::diff::Diff;
 use crate::diff::DiffHunk;
 use crate::diff::DiffHunkKind;
 use crate::merge::Merge;
+use crate::settings::UserSettings;
 
 /// A diff line which may contain small hunks originating from both sides.
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -193,6 +195,34 @@ pub enum MergeResult {
     Conflict(Vec<Merge<BString>>),
 }
 
+/// Granularity of hunks when merging files.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileMergeHunkLevel {
+    /// Changes to the same line conflict.
+    #[default]
+    Line,
+    /// Conflicting lines are merged again word by word, so changes to
+    /// different words of the same line don't conflict.
+    Word,
+}
+
+/// Options for merging file contents.
+#[derive(Clone, Debug, Default)]
+pub struct MergeOptions {
+    /// Granularity of hunks when merging files.
+    pub hunk_level: FileMergeHunkLevel,
+}
+
+impl MergeOptions {
+    /// Loads the merge options from the `merge` table.
+    pub fn from_settings(settings: &UserSettings) -> Result<Self, ConfigGetError> {
+        Ok(MergeOptions {
+            hunk_level: settings.get("merge.hunk-level")?,
+        })
+    }
+}
+
 pub fn merge<T: AsRef<[u8]>>(slices: &Merge<T>) -> MergeResult {
     // TODO: Using the first remove as base (first in the inputs) is how it's
     // usually done for 3-way conflicts. Are there better heuristics when there are
@@ -202,6 +232,62 @@ pub fn merge<T: AsRef<[u8]>>(slices: &Merge<T>) -> MergeResult {
     merge_hunks(&Diff::by_line(diff_inputs), num_diffs)
 }
 
+/// Merges `slices` like [`merge()`], but with the given `options`.
+///
+/// With [`FileMergeHunkLevel::Word`], each conflicting hunk is merged again
+/// word by word. The hunk is resolved if the changes don't overlap at word
+/// level. Otherwise, the whole lines are left conflicted.
+pub fn merge_with_options<T: AsRef<[u8]>>(
+    slices: &Merge<T>,
+    options: &MergeOptions,
+) -> MergeResult {
+    let merge_result = merge(slices);
+    match (options.hunk_level, merge_result) {
+        (FileMergeHunkLevel::Line, merge_result) => merge_result,
+        (_, MergeResult::Resolved(content)) => MergeResult::Resolved(content),
+        (FileMergeHunkLevel::Word, MergeResult::Conflict(hunks)) => refine_by_word(hunks),
+    }
+}
+
+fn refine_by_word(hunks: Vec<Merge<BString>>) -> MergeResult {
+    let mut resolved_hunk = BString::new(vec![]);
+    let mut merge_hunks: Vec<Merge<BString>> = vec![];
+    for hunk in hunks {
+        let resolved = match hunk.as_resolved() {
+            Some(resolved) => Some(resolved.clone()),
+            None => merge_hunk_by_word(&hunk),
+        };
+        if let Some(resolved) = resolved {
+            resolved_hunk.extend_from_slice(&resolved);
+        } else {
+            if !resolved_hunk.is_empty() {
+                merge_hunks.push(Merge::resolved(mem::take(&mut resolved_hunk)));
+            }
+            merge_hunks.push(hunk);
+        }
+    }
+
+    if merge_hunks.is_empty() {
+        MergeResult::Resolved(resolved_hunk)
+    } else {
+        if !resolved_hunk.is_empty() {
+            merge_hunks.push(Merge::resolved(resolved_hunk));
+        }
+        MergeResult::Conflict(merge_hunks)
+    }
+}
+
+/// Merges the conflicting `hunk` word by word. Returns `None` if the changes
+/// overlap.
+fn merge_hunk_by_word(hunk: &Merge<BString>) -> Option<BString> {
+    let num_diffs = hunk.removes().len();
+    let diff = Diff::by_word(hunk.removes().chain(hunk.adds()));
+    match merge_hunks(&diff, num_diffs) {
+        MergeResult::Resolved(content) => Some(content),
+        MergeResult::Conflict(_) => None,
+    }
+}
+
 fn merge_hunks(diff: &Diff, num_diffs: usize) -> MergeResult {
     let mut resolved_hunk = BString::new(vec![]);
     let mut merge_hunks: Vec<Merge<BString>> = vec![];
@@ -440,6 +526,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_merge_by_word() {
+        let merge_by_word = |removes: &[&[u8]], adds: &[&[u8]]| {
+            let options = MergeOptions {
+                hunk_level: FileMergeHunkLevel::Word,
+            };
+            merge_with_options(&Merge::from_removes_adds(removes, adds), &options)
+        };
+        // Two sides changed different words of the same line
+        assert_eq!(
+            merge(&[b"a b c\n"], &[b"a1 b c\n", b"a b c1\n"]),
+            MergeResult::Conflict(vec![Merge::from_removes_adds(
+                vec![hunk(b"a b c\n")],
+                vec![hunk(b"a1 b c\n"), hunk(b"a b c1\n")]
+            )])
+        );
+        assert_eq!(
+            merge_by_word(&[b"a b c\n"], &[b"a1 b c\n", b"a b c1\n"]),
+            MergeResult::Resolved(hunk(b"a1 b c1\n"))
+        );
+        // Two sides changed the same word: the whole lines conflict
+        assert_eq!(
+            merge_by_word(&[b"x\na b c\ny\n"], &[b"x\na b1 c\ny\n", b"x\na b2 c\ny\n"]),
+            MergeResult::Conflict(vec![
+                Merge::resolved(hunk(b"x\n")),
+                Merge::from_removes_adds(
+                    vec![hunk(b"a b c\n")],
+                    vec![hunk(b"a b1 c\n"), hunk(b"a b2 c\n")]
+                ),
+                Merge::resolved(hunk(b"y\n"))
+            ])
+        );
+        // Only the hunks with overlapping words conflict
+        assert_eq!(
+            merge_by_word(
+                &[b"a b\nx\nc d\n"],
+                &[b"a1 b\nx\nc d1\n", b"a b1\nx\nc d2\n"]
+            ),
+            MergeResult::Conflict(vec![
+                Merge::resolved(hunk(b"a1 b1\nx\n")),
+                Merge::from_removes_adds(
+                    vec![hunk(b"c d\n")],
+                    vec![hunk(b"c d1\n"), hunk(b"c d2\n")]
+                ),
+            ])
+        );
+        // Lines resolved at line level are unaffected
+        assert_eq!(
+            merge_by_word(&[b"a\nb\n"], &[b"a1\nb\n", b"a\nb1\n"]),
+            MergeResult::Resolved(hunk(b"a1\nb1\n"))
+        );
+    }
+
     #[test]
     fn test_merge_multi_hunk() {
         // Two sides left one line unchanged, and added conflicting additional lines
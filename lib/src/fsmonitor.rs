@@ -22,6 +22,7 @@
 
 #![warn(missing_docs)]
 
+use std::ffi::OsString;
 use std::path::PathBuf;
 
 use crate::config::ConfigGetError;
@@ -34,12 +35,27 @@ pub struct WatchmanConfig {
     pub register_trigger: bool,
 }
 
+/// Config for the built-in filesystem monitor daemon.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct BuiltinConfig {
+    /// Whether to start the daemon in the background if it isn't running.
+    pub auto_start: bool,
+    /// Program and arguments to run the daemon in the foreground. The
+    /// working copy path is appended to the arguments. This isn't read from
+    /// the config, and should be set by the application if `auto_start` is
+    /// enabled.
+    pub daemon_command: Vec<OsString>,
+}
+
 /// The recognized kinds of filesystem monitors.
 #[derive(Eq, PartialEq, Clone, Debug)]
 pub enum FsmonitorSettings {
     /// The Watchman filesystem monitor (<https://facebook.github.io/watchman/>).
     Watchman(WatchmanConfig),
 
+    /// The built-in filesystem monitor daemon.
+    Builtin(BuiltinConfig),
+
     /// Only used in tests.
     Test {
         /// The set of changed files to pretend that the filesystem monitor is
@@ -63,6 +79,10 @@ impl FsmonitorSettings {
                 // TODO: rename to "register-snapshot-trigger" for consistency?
                 register_trigger: settings.get_bool("core.watchman.register_snapshot_trigger")?,
             })),
+            "builtin" => Ok(Self::Builtin(BuiltinConfig {
+                auto_start: settings.get_bool("core.builtin-fsmonitor.auto-start")?,
+                daemon_command: vec![],
+            })),
             "test" => Err(ConfigGetError::Type {
                 name: name.to_owned(),
                 error: "Cannot use test fsmonitor in real repository".into(),
@@ -78,6 +98,9 @@ impl FsmonitorSettings {
     }
 }
 
+#[cfg(all(unix, feature = "fsmonitor-daemon"))]
+pub mod builtin;
+
 /// Filesystem monitor integration using Watchman
 /// (<https://facebook.github.io/watchman/>). Requires `watchman` to already be
 /// installed on the system.
@@ -0,0 +1,669 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Built-in filesystem monitor daemon.
+//!
+//! The daemon watches a working copy using the platform's file notification
+//! API (inotify on Linux) and keeps track of the paths changed since it
+//! started. Clients connect to it over a Unix socket in the working-copy state
+//! directory and ask for the paths changed since a token returned by a
+//! previous query.
+//!
+//! Unlike Watchman, the daemon doesn't crawl the working copy. Changed paths
+//! may therefore be directories (e.g. if a directory was renamed), and should
+//! be interpreted as path prefixes.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io;
+use std::io::BufRead as _;
+use std::io::BufReader;
+use std::io::Write as _;
+use std::os::unix::net::UnixListener;
+use std::os::unix::net::UnixStream;
+use std::os::unix::process::CommandExt as _;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use notify::event::AccessKind;
+use notify::event::AccessMode;
+use notify::EventKind;
+use notify::RecursiveMode;
+use notify::Watcher as _;
+use rustix::event::PollFd;
+use rustix::event::PollFlags;
+use rustix::fs::FlockOperation;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+use tracing::info;
+use tracing::instrument;
+use tracing::warn;
+
+/// Name of the socket file in the working-copy state directory.
+const SOCKET_FILE_NAME: &str = "fsmonitor.sock";
+
+/// Name of the lock file which is held while the daemon is running.
+const LOCK_FILE_NAME: &str = "fsmonitor.lock";
+
+/// Prefix of the files created to synchronize queries with the event stream.
+const COOKIE_FILE_PREFIX: &str = "fsmonitor-cookie-";
+
+/// How long to wait for the event of a cookie file before giving up and
+/// telling the client to crawl the working copy.
+const COOKIE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often the daemon checks whether it should shut down.
+const SHUTDOWN_POLL_INTERVAL_MS: i32 = 500;
+
+/// Maximum number of changed paths to remember. If more paths are changed,
+/// the previously issued tokens are invalidated, and the clients will crawl
+/// the working copy.
+const MAX_CHANGED_PATHS: usize = 100_000;
+
+#[allow(missing_docs)]
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Could not connect to the filesystem monitor daemon at {path}")]
+    Connect { path: PathBuf, source: io::Error },
+
+    #[error("Failed to communicate with the filesystem monitor daemon")]
+    Io(#[from] io::Error),
+
+    #[error("Invalid message from the filesystem monitor daemon")]
+    Protocol(#[from] serde_json::Error),
+
+    #[error("Filesystem monitor daemon failed: {0}")]
+    Daemon(String),
+
+    #[error("A filesystem monitor daemon is already running for {0}")]
+    AlreadyRunning(PathBuf),
+
+    #[error("Failed to start the filesystem monitor daemon")]
+    Spawn(#[source] io::Error),
+}
+
+/// Result of a query for changed paths.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct QueryResult {
+    /// Token to pass to the next query.
+    pub token: String,
+    /// Paths changed since the token passed to the query, relative to the
+    /// working copy root. If it is `None`, then the caller must crawl the
+    /// entire working copy themselves.
+    pub changed_paths: Option<Vec<PathBuf>>,
+}
+
+/// State of a running daemon.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DaemonStatus {
+    /// Process ID of the daemon.
+    pub pid: u32,
+    /// Working copy watched by the daemon.
+    pub working_copy_path: PathBuf,
+    /// Number of distinct paths changed since the daemon started.
+    pub changed_path_count: usize,
+    /// Set if the daemon failed to watch the working copy. Queries will ask
+    /// the client to crawl the working copy.
+    pub watch_error: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum Request {
+    Query { since: Option<String> },
+    Status,
+    Stop,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum Response {
+    Query(QueryResult),
+    Status(DaemonStatus),
+    Stop,
+    Error { message: String },
+}
+
+/// Returns the path to the socket of the daemon for the working copy whose
+/// state is stored in `state_path`.
+pub fn socket_path(state_path: &Path) -> PathBuf {
+    state_path.join(SOCKET_FILE_NAME)
+}
+
+/// Connection to a running daemon.
+pub struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Client {
+    /// Connects to the daemon listening on `socket_path`.
+    pub fn connect(socket_path: &Path) -> Result<Self, Error> {
+        let stream = UnixStream::connect(socket_path).map_err(|source| Error::Connect {
+            path: socket_path.to_owned(),
+            source,
+        })?;
+        let writer = stream.try_clone()?;
+        Ok(Client {
+            reader: BufReader::new(stream),
+            writer,
+        })
+    }
+
+    /// Queries for paths changed since `since`, which is a token returned by
+    /// a previous query.
+    #[instrument(skip(self))]
+    pub fn query(&mut self, since: Option<&str>) -> Result<QueryResult, Error> {
+        let since = since.map(|token| token.to_owned());
+        match self.request(&Request::Query { since })? {
+            Response::Query(result) => Ok(result),
+            other => Err(unexpected_response(other)),
+        }
+    }
+
+    /// Returns the state of the daemon.
+    pub fn status(&mut self) -> Result<DaemonStatus, Error> {
+        match self.request(&Request::Status)? {
+            Response::Status(status) => Ok(status),
+            other => Err(unexpected_response(other)),
+        }
+    }
+
+    /// Asks the daemon to exit.
+    pub fn stop(&mut self) -> Result<(), Error> {
+        match self.request(&Request::Stop)? {
+            Response::Stop => Ok(()),
+            other => Err(unexpected_response(other)),
+        }
+    }
+
+    fn request(&mut self, request: &Request) -> Result<Response, Error> {
+        write_message(&mut self.writer, request)?;
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(Error::Io(io::ErrorKind::UnexpectedEof.into()));
+        }
+        match serde_json::from_str(&line)? {
+            Response::Error { message } => Err(Error::Daemon(message)),
+            response => Ok(response),
+        }
+    }
+}
+
+fn unexpected_response(response: Response) -> Error {
+    Error::Daemon(format!("Unexpected response: {response:?}"))
+}
+
+fn write_message(stream: &mut UnixStream, message: &impl Serialize) -> io::Result<()> {
+    let mut data = serde_json::to_vec(message)?;
+    data.push(b'\n');
+    stream.write_all(&data)
+}
+
+/// Starts a daemon for the working copy in the background by running the
+/// `daemon_command` with the working copy path appended.
+pub fn spawn(daemon_command: &[OsString], working_copy_path: &Path) -> Result<(), Error> {
+    let Some((program, args)) = daemon_command.split_first() else {
+        return Err(Error::Spawn(io::Error::other(
+            "No command to run the daemon was specified",
+        )));
+    };
+    info!(
+        ?program,
+        ?working_copy_path,
+        "Starting filesystem monitor daemon"
+    );
+    Command::new(program)
+        .args(args)
+        .arg(working_copy_path)
+        .current_dir(working_copy_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // Don't receive signals sent to the terminal's foreground process group.
+        .process_group(0)
+        .spawn()
+        .map_err(Error::Spawn)?;
+    Ok(())
+}
+
+#[derive(Debug)]
+struct ChangeLog {
+    /// Randomly generated so that tokens from another daemon instance are
+    /// recognized.
+    instance_id: String,
+    /// Incremented for each changed path.
+    seq: u64,
+    /// Tokens older than this are invalid because events may have been lost.
+    min_valid_seq: u64,
+    /// Changed paths relative to the working copy root, mapped to the `seq` of
+    /// their latest change.
+    changed_paths: HashMap<PathBuf, u64>,
+    /// Cookie files whose events have been received.
+    seen_cookies: Vec<PathBuf>,
+    /// Set if watching the working copy failed.
+    watch_error: Option<String>,
+}
+
+impl ChangeLog {
+    fn new(instance_id: String) -> Self {
+        ChangeLog {
+            instance_id,
+            seq: 0,
+            min_valid_seq: 0,
+            changed_paths: HashMap::new(),
+            seen_cookies: vec![],
+            watch_error: None,
+        }
+    }
+
+    fn record_change(&mut self, path: PathBuf) {
+        if self.changed_paths.len() >= MAX_CHANGED_PATHS && !self.changed_paths.contains_key(&path)
+        {
+            info!("Too many changed paths; invalidating tokens");
+            self.invalidate();
+        }
+        self.seq += 1;
+        self.changed_paths.insert(path, self.seq);
+    }
+
+    /// Forgets the changes up to the `token`. Tokens older than that are
+    /// invalidated.
+    ///
+    /// The client stores the token of the last query, so the changes before
+    /// the token of the next query are no longer needed.
+    fn prune(&mut self, token: &str) {
+        let Some(seq) = self.parse_token(token) else {
+            return;
+        };
+        self.min_valid_seq = seq;
+        self.changed_paths.retain(|_, path_seq| *path_seq > seq);
+    }
+
+    /// Invalidates all previously issued tokens.
+    fn invalidate(&mut self) {
+        self.seq += 1;
+        self.min_valid_seq = self.seq;
+        self.changed_paths.clear();
+    }
+
+    fn token(&self) -> String {
+        format!("{}:{}", self.instance_id, self.seq)
+    }
+
+    /// Returns the `seq` of the token if it's valid.
+    fn parse_token(&self, token: &str) -> Option<u64> {
+        let (instance_id, seq) = token.rsplit_once(':')?;
+        let seq: u64 = seq.parse().ok()?;
+        if instance_id != self.instance_id || seq < self.min_valid_seq || seq > self.seq {
+            return None;
+        }
+        Some(seq)
+    }
+
+    fn changed_since(&self, token: Option<&str>) -> Option<Vec<PathBuf>> {
+        let seq = self.parse_token(token?)?;
+        let mut paths: Vec<_> = self
+            .changed_paths
+            .iter()
+            .filter(|(_, &path_seq)| path_seq > seq)
+            .map(|(path, _)| path.clone())
+            .collect();
+        paths.sort_unstable();
+        Some(paths)
+    }
+}
+
+struct DaemonState {
+    working_copy_path: PathBuf,
+    state_path: PathBuf,
+    log: Mutex<ChangeLog>,
+    log_changed: Condvar,
+    shutdown: AtomicBool,
+    /// Incremented for each request to name cookie files uniquely.
+    cookie_seq: AtomicU64,
+}
+
+impl DaemonState {
+    fn handle_event(&self, event: notify::Result<notify::Event>) {
+        let mut log = self.log.lock().unwrap();
+        let event = match event {
+            Ok(event) => event,
+            Err(err) => {
+                warn!(?err, "Filesystem monitor error");
+                log.invalidate();
+                return;
+            }
+        };
+        if event.need_rescan() {
+            info!("Events were lost; invalidating tokens");
+            log.invalidate();
+            return;
+        }
+        match event.kind {
+            // Reading files doesn't change them.
+            EventKind::Access(AccessKind::Close(AccessMode::Write)) => {}
+            EventKind::Access(_) => return,
+            _ => {}
+        }
+        for path in event.paths {
+            if path == self.working_copy_path && matches!(event.kind, EventKind::Remove(_)) {
+                info!("Working copy was removed; shutting down");
+                self.shutdown.store(true, Ordering::Relaxed);
+            }
+            if path.parent() == Some(&self.state_path)
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(COOKIE_FILE_PREFIX))
+            {
+                log.seen_cookies.push(path);
+                continue;
+            }
+            let Ok(relative_path) = path.strip_prefix(&self.working_copy_path) else {
+                continue;
+            };
+            if is_excluded_path(relative_path) {
+                continue;
+            }
+            log.record_change(relative_path.to_owned());
+        }
+        self.log_changed.notify_all();
+    }
+
+    /// Waits until all events that happened before this call have been
+    /// processed. Returns false on timeout.
+    fn sync(&self, cookie_seq: u64) -> io::Result<bool> {
+        let cookie_path = self.state_path.join(format!(
+            "{COOKIE_FILE_PREFIX}{}-{cookie_seq}",
+            std::process::id()
+        ));
+        File::create(&cookie_path)?;
+        let deadline = Instant::now() + COOKIE_TIMEOUT;
+        let mut log = self.log.lock().unwrap();
+        let found = loop {
+            if let Some(pos) = log.seen_cookies.iter().position(|p| *p == cookie_path) {
+                log.seen_cookies.swap_remove(pos);
+                break true;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                break false;
+            }
+            log = self
+                .log_changed
+                .wait_timeout(log, deadline - now)
+                .unwrap()
+                .0;
+        };
+        drop(log);
+        std::fs::remove_file(&cookie_path)?;
+        Ok(found)
+    }
+
+    fn handle_request(&self, request: Request, cookie_seq: u64) -> Response {
+        match request {
+            Request::Query { since } => {
+                let synced = match self.sync(cookie_seq) {
+                    Ok(synced) => synced,
+                    Err(err) => {
+                        return Response::Error {
+                            message: format!("Failed to create cookie file: {err}"),
+                        }
+                    }
+                };
+                let mut log = self.log.lock().unwrap();
+                let changed_paths = if synced && log.watch_error.is_none() {
+                    log.changed_since(since.as_deref())
+                } else {
+                    None
+                };
+                if let Some(since) = &since {
+                    log.prune(since);
+                }
+                Response::Query(QueryResult {
+                    token: log.token(),
+                    changed_paths,
+                })
+            }
+            Request::Status => {
+                let log = self.log.lock().unwrap();
+                Response::Status(DaemonStatus {
+                    pid: std::process::id(),
+                    working_copy_path: self.working_copy_path.clone(),
+                    changed_path_count: log.changed_paths.len(),
+                    watch_error: log.watch_error.clone(),
+                })
+            }
+            Request::Stop => {
+                self.shutdown.store(true, Ordering::Relaxed);
+                Response::Stop
+            }
+        }
+    }
+}
+
+/// Returns true if changes to `path` should never be reported.
+fn is_excluded_path(path: &Path) -> bool {
+    match path.components().next() {
+        Some(Component::Normal(name)) => name == ".jj" || name == ".git",
+        _ => true,
+    }
+}
+
+/// Runs the daemon for the working copy until it's asked to stop, or the
+/// working copy is removed.
+///
+/// `state_path` is the directory where the socket is created. It should be
+/// inside the working copy, or every query will time out.
+#[instrument]
+pub fn run(working_copy_path: &Path, state_path: &Path) -> Result<(), Error> {
+    let working_copy_path = dunce::canonicalize(working_copy_path)?;
+    let state_path = dunce::canonicalize(state_path)?;
+
+    // Held until the daemon exits so that only one daemon runs per working copy.
+    let lock_file = File::create(state_path.join(LOCK_FILE_NAME))?;
+    if rustix::fs::flock(&lock_file, FlockOperation::NonBlockingLockExclusive).is_err() {
+        return Err(Error::AlreadyRunning(working_copy_path));
+    }
+
+    let instance_id = format!("{:016x}", rand::random::<u64>());
+    let state = Arc::new(DaemonState {
+        working_copy_path: working_copy_path.clone(),
+        state_path: state_path.clone(),
+        log: Mutex::new(ChangeLog::new(instance_id)),
+        log_changed: Condvar::new(),
+        shutdown: AtomicBool::new(false),
+        cookie_seq: AtomicU64::new(0),
+    });
+
+    let (event_tx, event_rx) = mpsc::channel();
+    let event_thread = {
+        let state = state.clone();
+        thread::spawn(move || {
+            for event in event_rx {
+                state.handle_event(event);
+            }
+        })
+    };
+    // The watcher must be set up before the socket is bound, so that clients
+    // never get a token from before events started being recorded.
+    let mut watcher = notify::recommended_watcher(event_tx).map_err(io::Error::other)?;
+    info!(?working_copy_path, "Watching working copy");
+    if let Err(err) = watcher.watch(&working_copy_path, RecursiveMode::Recursive) {
+        warn!(?err, "Failed to watch working copy");
+        state.log.lock().unwrap().watch_error = Some(err.to_string());
+    }
+
+    let socket_path = socket_path(&state_path);
+    // The lock is held, so any existing socket is stale.
+    match std::fs::remove_file(&socket_path) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+    let listener = UnixListener::bind(&socket_path)?;
+    info!(?socket_path, "Listening");
+
+    while !state.shutdown.load(Ordering::Relaxed) {
+        let mut poll_fds = [PollFd::new(&listener, PollFlags::IN)];
+        match rustix::event::poll(&mut poll_fds, SHUTDOWN_POLL_INTERVAL_MS) {
+            Ok(0) => continue,
+            Ok(_) => {}
+            Err(rustix::io::Errno::INTR) => continue,
+            Err(errno) => return Err(io::Error::from(errno).into()),
+        }
+        let (stream, _) = listener.accept()?;
+        // Each client is served by its own thread so an idle client doesn't
+        // block the others.
+        let state = state.clone();
+        thread::spawn(move || {
+            if let Err(err) = serve_connection(&state, stream) {
+                warn!(?err, "Failed to serve client");
+            }
+        });
+    }
+
+    info!("Shutting down");
+    drop(watcher);
+    event_thread.join().unwrap();
+    // The socket may have been removed with the working copy.
+    std::fs::remove_file(&socket_path).ok();
+    drop(lock_file);
+    Ok(())
+}
+
+fn serve_connection(state: &DaemonState, stream: UnixStream) -> Result<(), Error> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let response = match serde_json::from_str(&line?) {
+            Ok(request) => {
+                let cookie_seq = state.cookie_seq.fetch_add(1, Ordering::Relaxed) + 1;
+                state.handle_request(request, cookie_seq)
+            }
+            Err(err) => Response::Error {
+                message: format!("Invalid request: {err}"),
+            },
+        };
+        write_message(&mut writer, &response)?;
+        if state.shutdown.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_log() {
+        let mut log = ChangeLog::new("abc".to_owned());
+        let token0 = log.token();
+        assert_eq!(log.changed_since(None), None);
+        assert_eq!(log.changed_since(Some(&token0)), Some(vec![]));
+
+        log.record_change(PathBuf::from("b"));
+        log.record_change(PathBuf::from("a"));
+        let token1 = log.token();
+        log.record_change(PathBuf::from("b"));
+        assert_eq!(
+            log.changed_since(Some(&token0)),
+            Some(vec![PathBuf::from("a"), PathBuf::from("b")])
+        );
+        assert_eq!(
+            log.changed_since(Some(&token1)),
+            Some(vec![PathBuf::from("b")])
+        );
+
+        // Tokens from other instances or from the future are invalid
+        assert_eq!(log.changed_since(Some("xyz:1")), None);
+        assert_eq!(log.changed_since(Some("abc:100")), None);
+        assert_eq!(log.changed_since(Some("garbage")), None);
+
+        // Lost events invalidate previous tokens
+        log.invalidate();
+        assert_eq!(log.changed_since(Some(&token1)), None);
+        assert_eq!(log.changed_since(Some(&log.token())), Some(vec![]));
+    }
+
+    #[test]
+    fn test_change_log_prune() {
+        let mut log = ChangeLog::new("abc".to_owned());
+        let token0 = log.token();
+        log.record_change(PathBuf::from("a"));
+        let token1 = log.token();
+        log.record_change(PathBuf::from("b"));
+        let token2 = log.token();
+
+        // Changes up to the token are forgotten, and older tokens are invalid
+        log.prune(&token1);
+        assert_eq!(log.changed_paths.len(), 1);
+        assert_eq!(log.changed_since(Some(&token0)), None);
+        assert_eq!(
+            log.changed_since(Some(&token1)),
+            Some(vec![PathBuf::from("b")])
+        );
+        assert_eq!(log.changed_since(Some(&token2)), Some(vec![]));
+
+        // Invalid tokens are ignored
+        log.prune("garbage");
+        log.prune(&token0);
+        assert_eq!(log.changed_paths.len(), 1);
+    }
+
+    #[test]
+    fn test_change_log_max_changed_paths() {
+        let mut log = ChangeLog::new("abc".to_owned());
+        let token0 = log.token();
+        for i in 0..MAX_CHANGED_PATHS {
+            log.record_change(PathBuf::from(i.to_string()));
+        }
+        assert!(log.changed_since(Some(&token0)).is_some());
+
+        // Changing a known path doesn't grow the log
+        log.record_change(PathBuf::from("0"));
+        assert!(log.changed_since(Some(&token0)).is_some());
+
+        // Too many paths invalidate the previous tokens
+        log.record_change(PathBuf::from("new"));
+        assert_eq!(log.changed_since(Some(&token0)), None);
+        assert_eq!(log.changed_paths.len(), 1);
+    }
+
+    #[test]
+    fn test_is_excluded_path() {
+        assert!(is_excluded_path(Path::new("")));
+        assert!(is_excluded_path(Path::new(".jj/working_copy")));
+        assert!(is_excluded_path(Path::new(".git")));
+        assert!(!is_excluded_path(Path::new("src/.git")));
+        assert!(!is_excluded_path(Path::new("file")));
+    }
+}
@@ -36,6 +36,9 @@ use crate::backend::CommitId;
 use crate::backend::TreeValue;
 use crate::commit::Commit;
 use crate::git_backend::GitBackend;
+use crate::git_lfs::transfer_diff_objects;
+use crate::git_lfs::GitLfsError;
+use crate::git_lfs::LfsObjectStore;
 use crate::git_subprocess::GitSubprocessContext;
 use crate::git_subprocess::GitSubprocessError;
 use crate::index::Index;
@@ -50,7 +53,9 @@ use crate::refs::BookmarkPushUpdate;
 use crate::repo::MutableRepo;
 use crate::repo::Repo;
 use crate::repo_path::RepoPath;
+use crate::revset::RevsetEvaluationError;
 use crate::revset::RevsetExpression;
+use crate::revset::RevsetIteratorExt as _;
 use crate::settings::GitSettings;
 use crate::store::Store;
 use crate::str_util::StringPattern;
@@ -229,6 +234,12 @@ pub fn get_git_repo(store: &Store) -> Result<gix::Repository, UnexpectedGitBacke
     get_git_backend(store).map(|backend| backend.git_repo())
 }
 
+/// Returns the store of Git LFS objects in the underlying Git repo.
+pub fn get_lfs_object_store(store: &Store) -> Result<LfsObjectStore, UnexpectedGitBackendError> {
+    let git_backend = get_git_backend(store)?;
+    Ok(LfsObjectStore::new(git_backend.git_repo_path().join("lfs")))
+}
+
 /// Checks if `git_ref` points to a Git commit object, and returns its id.
 ///
 /// If the ref points to the previously `known_target` (i.e. unchanged), this
@@ -2211,6 +2222,134 @@ fn allow_push(
     }
 }
 
+#[derive(Debug, Error)]
+pub enum GitLfsTransferError {
+    #[error("No git remote named '{0}'")]
+    NoSuchRemote(String),
+    #[error(transparent)]
+    Lfs(#[from] GitLfsError),
+    #[error(transparent)]
+    Revset(#[from] RevsetEvaluationError),
+    #[error("Unexpected git error when transferring LFS objects")]
+    InternalGitError(#[from] git2::Error),
+    #[error(transparent)]
+    UnexpectedBackend(#[from] UnexpectedGitBackendError),
+}
+
+/// Returns the store of Git LFS objects in the remote repo if the remote is
+/// located in the local filesystem.
+pub fn get_remote_lfs_object_store(
+    store: &Store,
+    remote_name: &str,
+) -> Result<Option<LfsObjectStore>, GitLfsTransferError> {
+    let git_repo = get_git_backend(store)?.open_git_repo()?;
+    let remote = git_repo.find_remote(remote_name).map_err(|err| {
+        if is_remote_not_found_err(&err) {
+            GitLfsTransferError::NoSuchRemote(remote_name.to_owned())
+        } else {
+            GitLfsTransferError::InternalGitError(err)
+        }
+    })?;
+    let Some(remote_path) = remote.url().and_then(local_remote_path) else {
+        return Ok(None);
+    };
+    let git_dir = if remote_path.join(".git").is_dir() {
+        remote_path.join(".git")
+    } else {
+        remote_path // bare repo
+    };
+    Ok(Some(LfsObjectStore::new(git_dir.join("lfs"))))
+}
+
+/// Returns the filesystem path if the remote `url` refers to a local repo.
+fn local_remote_path(url: &str) -> Option<PathBuf> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return Some(PathBuf::from(path));
+    }
+    if url.contains("://") {
+        return None;
+    }
+    // scp-like syntax "host:path" is used only if there are no slashes
+    // before the first colon.
+    match url.split_once(':') {
+        // Windows drive letter, e.g. "C:\path"
+        Some((host, _)) if cfg!(windows) && host.len() == 1 => Some(PathBuf::from(url)),
+        Some((host, _)) if !host.contains('/') => None,
+        _ => Some(PathBuf::from(url)),
+    }
+}
+
+/// Copies Git LFS objects added by the commits in `old_heads..new_heads` from
+/// the remote to the local repo. Returns the number of copied objects.
+///
+/// Only remotes in the local filesystem are supported. Nothing is copied from
+/// other remotes.
+pub fn fetch_lfs_objects(
+    repo: &dyn Repo,
+    remote_name: &str,
+    old_heads: &[CommitId],
+    new_heads: &[CommitId],
+) -> Result<usize, GitLfsTransferError> {
+    let Some(remote_store) = get_remote_lfs_object_store(repo.store(), remote_name)? else {
+        return Ok(0);
+    };
+    if !remote_store.lfs_dir().is_dir() {
+        return Ok(0);
+    }
+    let local_store = get_lfs_object_store(repo.store())?;
+    transfer_commit_lfs_objects(repo, old_heads, new_heads, &remote_store, &local_store)
+}
+
+/// Copies Git LFS objects added by the commits in `old_heads..new_heads` from
+/// the local repo to the remote. Returns the number of copied objects.
+///
+/// Only remotes in the local filesystem are supported. Nothing is copied to
+/// other remotes.
+pub fn push_lfs_objects(
+    repo: &dyn Repo,
+    remote_name: &str,
+    old_heads: &[CommitId],
+    new_heads: &[CommitId],
+) -> Result<usize, GitLfsTransferError> {
+    let local_store = get_lfs_object_store(repo.store())?;
+    if !local_store.lfs_dir().is_dir() {
+        return Ok(0);
+    }
+    let Some(remote_store) = get_remote_lfs_object_store(repo.store(), remote_name)? else {
+        return Ok(0);
+    };
+    transfer_commit_lfs_objects(repo, old_heads, new_heads, &local_store, &remote_store)
+}
+
+fn transfer_commit_lfs_objects(
+    repo: &dyn Repo,
+    old_heads: &[CommitId],
+    new_heads: &[CommitId],
+    source: &LfsObjectStore,
+    dest: &LfsObjectStore,
+) -> Result<usize, GitLfsTransferError> {
+    let commits: Vec<_> = RevsetExpression::commits(old_heads.to_vec())
+        .range(&RevsetExpression::commits(new_heads.to_vec()))
+        .evaluate(repo)?
+        .iter()
+        .commits(repo.store())
+        .try_collect()?;
+    // Only the files changed by each commit are scanned. Objects of the other
+    // files should have been transferred together with the ancestors.
+    let diffs: Vec<_> = commits
+        .iter()
+        .map(|commit| -> BackendResult<_> { Ok((commit.parent_tree(repo)?, commit.tree()?)) })
+        .try_collect()
+        .map_err(GitLfsError::from)?;
+    Ok(transfer_diff_objects(
+        diffs
+            .iter()
+            .map(|(from_tree, to_tree)| (from_tree, to_tree)),
+        source,
+        dest,
+    )?)
+}
+
 #[non_exhaustive]
 #[derive(Default)]
 #[allow(clippy::type_complexity)]
@@ -31,6 +31,7 @@ use std::str;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::MutexGuard;
+use std::sync::TryLockError;
 use std::time::SystemTime;
 
 use async_trait::async_trait;
@@ -154,6 +155,9 @@ pub struct GitBackend {
     // used in a single-threaded context.
     base_repo: gix::ThreadSafeRepository,
     repo: Mutex<gix::Repository>,
+    // Additional instances used to read objects while `repo` is locked by
+    // another thread.
+    spare_repos: Mutex<Vec<gix::Repository>>,
     root_commit_id: CommitId,
     root_change_id: ChangeId,
     empty_tree_id: TreeId,
@@ -174,6 +178,7 @@ impl GitBackend {
         GitBackend {
             base_repo,
             repo,
+            spare_repos: Mutex::new(vec![]),
             root_commit_id,
             root_change_id,
             empty_tree_id,
@@ -301,6 +306,23 @@ impl GitBackend {
         self.repo.lock().unwrap()
     }
 
+    /// Calls `f` with a repo instance that isn't used by other threads.
+    ///
+    /// Unlike `lock_git_repo()`, this doesn't block if the cached instance is
+    /// locked by another thread, so objects can be read concurrently.
+    fn with_git_repo_for_read<T>(&self, f: impl FnOnce(&gix::Repository) -> T) -> T {
+        match self.repo.try_lock() {
+            Ok(repo) => return f(&repo),
+            Err(TryLockError::WouldBlock) => {}
+            Err(TryLockError::Poisoned(err)) => panic!("{err}"),
+        }
+        let spare_repo = self.spare_repos.lock().unwrap().pop();
+        let repo = spare_repo.unwrap_or_else(|| self.base_repo.to_thread_local());
+        let result = f(&repo);
+        self.spare_repos.lock().unwrap().push(repo);
+        result
+    }
+
     /// Returns new thread-local instance to access to the underlying Git repo.
     pub fn git_repo(&self) -> gix::Repository {
         self.base_repo.to_thread_local()
@@ -411,13 +433,15 @@ impl GitBackend {
 
     fn read_file_sync(&self, id: &FileId) -> BackendResult<Box<dyn Read>> {
         let git_blob_id = validate_git_object_id(id)?;
-        let locked_repo = self.lock_git_repo();
-        let mut blob = locked_repo
-            .find_object(git_blob_id)
-            .map_err(|err| map_not_found_err(err, id))?
-            .try_into_blob()
-            .map_err(|err| to_read_object_err(err, id))?;
-        Ok(Box::new(Cursor::new(blob.take_data())))
+        let data = self.with_git_repo_for_read(|repo| {
+            let mut blob = repo
+                .find_object(git_blob_id)
+                .map_err(|err| map_not_found_err(err, id))?
+                .try_into_blob()
+                .map_err(|err| to_read_object_err(err, id))?;
+            BackendResult::Ok(blob.take_data())
+        })?;
+        Ok(Box::new(Cursor::new(data)))
     }
 
     fn new_diff_platform(&self) -> BackendResult<gix::diff::blob::Platform> {
@@ -968,14 +992,16 @@ impl Backend for GitBackend {
 
     async fn read_symlink(&self, _path: &RepoPath, id: &SymlinkId) -> BackendResult<String> {
         let git_blob_id = validate_git_object_id(id)?;
-        let locked_repo = self.lock_git_repo();
-        let mut blob = locked_repo
-            .find_object(git_blob_id)
-            .map_err(|err| map_not_found_err(err, id))?
-            .try_into_blob()
-            .map_err(|err| to_read_object_err(err, id))?;
-        let target = String::from_utf8(blob.take_data())
-            .map_err(|err| to_invalid_utf8_err(err.utf8_error(), id))?;
+        let data = self.with_git_repo_for_read(|repo| {
+            let mut blob = repo
+                .find_object(git_blob_id)
+                .map_err(|err| map_not_found_err(err, id))?
+                .try_into_blob()
+                .map_err(|err| to_read_object_err(err, id))?;
+            BackendResult::Ok(blob.take_data())
+        })?;
+        let target =
+            String::from_utf8(data).map_err(|err| to_invalid_utf8_err(err.utf8_error(), id))?;
         Ok(target)
     }
 
@@ -2056,6 +2082,42 @@ mod tests {
             .any(|git_ref| git_ref.target().unwrap() == git_id(&commit_id)));
     }
 
+    #[test]
+    fn read_file_while_repo_is_locked() {
+        let settings = user_settings();
+        let temp_dir = testutils::new_temp_dir();
+        let backend = GitBackend::init_internal(&settings, temp_dir.path()).unwrap();
+        let path = RepoPath::from_internal_string("file");
+        let file_id = backend
+            .write_file(path, &mut &b"content"[..])
+            .block_on()
+            .unwrap();
+
+        // Another thread reading files shouldn't wait for the cached instance
+        // to be unlocked.
+        let _locked_repo = backend.lock_git_repo();
+        let contents = std::thread::scope(|scope| {
+            let handles = (0..2)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut contents = String::new();
+                        backend
+                            .read_file_sync(&file_id)
+                            .unwrap()
+                            .read_to_string(&mut contents)
+                            .unwrap();
+                        contents
+                    })
+                })
+                .collect_vec();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect_vec()
+        });
+        assert_eq!(contents, ["content", "content"]);
+    }
+
     #[test]
     fn import_head_commits_duplicates() {
         let settings = user_settings();
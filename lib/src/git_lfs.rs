@@ -0,0 +1,425 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Git LFS pointer files and local LFS object storage.
+//!
+//! Files assigned the `filter=lfs` attribute are stored as small pointer files
+//! referring to the actual content by SHA-256 hash. The content is kept in the
+//! LFS object directory (e.g. `.git/lfs/objects`.)
+
+use std::collections::HashSet;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+
+use futures::StreamExt as _;
+use pollster::FutureExt as _;
+use sha2::Digest as _;
+use sha2::Sha256;
+use tempfile::NamedTempFile;
+use thiserror::Error;
+
+use crate::backend::BackendError;
+use crate::backend::TreeValue;
+use crate::file_util::persist_content_addressed_temp_file;
+use crate::gitattributes::GitAttributeState;
+use crate::gitattributes::GitAttributesCache;
+use crate::gitattributes::GitAttributesError;
+use crate::matchers::EverythingMatcher;
+use crate::merged_tree::MergedTree;
+use crate::merged_tree::TreeDiffEntry;
+use crate::repo_path::RepoPath;
+
+/// Version line of the pointer file format.
+pub const LFS_POINTER_VERSION: &str = "https://git-lfs.github.com/spec/v1";
+
+/// Pointer files larger than this are never parsed.
+pub const MAX_LFS_POINTER_SIZE: usize = 1024;
+
+/// Error occurred while reading or transferring LFS objects.
+#[derive(Debug, Error)]
+pub enum GitLfsError {
+    /// Failed to read tree or file from the backend.
+    #[error(transparent)]
+    Backend(#[from] BackendError),
+    /// Failed to load `.gitattributes` files.
+    #[error(transparent)]
+    GitAttributes(#[from] GitAttributesError),
+    /// Failed to access LFS object in the object directory.
+    #[error("Failed to access LFS object {oid}")]
+    Object {
+        /// Object id of the LFS object.
+        oid: String,
+        /// Underlying error.
+        source: io::Error,
+    },
+    /// LFS objects referenced by pointer files don't exist in the object
+    /// directory.
+    #[error("LFS objects not found: {}", oids.join(", "))]
+    MissingObjects {
+        /// Object ids of the missing LFS objects.
+        oids: Vec<String>,
+    },
+}
+
+/// Parsed Git LFS pointer file.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct LfsPointer {
+    oid: String,
+    size: u64,
+}
+
+impl LfsPointer {
+    /// Parses the pointer file `content`. Returns `None` if the content isn't
+    /// a valid pointer.
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        if content.len() > MAX_LFS_POINTER_SIZE || !content.ends_with(b"\n") {
+            return None;
+        }
+        let content = std::str::from_utf8(content).ok()?;
+        let mut lines = content.lines();
+        if lines.next()? != format!("version {LFS_POINTER_VERSION}") {
+            return None;
+        }
+        let mut oid = None;
+        let mut size = None;
+        for line in lines {
+            let (key, value) = line.split_once(' ')?;
+            match key {
+                "oid" => {
+                    let hex = value.strip_prefix("sha256:")?;
+                    if hex.len() != 64
+                        || !hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+                    {
+                        return None;
+                    }
+                    oid = Some(hex.to_owned());
+                }
+                "size" => size = Some(value.parse().ok()?),
+                // Extensions and unknown keys are ignored.
+                _ => {}
+            }
+        }
+        Some(LfsPointer {
+            oid: oid?,
+            size: size?,
+        })
+    }
+
+    /// Object id, which is the hex-encoded SHA-256 hash of the content.
+    pub fn oid(&self) -> &str {
+        &self.oid
+    }
+
+    /// Size of the content in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Serializes the pointer in the canonical format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        format!(
+            "version {LFS_POINTER_VERSION}\noid sha256:{}\nsize {}\n",
+            self.oid, self.size
+        )
+        .into_bytes()
+    }
+}
+
+/// Returns true if the file at `path` is assigned the `filter=lfs` attribute.
+pub fn is_lfs_path(
+    attributes: &GitAttributesCache,
+    path: &RepoPath,
+) -> Result<bool, GitAttributesError> {
+    let state = attributes.get(path, "filter")?;
+    Ok(state == GitAttributeState::Value("lfs".to_owned()))
+}
+
+/// Content-addressed storage of LFS objects in the local filesystem.
+#[derive(Clone, Debug)]
+pub struct LfsObjectStore {
+    lfs_dir: PathBuf,
+}
+
+impl LfsObjectStore {
+    /// Creates store backed by the `lfs_dir` directory (e.g. `.git/lfs`.) The
+    /// directory doesn't have to exist.
+    pub fn new(lfs_dir: impl Into<PathBuf>) -> Self {
+        LfsObjectStore {
+            lfs_dir: lfs_dir.into(),
+        }
+    }
+
+    /// Path to the LFS directory.
+    pub fn lfs_dir(&self) -> &Path {
+        &self.lfs_dir
+    }
+
+    /// Path to the object file, which is `objects/<xx>/<yy>/<oid>`.
+    pub fn object_path(&self, pointer: &LfsPointer) -> PathBuf {
+        let oid = pointer.oid();
+        self.lfs_dir
+            .join("objects")
+            .join(&oid[0..2])
+            .join(&oid[2..4])
+            .join(oid)
+    }
+
+    /// Returns true if the object exists.
+    pub fn has_object(&self, pointer: &LfsPointer) -> bool {
+        fs::metadata(self.object_path(pointer))
+            .is_ok_and(|metadata| metadata.is_file() && metadata.len() == pointer.size())
+    }
+
+    /// Opens the object file. Returns `None` if the object doesn't exist.
+    pub fn open_object(&self, pointer: &LfsPointer) -> io::Result<Option<File>> {
+        if !self.has_object(pointer) {
+            return Ok(None);
+        }
+        File::open(self.object_path(pointer)).map(Some)
+    }
+
+    /// Writes the `contents` as a new object, and returns the pointer to it.
+    pub fn write_object(&self, contents: &mut dyn Read) -> io::Result<LfsPointer> {
+        let temp_dir = self.lfs_dir.join("tmp");
+        fs::create_dir_all(&temp_dir)?;
+        let mut temp_file = NamedTempFile::new_in(&temp_dir)?;
+        let mut hasher = Sha256::new();
+        let mut size = 0;
+        let mut buf = vec![0; 1 << 16];
+        loop {
+            let len = contents.read(&mut buf)?;
+            if len == 0 {
+                break;
+            }
+            hasher.update(&buf[..len]);
+            temp_file.write_all(&buf[..len])?;
+            size += len as u64;
+        }
+        let pointer = LfsPointer {
+            oid: hex::encode(hasher.finalize()),
+            size,
+        };
+        let object_path = self.object_path(&pointer);
+        fs::create_dir_all(object_path.parent().unwrap())?;
+        persist_content_addressed_temp_file(temp_file, object_path)?;
+        Ok(pointer)
+    }
+
+    /// Copies the object to the `dest` store. Returns false if the object
+    /// doesn't need to be copied. Fails if the object doesn't exist in this
+    /// store.
+    pub fn copy_object_to(
+        &self,
+        dest: &LfsObjectStore,
+        pointer: &LfsPointer,
+    ) -> Result<bool, GitLfsError> {
+        let object_err = |source| GitLfsError::Object {
+            oid: pointer.oid().to_owned(),
+            source,
+        };
+        if dest.has_object(pointer) {
+            return Ok(false);
+        }
+        let Some(mut file) = self.open_object(pointer).map_err(object_err)? else {
+            return Err(GitLfsError::MissingObjects {
+                oids: vec![pointer.oid().to_owned()],
+            });
+        };
+        let copied = dest.write_object(&mut file).map_err(object_err)?;
+        if copied != *pointer {
+            return Err(object_err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "object content doesn't match the object id",
+            )));
+        }
+        Ok(true)
+    }
+}
+
+/// Reads LFS pointers from files added or modified between `from_tree` and
+/// `to_tree`, which are assigned the `filter=lfs` attribute in the `to_tree`.
+/// Files that aren't valid pointers are skipped.
+pub async fn collect_diff_pointers(
+    from_tree: &MergedTree,
+    to_tree: &MergedTree,
+) -> Result<Vec<LfsPointer>, GitLfsError> {
+    let attributes = GitAttributesCache::for_tree(to_tree);
+    let mut pointers = vec![];
+    let mut diff_stream = from_tree.diff_stream(to_tree, &EverythingMatcher);
+    while let Some(TreeDiffEntry { path, values }) = diff_stream.next().await {
+        let (_, after) = values?;
+        let Some(Some(TreeValue::File { id, .. })) = after.as_resolved() else {
+            continue;
+        };
+        if !is_lfs_path(&attributes, &path)? {
+            continue;
+        }
+        let mut content = vec![];
+        to_tree
+            .store()
+            .read_file(&path, id)?
+            .take(MAX_LFS_POINTER_SIZE as u64 + 1)
+            .read_to_end(&mut content)
+            .map_err(|err| BackendError::ReadFile {
+                path: path.clone(),
+                id: id.clone(),
+                source: err.into(),
+            })?;
+        pointers.extend(LfsPointer::parse(&content));
+    }
+    Ok(pointers)
+}
+
+/// Copies objects referenced by the LFS pointers added in the `diffs` of
+/// `(from_tree, to_tree)` pairs from `source` to `dest`. Returns the number of
+/// copied objects.
+///
+/// If some objects exist in neither `source` nor `dest`, the other objects are
+/// copied, and then an error listing the missing objects is returned.
+pub fn transfer_diff_objects<'a>(
+    diffs: impl IntoIterator<Item = (&'a MergedTree, &'a MergedTree)>,
+    source: &LfsObjectStore,
+    dest: &LfsObjectStore,
+) -> Result<usize, GitLfsError> {
+    let mut seen = HashSet::new();
+    let mut missing_oids = vec![];
+    let mut count = 0;
+    for (from_tree, to_tree) in diffs {
+        for pointer in collect_diff_pointers(from_tree, to_tree).block_on()? {
+            if !seen.insert(pointer.oid.clone()) || dest.has_object(&pointer) {
+                continue;
+            }
+            if !source.has_object(&pointer) {
+                missing_oids.push(pointer.oid);
+                continue;
+            }
+            if source.copy_object_to(dest, &pointer)? {
+                count += 1;
+            }
+        }
+    }
+    if !missing_oids.is_empty() {
+        return Err(GitLfsError::MissingObjects { oids: missing_oids });
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    const EMPTY_OID: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    #[test]
+    fn test_parse_pointer() {
+        let content = format!("version {LFS_POINTER_VERSION}\noid sha256:{EMPTY_OID}\nsize 0\n");
+        let pointer = LfsPointer::parse(content.as_bytes()).unwrap();
+        assert_eq!(pointer.oid(), EMPTY_OID);
+        assert_eq!(pointer.size(), 0);
+        assert_eq!(pointer.to_bytes(), content.as_bytes());
+
+        // Extension keys are ignored
+        let content = format!(
+            "version {LFS_POINTER_VERSION}\next-0-foo sha256:{EMPTY_OID}\noid sha256:{EMPTY_OID}\n\
+             size 12\n"
+        );
+        assert_eq!(LfsPointer::parse(content.as_bytes()).unwrap().size(), 12);
+    }
+
+    #[test]
+    fn test_parse_invalid_pointer() {
+        let parse = |s: String| LfsPointer::parse(s.as_bytes());
+        assert_eq!(parse("".to_owned()), None);
+        assert_eq!(parse("plain text\n".to_owned()), None);
+        // Missing trailing newline
+        assert_eq!(
+            parse(format!(
+                "version {LFS_POINTER_VERSION}\noid sha256:{EMPTY_OID}\nsize 0"
+            )),
+            None
+        );
+        // Missing size
+        assert_eq!(
+            parse(format!(
+                "version {LFS_POINTER_VERSION}\noid sha256:{EMPTY_OID}\n"
+            )),
+            None
+        );
+        // Bad hash
+        assert_eq!(
+            parse(format!(
+                "version {LFS_POINTER_VERSION}\noid sha256:{}\nsize 0\n",
+                EMPTY_OID.to_uppercase()
+            )),
+            None
+        );
+        assert_eq!(
+            parse(format!(
+                "version {LFS_POINTER_VERSION}\noid sha1:abc\nsize 0\n"
+            )),
+            None
+        );
+        // Bad size
+        assert_eq!(
+            parse(format!(
+                "version {LFS_POINTER_VERSION}\noid sha256:{EMPTY_OID}\nsize -1\n"
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn test_object_store() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store1 = LfsObjectStore::new(temp_dir.path().join("lfs1"));
+        let store2 = LfsObjectStore::new(temp_dir.path().join("lfs2"));
+
+        let pointer = store1.write_object(&mut &b""[..]).unwrap();
+        assert_eq!(pointer.oid(), EMPTY_OID);
+        assert_eq!(
+            store1.object_path(&pointer),
+            temp_dir.path().join("lfs1/objects/e3/b0").join(EMPTY_OID)
+        );
+        assert!(store1.has_object(&pointer));
+        assert!(!store2.has_object(&pointer));
+        assert!(store2.open_object(&pointer).unwrap().is_none());
+
+        let pointer = store1.write_object(&mut &b"content"[..]).unwrap();
+        assert_eq!(pointer.size(), 7);
+        assert!(store1.copy_object_to(&store2, &pointer).unwrap());
+        assert!(!store1.copy_object_to(&store2, &pointer).unwrap());
+        assert_matches!(
+            store2.copy_object_to(&store1, &LfsPointer {
+                oid: "0".repeat(64),
+                size: 0,
+            }),
+            Err(GitLfsError::MissingObjects { oids }) if oids == ["0".repeat(64)]
+        );
+        let mut content = vec![];
+        store2
+            .open_object(&pointer)
+            .unwrap()
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap();
+        assert_eq!(content, b"content");
+    }
+}
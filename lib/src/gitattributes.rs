@@ -0,0 +1,389 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Matching paths against `.gitattributes` files.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read as _;
+use std::iter;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use ignore::gitignore;
+use itertools::Itertools as _;
+use thiserror::Error;
+
+use crate::backend::BackendError;
+use crate::backend::TreeValue;
+use crate::merged_tree::MergedTree;
+use crate::repo_path::RepoPath;
+use crate::repo_path::RepoPathBuf;
+use crate::repo_path::RepoPathComponent;
+
+/// Error occurred while loading `.gitattributes` files.
+#[derive(Debug, Error)]
+pub enum GitAttributesError {
+    /// Failed to read `.gitattributes` file from disk.
+    #[error("Failed to read attributes from file {path}")]
+    ReadFile {
+        /// Path to the file.
+        path: PathBuf,
+        /// Underlying error.
+        source: std::io::Error,
+    },
+    /// Failed to read `.gitattributes` file from the backend.
+    #[error("Failed to read attributes from file {path:?}")]
+    ReadBackendFile {
+        /// Repository path to the file.
+        path: RepoPathBuf,
+        /// Underlying error.
+        source: BackendError,
+    },
+}
+
+/// State of an attribute assigned to a path.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum GitAttributeState {
+    /// The attribute is set, e.g. `text`.
+    Set,
+    /// The attribute is unset, e.g. `-text`.
+    Unset,
+    /// The attribute is set to a value, e.g. `eol=crlf`.
+    Value(String),
+    /// The attribute is not specified, or reset by `!text`.
+    Unspecified,
+}
+
+impl GitAttributeState {
+    /// Returns the value if the attribute is set to a value.
+    pub fn as_value(&self) -> Option<&str> {
+        match self {
+            GitAttributeState::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Line of `.gitattributes` file.
+struct GitAttributesRule {
+    matcher: gitignore::Gitignore,
+    attributes: Vec<(String, GitAttributeState)>,
+}
+
+impl fmt::Debug for GitAttributesRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GitAttributesRule")
+            .field("attributes", &self.attributes)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Models the effective contents of multiple `.gitattributes` files.
+#[derive(Debug)]
+pub struct GitAttributesFile {
+    parent: Option<Arc<GitAttributesFile>>,
+    prefix: String,
+    rules: Vec<GitAttributesRule>,
+}
+
+impl GitAttributesFile {
+    /// Creates an empty attributes file.
+    pub fn empty() -> Arc<GitAttributesFile> {
+        Arc::new(GitAttributesFile {
+            parent: None,
+            prefix: String::new(),
+            rules: vec![],
+        })
+    }
+
+    /// Concatenates new `.gitattributes` content at the `prefix` directory.
+    ///
+    /// The `prefix` should be a slash-separated path relative to the workspace
+    /// root. Lines that can't be parsed are ignored as Git does.
+    pub fn chain(self: &Arc<GitAttributesFile>, prefix: &str, input: &[u8]) -> Arc<Self> {
+        let rules = input
+            .split(|b| *b == b'\n')
+            .filter_map(|line| std::str::from_utf8(line).ok())
+            .filter_map(|line| parse_rule(prefix, line))
+            .collect_vec();
+        if rules.is_empty() {
+            return self.clone();
+        }
+        let parent = if self.rules.is_empty() {
+            self.parent.clone() // omit the empty root
+        } else {
+            Some(self.clone())
+        };
+        Arc::new(GitAttributesFile {
+            parent,
+            prefix: prefix.to_owned(),
+            rules,
+        })
+    }
+
+    /// Returns the state of the attribute `name` for the file `path`.
+    ///
+    /// The `path` should be a slash-separated path relative to the workspace
+    /// root. Rules in deeper directories take precedence, and later rules
+    /// take precedence within the same file.
+    pub fn get(&self, path: &str, name: &str) -> GitAttributeState {
+        iter::successors(Some(self), |file| file.parent.as_deref())
+            .filter(|file| path.starts_with(&file.prefix))
+            .flat_map(|file| file.rules.iter().rev())
+            .filter(|rule| rule.matcher.matched(path, false).is_ignore())
+            .find_map(|rule| {
+                rule.attributes
+                    .iter()
+                    .rev()
+                    .find(|(attr_name, _)| attr_name == name)
+                    .map(|(_, state)| state.clone())
+            })
+            .unwrap_or(GitAttributeState::Unspecified)
+    }
+}
+
+fn parse_rule(prefix: &str, line: &str) -> Option<GitAttributesRule> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let mut words = line.split_ascii_whitespace();
+    let pattern = words.next()?;
+    // Negative patterns are forbidden in .gitattributes.
+    if pattern.starts_with('!') {
+        return None;
+    }
+    let mut attributes = vec![];
+    for word in words {
+        let (name, state) = if let Some(name) = word.strip_prefix('-') {
+            (name, GitAttributeState::Unset)
+        } else if let Some(name) = word.strip_prefix('!') {
+            (name, GitAttributeState::Unspecified)
+        } else if let Some((name, value)) = word.split_once('=') {
+            (name, GitAttributeState::Value(value.to_owned()))
+        } else {
+            (word, GitAttributeState::Set)
+        };
+        if name == "binary" && state == GitAttributeState::Set {
+            // Built-in macro attribute
+            for macro_name in ["diff", "merge", "text"] {
+                attributes.push((macro_name.to_owned(), GitAttributeState::Unset));
+            }
+        }
+        attributes.push((name.to_owned(), state));
+    }
+    let mut builder = gitignore::GitignoreBuilder::new(prefix);
+    builder.add_line(None, pattern).ok()?;
+    let matcher = builder.build().ok()?;
+    Some(GitAttributesRule {
+        matcher,
+        attributes,
+    })
+}
+
+/// Function to read the contents of `.gitattributes` file at the given path.
+pub type GitAttributesReadFn<'a> =
+    dyn Fn(&RepoPath) -> Result<Option<Vec<u8>>, GitAttributesError> + Send + Sync + 'a;
+
+/// Loads `.gitattributes` files on demand, and caches them per directory.
+pub struct GitAttributesCache<'a> {
+    base: Arc<GitAttributesFile>,
+    read_file: Box<GitAttributesReadFn<'a>>,
+    dirs: Mutex<HashMap<RepoPathBuf, Arc<GitAttributesFile>>>,
+}
+
+impl<'a> GitAttributesCache<'a> {
+    /// Creates new cache that reads `.gitattributes` files by `read_file`.
+    pub fn new(
+        base: Arc<GitAttributesFile>,
+        read_file: impl Fn(&RepoPath) -> Result<Option<Vec<u8>>, GitAttributesError> + Send + Sync + 'a,
+    ) -> Self {
+        GitAttributesCache {
+            base,
+            read_file: Box::new(read_file),
+            dirs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Creates new cache that reads `.gitattributes` files from the `tree`.
+    ///
+    /// Conflicted `.gitattributes` files are ignored.
+    pub fn for_tree(tree: &'a MergedTree) -> Self {
+        GitAttributesCache::new(GitAttributesFile::empty(), |path| {
+            let read_err = |err| GitAttributesError::ReadBackendFile {
+                path: path.to_owned(),
+                source: err,
+            };
+            let value = tree.path_value(path).map_err(read_err)?;
+            let Some(Some(TreeValue::File { id, .. })) = value.as_resolved() else {
+                return Ok(None);
+            };
+            let mut content = vec![];
+            tree.store()
+                .read_file(path, id)
+                .map_err(read_err)?
+                .read_to_end(&mut content)
+                .map_err(|err| {
+                    read_err(BackendError::ReadFile {
+                        path: path.to_owned(),
+                        id: id.clone(),
+                        source: err.into(),
+                    })
+                })?;
+            Ok(Some(content))
+        })
+    }
+
+    /// Returns the attributes effective in the directory `dir`.
+    pub fn for_dir(&self, dir: &RepoPath) -> Result<Arc<GitAttributesFile>, GitAttributesError> {
+        if let Some(file) = self.dirs.lock().unwrap().get(dir) {
+            return Ok(file.clone());
+        }
+        let parent = match dir.parent() {
+            Some(parent_dir) => self.for_dir(parent_dir)?,
+            None => self.base.clone(),
+        };
+        let attributes_path = dir.join(RepoPathComponent::new(".gitattributes"));
+        let file = match (self.read_file)(&attributes_path)? {
+            Some(content) => parent.chain(&dir.to_internal_dir_string(), &content),
+            None => parent,
+        };
+        self.dirs
+            .lock()
+            .unwrap()
+            .insert(dir.to_owned(), file.clone());
+        Ok(file)
+    }
+
+    /// Returns the state of the attribute `name` for the file `path`.
+    pub fn get(
+        &self,
+        path: &RepoPath,
+        name: &str,
+    ) -> Result<GitAttributeState, GitAttributesError> {
+        let dir = path.parent().unwrap_or(RepoPath::root());
+        let file = self.for_dir(dir)?;
+        Ok(file.get(path.as_internal_file_string(), name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(input: &[u8], path: &str, name: &str) -> GitAttributeState {
+        GitAttributesFile::empty().chain("", input).get(path, name)
+    }
+
+    fn value(s: &str) -> GitAttributeState {
+        GitAttributeState::Value(s.to_owned())
+    }
+
+    #[test]
+    fn test_gitattributes_empty_file() {
+        let file = GitAttributesFile::empty();
+        assert_eq!(file.get("foo", "text"), GitAttributeState::Unspecified);
+    }
+
+    #[test]
+    fn test_gitattributes_states() {
+        let input = b"* text -diff eol=crlf !merge\n";
+        assert_eq!(get(input, "foo", "text"), GitAttributeState::Set);
+        assert_eq!(get(input, "foo", "diff"), GitAttributeState::Unset);
+        assert_eq!(get(input, "foo", "eol"), value("crlf"));
+        assert_eq!(get(input, "foo", "merge"), GitAttributeState::Unspecified);
+        assert_eq!(get(input, "foo", "other"), GitAttributeState::Unspecified);
+    }
+
+    #[test]
+    fn test_gitattributes_patterns() {
+        let input = b"*.txt text\n/root.c eol=lf\nsub/*.c eol=crlf\n";
+        assert_eq!(get(input, "a.txt", "text"), GitAttributeState::Set);
+        assert_eq!(get(input, "dir/a.txt", "text"), GitAttributeState::Set);
+        assert_eq!(get(input, "a.txtx", "text"), GitAttributeState::Unspecified);
+        assert_eq!(get(input, "root.c", "eol"), value("lf"));
+        assert_eq!(
+            get(input, "dir/root.c", "eol"),
+            GitAttributeState::Unspecified
+        );
+        assert_eq!(get(input, "sub/a.c", "eol"), value("crlf"));
+        assert_eq!(
+            get(input, "dir/sub/a.c", "eol"),
+            GitAttributeState::Unspecified
+        );
+    }
+
+    #[test]
+    fn test_gitattributes_directory_pattern_is_not_recursive() {
+        let input = b"dir text\n";
+        assert_eq!(get(input, "dir", "text"), GitAttributeState::Set);
+        assert_eq!(
+            get(input, "dir/foo", "text"),
+            GitAttributeState::Unspecified
+        );
+    }
+
+    #[test]
+    fn test_gitattributes_precedence() {
+        // Later lines override earlier lines
+        let input = b"* text\n*.bin -text\n";
+        assert_eq!(get(input, "a.txt", "text"), GitAttributeState::Set);
+        assert_eq!(get(input, "a.bin", "text"), GitAttributeState::Unset);
+        // Unrelated attributes don't hide earlier lines
+        let input = b"* text\n*.bin eol=lf\n";
+        assert_eq!(get(input, "a.bin", "text"), GitAttributeState::Set);
+        // Deeper files override shallower files
+        let file = GitAttributesFile::empty()
+            .chain("", b"*.txt eol=lf\n")
+            .chain("dir/", b"*.txt eol=crlf\n");
+        assert_eq!(file.get("a.txt", "eol"), value("lf"));
+        assert_eq!(file.get("dir/a.txt", "eol"), value("crlf"));
+    }
+
+    #[test]
+    fn test_gitattributes_binary_macro() {
+        let input = b"* text\n*.png binary\n";
+        assert_eq!(get(input, "a.png", "text"), GitAttributeState::Unset);
+        assert_eq!(get(input, "a.png", "diff"), GitAttributeState::Unset);
+        assert_eq!(get(input, "a.png", "binary"), GitAttributeState::Set);
+    }
+
+    #[test]
+    fn test_gitattributes_invalid_lines() {
+        let input = b"# comment\n\n!negated text\n* eol=lf\n\xff text\n";
+        assert_eq!(
+            get(input, "negated", "text"),
+            GitAttributeState::Unspecified
+        );
+        assert_eq!(get(input, "foo", "eol"), value("lf"));
+    }
+
+    #[test]
+    fn test_gitattributes_cache() {
+        let cache = GitAttributesCache::new(GitAttributesFile::empty(), |path| {
+            match path.as_internal_file_string() {
+                ".gitattributes" => Ok(Some(b"* text\n".to_vec())),
+                "dir/.gitattributes" => Ok(Some(b"*.bin -text\n".to_vec())),
+                _ => Ok(None),
+            }
+        });
+        let get = |path| cache.get(RepoPath::from_internal_string(path), "text");
+        assert_eq!(get("a.bin").unwrap(), GitAttributeState::Set);
+        assert_eq!(get("dir/a.bin").unwrap(), GitAttributeState::Unset);
+        assert_eq!(get("dir/sub/a.bin").unwrap(), GitAttributeState::Unset);
+        assert_eq!(get("dir/a.txt").unwrap(), GitAttributeState::Set);
+    }
+}
@@ -14,6 +14,7 @@
 
 #![allow(missing_docs)]
 
+use std::collections::HashMap;
 use std::fs;
 use std::io;
 use std::iter;
@@ -42,11 +43,29 @@ pub enum GitIgnoreError {
     },
 }
 
+/// Pattern that decided whether a path is ignored.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GitIgnoreMatch {
+    /// File the pattern was read from.
+    pub source: PathBuf,
+    /// 1-based line number of the pattern in the `source` file.
+    pub line_number: usize,
+    /// The pattern as written in the file.
+    pub pattern: String,
+    /// Whether the path is ignored, as opposed to re-included by a negated
+    /// pattern such as `!foo`.
+    pub is_ignored: bool,
+}
+
 /// Models the effective contents of multiple .gitignore files.
 #[derive(Debug)]
 pub struct GitIgnoreFile {
     parent: Option<Arc<GitIgnoreFile>>,
     matcher: gitignore::Gitignore,
+    source: PathBuf,
+    // Maps each pattern to the last line it appears on. The underlying matcher
+    // reports the last matching pattern, so duplicates resolve to the same line.
+    line_numbers: HashMap<String, usize>,
 }
 
 impl GitIgnoreFile {
@@ -54,6 +73,8 @@ impl GitIgnoreFile {
         Arc::new(GitIgnoreFile {
             parent: None,
             matcher: gitignore::Gitignore::empty(),
+            source: PathBuf::new(),
+            line_numbers: HashMap::new(),
         })
     }
 
@@ -68,6 +89,7 @@ impl GitIgnoreFile {
         input: &[u8],
     ) -> Result<Arc<GitIgnoreFile>, GitIgnoreError> {
         let mut builder = gitignore::GitignoreBuilder::new(prefix);
+        let mut line_numbers = HashMap::new();
         for (i, input_line) in input.split(|b| *b == b'\n').enumerate() {
             let line =
                 std::str::from_utf8(input_line).map_err(|err| GitIgnoreError::InvalidUtf8 {
@@ -85,6 +107,13 @@ impl GitIgnoreFile {
                     path: ignore_path.to_path_buf(),
                     source: err,
                 })?;
+            // Trailing whitespace is stripped from patterns unless escaped.
+            let pattern = if line.ends_with("\\ ") {
+                line
+            } else {
+                line.trim_end()
+            };
+            line_numbers.insert(pattern.to_owned(), i + 1);
         }
         let matcher = builder.build().map_err(|err| GitIgnoreError::Underlying {
             path: ignore_path.to_path_buf(),
@@ -95,7 +124,12 @@ impl GitIgnoreFile {
         } else {
             Some(self.clone())
         };
-        Ok(Arc::new(GitIgnoreFile { parent, matcher }))
+        Ok(Arc::new(GitIgnoreFile {
+            parent,
+            matcher,
+            source: ignore_path.to_path_buf(),
+            line_numbers,
+        }))
     }
 
     /// Concatenates new `.gitignore` file at the `prefix` directory.
@@ -118,19 +152,21 @@ impl GitIgnoreFile {
         }
     }
 
+    fn matched_glob(&self, path: &str, is_dir: bool) -> Option<(&Self, &gitignore::Glob)> {
+        iter::successors(Some(self), |file| file.parent.as_deref()).find_map(|file| {
+            // TODO: the documentation warns that
+            // `matched_path_or_any_parents` is slower than `matched`;
+            // ideally, we would switch to that.
+            match file.matcher.matched_path_or_any_parents(path, is_dir) {
+                ignore::Match::None => None,
+                ignore::Match::Ignore(glob) | ignore::Match::Whitelist(glob) => Some((file, glob)),
+            }
+        })
+    }
+
     fn matches_helper(&self, path: &str, is_dir: bool) -> bool {
-        iter::successors(Some(self), |file| file.parent.as_deref())
-            .find_map(|file| {
-                // TODO: the documentation warns that
-                // `matched_path_or_any_parents` is slower than `matched`;
-                // ideally, we would switch to that.
-                match file.matcher.matched_path_or_any_parents(path, is_dir) {
-                    ignore::Match::None => None,
-                    ignore::Match::Ignore(_) => Some(true),
-                    ignore::Match::Whitelist(_) => Some(false),
-                }
-            })
-            .unwrap_or_default()
+        self.matched_glob(path, is_dir)
+            .is_some_and(|(_, glob)| !glob.is_whitelist())
     }
 
     /// Returns whether specified path (not just file!) should be ignored. This
@@ -148,6 +184,29 @@ impl GitIgnoreFile {
         };
         self.matches_helper(path, is_dir)
     }
+
+    /// Returns the pattern that decides whether the specified path is ignored,
+    /// or `None` if no pattern matches the path.
+    ///
+    /// Like [`GitIgnoreFile::matches()`], a path ending with slash is
+    /// considered a directory.
+    pub fn explain(&self, path: &str) -> Option<GitIgnoreMatch> {
+        let (path, is_dir) = match path.strip_suffix('/') {
+            Some(path) => (path, true),
+            None => (path, false),
+        };
+        let (file, glob) = self.matched_glob(path, is_dir)?;
+        Some(GitIgnoreMatch {
+            source: file.source.clone(),
+            line_number: file
+                .line_numbers
+                .get(glob.original())
+                .copied()
+                .unwrap_or_default(),
+            pattern: glob.original().to_owned(),
+            is_ignored: !glob.is_whitelist(),
+        })
+    }
 }
 
 #[cfg(test)]
@@ -453,4 +512,53 @@ mod tests {
             .unwrap();
         assert!(ignore.matches("foo/bar.ext"));
     }
+
+    #[test]
+    fn test_gitignore_explain() {
+        let file1 = GitIgnoreFile::empty()
+            .chain(
+                "",
+                Path::new(".gitignore"),
+                b"# comment\n*.o\n\n/target  \n",
+            )
+            .unwrap();
+        let file2 = file1
+            .chain("dir/", Path::new("dir/.gitignore"), b"*.o\n!keep.o\n")
+            .unwrap();
+        assert_eq!(file1.explain("foo"), None);
+        assert_eq!(
+            file1.explain("dir/foo.o"),
+            Some(GitIgnoreMatch {
+                source: PathBuf::from(".gitignore"),
+                line_number: 2,
+                pattern: "*.o".to_owned(),
+                is_ignored: true,
+            })
+        );
+        assert_eq!(
+            file1.explain("target/debug/foo"),
+            Some(GitIgnoreMatch {
+                source: PathBuf::from(".gitignore"),
+                line_number: 4,
+                pattern: "/target".to_owned(),
+                is_ignored: true,
+            })
+        );
+        assert_eq!(
+            file2.explain("dir/keep.o"),
+            Some(GitIgnoreMatch {
+                source: PathBuf::from("dir/.gitignore"),
+                line_number: 2,
+                pattern: "!keep.o".to_owned(),
+                is_ignored: false,
+            })
+        );
+        // The innermost file decides
+        assert_eq!(
+            file2
+                .explain("dir/foo.o")
+                .map(|m| (m.source, m.line_number)),
+            Some((PathBuf::from("dir/.gitignore"), 1))
+        );
+    }
 }
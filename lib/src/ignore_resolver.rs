@@ -0,0 +1,196 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Resolves the ignore patterns that apply to a workspace.
+//!
+//! Patterns are read from the following files, in increasing order of
+//! precedence:
+//!
+//! 1. The global excludes file: `core.excludesFile` in the Git config, or
+//!    `$XDG_CONFIG_HOME/git/ignore` if unset.
+//! 2. `info/exclude` in the backing Git repository, and in the `.git`
+//!    directory at the workspace root if there is one.
+//! 3. `.gitignore` files in the working copy.
+//! 4. `.jjignore` files in the working copy, which use the same syntax as
+//!    `.gitignore` but are ignored by Git.
+//!
+//! Files in a subdirectory take precedence over files in its parent
+//! directories.
+
+use std::iter;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::gitignore::GitIgnoreError;
+use crate::gitignore::GitIgnoreFile;
+use crate::gitignore::GitIgnoreMatch;
+use crate::repo_path::RepoPath;
+use crate::store::Store;
+
+/// Name of the per-directory ignore file shared with Git.
+pub const GIT_IGNORE_FILE_NAME: &str = ".gitignore";
+/// Name of the per-directory ignore file only read by jj.
+pub const JJ_IGNORE_FILE_NAME: &str = ".jjignore";
+
+/// Chains the ignore files in the working-copy directory `dir`, which is
+/// located at `disk_dir` on disk.
+pub fn chain_directory_ignores(
+    ignores: &Arc<GitIgnoreFile>,
+    dir: &RepoPath,
+    disk_dir: &Path,
+) -> Result<Arc<GitIgnoreFile>, GitIgnoreError> {
+    let prefix = dir.to_internal_dir_string();
+    ignores
+        .chain_with_file(&prefix, disk_dir.join(GIT_IGNORE_FILE_NAME))?
+        .chain_with_file(&prefix, disk_dir.join(JJ_IGNORE_FILE_NAME))
+}
+
+/// Ignore patterns of a workspace.
+#[derive(Clone, Debug)]
+pub struct IgnoreResolver {
+    workspace_root: PathBuf,
+    base_ignores: Arc<GitIgnoreFile>,
+}
+
+impl IgnoreResolver {
+    /// Loads the ignore files that apply to the whole workspace at
+    /// `workspace_root`. Per-directory files are loaded on demand.
+    pub fn load(workspace_root: &Path, store: &Store) -> Result<Self, GitIgnoreError> {
+        let mut base_ignores = GitIgnoreFile::empty();
+        if let Some(path) = excludes_file_path(workspace_root, store) {
+            base_ignores = base_ignores.chain_with_file("", path)?;
+        }
+        for git_dir in git_dirs(workspace_root, store) {
+            base_ignores =
+                base_ignores.chain_with_file("", git_dir.join("info").join("exclude"))?;
+        }
+        Ok(IgnoreResolver {
+            workspace_root: workspace_root.to_owned(),
+            base_ignores,
+        })
+    }
+
+    /// Ignore patterns that apply to every directory in the working copy.
+    ///
+    /// This is suitable for `SnapshotOptions::base_ignores`.
+    pub fn base_ignores(&self) -> &Arc<GitIgnoreFile> {
+        &self.base_ignores
+    }
+
+    /// Returns the ignore patterns that apply to entries of `dir`, including
+    /// the per-directory files in `dir` and its ancestors.
+    pub fn ignores_for_dir(&self, dir: &RepoPath) -> Result<Arc<GitIgnoreFile>, GitIgnoreError> {
+        let dirs = iter::successors(Some(dir), |dir| dir.parent()).collect::<Vec<_>>();
+        let mut ignores = self.base_ignores.clone();
+        for dir in dirs.into_iter().rev() {
+            // Paths that can't be mapped to the file system have no ignore
+            // files.
+            let Ok(disk_dir) = dir.to_fs_path(&self.workspace_root) else {
+                break;
+            };
+            ignores = chain_directory_ignores(&ignores, dir, &disk_dir)?;
+        }
+        Ok(ignores)
+    }
+
+    /// Returns the pattern that decides whether `path` is ignored, or `None`
+    /// if no pattern matches the path.
+    ///
+    /// Like snapshots, this doesn't read ignore files in directories below
+    /// `path`'s parent.
+    pub fn explain(
+        &self,
+        path: &RepoPath,
+        is_dir: bool,
+    ) -> Result<Option<GitIgnoreMatch>, GitIgnoreError> {
+        let Some(parent) = path.parent() else {
+            return Ok(None); // the root directory is never ignored
+        };
+        let ignores = self.ignores_for_dir(parent)?;
+        let path_str = if is_dir {
+            path.to_internal_dir_string()
+        } else {
+            path.as_internal_file_string().to_owned()
+        };
+        Ok(ignores.explain(&path_str))
+    }
+}
+
+#[cfg(feature = "git")]
+fn excludes_file_path(workspace_root: &Path, store: &Store) -> Option<PathBuf> {
+    let get_excludes_file_path = |config: &gix::config::File| -> Option<PathBuf> {
+        // TODO: maybe use path() and interpolate(), which can process non-utf-8
+        // path on Unix.
+        if let Some(value) = config.string("core.excludesFile") {
+            let path = std::str::from_utf8(&value)
+                .ok()
+                .map(crate::file_util::expand_home_path)?;
+            // The configured path is usually absolute, but if it's relative,
+            // the "git" command would read the file at the work-tree directory.
+            Some(workspace_root.join(path))
+        } else {
+            default_excludes_file_path()
+        }
+    };
+
+    if let Ok(git_backend) = crate::git::get_git_backend(store) {
+        let git_repo = git_backend.git_repo();
+        get_excludes_file_path(&git_repo.config_snapshot())
+    } else if let Ok(git_config) = gix::config::File::from_globals() {
+        get_excludes_file_path(&git_config)
+    } else {
+        default_excludes_file_path()
+    }
+}
+
+#[cfg(not(feature = "git"))]
+fn excludes_file_path(_workspace_root: &Path, _store: &Store) -> Option<PathBuf> {
+    // Git config can't be read without the "git" feature.
+    default_excludes_file_path()
+}
+
+fn default_excludes_file_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|x| !x.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|x| Path::new(&x).join(".config")))?;
+    Some(config_home.join("git").join("ignore"))
+}
+
+/// Returns the Git directories whose `info/exclude` files apply to the
+/// workspace.
+fn git_dirs(workspace_root: &Path, store: &Store) -> Vec<PathBuf> {
+    let mut git_dirs: Vec<PathBuf> = vec![];
+    #[cfg(feature = "git")]
+    if let Ok(git_backend) = crate::git::get_git_backend(store) {
+        git_dirs.push(git_backend.git_repo_path().to_owned());
+    }
+    #[cfg(not(feature = "git"))]
+    let _ = store;
+    // The workspace may be inside a Git working tree that isn't backing the
+    // repo, e.g. in non-colocated workspaces.
+    let workspace_git_dir = workspace_root.join(".git");
+    if workspace_git_dir.is_dir() {
+        let canonicalize = |path: &Path| dunce::canonicalize(path).unwrap_or(path.to_owned());
+        let canonical_dir = canonicalize(&workspace_git_dir);
+        if !git_dirs
+            .iter()
+            .any(|dir| canonicalize(dir) == canonical_dir)
+        {
+            git_dirs.push(workspace_git_dir);
+        }
+    }
+    git_dirs
+}
@@ -31,17 +31,20 @@ pub mod content_hash;
 pub mod absorb;
 pub mod annotate;
 pub mod backend;
+pub mod case_collisions;
 pub mod commit;
 pub mod commit_builder;
 pub mod config;
 mod config_resolver;
 pub mod conflicts;
+pub mod content_filter;
 pub mod copies;
 pub mod dag_walk;
 pub mod default_index;
 pub mod default_submodule_store;
 pub mod diff;
 pub mod dsl_util;
+pub mod eol;
 pub mod extensions_map;
 pub mod file_util;
 pub mod files;
@@ -65,25 +68,30 @@ pub mod git {
 }
 #[cfg(feature = "git")]
 pub mod git_backend;
+pub mod git_lfs;
 #[cfg(feature = "git")]
 mod git_subprocess;
+pub mod gitattributes;
 pub mod gitignore;
 pub mod gpg_signing;
 pub mod graph;
 pub mod hex_util;
 pub mod id_prefix;
+pub mod ignore_resolver;
 pub mod index;
 pub mod local_backend;
 pub mod local_working_copy;
 pub mod lock;
 pub mod matchers;
 pub mod merge;
+pub mod merge_driver;
 pub mod merged_tree;
 pub mod object_id;
 pub mod op_heads_store;
 pub mod op_store;
 pub mod op_walk;
 pub mod operation;
+mod path_command;
 #[allow(missing_docs)]
 pub mod protos;
 pub mod refs;
@@ -94,18 +102,22 @@ mod revset_parser;
 pub mod rewrite;
 #[cfg(feature = "testing")]
 pub mod secret_backend;
+pub mod segmented_tree_state;
 pub mod settings;
 pub mod signing;
 pub mod simple_op_heads_store;
 pub mod simple_op_store;
+pub mod sparse;
 pub mod ssh_signing;
 pub mod stacked_table;
 pub mod store;
 pub mod str_util;
+pub mod structured_merge;
 pub mod submodule_store;
 #[cfg(feature = "testing")]
 pub mod test_signing_backend;
 pub mod time_util;
+pub mod trailer;
 pub mod transaction;
 pub mod tree;
 pub mod tree_builder;
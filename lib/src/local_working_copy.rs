@@ -17,6 +17,7 @@
 
 use std::any::Any;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::collections::HashSet;
 use std::error::Error;
 use std::fs;
@@ -26,15 +27,15 @@ use std::fs::Metadata;
 use std::fs::OpenOptions;
 use std::io;
 use std::io::Read;
+use std::io::Seek as _;
+use std::io::SeekFrom;
 use std::io::Write;
-use std::iter;
 use std::mem;
 use std::ops::Range;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::path::PathBuf;
-use std::slice;
 use std::sync::mpsc::channel;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
@@ -64,6 +65,8 @@ use crate::backend::MillisSinceEpoch;
 use crate::backend::SymlinkId;
 use crate::backend::TreeId;
 use crate::backend::TreeValue;
+use crate::case_collisions::find_case_collisions;
+use crate::case_collisions::CaseCollisionFinder;
 use crate::commit::Commit;
 use crate::conflicts;
 use crate::conflicts::choose_materialized_conflict_marker_len;
@@ -72,14 +75,31 @@ use crate::conflicts::materialize_tree_value;
 use crate::conflicts::ConflictMarkerStyle;
 use crate::conflicts::MaterializedTreeValue;
 use crate::conflicts::MIN_CONFLICT_MARKER_LEN;
+use crate::content_filter::ContentFilterKind;
+use crate::content_filter::ContentFilterRunner;
+use crate::eol::EolConversion;
+use crate::file_util::check_case_insensitive_fs;
 use crate::file_util::check_symlink_support;
 use crate::file_util::try_symlink;
+use crate::fileset::FilesetParseError;
+#[cfg(all(unix, feature = "fsmonitor-daemon"))]
+use crate::fsmonitor::builtin;
 #[cfg(feature = "watchman")]
 use crate::fsmonitor::watchman;
+#[cfg(all(unix, feature = "fsmonitor-daemon"))]
+use crate::fsmonitor::BuiltinConfig;
 use crate::fsmonitor::FsmonitorSettings;
 #[cfg(feature = "watchman")]
 use crate::fsmonitor::WatchmanConfig;
+use crate::git_lfs::is_lfs_path;
+use crate::git_lfs::LfsObjectStore;
+use crate::git_lfs::LfsPointer;
+use crate::git_lfs::MAX_LFS_POINTER_SIZE;
+use crate::gitattributes::GitAttributesCache;
+use crate::gitattributes::GitAttributesError;
+use crate::gitattributes::GitAttributesFile;
 use crate::gitignore::GitIgnoreFile;
+use crate::ignore_resolver::chain_directory_ignores;
 use crate::lock::FileLock;
 use crate::matchers::DifferenceMatcher;
 use crate::matchers::EverythingMatcher;
@@ -99,6 +119,16 @@ use crate::op_store::WorkspaceId;
 use crate::repo_path::RepoPath;
 use crate::repo_path::RepoPathBuf;
 use crate::repo_path::RepoPathComponent;
+use crate::segmented_tree_state;
+use crate::segmented_tree_state::decode_file_state_record;
+use crate::segmented_tree_state::decode_file_state_record_path;
+use crate::segmented_tree_state::encode_file_state_record;
+use crate::segmented_tree_state::SegmentFileStates;
+use crate::segmented_tree_state::SegmentKind;
+use crate::segmented_tree_state::SegmentedTreeStateError;
+use crate::segmented_tree_state::FILE_STATE_RECORD_LEN;
+use crate::sparse::sparse_matcher;
+use crate::sparse::SparsePattern;
 use crate::store::Store;
 use crate::tree::Tree;
 use crate::working_copy::CheckoutError;
@@ -208,15 +238,20 @@ impl FileState {
     }
 }
 
-/// Owned map of path to file states, backed by proto data.
-#[derive(Clone, Debug)]
+/// Owned map of path to file states, stored as a table of fixed-size records
+/// sorted by path.
+///
+/// The records use the same layout as the "segmented" tree state file, so
+/// they can be read from and written to the file without conversion.
+#[derive(Clone, Debug, Default)]
 struct FileStatesMap {
-    data: Vec<crate::protos::working_copy::FileStateEntry>,
+    records: Vec<u8>,
+    paths: String,
 }
 
 impl FileStatesMap {
     fn new() -> Self {
-        FileStatesMap { data: Vec::new() }
+        Self::default()
     }
 
     fn from_proto(
@@ -231,14 +266,44 @@ impl FileStatesMap {
             });
         }
         debug_assert!(is_file_state_entries_proto_unique_and_sorted(&data));
-        FileStatesMap { data }
+        let mut builder = FileStatesBuilder::with_capacity(data.len());
+        for entry in &data {
+            let (path, state) = file_state_entry_from_proto(entry);
+            builder.push(path, &state);
+        }
+        builder.build()
+    }
+
+    /// Creates map from records decoded from the tree state file. Returns
+    /// `None` if the records aren't sorted or contain deleted entries.
+    fn from_segment(segment: SegmentFileStates<'_>) -> Option<Self> {
+        let states = FileStates {
+            records: segment.records,
+            paths: segment.paths,
+        };
+        let is_valid = (0..states.len()).all(|i| states.state_at(i).is_some())
+            && states
+                .paths()
+                .tuple_windows()
+                .all(|(path1, path2)| path1 < path2);
+        is_valid.then(|| FileStatesMap {
+            records: segment.records.to_vec(),
+            paths: segment.paths.to_owned(),
+        })
+    }
+
+    fn to_proto(&self) -> Vec<crate::protos::working_copy::FileStateEntry> {
+        self.all()
+            .iter()
+            .map(|(path, state)| file_state_entry_to_proto(path.to_owned(), &state))
+            .collect()
     }
 
     /// Merges changed and deleted entries into this map. The changed entries
     /// must be sorted by path.
     fn merge_in(
         &mut self,
-        changed_file_states: Vec<(RepoPathBuf, FileState)>,
+        changed_file_states: &[(RepoPathBuf, FileState)],
         deleted_files: &HashSet<RepoPathBuf>,
     ) {
         if changed_file_states.is_empty() && deleted_files.is_empty() {
@@ -251,64 +316,113 @@ impl FileStatesMap {
                 .all(|((path1, _), (path2, _))| path1 < path2),
             "changed_file_states must be sorted and have no duplicates"
         );
-        self.data = itertools::merge_join_by(
-            mem::take(&mut self.data),
+        let old_states = self.all();
+        let mut builder = FileStatesBuilder::with_capacity(old_states.len());
+        for diff in itertools::merge_join_by(
+            0..old_states.len(),
             changed_file_states,
-            |old_entry, (changed_path, _)| {
-                RepoPath::from_internal_string(&old_entry.path).cmp(changed_path)
-            },
-        )
-        .filter_map(|diff| match diff {
-            EitherOrBoth::Both(_, (path, state)) | EitherOrBoth::Right((path, state)) => {
-                debug_assert!(!deleted_files.contains(&path));
-                Some(file_state_entry_to_proto(path, &state))
-            }
-            EitherOrBoth::Left(entry) => {
-                let present = !deleted_files.contains(RepoPath::from_internal_string(&entry.path));
-                present.then_some(entry)
+            |&old_index, (changed_path, _)| old_states.path_at(old_index).cmp(changed_path),
+        ) {
+            match diff {
+                EitherOrBoth::Both(_, (path, state)) | EitherOrBoth::Right((path, state)) => {
+                    debug_assert!(!deleted_files.contains(path));
+                    builder.push(path, state);
+                }
+                EitherOrBoth::Left(old_index) => {
+                    let path = old_states.path_at(old_index);
+                    if !deleted_files.contains(path) {
+                        builder.push_record(path, old_states.record_at(old_index));
+                    }
+                }
             }
-        })
-        .collect();
+        }
+        *self = builder.build();
     }
 
     fn clear(&mut self) {
-        self.data.clear();
+        self.records.clear();
+        self.paths.clear();
     }
 
     /// Returns read-only map containing all file states.
     fn all(&self) -> FileStates<'_> {
-        FileStates::from_sorted(&self.data)
+        FileStates {
+            records: &self.records,
+            paths: &self.paths,
+        }
+    }
+}
+
+/// Builds `FileStatesMap` from entries sorted by path.
+struct FileStatesBuilder {
+    records: Vec<u8>,
+    paths: String,
+}
+
+impl FileStatesBuilder {
+    fn with_capacity(len: usize) -> Self {
+        FileStatesBuilder {
+            records: Vec::with_capacity(len * FILE_STATE_RECORD_LEN),
+            paths: String::new(),
+        }
+    }
+
+    fn push(&mut self, path: &RepoPath, state: &FileState) {
+        let path_range = self.push_path(path);
+        encode_file_state_record(&mut self.records, path_range, Some(state));
+    }
+
+    /// Copies encoded `record` of the `path`, rewriting its path offset.
+    fn push_record(&mut self, path: &RepoPath, record: &[u8]) {
+        let path_range = self.push_path(path);
+        let start = self.records.len();
+        self.records.extend_from_slice(record);
+        self.records[start..start + 8].copy_from_slice(&(path_range.start as u64).to_le_bytes());
+    }
+
+    fn push_path(&mut self, path: &RepoPath) -> Range<usize> {
+        let start = self.paths.len();
+        self.paths.push_str(path.as_internal_file_string());
+        start..self.paths.len()
+    }
+
+    fn build(self) -> FileStatesMap {
+        FileStatesMap {
+            records: self.records,
+            paths: self.paths,
+        }
     }
 }
 
 /// Read-only map of path to file states, possibly filtered by path prefix.
 #[derive(Clone, Copy, Debug)]
 pub struct FileStates<'a> {
-    data: &'a [crate::protos::working_copy::FileStateEntry],
+    records: &'a [u8],
+    paths: &'a str,
 }
 
 impl<'a> FileStates<'a> {
-    fn from_sorted(data: &'a [crate::protos::working_copy::FileStateEntry]) -> Self {
-        debug_assert!(is_file_state_entries_proto_unique_and_sorted(data));
-        FileStates { data }
-    }
-
     /// Returns file states under the given directory path.
     pub fn prefixed(&self, base: &RepoPath) -> Self {
         let range = self.prefixed_range(base);
-        Self::from_sorted(&self.data[range])
+        self.slice(range)
     }
 
     /// Faster version of `prefixed("<dir>/<base>")`. Requires that all entries
     /// share the same prefix `dir`.
     fn prefixed_at(&self, dir: &RepoPath, base: &RepoPathComponent) -> Self {
         let range = self.prefixed_range_at(dir, base);
-        Self::from_sorted(&self.data[range])
+        self.slice(range)
     }
 
     /// Returns true if this contains no entries.
     pub fn is_empty(&self) -> bool {
-        self.data.is_empty()
+        self.records.is_empty()
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.records.len() / FILE_STATE_RECORD_LEN
     }
 
     /// Returns true if the given `path` exists.
@@ -319,46 +433,93 @@ impl<'a> FileStates<'a> {
     /// Returns file state for the given `path`.
     pub fn get(&self, path: &RepoPath) -> Option<FileState> {
         let pos = self.exact_position(path)?;
-        let (_, state) = file_state_entry_from_proto(&self.data[pos]);
-        Some(state)
+        self.state_at(pos)
     }
 
     /// Faster version of `get("<dir>/<name>")`. Requires that all entries share
     /// the same prefix `dir`.
     fn get_at(&self, dir: &RepoPath, name: &RepoPathComponent) -> Option<FileState> {
         let pos = self.exact_position_at(dir, name)?;
-        let (_, state) = file_state_entry_from_proto(&self.data[pos]);
-        Some(state)
+        self.state_at(pos)
+    }
+
+    fn slice(&self, range: Range<usize>) -> Self {
+        let records_range = range.start * FILE_STATE_RECORD_LEN..range.end * FILE_STATE_RECORD_LEN;
+        FileStates {
+            records: &self.records[records_range],
+            paths: self.paths,
+        }
+    }
+
+    fn record_at(&self, index: usize) -> &'a [u8] {
+        let start = index * FILE_STATE_RECORD_LEN;
+        &self.records[start..start + FILE_STATE_RECORD_LEN]
+    }
+
+    fn path_str_at(&self, index: usize) -> &'a str {
+        &self.paths[decode_file_state_record_path(self.record_at(index))]
+    }
+
+    fn path_at(&self, index: usize) -> &'a RepoPath {
+        RepoPath::from_internal_string(self.path_str_at(index))
+    }
+
+    fn state_at(&self, index: usize) -> Option<FileState> {
+        decode_file_state_record(self.record_at(index))
+    }
+
+    /// Returns the index of the first entry for which `cmp` doesn't return
+    /// `Less`, and whether that entry compares `Equal`.
+    fn search_by(&self, mut cmp: impl FnMut(&'a str) -> Ordering) -> (usize, bool) {
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if cmp(self.path_str_at(mid)) == Ordering::Less {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        let found = low < self.len() && cmp(self.path_str_at(low)) == Ordering::Equal;
+        (low, found)
+    }
+
+    fn partition_point(&self, mut pred: impl FnMut(&'a str) -> bool) -> usize {
+        let (pos, _) = self.search_by(|path| {
+            if pred(path) {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        });
+        pos
     }
 
     fn exact_position(&self, path: &RepoPath) -> Option<usize> {
-        self.data
-            .binary_search_by(|entry| RepoPath::from_internal_string(&entry.path).cmp(path))
-            .ok()
+        let (pos, found) = self.search_by(|entry| RepoPath::from_internal_string(entry).cmp(path));
+        found.then_some(pos)
     }
 
     fn exact_position_at(&self, dir: &RepoPath, name: &RepoPathComponent) -> Option<usize> {
         debug_assert!(self.paths().all(|path| path.starts_with(dir)));
         let slash_len = !dir.is_root() as usize;
         let prefix_len = dir.as_internal_file_string().len() + slash_len;
-        self.data
-            .binary_search_by(|entry| {
-                let tail = entry.path.get(prefix_len..).unwrap_or("");
-                match tail.split_once('/') {
-                    // "<name>/*" > "<name>"
-                    Some((pre, _)) => pre.cmp(name.as_internal_str()).then(Ordering::Greater),
-                    None => tail.cmp(name.as_internal_str()),
-                }
-            })
-            .ok()
+        let (pos, found) = self.search_by(|entry| {
+            let tail = entry.get(prefix_len..).unwrap_or("");
+            match tail.split_once('/') {
+                // "<name>/*" > "<name>"
+                Some((pre, _)) => pre.cmp(name.as_internal_str()).then(Ordering::Greater),
+                None => tail.cmp(name.as_internal_str()),
+            }
+        });
+        found.then_some(pos)
     }
 
     fn prefixed_range(&self, base: &RepoPath) -> Range<usize> {
-        let start = self
-            .data
-            .partition_point(|entry| RepoPath::from_internal_string(&entry.path) < base);
-        let len = self.data[start..]
-            .partition_point(|entry| RepoPath::from_internal_string(&entry.path).starts_with(base));
+        let start = self.partition_point(|entry| RepoPath::from_internal_string(entry) < base);
+        let len = self
+            .slice(start..self.len())
+            .partition_point(|entry| RepoPath::from_internal_string(entry).starts_with(base));
         start..(start + len)
     }
 
@@ -366,13 +527,13 @@ impl<'a> FileStates<'a> {
         debug_assert!(self.paths().all(|path| path.starts_with(dir)));
         let slash_len = !dir.is_root() as usize;
         let prefix_len = dir.as_internal_file_string().len() + slash_len;
-        let start = self.data.partition_point(|entry| {
-            let tail = entry.path.get(prefix_len..).unwrap_or("");
+        let start = self.partition_point(|entry| {
+            let tail = entry.get(prefix_len..).unwrap_or("");
             let entry_name = tail.split_once('/').map_or(tail, |(name, _)| name);
             entry_name < base.as_internal_str()
         });
-        let len = self.data[start..].partition_point(|entry| {
-            let tail = entry.path.get(prefix_len..).unwrap_or("");
+        let len = self.slice(start..self.len()).partition_point(|entry| {
+            let tail = entry.get(prefix_len..).unwrap_or("");
             let entry_name = tail.split_once('/').map_or(tail, |(name, _)| name);
             entry_name == base.as_internal_str()
         });
@@ -381,21 +542,57 @@ impl<'a> FileStates<'a> {
 
     /// Iterates file state entries sorted by path.
     pub fn iter(&self) -> FileStatesIter<'a> {
-        self.data.iter().map(file_state_entry_from_proto)
+        FileStatesIter {
+            states: *self,
+            range: 0..self.len(),
+        }
     }
 
     /// Iterates sorted file paths.
     pub fn paths(&self) -> impl ExactSizeIterator<Item = &'a RepoPath> {
-        self.data
-            .iter()
-            .map(|entry| RepoPath::from_internal_string(&entry.path))
+        let states = *self;
+        (0..states.len()).map(move |index| states.path_at(index))
     }
 }
 
-type FileStatesIter<'a> = iter::Map<
-    slice::Iter<'a, crate::protos::working_copy::FileStateEntry>,
-    fn(&crate::protos::working_copy::FileStateEntry) -> (&RepoPath, FileState),
->;
+/// Iterator over file state entries sorted by path.
+#[derive(Clone, Debug)]
+pub struct FileStatesIter<'a> {
+    states: FileStates<'a>,
+    range: Range<usize>,
+}
+
+impl<'a> FileStatesIter<'a> {
+    fn entry_at(&self, index: usize) -> (&'a RepoPath, FileState) {
+        let state = self
+            .states
+            .state_at(index)
+            .expect("file state should exist");
+        (self.states.path_at(index), state)
+    }
+}
+
+impl<'a> Iterator for FileStatesIter<'a> {
+    type Item = (&'a RepoPath, FileState);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.range.next()?;
+        Some(self.entry_at(index))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for FileStatesIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let index = self.range.next_back()?;
+        Some(self.entry_at(index))
+    }
+}
+
+impl ExactSizeIterator for FileStatesIter<'_> {}
 
 impl<'a> IntoIterator for FileStates<'a> {
     type Item = (&'a RepoPath, FileState);
@@ -406,14 +603,57 @@ impl<'a> IntoIterator for FileStates<'a> {
     }
 }
 
+/// Format of the file the tree state is stored in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TreeStateFormat {
+    /// Single protobuf message, which is rewritten on every update.
+    #[default]
+    Proto,
+    /// Segmented file, which is updated by appending the changed file states.
+    Segmented,
+}
+
+impl TreeStateFormat {
+    fn file_name(self) -> &'static str {
+        match self {
+            TreeStateFormat::Proto => "tree_state",
+            TreeStateFormat::Segmented => "segmented_tree_state",
+        }
+    }
+}
+
+/// The segmented tree state file is compacted once it has this many delta
+/// segments.
+const MAX_DELTA_SEGMENTS: usize = 64;
+
+/// Layout of the segmented tree state file as last read or written.
+#[derive(Clone, Debug)]
+struct SegmentedFileInfo {
+    /// Length of the complete segments.
+    len: u64,
+    /// Length of the header and the full segment.
+    base_len: u64,
+    num_delta_segments: usize,
+    num_delta_records: usize,
+    /// Encoded metadata of the last segment.
+    metadata: Vec<u8>,
+}
+
 pub struct TreeState {
     store: Arc<Store>,
     working_copy_path: PathBuf,
     state_path: PathBuf,
     tree_id: MergedTreeId,
     file_states: FileStatesMap,
-    // Currently only path prefixes
-    sparse_patterns: Vec<RepoPathBuf>,
+    format: TreeStateFormat,
+    /// File states changed since the state was loaded or saved. `None` if the
+    /// whole file has to be rewritten.
+    file_state_changes: Option<BTreeMap<RepoPathBuf, Option<FileState>>>,
+    /// Layout of the segmented tree state file. `None` if the file has to be
+    /// rewritten.
+    segmented_file: Option<SegmentedFileInfo>,
+    sparse_patterns: Vec<SparsePattern>,
     own_mtime: MillisSinceEpoch,
     symlink_support: bool,
 
@@ -421,6 +661,9 @@ pub struct TreeState {
     /// the repo is configured to use the Watchman filesystem monitor and
     /// Watchman has been queried at least once.
     watchman_clock: Option<crate::protos::working_copy::WatchmanClock>,
+
+    /// The most recent token returned by the built-in filesystem monitor.
+    fsmonitor_token: Option<String>,
 }
 
 fn file_state_from_proto(proto: &crate::protos::working_copy::FileState) -> FileState {
@@ -502,18 +745,49 @@ fn is_file_state_entries_proto_unique_and_sorted(
 
 fn sparse_patterns_from_proto(
     proto: Option<&crate::protos::working_copy::SparsePatterns>,
-) -> Vec<RepoPathBuf> {
+) -> Result<Vec<SparsePattern>, (String, FilesetParseError)> {
     let mut sparse_patterns = vec![];
     if let Some(proto_sparse_patterns) = proto {
-        for prefix in &proto_sparse_patterns.prefixes {
-            sparse_patterns.push(RepoPathBuf::from_internal_string(prefix));
+        if proto_sparse_patterns.filesets.is_empty() {
+            // Working copies written before fileset patterns were supported.
+            for prefix in &proto_sparse_patterns.prefixes {
+                sparse_patterns.push(SparsePattern::from_prefix(
+                    RepoPathBuf::from_internal_string(prefix),
+                ));
+            }
+        } else {
+            for text in &proto_sparse_patterns.filesets {
+                let pattern = SparsePattern::parse(text).map_err(|err| (text.clone(), err))?;
+                sparse_patterns.push(pattern);
+            }
         }
     } else {
         // For compatibility with old working copies.
         // TODO: Delete this is late 2022 or so.
-        sparse_patterns.push(RepoPathBuf::root());
+        sparse_patterns.push(SparsePattern::root());
     }
-    sparse_patterns
+    Ok(sparse_patterns)
+}
+
+fn sparse_patterns_to_proto(
+    sparse_patterns: &[SparsePattern],
+) -> crate::protos::working_copy::SparsePatterns {
+    let mut proto = crate::protos::working_copy::SparsePatterns::default();
+    let prefixes: Option<Vec<_>> = sparse_patterns
+        .iter()
+        .map(|pattern| pattern.as_prefix())
+        .collect();
+    if let Some(prefixes) = prefixes {
+        proto.prefixes = prefixes
+            .iter()
+            .map(|path| path.as_internal_file_string().to_owned())
+            .collect();
+    }
+    proto.filesets = sparse_patterns
+        .iter()
+        .map(|pattern| pattern.as_str().to_owned())
+        .collect();
+    proto
 }
 
 /// Creates intermediate directories from the `working_copy_path` to the
@@ -596,6 +870,30 @@ fn remove_old_file(disk_path: &Path) -> Result<bool, CheckoutError> {
     }
 }
 
+/// Removes the empty parent directories of the deleted `disk_path`.
+fn remove_empty_parent_dirs(disk_path: &Path) {
+    let mut parent_dir = disk_path.parent().unwrap();
+    loop {
+        if fs::remove_dir(parent_dir).is_err() {
+            break;
+        }
+        parent_dir = parent_dir.parent().unwrap();
+    }
+}
+
+/// Creates new file named `disk_path` for writing. Fails if the file already
+/// exists.
+fn create_new_file(disk_path: &Path) -> Result<File, CheckoutError> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true) // Don't overwrite un-ignored file. Don't follow symlink.
+        .open(disk_path)
+        .map_err(|err| CheckoutError::Other {
+            message: format!("Failed to open file {} for writing", disk_path.display()),
+            err: err.into(),
+        })
+}
+
 /// Checks if new file or symlink named `disk_path` can be created.
 ///
 /// If the file already exists, this function return `Ok(false)` to signal
@@ -719,6 +1017,7 @@ fn file_state(metadata: &Metadata) -> Option<FileState> {
 struct FsmonitorMatcher {
     matcher: Option<Box<dyn Matcher>>,
     watchman_clock: Option<crate::protos::working_copy::WatchmanClock>,
+    fsmonitor_token: Option<String>,
 }
 
 #[derive(Debug, Error)]
@@ -730,12 +1029,23 @@ pub enum TreeStateError {
         path: PathBuf,
         source: prost::DecodeError,
     },
+    #[error("Decoding tree state from {path}")]
+    DecodeSegmentedTreeState {
+        path: PathBuf,
+        source: SegmentedTreeStateError,
+    },
     #[error("Writing tree state to temporary file {path}")]
     WriteTreeState { path: PathBuf, source: io::Error },
     #[error("Persisting tree state to file {path}")]
     PersistTreeState { path: PathBuf, source: io::Error },
     #[error("Filesystem monitor error")]
     Fsmonitor(#[source] Box<dyn Error + Send + Sync>),
+    #[error("Invalid sparse pattern {pattern:?} in tree state {path}")]
+    InvalidSparsePattern {
+        path: PathBuf,
+        pattern: String,
+        source: Box<FilesetParseError>,
+    },
 }
 
 impl TreeState {
@@ -751,12 +1061,26 @@ impl TreeState {
         self.file_states.all()
     }
 
-    pub fn sparse_patterns(&self) -> &Vec<RepoPathBuf> {
+    pub fn sparse_patterns(&self) -> &Vec<SparsePattern> {
         &self.sparse_patterns
     }
 
+    /// Returns the format of the file the tree state is stored in.
+    pub fn format(&self) -> TreeStateFormat {
+        self.format
+    }
+
+    /// Sets the format of the file the tree state will be saved in. The file
+    /// in the old format is removed on save.
+    pub fn set_format(&mut self, format: TreeStateFormat) {
+        if self.format != format {
+            self.format = format;
+            self.file_state_changes = None;
+        }
+    }
+
     fn sparse_matcher(&self) -> Box<dyn Matcher> {
-        Box::new(PrefixMatcher::new(&self.sparse_patterns))
+        sparse_matcher(&self.sparse_patterns)
     }
 
     pub fn init(
@@ -777,10 +1101,14 @@ impl TreeState {
             state_path,
             tree_id,
             file_states: FileStatesMap::new(),
-            sparse_patterns: vec![RepoPathBuf::root()],
+            format: TreeStateFormat::Proto,
+            file_state_changes: None,
+            segmented_file: None,
+            sparse_patterns: vec![SparsePattern::root()],
             own_mtime: MillisSinceEpoch(0),
             symlink_support: check_symlink_support().unwrap_or(false),
             watchman_clock: None,
+            fsmonitor_token: None,
         }
     }
 
@@ -789,7 +1117,23 @@ impl TreeState {
         working_copy_path: PathBuf,
         state_path: PathBuf,
     ) -> Result<TreeState, TreeStateError> {
-        let tree_state_path = state_path.join("tree_state");
+        let segmented_path = state_path.join(TreeStateFormat::Segmented.file_name());
+        match fs::read(&segmented_path) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(TreeStateError::ReadTreeState {
+                    path: segmented_path,
+                    source: err,
+                });
+            }
+            Ok(buf) => {
+                let mut wc = TreeState::empty(store, working_copy_path, state_path);
+                wc.read_segmented(&segmented_path, &buf)?;
+                return Ok(wc);
+            }
+        }
+
+        let tree_state_path = state_path.join(TreeStateFormat::Proto.file_name());
         let file = match File::open(&tree_state_path) {
             Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
                 return TreeState::init(store, working_copy_path, state_path);
@@ -809,7 +1153,8 @@ impl TreeState {
     }
 
     fn update_own_mtime(&mut self) {
-        if let Ok(metadata) = self.state_path.join("tree_state").symlink_metadata() {
+        let path = self.state_path.join(self.format.file_name());
+        if let Ok(metadata) = path.symlink_metadata() {
             self.own_mtime = mtime_from_metadata(&metadata);
         } else {
             self.own_mtime = MillisSinceEpoch(0);
@@ -824,12 +1169,73 @@ impl TreeState {
                 path: tree_state_path.to_owned(),
                 source: err,
             })?;
-        let proto = crate::protos::working_copy::TreeState::decode(&*buf).map_err(|err| {
+        let mut proto = crate::protos::working_copy::TreeState::decode(&*buf).map_err(|err| {
             TreeStateError::DecodeTreeState {
                 path: tree_state_path.to_owned(),
                 source: err,
             }
         })?;
+        self.file_states = FileStatesMap::from_proto(
+            mem::take(&mut proto.file_states),
+            proto.is_file_states_sorted,
+        );
+        self.read_metadata(proto)
+    }
+
+    fn read_segmented(&mut self, tree_state_path: &Path, buf: &[u8]) -> Result<(), TreeStateError> {
+        self.format = TreeStateFormat::Segmented;
+        self.update_own_mtime();
+        let to_err = |source| TreeStateError::DecodeSegmentedTreeState {
+            path: tree_state_path.to_owned(),
+            source,
+        };
+        let decoded = segmented_tree_state::decode(buf).map_err(to_err)?;
+        self.file_states = FileStatesMap::from_segment(decoded.base)
+            .ok_or(to_err(SegmentedTreeStateError::InvalidFileStates))?;
+        let mut num_delta_records = 0;
+        for delta in &decoded.deltas {
+            let delta = FileStates {
+                records: delta.records,
+                paths: delta.paths,
+            };
+            if !delta
+                .paths()
+                .tuple_windows()
+                .all(|(path1, path2)| path1 < path2)
+            {
+                return Err(to_err(SegmentedTreeStateError::InvalidFileStates));
+            }
+            let mut changed_file_states = vec![];
+            let mut deleted_files = HashSet::new();
+            for index in 0..delta.len() {
+                let path = delta.path_at(index).to_owned();
+                match delta.state_at(index) {
+                    Some(state) => changed_file_states.push((path, state)),
+                    None => {
+                        deleted_files.insert(path);
+                    }
+                }
+            }
+            self.file_states
+                .merge_in(&changed_file_states, &deleted_files);
+            num_delta_records += delta.len();
+        }
+        self.file_state_changes = Some(BTreeMap::new());
+        self.segmented_file = Some(SegmentedFileInfo {
+            len: decoded.len as u64,
+            base_len: decoded.base_len as u64,
+            num_delta_segments: decoded.deltas.len(),
+            num_delta_records,
+            metadata: decoded.metadata.encode_to_vec(),
+        });
+        self.read_metadata(decoded.metadata)
+    }
+
+    /// Reads the fields other than the file states.
+    fn read_metadata(
+        &mut self,
+        proto: crate::protos::working_copy::TreeState,
+    ) -> Result<(), TreeStateError> {
         if proto.tree_ids.is_empty() {
             self.tree_id = MergedTreeId::Legacy(TreeId::new(proto.legacy_tree_id.clone()));
         } else {
@@ -840,16 +1246,61 @@ impl TreeState {
                 .collect();
             self.tree_id = MergedTreeId::Merge(tree_ids_builder.build());
         }
-        self.file_states =
-            FileStatesMap::from_proto(proto.file_states, proto.is_file_states_sorted);
-        self.sparse_patterns = sparse_patterns_from_proto(proto.sparse_patterns.as_ref());
+        self.sparse_patterns = sparse_patterns_from_proto(proto.sparse_patterns.as_ref()).map_err(
+            |(pattern, source)| TreeStateError::InvalidSparsePattern {
+                path: self.state_path.clone(),
+                pattern,
+                source: Box::new(source),
+            },
+        )?;
         self.watchman_clock = proto.watchman_clock;
+        self.fsmonitor_token = (!proto.fsmonitor_token.is_empty()).then_some(proto.fsmonitor_token);
         Ok(())
     }
 
-    #[allow(unknown_lints)] // XXX FIXME (aseipp): nightly bogons; re-test this occasionally
-    #[allow(clippy::assigning_clones)]
+    /// Merges changed and deleted file states, and records them so they can
+    /// be appended to the segmented tree state file.
+    fn merge_file_states(
+        &mut self,
+        changed_file_states: Vec<(RepoPathBuf, FileState)>,
+        deleted_files: &HashSet<RepoPathBuf>,
+    ) {
+        self.file_states
+            .merge_in(&changed_file_states, deleted_files);
+        if let Some(changes) = &mut self.file_state_changes {
+            changes.extend(
+                changed_file_states
+                    .into_iter()
+                    .map(|(path, state)| (path, Some(state))),
+            );
+            changes.extend(deleted_files.iter().map(|path| (path.clone(), None)));
+        }
+    }
+
     fn save(&mut self) -> Result<(), TreeStateError> {
+        match self.format {
+            TreeStateFormat::Proto => self.save_proto()?,
+            TreeStateFormat::Segmented => self.save_segmented()?,
+        }
+        self.file_state_changes = (self.format == TreeStateFormat::Segmented).then(BTreeMap::new);
+        // Remove the file in the other format so it wouldn't be loaded instead.
+        let other_format = match self.format {
+            TreeStateFormat::Proto => TreeStateFormat::Segmented,
+            TreeStateFormat::Segmented => TreeStateFormat::Proto,
+        };
+        let other_path = self.state_path.join(other_format.file_name());
+        match fs::remove_file(&other_path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                Err(TreeStateError::PersistTreeState {
+                    path: other_path,
+                    source: err,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn metadata_to_proto(&self) -> crate::protos::working_copy::TreeState {
         let mut proto: crate::protos::working_copy::TreeState = Default::default();
         match &self.tree_id {
             MergedTreeId::Legacy(tree_id) => {
@@ -860,22 +1311,118 @@ impl TreeState {
             }
         }
 
-        proto.file_states = self.file_states.data.clone();
+        proto.sparse_patterns = Some(sparse_patterns_to_proto(&self.sparse_patterns));
+        proto.watchman_clock = self.watchman_clock.clone();
+        proto.fsmonitor_token = self.fsmonitor_token.clone().unwrap_or_default();
+        proto
+    }
+
+    fn save_proto(&mut self) -> Result<(), TreeStateError> {
+        let mut proto = self.metadata_to_proto();
+        proto.file_states = self.file_states.to_proto();
         // `FileStatesMap` is guaranteed to be sorted.
         proto.is_file_states_sorted = true;
-        let mut sparse_patterns = crate::protos::working_copy::SparsePatterns::default();
-        for path in &self.sparse_patterns {
-            sparse_patterns
-                .prefixes
-                .push(path.as_internal_file_string().to_owned());
+        self.segmented_file = None;
+        self.write_tree_state_file(&proto.encode_to_vec())
+    }
+
+    fn save_segmented(&mut self) -> Result<(), TreeStateError> {
+        let metadata = self.metadata_to_proto().encode_to_vec();
+        if let (Some(file), Some(changes)) = (&self.segmented_file, &self.file_state_changes) {
+            if changes.is_empty() && file.metadata == metadata {
+                return Ok(());
+            }
+            // Append the changes unless the deltas would get large compared to
+            // the full segment.
+            let num_delta_segments = file.num_delta_segments + 1;
+            let num_delta_records = file.num_delta_records + changes.len();
+            let mut paths = String::new();
+            let mut records = Vec::with_capacity(changes.len() * FILE_STATE_RECORD_LEN);
+            for (path, state) in changes {
+                let start = paths.len();
+                paths.push_str(path.as_internal_file_string());
+                encode_file_state_record(&mut records, start..paths.len(), state.as_ref());
+            }
+            let mut segment = vec![];
+            segmented_tree_state::encode_segment(
+                &mut segment,
+                SegmentKind::Delta,
+                &metadata,
+                &records,
+                &paths,
+            );
+            let new_file_len = file.len + segment.len() as u64;
+            if num_delta_segments <= MAX_DELTA_SEGMENTS
+                && num_delta_records <= self.file_states.all().len() / 4
+                && new_file_len - file.base_len <= file.base_len
+            {
+                if let Some(new_file_len) = self.append_tree_state_segment(file.len, &segment)? {
+                    self.segmented_file = Some(SegmentedFileInfo {
+                        len: new_file_len,
+                        base_len: file.base_len,
+                        num_delta_segments,
+                        num_delta_records,
+                        metadata,
+                    });
+                    self.update_own_mtime();
+                    return Ok(());
+                }
+            }
         }
-        proto.sparse_patterns = Some(sparse_patterns);
-        proto.watchman_clock = self.watchman_clock.clone();
 
+        let mut buf = vec![];
+        segmented_tree_state::encode_header(&mut buf);
+        let file_states = self.file_states.all();
+        segmented_tree_state::encode_segment(
+            &mut buf,
+            SegmentKind::Full,
+            &metadata,
+            file_states.records,
+            file_states.paths,
+        );
+        self.write_tree_state_file(&buf)?;
+        self.segmented_file = Some(SegmentedFileInfo {
+            len: buf.len() as u64,
+            base_len: buf.len() as u64,
+            num_delta_segments: 0,
+            num_delta_records: 0,
+            metadata,
+        });
+        Ok(())
+    }
+
+    /// Appends `segment` to the segmented tree state file. Returns the new
+    /// file length, or `None` if the file was modified by someone else.
+    fn append_tree_state_segment(
+        &self,
+        file_len: u64,
+        segment: &[u8],
+    ) -> Result<Option<u64>, TreeStateError> {
+        let path = self.state_path.join(TreeStateFormat::Segmented.file_name());
+        let to_err = |err| TreeStateError::WriteTreeState {
+            path: path.clone(),
+            source: err,
+        };
+        let mut file = match OpenOptions::new().write(true).open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(to_err(err)),
+        };
+        if file.metadata().map_err(to_err)?.len() != file_len {
+            return Ok(None);
+        }
+        // Readers ignore the segment until it's written completely.
+        file.seek(SeekFrom::Start(file_len)).map_err(to_err)?;
+        file.write_all(segment).map_err(to_err)?;
+        file.sync_data().map_err(to_err)?;
+        Ok(Some(file_len + segment.len() as u64))
+    }
+
+    fn write_tree_state_file(&mut self, data: &[u8]) -> Result<(), TreeStateError> {
         let mut temp_file = NamedTempFile::new_in(&self.state_path).unwrap();
         temp_file
             .as_file_mut()
-            .write_all(&proto.encode_to_vec())
+            .write_all(data)
             .map_err(|err| TreeStateError::WriteTreeState {
                 path: self.state_path.clone(),
                 source: err,
@@ -885,7 +1432,7 @@ impl TreeState {
         self.update_own_mtime();
         // TODO: Retry if persisting fails (it will on Windows if the file happened to
         // be open for read).
-        let target_path = self.state_path.join("tree_state");
+        let target_path = self.state_path.join(self.format.file_name());
         temp_file
             .persist(&target_path)
             .map_err(|tempfile::PersistError { error, file: _ }| {
@@ -897,6 +1444,35 @@ impl TreeState {
         Ok(())
     }
 
+    /// Returns the store of LFS objects if the backend supports Git LFS.
+    fn lfs_object_store(&self) -> Option<LfsObjectStore> {
+        #[cfg(feature = "git")]
+        {
+            crate::git::get_lfs_object_store(&self.store).ok()
+        }
+        #[cfg(not(feature = "git"))]
+        {
+            None
+        }
+    }
+
+    /// Returns `.gitattributes` loader that reads files from the working copy.
+    fn disk_git_attributes(&self) -> GitAttributesCache<'_> {
+        GitAttributesCache::new(GitAttributesFile::empty(), |path| {
+            let disk_path = path.to_fs_path_unchecked(&self.working_copy_path);
+            match fs::read(&disk_path) {
+                Ok(content) => Ok(Some(content)),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+                // The parent path may be a file on file->dir transition
+                Err(_) if !disk_path.is_file() => Ok(None),
+                Err(err) => Err(GitAttributesError::ReadFile {
+                    path: disk_path,
+                    source: err,
+                }),
+            }
+        })
+    }
+
     fn current_tree(&self) -> BackendResult<MergedTree> {
         self.store.get_root_tree(&self.tree_id)
     }
@@ -938,6 +1514,35 @@ impl TreeState {
             .await
             .map_err(|err| TreeStateError::Fsmonitor(Box::new(err)))
     }
+
+    /// Queries the built-in filesystem monitor daemon for changed paths,
+    /// starting the daemon if it isn't running and `config.auto_start` is set.
+    ///
+    /// Returns the new token and the changed paths. If the paths are `None`,
+    /// the caller must crawl the entire working copy.
+    #[cfg(all(unix, feature = "fsmonitor-daemon"))]
+    #[instrument(skip(self))]
+    pub fn query_builtin_fsmonitor(
+        &self,
+        config: &BuiltinConfig,
+    ) -> Result<(Option<String>, Option<Vec<PathBuf>>), TreeStateError> {
+        let socket_path = builtin::socket_path(&self.state_path);
+        let mut client = match builtin::Client::connect(&socket_path) {
+            Ok(client) => client,
+            Err(err) if config.auto_start => {
+                tracing::info!(?err, "Filesystem monitor daemon is not running");
+                builtin::spawn(&config.daemon_command, &self.working_copy_path)
+                    .map_err(|err| TreeStateError::Fsmonitor(Box::new(err)))?;
+                // The new daemon doesn't know about earlier changes anyway.
+                return Ok((None, None));
+            }
+            Err(err) => return Err(TreeStateError::Fsmonitor(Box::new(err))),
+        };
+        let result = client
+            .query(self.fsmonitor_token.as_deref())
+            .map_err(|err| TreeStateError::Fsmonitor(Box::new(err)))?;
+        Ok((Some(result.token), result.changed_paths))
+    }
 }
 
 /// Functions to snapshot local-disk files to the store.
@@ -956,6 +1561,7 @@ impl TreeState {
             start_tracking_matcher,
             max_new_file_size,
             conflict_marker_style,
+            ref content_filters,
         } = options;
 
         let sparse_matcher = self.sparse_matcher();
@@ -965,6 +1571,7 @@ impl TreeState {
         let FsmonitorMatcher {
             matcher: fsmonitor_matcher,
             watchman_clock,
+            fsmonitor_token,
         } = self.make_fsmonitor_matcher(fsmonitor_settings)?;
         let fsmonitor_matcher = match fsmonitor_matcher.as_ref() {
             None => &EverythingMatcher,
@@ -975,6 +1582,7 @@ impl TreeState {
         if matcher.visit(RepoPath::root()).is_nothing() {
             // No need to load the current tree, set up channels, etc.
             self.watchman_clock = watchman_clock;
+            self.fsmonitor_token = fsmonitor_token;
             return Ok((is_dirty, SnapshotStats::default()));
         }
 
@@ -983,10 +1591,14 @@ impl TreeState {
         let (untracked_paths_tx, untracked_paths_rx) = channel();
         let (deleted_files_tx, deleted_files_rx) = channel();
 
+        let lfs_store = self.lfs_object_store();
+        let content_filters = content_filters.runner(&self.working_copy_path);
         trace_span!("traverse filesystem").in_scope(|| -> Result<(), SnapshotError> {
             let snapshotter = FileSnapshotter {
                 tree_state: self,
                 current_tree: &self.current_tree()?,
+                git_attributes: &self.disk_git_attributes(),
+                lfs_store: lfs_store.as_ref(),
                 matcher: &matcher,
                 start_tracking_matcher,
                 // Move tx sides so they'll be dropped at the end of the scope.
@@ -998,6 +1610,7 @@ impl TreeState {
                 progress,
                 max_new_file_size,
                 conflict_marker_style,
+                content_filters: &content_filters,
             };
             let directory_to_visit = DirectoryToVisit {
                 dir: RepoPathBuf::root(),
@@ -1014,12 +1627,18 @@ impl TreeState {
             snapshotter.into_result()
         })?;
 
-        let stats = SnapshotStats {
+        let mut stats = SnapshotStats {
             untracked_paths: untracked_paths_rx.into_iter().collect(),
+            case_collisions: vec![],
         };
         let mut tree_builder = MergedTreeBuilder::new(self.tree_id.clone());
+        let mut case_collision_finder = CaseCollisionFinder::new();
         trace_span!("process tree entries").in_scope(|| {
+            let file_states = self.file_states.all();
             for (path, tree_values) in &tree_entries_rx {
+                if tree_values.is_present() && !file_states.contains_path(&path) {
+                    case_collision_finder.add_path(&path);
+                }
                 tree_builder.set_or_remove(path, tree_values);
             }
         });
@@ -1037,14 +1656,15 @@ impl TreeState {
                 .sorted_unstable_by(|(path1, _), (path2, _)| path1.cmp(path2))
                 .collect_vec();
             is_dirty |= !changed_file_states.is_empty();
-            self.file_states
-                .merge_in(changed_file_states, &deleted_files);
+            self.merge_file_states(changed_file_states, &deleted_files);
         });
         trace_span!("write tree").in_scope(|| {
             let new_tree_id = tree_builder.write_tree(&self.store).unwrap();
             is_dirty |= new_tree_id != self.tree_id;
             self.tree_id = new_tree_id;
         });
+        stats.case_collisions = trace_span!("find case collisions")
+            .in_scope(|| case_collision_finder.finish(&self.current_tree()?, &EverythingMatcher))?;
         if cfg!(debug_assertions) {
             let tree = self.current_tree().unwrap();
             let tree_paths: HashSet<_> = tree
@@ -1056,6 +1676,7 @@ impl TreeState {
             assert_eq!(state_paths, tree_paths);
         }
         self.watchman_clock = watchman_clock;
+        self.fsmonitor_token = fsmonitor_token;
         Ok((is_dirty, stats))
     }
 
@@ -1064,15 +1685,35 @@ impl TreeState {
         &self,
         fsmonitor_settings: &FsmonitorSettings,
     ) -> Result<FsmonitorMatcher, SnapshotError> {
-        let (watchman_clock, changed_files) = match fsmonitor_settings {
-            FsmonitorSettings::None => (None, None),
-            FsmonitorSettings::Test { changed_files } => (None, Some(changed_files.clone())),
+        let (watchman_clock, fsmonitor_token, changed_files) = match fsmonitor_settings {
+            FsmonitorSettings::None => (None, None, None),
+            FsmonitorSettings::Test { changed_files } => (None, None, Some(changed_files.clone())),
+            #[cfg(all(unix, feature = "fsmonitor-daemon"))]
+            FsmonitorSettings::Builtin(config) => match self.query_builtin_fsmonitor(config) {
+                Ok((token, changed_paths)) => (None, token, changed_paths),
+                Err(err) => {
+                    tracing::warn!(?err, "Failed to query filesystem monitor");
+                    (None, None, None)
+                }
+            },
+            #[cfg(not(all(unix, feature = "fsmonitor-daemon")))]
+            FsmonitorSettings::Builtin(_) => {
+                return Err(SnapshotError::Other {
+                    message: "Failed to query the filesystem monitor".to_string(),
+                    err: "Cannot use the built-in filesystem monitor because jj was not compiled \
+                          with the `fsmonitor-daemon` feature, or the platform isn't supported \
+                          (consider disabling `core.fsmonitor`)"
+                        .into(),
+                });
+            }
             #[cfg(feature = "watchman")]
             FsmonitorSettings::Watchman(config) => match self.query_watchman(config) {
-                Ok((watchman_clock, changed_files)) => (Some(watchman_clock.into()), changed_files),
+                Ok((watchman_clock, changed_files)) => {
+                    (Some(watchman_clock.into()), None, changed_files)
+                }
                 Err(err) => {
                     tracing::warn!(?err, "Failed to query filesystem monitor");
-                    (None, None)
+                    (None, None, None)
                 }
             },
             #[cfg(not(feature = "watchman"))]
@@ -1095,12 +1736,19 @@ impl TreeState {
                         .collect_vec()
                 });
 
-                Some(Box::new(FilesMatcher::new(repo_paths)))
+                // The built-in monitor reports changed directories without
+                // crawling them, so its paths are matched as prefixes.
+                if matches!(fsmonitor_settings, FsmonitorSettings::Builtin(_)) {
+                    Some(Box::new(PrefixMatcher::new(repo_paths)))
+                } else {
+                    Some(Box::new(FilesMatcher::new(repo_paths)))
+                }
             }
         };
         Ok(FsmonitorMatcher {
             matcher,
             watchman_clock,
+            fsmonitor_token,
         })
     }
 }
@@ -1128,6 +1776,8 @@ struct PresentDirEntries {
 struct FileSnapshotter<'a> {
     tree_state: &'a TreeState,
     current_tree: &'a MergedTree,
+    git_attributes: &'a GitAttributesCache<'a>,
+    lfs_store: Option<&'a LfsObjectStore>,
     matcher: &'a dyn Matcher,
     start_tracking_matcher: &'a dyn Matcher,
     tree_entries_tx: Sender<(RepoPathBuf, MergedTreeValue)>,
@@ -1138,6 +1788,7 @@ struct FileSnapshotter<'a> {
     progress: Option<&'a SnapshotProgress<'a>>,
     max_new_file_size: u64,
     conflict_marker_style: ConflictMarkerStyle,
+    content_filters: &'a ContentFilterRunner<'a>,
 }
 
 impl FileSnapshotter<'_> {
@@ -1178,8 +1829,7 @@ impl FileSnapshotter<'_> {
             file_states,
         } = directory_to_visit;
 
-        let git_ignore = git_ignore
-            .chain_with_file(&dir.to_internal_dir_string(), disk_dir.join(".gitignore"))?;
+        let git_ignore = chain_directory_ignores(&git_ignore, &dir, &disk_dir)?;
         let dir_entries: Vec<_> = disk_dir
             .read_dir()
             .and_then(|entries| entries.try_collect())
@@ -1279,7 +1929,10 @@ impl FileSnapshotter<'_> {
                     message: format!("Failed to stat file {}", entry.path().display()),
                     err: err.into(),
                 })?;
-                if maybe_current_file_state.is_none() && metadata.len() > self.max_new_file_size {
+                if maybe_current_file_state.is_none()
+                    && metadata.len() > self.max_new_file_size
+                    && !self.is_lfs_file(&path)?
+                {
                     // Leave the large file untracked
                     let reason = UntrackedReason::FileTooLarge {
                         size: metadata.len(),
@@ -1465,6 +2118,15 @@ impl FileSnapshotter<'_> {
         &self.tree_state.store
     }
 
+    /// Returns true if the file content should be stored in the LFS object
+    /// store.
+    fn is_lfs_file(&self, path: &RepoPath) -> Result<bool, SnapshotError> {
+        if self.lfs_store.is_none() {
+            return Ok(false);
+        }
+        Ok(is_lfs_path(self.git_attributes, path)?)
+    }
+
     async fn write_path_to_store(
         &self,
         repo_path: &RepoPath,
@@ -1496,6 +2158,8 @@ impl FileSnapshotter<'_> {
                 message: format!("Failed to open file {}", disk_path.display()),
                 err: err.into(),
             })?;
+            let eol_conversion = EolConversion::for_path(self.git_attributes, repo_path)?;
+            let content = eol_conversion.convert_to_store(&content);
             let new_file_ids = conflicts::update_from_content(
                 &old_file_ids,
                 self.store(),
@@ -1546,7 +2210,43 @@ impl FileSnapshotter<'_> {
             message: format!("Failed to open file {}", disk_path.display()),
             err: err.into(),
         })?;
-        Ok(self.store().write_file(path, &mut file).await?)
+        if let Some(lfs_store) = self.lfs_store {
+            if is_lfs_path(self.git_attributes, path)? {
+                let pointer =
+                    clean_lfs_file(lfs_store, &mut file).map_err(|err| SnapshotError::Other {
+                        message: format!("Failed to write LFS object for {}", disk_path.display()),
+                        err: err.into(),
+                    })?;
+                return Ok(self
+                    .store()
+                    .write_file(path, &mut pointer.as_slice())
+                    .await?);
+            }
+        }
+        let eol_conversion = EolConversion::for_path(self.git_attributes, path)?;
+        let cleans = self
+            .content_filters
+            .applies_to(ContentFilterKind::Clean, path);
+        if cleans || eol_conversion.converts_to_store() {
+            let mut content = vec![];
+            file.read_to_end(&mut content)
+                .map_err(|err| SnapshotError::Other {
+                    message: format!("Failed to read file {}", disk_path.display()),
+                    err: err.into(),
+                })?;
+            // Like Git, run the clean filters before converting line endings.
+            let content =
+                self.content_filters
+                    .clean(path, content)
+                    .map_err(|err| SnapshotError::Other {
+                        message: format!("Failed to filter file {}", disk_path.display()),
+                        err: err.into(),
+                    })?;
+            let content = eol_conversion.convert_to_store(&content);
+            Ok(self.store().write_file(path, &mut content.as_ref()).await?)
+        } else {
+            Ok(self.store().write_file(path, &mut file).await?)
+        }
     }
 
     async fn write_symlink_to_store(
@@ -1580,6 +2280,70 @@ impl FileSnapshotter<'_> {
     }
 }
 
+/// Writes the working-copy file content to the LFS object store, and returns
+/// the pointer file content. If the file is already a pointer, it's returned
+/// as is.
+fn clean_lfs_file(lfs_store: &LfsObjectStore, file: &mut File) -> io::Result<Vec<u8>> {
+    let mut head = vec![];
+    file.take(MAX_LFS_POINTER_SIZE as u64 + 1)
+        .read_to_end(&mut head)?;
+    if LfsPointer::parse(&head).is_some() {
+        return Ok(head);
+    }
+    let pointer = lfs_store.write_object(&mut head.as_slice().chain(file))?;
+    Ok(pointer.to_bytes())
+}
+
+/// Returns reader of the LFS object if the stored `reader` content is a
+/// pointer to an existing object. Otherwise, returns the stored content as is.
+fn smudge_lfs_file(
+    lfs_store: &LfsObjectStore,
+    mut reader: Box<dyn Read>,
+) -> io::Result<Box<dyn Read>> {
+    let mut head = vec![];
+    (&mut reader)
+        .take(MAX_LFS_POINTER_SIZE as u64 + 1)
+        .read_to_end(&mut head)?;
+    if let Some(pointer) = LfsPointer::parse(&head) {
+        if let Some(file) = lfs_store.open_object(&pointer)? {
+            return Ok(Box::new(file));
+        }
+    }
+    Ok(Box::new(io::Cursor::new(head).chain(reader)))
+}
+
+/// Maximum number of files to create before their contents are written by
+/// `TreeState::write_pending_files()`. This bounds the number of open files.
+const MAX_PENDING_FILES: usize = 256;
+
+/// Tree value to be checked out.
+enum CheckoutValue {
+    /// Regular file whose contents will be read and written concurrently.
+    File {
+        id: FileId,
+        executable: bool,
+    },
+    Materialized(MaterializedTreeValue),
+}
+
+impl CheckoutValue {
+    fn is_absent(&self) -> bool {
+        matches!(self, CheckoutValue::Materialized(value) if value.is_absent())
+    }
+}
+
+/// Regular file which has been created on disk, but whose contents haven't
+/// been written yet.
+struct PendingFile {
+    path: RepoPathBuf,
+    disk_path: PathBuf,
+    file: File,
+    id: FileId,
+    executable: bool,
+    eol_conversion: EolConversion,
+    is_lfs: bool,
+}
+
 /// Functions to update local-disk files from the store.
 impl TreeState {
     fn write_file(
@@ -1588,15 +2352,19 @@ impl TreeState {
         contents: &mut dyn Read,
         executable: bool,
     ) -> Result<FileState, CheckoutError> {
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create_new(true) // Don't overwrite un-ignored file. Don't follow symlink.
-            .open(disk_path)
-            .map_err(|err| CheckoutError::Other {
-                message: format!("Failed to open file {} for writing", disk_path.display()),
-                err: err.into(),
-            })?;
-        let size = io::copy(contents, &mut file).map_err(|err| CheckoutError::Other {
+        let mut file = create_new_file(disk_path)?;
+        self.write_to_new_file(&mut file, disk_path, contents, executable)
+    }
+
+    /// Writes `contents` to the `file` which was just created at `disk_path`.
+    fn write_to_new_file(
+        &self,
+        file: &mut File,
+        disk_path: &Path,
+        contents: &mut dyn Read,
+        executable: bool,
+    ) -> Result<FileState, CheckoutError> {
+        let size = io::copy(contents, file).map_err(|err| CheckoutError::Other {
             message: format!("Failed to write file {}", disk_path.display()),
             err: err.into(),
         })?;
@@ -1634,14 +2402,7 @@ impl TreeState {
         executable: bool,
         materialized_conflict_data: Option<MaterializedConflictData>,
     ) -> Result<FileState, CheckoutError> {
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create_new(true) // Don't overwrite un-ignored file. Don't follow symlink.
-            .open(disk_path)
-            .map_err(|err| CheckoutError::Other {
-                message: format!("Failed to open file {} for writing", disk_path.display()),
-                err: err.into(),
-            })?;
+        let mut file = create_new_file(disk_path)?;
         file.write_all(&conflict_data)
             .map_err(|err| CheckoutError::Other {
                 message: format!("Failed to write conflict to file {}", disk_path.display()),
@@ -1683,12 +2444,7 @@ impl TreeState {
             other => CheckoutError::InternalBackendError(other),
         })?;
         let stats = self
-            .update(
-                &old_tree,
-                new_tree,
-                self.sparse_matcher().as_ref(),
-                options.conflict_marker_style,
-            )
+            .update(&old_tree, new_tree, self.sparse_matcher().as_ref(), options)
             .block_on()?;
         self.tree_id = new_tree.id();
         Ok(stats)
@@ -1696,7 +2452,7 @@ impl TreeState {
 
     pub fn set_sparse_patterns(
         &mut self,
-        sparse_patterns: Vec<RepoPathBuf>,
+        sparse_patterns: Vec<SparsePattern>,
         options: &CheckoutOptions,
     ) -> Result<CheckoutStats, CheckoutError> {
         let tree = self.current_tree().map_err(|err| match err {
@@ -1705,26 +2461,16 @@ impl TreeState {
             },
             other => CheckoutError::InternalBackendError(other),
         })?;
-        let old_matcher = PrefixMatcher::new(&self.sparse_patterns);
-        let new_matcher = PrefixMatcher::new(&sparse_patterns);
+        let old_matcher = sparse_matcher(&self.sparse_patterns);
+        let new_matcher = sparse_matcher(&sparse_patterns);
         let added_matcher = DifferenceMatcher::new(&new_matcher, &old_matcher);
         let removed_matcher = DifferenceMatcher::new(&old_matcher, &new_matcher);
         let empty_tree = MergedTree::resolved(Tree::empty(self.store.clone(), RepoPathBuf::root()));
         let added_stats = self
-            .update(
-                &empty_tree,
-                &tree,
-                &added_matcher,
-                options.conflict_marker_style,
-            )
+            .update(&empty_tree, &tree, &added_matcher, options)
             .block_on()?;
         let removed_stats = self
-            .update(
-                &tree,
-                &empty_tree,
-                &removed_matcher,
-                options.conflict_marker_style,
-            )
+            .update(&tree, &empty_tree, &removed_matcher, options)
             .block_on()?;
         self.sparse_patterns = sparse_patterns;
         assert_eq!(added_stats.updated_files, 0);
@@ -1745,7 +2491,7 @@ impl TreeState {
         old_tree: &MergedTree,
         new_tree: &MergedTree,
         matcher: &dyn Matcher,
-        conflict_marker_style: ConflictMarkerStyle,
+        options: &CheckoutOptions,
     ) -> Result<CheckoutStats, CheckoutError> {
         // TODO: maybe it's better not include the skipped counts in the "intended"
         // counts
@@ -1755,15 +2501,72 @@ impl TreeState {
             removed_files: 0,
             skipped_files: 0,
         };
+        // Only one of the colliding files could be written, so refuse to check
+        // out any of them. Case sensitivity can be set per directory (e.g. by
+        // casefold on ext4), so the directories of the collisions are probed.
+        // Collisions have to be found before writing any files, which takes
+        // an extra pass over the diff. Since a case-sensitive working copy
+        // root is unlikely to contain case-insensitive directories, the pass
+        // is skipped in that case.
+        if self.is_case_insensitive_dir(RepoPath::root()) {
+            for paths in find_case_collisions(old_tree, new_tree, matcher).await? {
+                let dir = paths[0].parent().unwrap();
+                if self.is_case_insensitive_dir(dir) {
+                    return Err(CheckoutError::CaseCollision { paths });
+                }
+            }
+        }
+        let git_attributes = GitAttributesCache::for_tree(new_tree);
+        let attributes_err = |err: GitAttributesError| CheckoutError::Other {
+            message: "Failed to load .gitattributes".to_string(),
+            err: err.into(),
+        };
+        let eol_conversion_for = |path: &RepoPath| {
+            EolConversion::for_path(&git_attributes, path).map_err(attributes_err)
+        };
+        let lfs_store = self.lfs_object_store();
+        let content_filters = options.content_filters.runner(&self.working_copy_path);
         let mut changed_file_states = Vec::new();
         let mut deleted_files = HashSet::new();
+        let mut pending_files = Vec::new();
+        let write_pending_files =
+            |pending_files: Vec<PendingFile>,
+             changed_file_states: &mut Vec<(RepoPathBuf, FileState)>,
+             deleted_files: &mut HashSet<RepoPathBuf>| {
+                let written =
+                    self.write_pending_files(pending_files, lfs_store.as_ref(), &content_filters)?;
+                for (path, disk_path, file_state) in written {
+                    if let Some(file_state) = file_state {
+                        changed_file_states.push((path, file_state));
+                    } else {
+                        // The contents couldn't be read. Remove the file as if
+                        // it were absent.
+                        fs::remove_file(&disk_path).ok();
+                        remove_empty_parent_dirs(&disk_path);
+                        deleted_files.insert(path);
+                    }
+                }
+                Ok::<_, CheckoutError>(())
+            };
         let mut diff_stream = old_tree
             .diff_stream(new_tree, matcher)
             .map(|TreeDiffEntry { path, values }| async {
                 match values {
                     Ok((before, after)) => {
+                        // Regular files are read by write_pending_files().
+                        if let Some(Some(TreeValue::File { id, executable })) = after.as_resolved()
+                        {
+                            let value = CheckoutValue::File {
+                                id: id.clone(),
+                                executable: *executable,
+                            };
+                            return (path, Ok((before, value)));
+                        }
                         let result = materialize_tree_value(&self.store, &path, after).await;
-                        (path, result.map(|value| (before, value)))
+                        (
+                            path,
+                            result.map(|value| (before, CheckoutValue::Materialized(value))),
+                        )
                     }
                     Err(err) => (path, Err(err)),
                 }
@@ -1787,7 +2590,10 @@ impl TreeState {
             // paths excluded by .gitignore can be marked as such so that
             // newly-"unignored" paths won't be snapshotted automatically.
             if matches!(before.as_normal(), Some(TreeValue::GitSubmodule(_)))
-                && matches!(after, MaterializedTreeValue::GitSubmodule(_))
+                && matches!(
+                    after,
+                    CheckoutValue::Materialized(MaterializedTreeValue::GitSubmodule(_))
+                )
             {
                 eprintln!("ignoring git submodule at {path:?}");
                 // Not updating the file state as if there were no diffs. Leave
@@ -1812,23 +2618,41 @@ impl TreeState {
             }
 
             // TODO: Check that the file has not changed before overwriting/removing it.
+            let after = match after {
+                CheckoutValue::File { id, executable } => {
+                    // Create the file now so that the following paths are
+                    // checked against it, and write the contents later.
+                    let is_lfs = lfs_store.is_some()
+                        && is_lfs_path(&git_attributes, &path).map_err(attributes_err)?;
+                    pending_files.push(PendingFile {
+                        eol_conversion: eol_conversion_for(&path)?,
+                        file: create_new_file(&disk_path)?,
+                        path,
+                        disk_path,
+                        id,
+                        executable,
+                        is_lfs,
+                    });
+                    if pending_files.len() >= MAX_PENDING_FILES {
+                        write_pending_files(
+                            mem::take(&mut pending_files),
+                            &mut changed_file_states,
+                            &mut deleted_files,
+                        )?;
+                    }
+                    continue;
+                }
+                CheckoutValue::Materialized(after) => after,
+            };
             let file_state = match after {
                 MaterializedTreeValue::Absent | MaterializedTreeValue::AccessDenied(_) => {
-                    let mut parent_dir = disk_path.parent().unwrap();
-                    loop {
-                        if fs::remove_dir(parent_dir).is_err() {
-                            break;
-                        }
-                        parent_dir = parent_dir.parent().unwrap();
-                    }
+                    remove_empty_parent_dirs(&disk_path);
                     deleted_files.insert(path);
                     continue;
                 }
-                MaterializedTreeValue::File {
-                    executable,
-                    mut reader,
-                    ..
-                } => self.write_file(&disk_path, &mut reader, executable)?,
+                MaterializedTreeValue::File { .. } => {
+                    unreachable!("unexpected unread file in diff at {path:?}");
+                }
                 MaterializedTreeValue::Symlink { id: _, target } => {
                     if self.symlink_support {
                         self.write_symlink(&disk_path, target)?
@@ -1851,10 +2675,12 @@ impl TreeState {
                     let conflict_marker_len = choose_materialized_conflict_marker_len(&contents);
                     let data = materialize_merge_result_to_bytes_with_marker_len(
                         &contents,
-                        conflict_marker_style,
+                        options.conflict_marker_style,
                         conflict_marker_len,
-                    )
-                    .into();
+                    );
+                    let data = eol_conversion_for(&path)?
+                        .convert_to_working_copy(&data)
+                        .into_owned();
                     let materialized_conflict_data = MaterializedConflictData {
                         conflict_marker_len: conflict_marker_len.try_into().unwrap_or(u32::MAX),
                     };
@@ -1875,11 +2701,107 @@ impl TreeState {
             };
             changed_file_states.push((path, file_state));
         }
-        self.file_states
-            .merge_in(changed_file_states, &deleted_files);
+        drop(diff_stream);
+        write_pending_files(pending_files, &mut changed_file_states, &mut deleted_files)?;
+        // Pending files are recorded after the other paths in the same batch.
+        changed_file_states.sort_unstable_by(|(path1, _), (path2, _)| path1.cmp(path2));
+        self.merge_file_states(changed_file_states, &deleted_files);
         Ok(stats)
     }
 
+    /// Returns true if file names in the directory `dir` appear to be
+    /// case-insensitive. If the directory doesn't exist yet, its nearest
+    /// existing parent is probed instead.
+    fn is_case_insensitive_dir(&self, dir: &RepoPath) -> bool {
+        let disk_dir = dir.to_fs_path_unchecked(&self.working_copy_path);
+        disk_dir
+            .ancestors()
+            .find(|path| path.symlink_metadata().is_ok_and(|meta| meta.is_dir()))
+            .is_some_and(|path| check_case_insensitive_fs(path).unwrap_or(false))
+    }
+
+    /// Reads and writes the contents of the `pending_files` concurrently, up to
+    /// the backend's concurrency. Returns `None` file states for files that
+    /// couldn't be read because access was denied.
+    fn write_pending_files(
+        &self,
+        pending_files: Vec<PendingFile>,
+        lfs_store: Option<&LfsObjectStore>,
+        content_filters: &ContentFilterRunner,
+    ) -> Result<Vec<(RepoPathBuf, PathBuf, Option<FileState>)>, CheckoutError> {
+        let write = |mut pending: PendingFile| {
+            let file_state = self.write_pending_file(&mut pending, lfs_store, content_filters)?;
+            Ok((pending.path, pending.disk_path, file_state))
+        };
+        // Each job processes at least this many files sequentially, so no more
+        // than `concurrency` files are read at a time.
+        let concurrency = self.store.concurrency().max(1);
+        let min_len = pending_files.len().div_ceil(concurrency).max(1);
+        pending_files
+            .into_par_iter()
+            .with_min_len(min_len)
+            .map(write)
+            .collect()
+    }
+
+    fn write_pending_file(
+        &self,
+        pending: &mut PendingFile,
+        lfs_store: Option<&LfsObjectStore>,
+        content_filters: &ContentFilterRunner,
+    ) -> Result<Option<FileState>, CheckoutError> {
+        let PendingFile {
+            path,
+            disk_path,
+            file,
+            id,
+            executable,
+            eol_conversion,
+            is_lfs,
+        } = pending;
+        let mut reader = match self.store.read_file(path, id) {
+            Ok(reader) => reader,
+            Err(BackendError::ReadAccessDenied { .. }) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let file_state = match lfs_store {
+            Some(lfs_store) if *is_lfs => {
+                let mut reader =
+                    smudge_lfs_file(lfs_store, reader).map_err(|err| CheckoutError::Other {
+                        message: format!("Failed to read LFS object for {path:?}"),
+                        err: err.into(),
+                    })?;
+                self.write_to_new_file(file, disk_path, &mut reader, *executable)?
+            }
+            _ if eol_conversion.converts_to_working_copy()
+                || content_filters.applies_to(ContentFilterKind::Smudge, path) =>
+            {
+                let mut content = vec![];
+                reader
+                    .read_to_end(&mut content)
+                    .map_err(|err| CheckoutError::Other {
+                        message: format!("Failed to read file {path:?}"),
+                        err: err.into(),
+                    })?;
+                let content = eol_conversion
+                    .convert_to_working_copy(&content)
+                    .into_owned();
+                // Like Git, run the smudge filters after converting line
+                // endings.
+                let content =
+                    content_filters
+                        .smudge(path, content)
+                        .map_err(|err| CheckoutError::Other {
+                            message: format!("Failed to filter file {path:?}"),
+                            err: err.into(),
+                        })?;
+                self.write_to_new_file(file, disk_path, &mut content.as_slice(), *executable)?
+            }
+            _ => self.write_to_new_file(file, disk_path, &mut reader, *executable)?,
+        };
+        Ok(Some(file_state))
+    }
+
     pub async fn reset(&mut self, new_tree: &MergedTree) -> Result<(), ResetError> {
         let old_tree = self.current_tree().map_err(|err| match err {
             err @ BackendError::ObjectNotFound { .. } => ResetError::SourceNotFound {
@@ -1931,14 +2853,14 @@ impl TreeState {
                 changed_file_states.push((path, file_state));
             }
         }
-        self.file_states
-            .merge_in(changed_file_states, &deleted_files);
+        self.merge_file_states(changed_file_states, &deleted_files);
         self.tree_id = new_tree.id();
         Ok(())
     }
 
     pub async fn recover(&mut self, new_tree: &MergedTree) -> Result<(), ResetError> {
         self.file_states.clear();
+        self.file_state_changes = None;
         self.tree_id = self.store.empty_merged_tree_id();
         self.reset(new_tree).await
     }
@@ -1987,7 +2909,7 @@ impl WorkingCopy for LocalWorkingCopy {
         Ok(self.tree_state()?.current_tree_id())
     }
 
-    fn sparse_patterns(&self) -> Result<&[RepoPathBuf], WorkingCopyStateError> {
+    fn sparse_patterns(&self) -> Result<&[SparsePattern], WorkingCopyStateError> {
         Ok(self.tree_state()?.sparse_patterns())
     }
 
@@ -2171,6 +3093,19 @@ impl LocalWorkingCopy {
                 err: err.into(),
             })
     }
+
+    #[cfg(all(unix, feature = "fsmonitor-daemon"))]
+    pub fn query_builtin_fsmonitor(
+        &self,
+        config: &BuiltinConfig,
+    ) -> Result<(Option<String>, Option<Vec<PathBuf>>), WorkingCopyStateError> {
+        self.tree_state()?
+            .query_builtin_fsmonitor(config)
+            .map_err(|err| WorkingCopyStateError {
+                message: "Failed to query the filesystem monitor".to_string(),
+                err: err.into(),
+            })
+    }
 }
 
 pub struct LocalWorkingCopyFactory {}
@@ -2304,13 +3239,13 @@ impl LockedWorkingCopy for LockedLocalWorkingCopy {
         Ok(())
     }
 
-    fn sparse_patterns(&self) -> Result<&[RepoPathBuf], WorkingCopyStateError> {
+    fn sparse_patterns(&self) -> Result<&[SparsePattern], WorkingCopyStateError> {
         self.wc.sparse_patterns()
     }
 
     fn set_sparse_patterns(
         &mut self,
-        new_sparse_patterns: Vec<RepoPathBuf>,
+        new_sparse_patterns: Vec<SparsePattern>,
         options: &CheckoutOptions,
     ) -> Result<CheckoutStats, CheckoutError> {
         // TODO: Write a "pending_checkout" file with new sparse patterns so we can
@@ -2366,10 +3301,25 @@ impl LockedLocalWorkingCopy {
         self.tree_state_dirty = true;
         Ok(())
     }
+
+    /// Sets the format of the tree state file. The tree state is rewritten in
+    /// the new format when the working copy is finished.
+    pub fn set_tree_state_format(
+        &mut self,
+        format: TreeStateFormat,
+    ) -> Result<(), WorkingCopyStateError> {
+        let tree_state = self.wc.tree_state_mut()?;
+        if tree_state.format() != format {
+            tree_state.set_format(format);
+            self.tree_state_dirty = true;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use assert_matches::assert_matches;
     use maplit::hashset;
 
     use super::*;
@@ -2378,6 +3328,44 @@ mod tests {
         RepoPath::from_internal_string(value)
     }
 
+    #[test]
+    fn test_sparse_patterns_proto() {
+        use crate::protos::working_copy::SparsePatterns;
+        let to_strings = |patterns: &[SparsePattern]| {
+            patterns.iter().map(|p| p.as_str().to_owned()).collect_vec()
+        };
+
+        // Legacy state without fileset patterns
+        let proto = SparsePatterns {
+            prefixes: vec!["".to_owned(), "dir/sub".to_owned()],
+            filesets: vec![],
+        };
+        let patterns = sparse_patterns_from_proto(Some(&proto)).unwrap();
+        assert_eq!(to_strings(&patterns), [".", "dir/sub"]);
+        assert_eq!(sparse_patterns_to_proto(&patterns).prefixes, proto.prefixes);
+        let patterns = sparse_patterns_from_proto(None).unwrap();
+        assert_eq!(to_strings(&patterns), ["."]);
+
+        // Prefixes are omitted if any pattern can't be represented as prefix
+        let patterns = vec![
+            SparsePattern::parse("dir").unwrap(),
+            SparsePattern::parse("~dir/test").unwrap(),
+        ];
+        let proto = sparse_patterns_to_proto(&patterns);
+        assert!(proto.prefixes.is_empty());
+        assert_eq!(proto.filesets, ["dir", "~dir/test"]);
+        assert_eq!(sparse_patterns_from_proto(Some(&proto)).unwrap(), patterns);
+
+        let proto = SparsePatterns {
+            prefixes: vec![],
+            filesets: vec!["dir(".to_owned()],
+        };
+        assert_matches!(
+            sparse_patterns_from_proto(Some(&proto)),
+            Err((pattern, _)) if pattern == "dir("
+        );
+    }
+
     #[test]
     fn test_file_states_merge() {
         let new_state = |size| FileState {
@@ -2413,7 +3401,7 @@ mod tests {
             repo_path("b/c").to_owned(),
             repo_path("b#").to_owned(),
         };
-        file_states.merge_in(changed_file_states, &deleted_files);
+        file_states.merge_in(&changed_file_states, &deleted_files);
         assert_eq!(
             file_states.all().iter().collect_vec(),
             vec![
@@ -2425,6 +3413,18 @@ mod tests {
                 new_static_entry("c", 13),
             ],
         );
+        assert_eq!(
+            file_states.all().iter().next_back(),
+            Some(new_static_entry("c", 13))
+        );
+
+        // Round trip through proto
+        let file_states = FileStatesMap::from_proto(file_states.to_proto(), true);
+        assert_eq!(file_states.all().len(), 6);
+        assert_eq!(
+            file_states.all().get(repo_path("b/d/f")),
+            Some(new_state(11))
+        );
     }
 
     #[test]
@@ -2448,7 +3448,8 @@ mod tests {
             new_proto_entry("b#", 4), // '#' < '/'
             new_proto_entry("bc", 5),
         ];
-        let file_states = FileStates::from_sorted(&data);
+        let file_states_map = FileStatesMap::from_proto(data, true);
+        let file_states = file_states_map.all();
 
         assert_eq!(
             file_states.prefixed(repo_path("")).paths().collect_vec(),
@@ -2512,7 +3513,8 @@ mod tests {
             new_proto_entry("b/e", 3),
             new_proto_entry("b#", 4), // '#' < '/'
         ];
-        let file_states = FileStates::from_sorted(&data);
+        let file_states_map = FileStatesMap::from_proto(data, true);
+        let file_states = file_states_map.all();
 
         // At root
         assert_eq!(
@@ -0,0 +1,320 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Merge drivers which resolve file conflicts of selected paths, like Git's
+//! `merge` attribute.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::thread;
+
+use bstr::BString;
+use futures::channel::oneshot;
+use itertools::Itertools as _;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::config::ConfigGetError;
+use crate::files;
+use crate::files::MergeResult;
+use crate::fileset;
+use crate::fileset::FilesetDiagnostics;
+use crate::fileset::FilesetExpression;
+use crate::fileset::FilesetParseError;
+use crate::matchers::Matcher;
+use crate::merge::Merge;
+use crate::path_command::command_for_path;
+use crate::path_command::run_with_input;
+use crate::repo_path::RepoPath;
+use crate::repo_path::RepoPathBuf;
+use crate::repo_path::RepoPathUiConverter;
+use crate::settings::UserSettings;
+use crate::structured_merge;
+
+/// Error occurred while loading merge drivers from the settings.
+#[derive(Debug, Error)]
+pub enum MergeDriverConfigError {
+    /// The driver configuration couldn't be read.
+    #[error(transparent)]
+    Config(#[from] ConfigGetError),
+    /// The `files` of a driver isn't a valid fileset.
+    #[error("Invalid `merge-drivers.{name}.files`")]
+    Files {
+        /// Name of the driver.
+        name: String,
+        /// Underlying error.
+        #[source]
+        source: FilesetParseError,
+    },
+    /// The `command` of a driver is empty or names an unknown built-in.
+    #[error("Invalid `merge-drivers.{name}.command`: {message}")]
+    Command {
+        /// Name of the driver.
+        name: String,
+        /// Description of the problem.
+        message: String,
+    },
+}
+
+/// How a merge driver resolves a file conflict.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MergeDriverKind {
+    /// Includes the lines of all sides of each conflicting hunk.
+    Union,
+    /// Takes the first side of each conflicting hunk.
+    Ours,
+    /// Takes the last side of each conflicting hunk.
+    Theirs,
+    /// Merges JSON, TOML, and YAML files key by key. See
+    /// [`structured_merge::merge()`].
+    Structured,
+    /// Runs an external command and arguments.
+    ///
+    /// `$base`, `$left`, `$right`, and `$output` in the arguments are
+    /// substituted with paths to temporary files, and `$path` with the
+    /// repo-relative path of the merged file. The `$output` file initially
+    /// contains the left side. The conflict is resolved to the content of
+    /// `$output` if the command exits successfully. Only 2-sided conflicts
+    /// are passed to external commands. If the command can't be run or
+    /// fails, a warning is logged and the file is left conflicted.
+    ///
+    /// The command runs on a separate thread, so it doesn't block the async
+    /// executor.
+    External(Vec<String>),
+}
+
+/// Merge driver applied to files matching a fileset.
+#[derive(Clone, Debug)]
+pub struct MergeDriver {
+    /// Name of the driver, used in error messages.
+    pub name: String,
+    /// Files to which the driver applies.
+    pub files: FilesetExpression,
+    /// How the driver resolves conflicts.
+    pub kind: MergeDriverKind,
+}
+
+impl MergeDriver {
+    /// Tries to resolve the conflicting file `contents`. Returns `None` if the
+    /// driver couldn't resolve the conflict.
+    pub async fn merge<T: AsRef<[u8]>>(
+        &self,
+        path: &RepoPath,
+        contents: &Merge<T>,
+    ) -> Option<BString> {
+        let pick_hunk: fn(&Merge<BString>) -> BString = match &self.kind {
+            MergeDriverKind::Union => union_hunk,
+            MergeDriverKind::Ours => |hunk| hunk.first().clone(),
+            MergeDriverKind::Theirs => |hunk| hunk.adds().last().unwrap().clone(),
+            MergeDriverKind::Structured => {
+                return structured_merge::merge(path, contents);
+            }
+            MergeDriverKind::External(command) => {
+                let [left, base, right] = contents.as_slice() else {
+                    return None;
+                };
+                let sides = [left, base, right].map(|side| side.as_ref().to_vec());
+                let (tx, rx) = oneshot::channel();
+                let name = self.name.clone();
+                let command = command.clone();
+                let path_buf = path.to_owned();
+                thread::spawn(move || {
+                    tx.send(run_external(&name, &command, &path_buf, sides))
+                        .ok();
+                });
+                let result = rx.await.unwrap_or_else(|_| {
+                    Err(io::Error::other(
+                        "merge driver thread terminated unexpectedly",
+                    ))
+                });
+                return result.unwrap_or_else(|err| {
+                    tracing::warn!(
+                        ?err,
+                        driver = self.name,
+                        ?path,
+                        "Failed to run merge driver"
+                    );
+                    None
+                });
+            }
+        };
+        match files::merge(contents) {
+            MergeResult::Resolved(content) => Some(content),
+            MergeResult::Conflict(hunks) => {
+                let mut content = BString::default();
+                for hunk in &hunks {
+                    match hunk.as_resolved() {
+                        Some(resolved) => content.extend_from_slice(resolved),
+                        None => content.extend_from_slice(&pick_hunk(hunk)),
+                    }
+                }
+                Some(content)
+            }
+        }
+    }
+}
+
+/// Runs the external `command` of the driver `name` on the
+/// `[left, base, right]` sides. Returns `None` if the command exited
+/// unsuccessfully.
+fn run_external(
+    name: &str,
+    command: &[String],
+    path: &RepoPathBuf,
+    [left, base, right]: [Vec<u8>; 3],
+) -> io::Result<Option<BString>> {
+    let temp_dir = tempfile::Builder::new().prefix("jj-merge-").tempdir()?;
+    let write_temp = |name: &str, content: &[u8]| {
+        let file_path = temp_dir.path().join(name);
+        fs::write(&file_path, content).map(|()| file_path)
+    };
+    let base_path = write_temp("base", &base)?;
+    let left_path = write_temp("left", &left)?;
+    let right_path = write_temp("right", &right)?;
+    let output_path = write_temp("output", &left)?;
+    let path_arg = |path: &Path| path.to_string_lossy().into_owned();
+    let mut command = command_for_path(command, path, |arg| {
+        arg.replace("$base", &path_arg(&base_path))
+            .replace("$left", &path_arg(&left_path))
+            .replace("$right", &path_arg(&right_path))
+            .replace("$output", &path_arg(&output_path))
+    });
+    let output = run_with_input(&mut command, b"")?;
+    if !output.status.success() {
+        tracing::warn!(
+            driver = name,
+            ?path,
+            status = %output.status,
+            stderr = %String::from_utf8_lossy(&output.stderr).trim_end(),
+            "Merge driver failed"
+        );
+        return Ok(None);
+    }
+    let content = fs::read(&output_path)?;
+    Ok(Some(content.into()))
+}
+
+fn union_hunk(hunk: &Merge<BString>) -> BString {
+    let mut content = BString::default();
+    for side in hunk.adds() {
+        content.extend_from_slice(side);
+        if !content.is_empty() && !content.ends_with(b"\n") {
+            content.push(b'\n');
+        }
+    }
+    content
+}
+
+/// Set of merge drivers configured by the user.
+///
+/// If more than one driver applies to a file, the first one in order of the
+/// driver names is used.
+#[derive(Debug, Default)]
+pub struct MergeDrivers {
+    drivers: Vec<MergeDriver>,
+    matchers: Vec<Box<dyn Matcher>>,
+}
+
+impl MergeDrivers {
+    /// Creates a set of merge drivers.
+    pub fn new(drivers: Vec<MergeDriver>) -> Self {
+        let matchers = drivers
+            .iter()
+            .map(|driver| driver.files.to_matcher())
+            .collect();
+        MergeDrivers { drivers, matchers }
+    }
+
+    /// Loads the merge drivers from the `merge-drivers` table.
+    pub fn from_settings(settings: &UserSettings) -> Result<Self, MergeDriverConfigError> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawCommand {
+            String(String),
+            Vec(Vec<String>),
+        }
+
+        #[derive(Deserialize)]
+        struct RawMergeDriver {
+            files: String,
+            command: RawCommand,
+        }
+
+        let drivers = settings
+            .table_keys("merge-drivers")
+            .sorted()
+            .map(|name| -> Result<_, MergeDriverConfigError> {
+                let driver: RawMergeDriver = settings.get(["merge-drivers", name])?;
+                let files = fileset::parse(
+                    &mut FilesetDiagnostics::new(),
+                    &driver.files,
+                    &RepoPathUiConverter::Fs {
+                        cwd: "".into(),
+                        base: "".into(),
+                    },
+                )
+                .map_err(|source| MergeDriverConfigError::Files {
+                    name: name.to_owned(),
+                    source,
+                })?;
+                let command_error = |message: String| MergeDriverConfigError::Command {
+                    name: name.to_owned(),
+                    message,
+                };
+                let kind =
+                    match driver.command {
+                        RawCommand::String(command) => match command.as_str() {
+                            ":union" => MergeDriverKind::Union,
+                            ":ours" => MergeDriverKind::Ours,
+                            ":theirs" => MergeDriverKind::Theirs,
+                            ":structured" => MergeDriverKind::Structured,
+                            _ if command.starts_with(':') => {
+                                return Err(command_error(format!(
+                                    "Unknown built-in merge driver `{command}`"
+                                )));
+                            }
+                            _ => MergeDriverKind::External(shlex::split(&command).ok_or_else(
+                                || command_error("Command has invalid quoting".to_owned()),
+                            )?),
+                        },
+                        RawCommand::Vec(args) => MergeDriverKind::External(args),
+                    };
+                if kind == MergeDriverKind::External(vec![]) {
+                    return Err(command_error("Command is empty".to_owned()));
+                }
+                Ok(MergeDriver {
+                    name: name.to_owned(),
+                    files,
+                    kind,
+                })
+            })
+            .try_collect()?;
+        Ok(MergeDrivers::new(drivers))
+    }
+
+    /// Returns true if no drivers are configured.
+    pub fn is_empty(&self) -> bool {
+        self.drivers.is_empty()
+    }
+
+    /// Returns the driver which applies to the `path`.
+    pub fn find(&self, path: &RepoPath) -> Option<&MergeDriver> {
+        self.drivers
+            .iter()
+            .zip(&self.matchers)
+            .find(|(_, matcher)| matcher.matches(path))
+            .map(|(driver, _)| driver)
+    }
+}
@@ -51,6 +51,7 @@ use crate::merge::Merge;
 use crate::merge::MergeBuilder;
 use crate::merge::MergedTreeVal;
 use crate::merge::MergedTreeValue;
+use crate::merge_driver::MergeDrivers;
 use crate::repo_path::RepoPath;
 use crate::repo_path::RepoPathBuf;
 use crate::repo_path::RepoPathComponent;
@@ -161,7 +162,16 @@ impl MergedTree {
     /// Tries to resolve any conflicts, resolving any conflicts that can be
     /// automatically resolved and leaving the rest unresolved.
     pub fn resolve(&self) -> BackendResult<MergedTree> {
-        let merged = merge_trees(&self.trees)?;
+        self.resolve_with_drivers(None)
+    }
+
+    /// Like `resolve()`, but also tries the `merge_drivers` on file conflicts
+    /// which can't be resolved line by line.
+    fn resolve_with_drivers(
+        &self,
+        merge_drivers: Option<&MergeDrivers>,
+    ) -> BackendResult<MergedTree> {
+        let merged = merge_trees(&self.trees, merge_drivers).block_on()?;
         // If the result can be resolved, then `merge_trees()` above would have returned
         // a resolved merge. However, that function will always preserve the arity of
         // conflicts it cannot resolve. So we simplify the conflict again
@@ -171,7 +181,7 @@ impl MergedTree {
         // particular,  that this last simplification doesn't enable further automatic
         // resolutions
         if cfg!(debug_assertions) {
-            let re_merged = merge_trees(&simplified).unwrap();
+            let re_merged = merge_trees(&simplified, None).block_on().unwrap();
             debug_assert_eq!(re_merged, simplified);
         }
         Ok(MergedTree { trees: simplified })
@@ -325,6 +335,40 @@ impl MergedTree {
         self.merge_no_resolve(base, other).resolve()
     }
 
+    /// Like `merge()`, but also resolves file conflicts by the merge drivers
+    /// configured for the paths.
+    ///
+    /// Since the drivers may run external commands, this should only be used
+    /// if the merged tree is written to a rewritten commit, not for display.
+    pub fn merge_with_drivers(
+        &self,
+        base: &MergedTree,
+        other: &MergedTree,
+    ) -> BackendResult<MergedTree> {
+        let store = self.store();
+        self.merge_no_resolve(base, other)
+            .resolve_with_drivers(Some(store.merge_drivers()))
+    }
+
+    /// Like `merge()`, but follows renames. `self_copies` describes copies from
+    /// `base` to `self`, and `other_copies` copies from `base` to `other`.
+    ///
+    /// If a file was renamed on one side and modified at the old path on the
+    /// other side, the modification is applied to the renamed file, and any
+    /// conflict is reported at the new path. Like `merge_with_drivers()`, the
+    /// configured merge drivers are used.
+    pub fn merge_with_copies(
+        &self,
+        base: &MergedTree,
+        other: &MergedTree,
+        self_copies: &CopyRecords,
+        other_copies: &CopyRecords,
+    ) -> BackendResult<MergedTree> {
+        let (base, other) = follow_renames(self, base, other, self_copies)?;
+        let (base, this) = follow_renames(&other, &base, self, other_copies)?;
+        this.merge_with_drivers(&base, &other)
+    }
+
     /// Merges this tree with `other`, using `base` as base, without attempting
     /// to resolve file conflicts.
     pub fn merge_no_resolve(&self, base: &MergedTree, other: &MergedTree) -> MergedTree {
@@ -339,6 +383,54 @@ impl MergedTree {
     }
 }
 
+/// Moves files renamed from `base` to `renamed` to their new paths in `base`
+/// and `other`. Returns the new `base` and `other` trees.
+///
+/// Only renamed files which were modified in `other` are moved, so the
+/// modification will be merged into the renamed file. Renames which would
+/// conflict with other changes in `other` are ignored.
+fn follow_renames(
+    renamed: &MergedTree,
+    base: &MergedTree,
+    other: &MergedTree,
+    copy_records: &CopyRecords,
+) -> BackendResult<(MergedTree, MergedTree)> {
+    let is_file = |value: &MergedTreeValue| value.is_present() && value.to_file_merge().is_some();
+    let mut base_builder = MergedTreeBuilder::new(base.id());
+    let mut other_builder = MergedTreeBuilder::new(other.id());
+    let mut is_modified = false;
+    for record in copy_records.iter() {
+        let (source, target) = (&record.source, &record.target);
+        // Skip ambiguous records, e.g. a file copied to multiple targets.
+        if copy_records.for_source(source) != Some(record)
+            || copy_records.for_target(target) != Some(record)
+        {
+            continue;
+        }
+        let base_source = base.path_value(source)?;
+        let other_source = other.path_value(source)?;
+        let is_rename = is_file(&base_source)
+            && renamed.path_value(source)?.is_absent()
+            && is_file(&renamed.path_value(target)?)
+            && base.path_value(target)?.is_absent()
+            && other.path_value(target)?.is_absent();
+        if is_rename && is_file(&other_source) && other_source != base_source {
+            base_builder.set_or_remove(source.clone(), Merge::absent());
+            base_builder.set_or_remove(target.clone(), base_source);
+            other_builder.set_or_remove(source.clone(), Merge::absent());
+            other_builder.set_or_remove(target.clone(), other_source);
+            is_modified = true;
+        }
+    }
+    if !is_modified {
+        return Ok((base.clone(), other.clone()));
+    }
+    let store = base.store();
+    let new_base = store.get_root_tree(&base_builder.write_tree(store)?)?;
+    let new_other = store.get_root_tree(&other_builder.write_tree(store)?)?;
+    Ok((new_base, new_other))
+}
+
 /// A single entry in a tree diff.
 pub struct TreeDiffEntry {
     /// The path.
@@ -438,7 +530,13 @@ fn trees_value<'a>(trees: &'a Merge<Tree>, basename: &RepoPathComponent) -> Merg
 
 /// The returned conflict will either be resolved or have the same number of
 /// sides as the input.
-fn merge_trees(merge: &Merge<Tree>) -> BackendResult<Merge<Tree>> {
+///
+/// Entries are merged concurrently up to the backend's concurrency, but the
+/// result doesn't depend on the order in which they complete.
+async fn merge_trees(
+    merge: &Merge<Tree>,
+    merge_drivers: Option<&MergeDrivers>,
+) -> BackendResult<Merge<Tree>> {
     if let Some(tree) = merge.resolve_trivial() {
         return Ok(Merge::resolved(tree.clone()));
     }
@@ -451,10 +549,31 @@ fn merge_trees(merge: &Merge<Tree>) -> BackendResult<Merge<Tree>> {
     // any conflicts.
     let mut new_tree = backend::Tree::default();
     let mut conflicts = vec![];
-    // TODO: Merge values concurrently
-    for (basename, path_merge) in all_merged_tree_entries(merge) {
-        let path = dir.join(basename);
-        let path_merge = merge_tree_values(store, &path, &path_merge).block_on()?;
+    let concurrency = store.concurrency();
+    let merged_values: Vec<_> = if concurrency <= 1 {
+        // Polling a stream of futures which complete immediately would only
+        // add overhead.
+        let mut merged_values = vec![];
+        for (basename, path_merge) in all_merged_tree_entries(merge) {
+            let path = dir.join(basename);
+            let path_merge = merge_tree_values(store, &path, &path_merge, merge_drivers).await?;
+            merged_values.push((basename, path_merge));
+        }
+        merged_values
+    } else {
+        // `buffered()` yields the merged values in the original order.
+        futures::stream::iter(all_merged_tree_entries(merge))
+            .map(|(basename, path_merge)| async move {
+                let path = dir.join(basename);
+                let path_merge =
+                    merge_tree_values(store, &path, &path_merge, merge_drivers).await?;
+                BackendResult::Ok((basename, path_merge))
+            })
+            .buffered(concurrency)
+            .try_collect()
+            .await?
+    };
+    for (basename, path_merge) in merged_values {
         match path_merge.into_resolved() {
             Ok(value) => {
                 new_tree.set_or_remove(basename, value);
@@ -465,7 +584,7 @@ fn merge_trees(merge: &Merge<Tree>) -> BackendResult<Merge<Tree>> {
         };
     }
     if conflicts.is_empty() {
-        let new_tree_id = store.write_tree(dir, new_tree).block_on()?;
+        let new_tree_id = store.write_tree(dir, new_tree).await?;
         Ok(Merge::resolved(new_tree_id))
     } else {
         // For each side of the conflict, overwrite the entries in `new_tree` with the
@@ -477,7 +596,7 @@ fn merge_trees(merge: &Merge<Tree>) -> BackendResult<Merge<Tree>> {
             for (basename, path_conflict) in &mut conflicts {
                 new_tree.set_or_remove(basename, path_conflict.next().unwrap());
             }
-            let tree = store.write_tree(dir, new_tree.clone()).block_on()?;
+            let tree = store.write_tree(dir, new_tree.clone()).await?;
             new_trees.push(tree);
         }
         Ok(Merge::from_vec(new_trees))
@@ -492,6 +611,7 @@ async fn merge_tree_values(
     store: &Arc<Store>,
     path: &RepoPath,
     values: &MergedTreeVal<'_>,
+    merge_drivers: Option<&MergeDrivers>,
 ) -> BackendResult<MergedTreeValue> {
     if let Some(resolved) = values.resolve_trivial() {
         return Ok(Merge::resolved(resolved.cloned()));
@@ -501,11 +621,13 @@ async fn merge_tree_values(
         // If all sides are trees or missing, merge the trees recursively, treating
         // missing trees as empty.
         let empty_tree_id = store.empty_tree_id();
-        let merged_tree = merge_trees(&trees)?;
+        // Boxed because the recursion would otherwise make the future
+        // infinitely sized.
+        let merged_tree = Box::pin(merge_trees(&trees, merge_drivers)).await?;
         Ok(merged_tree
             .map(|tree| (tree.id() != empty_tree_id).then(|| TreeValue::Tree(tree.id().clone()))))
     } else {
-        let maybe_resolved = try_resolve_file_values(store, path, values).await?;
+        let maybe_resolved = try_resolve_file_values(store, path, values, merge_drivers).await?;
         Ok(maybe_resolved.unwrap_or_else(|| values.cloned()))
     }
 }
@@ -522,7 +644,7 @@ pub async fn resolve_file_values(
         return Ok(Merge::resolved(resolved.clone()));
     }
 
-    let maybe_resolved = try_resolve_file_values(store, path, &values).await?;
+    let maybe_resolved = try_resolve_file_values(store, path, &values, None).await?;
     Ok(maybe_resolved.unwrap_or(values))
 }
 
@@ -530,6 +652,7 @@ async fn try_resolve_file_values<T: Borrow<TreeValue>>(
     store: &Arc<Store>,
     path: &RepoPath,
     values: &Merge<Option<T>>,
+    merge_drivers: Option<&MergeDrivers>,
 ) -> BackendResult<Option<MergedTreeValue>> {
     // The values may contain trees canceling each other (notably padded absent
     // trees), so we need to simplify them first.
@@ -538,7 +661,9 @@ async fn try_resolve_file_values<T: Borrow<TreeValue>>(
         .simplify();
     // No fast path for simplified.is_resolved(). If it could be resolved, it would
     // have been caught by values.resolve_trivial() above.
-    if let Some(resolved) = try_resolve_file_conflict(store, path, &simplified).await? {
+    if let Some(resolved) =
+        try_resolve_file_conflict(store, path, &simplified, merge_drivers).await?
+    {
         Ok(Some(Merge::normal(resolved)))
     } else {
         // Failed to merge the files, or the paths are not files
@@ -0,0 +1,64 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runs user-configured commands on a file, such as content filters and merge
+//! drivers.
+
+use std::io;
+use std::io::Write as _;
+use std::process::Command;
+use std::process::Output;
+use std::process::Stdio;
+
+use crate::repo_path::RepoPath;
+
+/// Builds the command from the program and arguments in `command_args`.
+///
+/// The arguments are first passed to `substitute` to replace caller-specific
+/// variables, then `$path` is substituted with the repo-relative `path`. The
+/// `command_args` must not be empty.
+pub(crate) fn command_for_path(
+    command_args: &[String],
+    path: &RepoPath,
+    substitute: impl Fn(&str) -> String,
+) -> Command {
+    let (program, args) = command_args.split_first().unwrap();
+    let path_str = path.as_internal_file_string();
+    let mut command = Command::new(program);
+    command.args(
+        args.iter()
+            .map(|arg| substitute(arg).replace("$path", path_str)),
+    );
+    command
+}
+
+/// Runs the `command` with `input` written to its stdin, and captures its
+/// stdout and stderr.
+pub(crate) fn run_with_input(command: &mut Command, input: &[u8]) -> io::Result<Output> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    std::thread::scope(|s| {
+        // Write from another thread so the command can't block on a full
+        // stdout pipe.
+        let writer = s.spawn(move || stdin.write_all(input));
+        let output = child.wait_with_output();
+        // The command may exit without reading all of its input.
+        writer.join().unwrap().ok();
+        output
+    })
+}
@@ -33,8 +33,15 @@ pub struct FileStateEntry {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SparsePatterns {
+    /// Path prefixes. Only set if all patterns are plain prefixes, so older
+    /// versions that don't understand `filesets` see an empty sparse checkout
+    /// instead of the wrong one.
     #[prost(string, repeated, tag = "1")]
     pub prefixes: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Fileset expressions relative to the workspace root. Takes precedence over
+    /// `prefixes` if set.
+    #[prost(string, repeated, tag = "2")]
+    pub filesets: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -53,6 +60,9 @@ pub struct TreeState {
     pub sparse_patterns: ::core::option::Option<SparsePatterns>,
     #[prost(message, optional, tag = "4")]
     pub watchman_clock: ::core::option::Option<WatchmanClock>,
+    /// Token returned by the built-in filesystem monitor.
+    #[prost(string, tag = "7")]
+    pub fsmonitor_token: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -42,12 +42,15 @@ use crate::commit::Commit;
 use crate::commit::CommitByCommitterTimestamp;
 use crate::commit_builder::CommitBuilder;
 use crate::commit_builder::DetachedCommitBuilder;
+use crate::config::ConfigGetError;
+use crate::copies::CopyRecords;
 use crate::dag_walk;
 use crate::default_index::DefaultIndexStore;
 use crate::default_index::DefaultMutableIndex;
 use crate::default_submodule_store::DefaultSubmoduleStore;
 use crate::file_util::IoResultExt as _;
 use crate::file_util::PathError;
+use crate::files::MergeOptions;
 use crate::index::ChangeIdIndex;
 use crate::index::Index;
 use crate::index::IndexReadError;
@@ -56,6 +59,8 @@ use crate::index::MutableIndex;
 use crate::index::ReadonlyIndex;
 use crate::local_backend::LocalBackend;
 use crate::merge::MergeBuilder;
+use crate::merge_driver::MergeDriverConfigError;
+use crate::merge_driver::MergeDrivers;
 use crate::object_id::HexPrefix;
 use crate::object_id::ObjectId;
 use crate::object_id::PrefixResolution;
@@ -153,6 +158,10 @@ pub enum RepoInitError {
     OpHeadsStore(#[from] OpHeadsStoreError),
     #[error(transparent)]
     Path(#[from] PathError),
+    #[error(transparent)]
+    MergeDrivers(#[from] MergeDriverConfigError),
+    #[error(transparent)]
+    MergeOptions(ConfigGetError),
 }
 
 impl ReadonlyRepo {
@@ -193,7 +202,10 @@ impl ReadonlyRepo {
         let backend = backend_initializer(settings, &store_path)?;
         let backend_path = store_path.join("type");
         fs::write(&backend_path, backend.name()).context(&backend_path)?;
-        let store = Store::new(backend, signer);
+        let merge_drivers = MergeDrivers::from_settings(settings)?;
+        let merge_options =
+            MergeOptions::from_settings(settings).map_err(RepoInitError::MergeOptions)?;
+        let store = Store::new(backend, signer, merge_drivers, merge_options);
 
         let op_store_path = repo_path.join("op_store");
         fs::create_dir(&op_store_path).context(&op_store_path)?;
@@ -458,6 +470,10 @@ pub enum StoreLoadError {
     Backend(#[from] BackendLoadError),
     #[error(transparent)]
     Signing(#[from] SignInitError),
+    #[error(transparent)]
+    MergeDrivers(#[from] MergeDriverConfigError),
+    #[error(transparent)]
+    MergeOptions(ConfigGetError),
 }
 
 impl StoreFactories {
@@ -659,6 +675,8 @@ impl RepoLoader {
         let store = Store::new(
             store_factories.load_backend(settings, &repo_path.join("store"))?,
             Signer::from_settings(settings)?,
+            MergeDrivers::from_settings(settings)?,
+            MergeOptions::from_settings(settings).map_err(StoreLoadError::MergeOptions)?,
         );
         let root_op_data = RootOperationData {
             root_commit_id: store.root_commit_id().clone(),
@@ -846,6 +864,9 @@ pub struct MutableRepo {
     //   commits. However, if the type is `Abandoned`, a new working-copy commit should be created
     //   on top of all of the new commits instead.
     parent_mapping: HashMap<CommitId, Rewrite>,
+    // Copy records between the old and new parents of rebased commits, keyed
+    // by (old parent, new parent).
+    copy_records_cache: HashMap<(CommitId, CommitId), Arc<CopyRecords>>,
 }
 
 impl MutableRepo {
@@ -861,6 +882,7 @@ impl MutableRepo {
             index: mut_index,
             view: DirtyCell::with_clean(mut_view),
             parent_mapping: Default::default(),
+            copy_records_cache: Default::default(),
         }
     }
 
@@ -868,6 +890,15 @@ impl MutableRepo {
         &self.base_repo
     }
 
+    /// Copy records between old and new parents of rebased commits. They are
+    /// cached so that rebasing many commits from one parent to another
+    /// computes them only once.
+    pub(crate) fn copy_records_cache_mut(
+        &mut self,
+    ) -> &mut HashMap<(CommitId, CommitId), Arc<CopyRecords>> {
+        &mut self.copy_records_cache
+    }
+
     fn view_mut(&mut self) -> &mut View {
         self.view.get_mut()
     }
@@ -14,6 +14,7 @@
 
 #![allow(missing_docs)]
 
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::sync::Arc;
@@ -32,6 +33,7 @@ use crate::backend::MergedTreeId;
 use crate::commit::Commit;
 use crate::commit::CommitIteratorExt;
 use crate::commit_builder::CommitBuilder;
+use crate::copies::CopyRecords;
 use crate::dag_walk;
 use crate::index::Index;
 use crate::index::IndexError;
@@ -118,6 +120,51 @@ pub fn restore_tree(
     }
 }
 
+/// Applies the changes between `old_base` and `commit` to `new_base`, where
+/// the bases are the parents of `commit` and their merged tree.
+///
+/// If that results in conflicts and `commit` is moved from a single parent to
+/// a single parent, the merge is retried following the renames from the old
+/// parent to the new parent, and from the old parent to `commit`. This carries
+/// changes to a file over to where it was renamed on the other side. The
+/// renames between the parents are cached in `mut_repo`.
+fn merge_rebased_tree(
+    mut_repo: &mut MutableRepo,
+    commit: &Commit,
+    (old_parents, old_base_tree): (&[Commit], &MergedTree),
+    (new_parents, new_base_tree): (&[Commit], &MergedTree),
+) -> BackendResult<MergedTree> {
+    let old_tree = commit.tree()?;
+    let new_tree = new_base_tree.merge_with_drivers(old_base_tree, &old_tree)?;
+    let ([old_parent], [new_parent]) = (old_parents, new_parents) else {
+        return Ok(new_tree);
+    };
+    if !new_tree.has_conflict() || old_parent.id() == new_parent.id() {
+        return Ok(new_tree);
+    }
+    let store = mut_repo.store().clone();
+    let key = (old_parent.id().clone(), new_parent.id().clone());
+    let new_parent_copies = match mut_repo.copy_records_cache_mut().entry(key) {
+        Entry::Occupied(entry) => entry.get().clone(),
+        Entry::Vacant(entry) => {
+            let copies = get_copy_records(&store, old_parent.id(), new_parent.id())?;
+            entry.insert(Arc::new(copies)).clone()
+        }
+    };
+    let commit_copies = get_copy_records(&store, old_parent.id(), commit.id())?;
+    if new_parent_copies.iter().next().is_none() && commit_copies.iter().next().is_none() {
+        return Ok(new_tree);
+    }
+    new_base_tree.merge_with_copies(old_base_tree, &old_tree, &new_parent_copies, &commit_copies)
+}
+
+fn get_copy_records(store: &Store, root: &CommitId, head: &CommitId) -> BackendResult<CopyRecords> {
+    let stream = store.get_copy_records(None, root, head)?;
+    let mut copy_records = CopyRecords::default();
+    copy_records.add_records(stream.collect::<Vec<_>>().block_on())?;
+    Ok(copy_records)
+}
+
 pub fn rebase_commit(
     mut_repo: &mut MutableRepo,
     old_commit: Commit,
@@ -251,10 +298,15 @@ impl<'repo> CommitRewriter<'repo> {
         } else {
             let old_base_tree = merge_commit_trees(self.mut_repo, &old_parents)?;
             let new_base_tree = merge_commit_trees(self.mut_repo, &new_parents)?;
-            let old_tree = self.old_commit.tree()?;
+            let new_tree = merge_rebased_tree(
+                self.mut_repo,
+                &self.old_commit,
+                (&old_parents, &old_base_tree),
+                (&new_parents, &new_base_tree),
+            )?;
             (
                 old_base_tree.id() == *self.old_commit.tree_id(),
-                new_base_tree.merge(&old_base_tree, &old_tree)?.id(),
+                new_tree.id(),
             )
         };
         // Ensure we don't abandon commits with multiple parents (merge commits), even
@@ -0,0 +1,397 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Layout of the "segmented" tree state file of the local working copy.
+//!
+//! The file consists of a header followed by segments. The first segment
+//! contains all file states, and each following segment contains the file
+//! states changed since the previous one. Saving a tree state thus appends a
+//! (usually small) segment instead of rewriting the whole file. Segments which
+//! weren't written completely are ignored.
+//!
+//! All integers are little-endian, and all tables are 8-byte aligned so they
+//! can be used directly from the buffer the file is read into.
+//!
+//! ```text
+//! header:  magic "JJTS" | version: u32
+//! segment: kind: u32 | reserved: u32 | body length: u64 | checksum: u64 | body
+//! body:    metadata length: u64 | record count: u64 | paths length: u64
+//!          | metadata (padded) | records | paths (padded)
+//! record:  path offset: u64 | path length: u32 | file type: u8 | flags: u8
+//!          | reserved: u16 | mtime: i64 | size: u64
+//!          | conflict marker length: u32 | reserved: u32
+//! ```
+//!
+//! The metadata is an encoded `TreeState` proto without file states. The
+//! records are sorted by path, and point into the paths table.
+
+#![allow(missing_docs)]
+
+use std::ops::Range;
+
+use blake2::Blake2b512;
+use digest::Digest as _;
+use thiserror::Error;
+
+use crate::backend::MillisSinceEpoch;
+use crate::local_working_copy::FileState;
+use crate::local_working_copy::FileType;
+use crate::local_working_copy::MaterializedConflictData;
+
+const MAGIC: &[u8; 4] = b"JJTS";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 8;
+const SEGMENT_HEADER_LEN: usize = 24;
+const BODY_HEADER_LEN: usize = 24;
+
+/// Length of an encoded file state.
+pub(crate) const FILE_STATE_RECORD_LEN: usize = 40;
+
+// Same values as in the `FileType` proto.
+const FILE_TYPE_NORMAL: u8 = 0;
+const FILE_TYPE_SYMLINK: u8 = 1;
+const FILE_TYPE_EXECUTABLE: u8 = 2;
+const FILE_TYPE_GIT_SUBMODULE: u8 = 4;
+/// Marks a file state removed since the previous segment.
+const FILE_TYPE_DELETED: u8 = 0xff;
+
+const FLAG_MATERIALIZED_CONFLICT_DATA: u8 = 1;
+
+/// Error occurred while decoding a segmented tree state file.
+#[derive(Debug, Error)]
+pub enum SegmentedTreeStateError {
+    #[error("Unsupported tree state file format")]
+    UnsupportedFormat,
+    #[error("No complete segment in tree state file")]
+    NoSegment,
+    #[error("Invalid segment at offset {0}")]
+    InvalidSegment(usize),
+    #[error("Unsorted or invalid file states")]
+    InvalidFileStates,
+    #[error("Invalid metadata in segment at offset {offset}")]
+    InvalidMetadata {
+        offset: usize,
+        source: prost::DecodeError,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum SegmentKind {
+    /// Contains all file states.
+    Full = 1,
+    /// Contains the file states changed since the previous segment.
+    Delta = 2,
+}
+
+/// File states of a segment.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SegmentFileStates<'a> {
+    pub records: &'a [u8],
+    pub paths: &'a str,
+}
+
+/// Contents of a segmented tree state file.
+#[derive(Debug)]
+pub(crate) struct DecodedTreeState<'a> {
+    pub metadata: crate::protos::working_copy::TreeState,
+    pub base: SegmentFileStates<'a>,
+    pub deltas: Vec<SegmentFileStates<'a>>,
+    /// Length of the file up to the end of the full segment.
+    pub base_len: usize,
+    /// Length of the file up to the end of the last complete segment.
+    pub len: usize,
+}
+
+pub(crate) fn encode_header(out: &mut Vec<u8>) {
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+}
+
+/// Appends a segment to `out`. `records` must point into `paths`.
+pub(crate) fn encode_segment(
+    out: &mut Vec<u8>,
+    kind: SegmentKind,
+    metadata: &[u8],
+    records: &[u8],
+    paths: &str,
+) {
+    debug_assert_eq!(out.len() % 8, 0);
+    let mut body = Vec::with_capacity(
+        BODY_HEADER_LEN + padded_len(metadata.len()) + records.len() + padded_len(paths.len()),
+    );
+    body.extend_from_slice(&(metadata.len() as u64).to_le_bytes());
+    body.extend_from_slice(&((records.len() / FILE_STATE_RECORD_LEN) as u64).to_le_bytes());
+    body.extend_from_slice(&(paths.len() as u64).to_le_bytes());
+    body.extend_from_slice(metadata);
+    body.resize(BODY_HEADER_LEN + padded_len(metadata.len()), 0);
+    body.extend_from_slice(records);
+    body.extend_from_slice(paths.as_bytes());
+    body.resize(padded_len(body.len()), 0);
+
+    out.extend_from_slice(&(kind as u32).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&(body.len() as u64).to_le_bytes());
+    out.extend_from_slice(&checksum(&body).to_le_bytes());
+    out.extend_from_slice(&body);
+}
+
+/// Decodes the complete segments in `buf`.
+pub(crate) fn decode(buf: &[u8]) -> Result<DecodedTreeState<'_>, SegmentedTreeStateError> {
+    if buf.get(..4) != Some(MAGIC) || read_u32(buf, 4) != Some(VERSION) {
+        return Err(SegmentedTreeStateError::UnsupportedFormat);
+    }
+    let mut offset = HEADER_LEN;
+    let mut metadata = None;
+    let mut base = None;
+    let mut base_len = 0;
+    let mut deltas = vec![];
+    // Stop at the first incomplete segment, which was probably interrupted
+    // while being written.
+    while let Some((kind, body)) = split_segment(buf, offset) {
+        let invalid = || SegmentedTreeStateError::InvalidSegment(offset);
+        let (segment_metadata, file_states) = decode_segment_body(body).ok_or_else(invalid)?;
+        match kind {
+            k if k == SegmentKind::Full as u32 => {
+                base = Some(file_states);
+                base_len = offset + SEGMENT_HEADER_LEN + body.len();
+                deltas.clear();
+            }
+            k if k == SegmentKind::Delta as u32 && base.is_some() => {
+                deltas.push(file_states);
+            }
+            _ => return Err(invalid()),
+        }
+        metadata = Some((offset, segment_metadata));
+        offset += SEGMENT_HEADER_LEN + body.len();
+    }
+    let (Some(base), Some((metadata_offset, metadata))) = (base, metadata) else {
+        return Err(SegmentedTreeStateError::NoSegment);
+    };
+    let metadata = prost::Message::decode(metadata).map_err(|source| {
+        SegmentedTreeStateError::InvalidMetadata {
+            offset: metadata_offset,
+            source,
+        }
+    })?;
+    Ok(DecodedTreeState {
+        metadata,
+        base,
+        deltas,
+        base_len,
+        len: offset,
+    })
+}
+
+fn split_segment(buf: &[u8], offset: usize) -> Option<(u32, &[u8])> {
+    let kind = read_u32(buf, offset)?;
+    let body_len = usize::try_from(read_u64(buf, offset + 8)?).ok()?;
+    let expected_checksum = read_u64(buf, offset + 16)?;
+    let body_start = offset + SEGMENT_HEADER_LEN;
+    let body = buf.get(body_start..body_start.checked_add(body_len)?)?;
+    (checksum(body) == expected_checksum).then_some((kind, body))
+}
+
+fn decode_segment_body(body: &[u8]) -> Option<(&[u8], SegmentFileStates<'_>)> {
+    let metadata_len = usize::try_from(read_u64(body, 0)?).ok()?;
+    let num_records = usize::try_from(read_u64(body, 8)?).ok()?;
+    let paths_len = usize::try_from(read_u64(body, 16)?).ok()?;
+    let metadata_start = BODY_HEADER_LEN;
+    let records_start = metadata_start.checked_add(padded_len(metadata_len))?;
+    let paths_start = records_start.checked_add(num_records.checked_mul(FILE_STATE_RECORD_LEN)?)?;
+    let metadata = body.get(metadata_start..metadata_start.checked_add(metadata_len)?)?;
+    let records = body.get(records_start..paths_start)?;
+    let paths = body.get(paths_start..paths_start.checked_add(paths_len)?)?;
+    let paths = std::str::from_utf8(paths).ok()?;
+    validate_file_state_records(records, paths)
+        .then_some((metadata, SegmentFileStates { records, paths }))
+}
+
+/// Appends a record for the file at `path` in the paths table. If `state` is
+/// `None`, the record marks the file as deleted.
+pub(crate) fn encode_file_state_record(
+    records: &mut Vec<u8>,
+    path: Range<usize>,
+    state: Option<&FileState>,
+) {
+    let (file_type, flags, mtime, size, conflict_marker_len) = match state {
+        Some(state) => {
+            let file_type = match state.file_type {
+                #[cfg(unix)]
+                FileType::Normal { executable: false } => FILE_TYPE_NORMAL,
+                #[cfg(unix)]
+                FileType::Normal { executable: true } => FILE_TYPE_EXECUTABLE,
+                #[cfg(windows)]
+                FileType::Normal { executable: () } => FILE_TYPE_NORMAL,
+                FileType::Symlink => FILE_TYPE_SYMLINK,
+                FileType::GitSubmodule => FILE_TYPE_GIT_SUBMODULE,
+            };
+            let (flags, conflict_marker_len) = match state.materialized_conflict_data {
+                Some(data) => (FLAG_MATERIALIZED_CONFLICT_DATA, data.conflict_marker_len),
+                None => (0, 0),
+            };
+            (
+                file_type,
+                flags,
+                state.mtime.0,
+                state.size,
+                conflict_marker_len,
+            )
+        }
+        None => (FILE_TYPE_DELETED, 0, 0, 0, 0),
+    };
+    records.extend_from_slice(&(path.start as u64).to_le_bytes());
+    records.extend_from_slice(&u32::try_from(path.len()).unwrap().to_le_bytes());
+    records.extend_from_slice(&[file_type, flags, 0, 0]);
+    records.extend_from_slice(&mtime.to_le_bytes());
+    records.extend_from_slice(&size.to_le_bytes());
+    records.extend_from_slice(&conflict_marker_len.to_le_bytes());
+    records.extend_from_slice(&0u32.to_le_bytes());
+}
+
+/// Returns the range of the path of the `record` in the paths table.
+pub(crate) fn decode_file_state_record_path(record: &[u8]) -> Range<usize> {
+    let start = read_u64(record, 0).unwrap() as usize;
+    let len = read_u32(record, 8).unwrap() as usize;
+    start..start + len
+}
+
+/// Decodes the file state of the `record`, or returns `None` if the record
+/// marks the file as deleted.
+pub(crate) fn decode_file_state_record(record: &[u8]) -> Option<FileState> {
+    let file_type = match record[12] {
+        FILE_TYPE_NORMAL => FileType::Normal {
+            executable: Default::default(),
+        },
+        #[cfg(unix)]
+        FILE_TYPE_EXECUTABLE => FileType::Normal { executable: true },
+        #[cfg(windows)]
+        FILE_TYPE_EXECUTABLE => FileType::Normal { executable: () },
+        FILE_TYPE_SYMLINK => FileType::Symlink,
+        FILE_TYPE_GIT_SUBMODULE => FileType::GitSubmodule,
+        _ => return None,
+    };
+    let materialized_conflict_data =
+        (record[13] & FLAG_MATERIALIZED_CONFLICT_DATA != 0).then(|| MaterializedConflictData {
+            conflict_marker_len: read_u32(record, 32).unwrap(),
+        });
+    Some(FileState {
+        file_type,
+        mtime: MillisSinceEpoch(read_u64(record, 16).unwrap() as i64),
+        size: read_u64(record, 24).unwrap(),
+        materialized_conflict_data,
+    })
+}
+
+/// Checks that the `records` are well-formed and point into `paths`.
+fn validate_file_state_records(records: &[u8], paths: &str) -> bool {
+    records.len() % FILE_STATE_RECORD_LEN == 0
+        && records.chunks_exact(FILE_STATE_RECORD_LEN).all(|record| {
+            let start = read_u64(record, 0).unwrap();
+            let len = read_u32(record, 8).unwrap();
+            let file_type_known = matches!(
+                record[12],
+                FILE_TYPE_NORMAL
+                    | FILE_TYPE_SYMLINK
+                    | FILE_TYPE_EXECUTABLE
+                    | FILE_TYPE_GIT_SUBMODULE
+                    | FILE_TYPE_DELETED
+            );
+            let path = usize::try_from(start)
+                .ok()
+                .and_then(|start| paths.get(start..start.checked_add(len as usize)?));
+            file_type_known && path.is_some()
+        })
+}
+
+fn checksum(data: &[u8]) -> u64 {
+    let hash = Blake2b512::digest(data);
+    u64::from_le_bytes(hash[..8].try_into().unwrap())
+}
+
+fn padded_len(len: usize) -> usize {
+    len.next_multiple_of(8)
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> Option<u32> {
+    let bytes = buf.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> Option<u64> {
+    let bytes = buf.get(offset..offset.checked_add(8)?)?;
+    Some(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_state(size: u64) -> FileState {
+        FileState {
+            file_type: FileType::Symlink,
+            mtime: MillisSinceEpoch(-1),
+            size,
+            materialized_conflict_data: Some(MaterializedConflictData {
+                conflict_marker_len: 7,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_encode_decode_segments() {
+        let mut records = vec![];
+        encode_file_state_record(&mut records, 0..1, Some(&new_state(1)));
+        encode_file_state_record(&mut records, 1..4, None);
+        let paths = "ab/c";
+
+        let mut buf = vec![];
+        encode_header(&mut buf);
+        encode_segment(&mut buf, SegmentKind::Full, b"", &records[..40], "a");
+        let full_len = buf.len();
+        encode_segment(&mut buf, SegmentKind::Delta, b"", &records, paths);
+        assert_eq!(buf.len() % 8, 0);
+
+        let decoded = decode(&buf).unwrap();
+        assert_eq!(decoded.len, buf.len());
+        assert_eq!(decoded.base.paths, "a");
+        assert_eq!(decoded.deltas.len(), 1);
+        let delta = decoded.deltas[0];
+        let mut delta_records = delta.records.chunks_exact(FILE_STATE_RECORD_LEN);
+        let record = delta_records.next().unwrap();
+        assert_eq!(&delta.paths[decode_file_state_record_path(record)], "a");
+        assert_eq!(decode_file_state_record(record), Some(new_state(1)));
+        let record = delta_records.next().unwrap();
+        assert_eq!(&delta.paths[decode_file_state_record_path(record)], "b/c");
+        assert_eq!(decode_file_state_record(record), None);
+
+        // Incomplete or corrupt segment is ignored
+        let decoded = decode(&buf[..buf.len() - 1]).unwrap();
+        assert_eq!(decoded.len, full_len);
+        assert!(decoded.deltas.is_empty());
+        let mut corrupt_buf = buf.clone();
+        *corrupt_buf.last_mut().unwrap() ^= 1;
+        let decoded = decode(&corrupt_buf).unwrap();
+        assert_eq!(decoded.len, full_len);
+
+        // A full segment is required
+        assert!(matches!(
+            decode(&buf[..HEADER_LEN]),
+            Err(SegmentedTreeStateError::NoSegment)
+        ));
+        assert!(matches!(
+            decode(b"JJTS\0\0\0\0"),
+            Err(SegmentedTreeStateError::UnsupportedFormat)
+        ));
+    }
+}
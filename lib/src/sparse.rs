@@ -0,0 +1,259 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sparse checkout patterns.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::path::PathBuf;
+
+use itertools::Either;
+use itertools::Itertools as _;
+
+use crate::dsl_util;
+use crate::fileset;
+use crate::fileset::FilePattern;
+use crate::fileset::FilesetDiagnostics;
+use crate::fileset::FilesetExpression;
+use crate::fileset::FilesetParseError;
+use crate::matchers::Matcher;
+use crate::repo_path::RepoPath;
+use crate::repo_path::RepoPathBuf;
+use crate::repo_path::RepoPathUiConverter;
+
+/// Fileset expression that selects paths to be checked out in the working
+/// copy.
+///
+/// Paths in the expression are relative to the workspace root. A pattern of
+/// the form `~x` is an exclusion: paths matching `x` are removed from the
+/// union of all other patterns, regardless of the order of the patterns.
+#[derive(Clone, Debug)]
+pub struct SparsePattern {
+    text: String,
+    expression: FilesetExpression,
+}
+
+impl SparsePattern {
+    /// Parses `text` as fileset expression relative to the workspace root.
+    ///
+    /// Plain path prefixes are normalized so that e.g. `./foo` and `root:foo`
+    /// are stored as `foo`.
+    pub fn parse(text: &str) -> Result<Self, FilesetParseError> {
+        match parse_expression(text)? {
+            FilesetExpression::Pattern(FilePattern::PrefixPath(path)) => {
+                Ok(SparsePattern::from_prefix(path))
+            }
+            expression => Ok(SparsePattern {
+                text: text.to_owned(),
+                expression,
+            }),
+        }
+    }
+
+    /// Pattern that matches all paths under `path` recursively.
+    pub fn from_prefix(path: RepoPathBuf) -> Self {
+        let bare_text = if path.is_root() {
+            ".".to_owned()
+        } else {
+            path.as_internal_file_string().to_owned()
+        };
+        // Prefer the plain path form if it is parsed back to the same prefix.
+        let text = match parse_expression(&bare_text) {
+            Ok(FilesetExpression::Pattern(FilePattern::PrefixPath(parsed))) if parsed == path => {
+                bare_text
+            }
+            _ => format!(
+                r#"root:"{}""#,
+                dsl_util::escape_string(path.as_internal_file_string())
+            ),
+        };
+        SparsePattern {
+            text,
+            expression: FilesetExpression::prefix_path(path),
+        }
+    }
+
+    /// Pattern that matches all paths.
+    pub fn root() -> Self {
+        SparsePattern::from_prefix(RepoPathBuf::root())
+    }
+
+    /// Source text of this pattern.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Parsed expression of this pattern.
+    pub fn expression(&self) -> &FilesetExpression {
+        &self.expression
+    }
+
+    /// Returns the path if this pattern is a plain path prefix, which can be
+    /// represented in the legacy working-copy state format.
+    pub fn as_prefix(&self) -> Option<&RepoPath> {
+        match &self.expression {
+            FilesetExpression::Pattern(FilePattern::PrefixPath(path)) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns the excluded expression if this pattern is of the form `~x`.
+    pub fn as_exclusion(&self) -> Option<&FilesetExpression> {
+        match &self.expression {
+            FilesetExpression::Difference(all, excluded)
+                if matches!(**all, FilesetExpression::All) =>
+            {
+                Some(excluded)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SparsePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+// Patterns are identified by their source text. Equivalent expressions written
+// differently are considered different patterns.
+
+impl Eq for SparsePattern {}
+
+impl PartialEq for SparsePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Ord for SparsePattern {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.text.cmp(&other.text)
+    }
+}
+
+impl PartialOrd for SparsePattern {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for SparsePattern {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.text.hash(state);
+    }
+}
+
+/// Builds a matcher for the paths selected by the sparse `patterns`.
+pub fn sparse_matcher(patterns: &[SparsePattern]) -> Box<dyn Matcher> {
+    let (included, excluded): (Vec<_>, Vec<_>) =
+        patterns
+            .iter()
+            .partition_map(|pattern| match pattern.as_exclusion() {
+                Some(excluded) => Either::Right(excluded.clone()),
+                None => Either::Left(pattern.expression.clone()),
+            });
+    let expression = if excluded.is_empty() {
+        FilesetExpression::union_all(included)
+    } else {
+        FilesetExpression::union_all(included).difference(FilesetExpression::union_all(excluded))
+    };
+    expression.to_matcher()
+}
+
+fn parse_expression(text: &str) -> Result<FilesetExpression, FilesetParseError> {
+    // Both paths are empty so that patterns are resolved relative to the
+    // workspace root.
+    let path_converter = RepoPathUiConverter::Fs {
+        cwd: PathBuf::new(),
+        base: PathBuf::new(),
+    };
+    fileset::parse_maybe_bare(&mut FilesetDiagnostics::new(), text, &path_converter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo_path(value: &str) -> &RepoPath {
+        RepoPath::from_internal_string(value)
+    }
+
+    fn parse(text: &str) -> SparsePattern {
+        SparsePattern::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(".").as_prefix(), Some(RepoPath::root()));
+        assert_eq!(parse("foo/bar").as_prefix(), Some(repo_path("foo/bar")));
+        assert_eq!(parse("root:foo").as_prefix(), Some(repo_path("foo")));
+        assert_eq!(parse("root:foo").as_str(), "foo");
+        assert_eq!(parse("./foo/").as_str(), "foo");
+        assert_eq!(parse("root:''").as_str(), ".");
+        assert_eq!(parse("glob:*.rs").as_str(), "glob:*.rs");
+        assert_eq!(parse("glob:*.rs").as_prefix(), None);
+        assert!(parse("~foo").as_exclusion().is_some());
+        assert!(parse("foo").as_exclusion().is_none());
+        assert!(parse("foo ~ bar").as_exclusion().is_none());
+        assert!(SparsePattern::parse("..").is_err());
+        assert!(SparsePattern::parse("foo(").is_err());
+    }
+
+    #[test]
+    fn test_from_prefix() {
+        let pattern = SparsePattern::from_prefix(RepoPathBuf::root());
+        assert_eq!(pattern.as_str(), ".");
+        assert_eq!(pattern, SparsePattern::root());
+
+        let pattern = SparsePattern::from_prefix(repo_path("foo/bar").to_owned());
+        assert_eq!(pattern.as_str(), "foo/bar");
+
+        // Paths that can't be written as bare strings are quoted
+        for path in ["all()", "a\"b", "x|y", "~foo"] {
+            let pattern = SparsePattern::from_prefix(repo_path(path).to_owned());
+            assert!(pattern.as_str().starts_with("root:"), "{pattern}");
+            assert_eq!(parse(pattern.as_str()).as_prefix(), Some(repo_path(path)));
+        }
+    }
+
+    #[test]
+    fn test_sparse_matcher() {
+        let matcher = sparse_matcher(&[]);
+        assert!(!matcher.matches(repo_path("foo")));
+
+        let matcher = sparse_matcher(&[parse("src"), parse("README")]);
+        assert!(matcher.matches(repo_path("src/lib.rs")));
+        assert!(matcher.matches(repo_path("README")));
+        assert!(!matcher.matches(repo_path("docs/index.md")));
+
+        // Exclusions apply regardless of their order
+        let matcher = sparse_matcher(&[
+            parse("~src/testdata"),
+            parse("src"),
+            parse("glob:docs/*.md"),
+        ]);
+        assert!(matcher.matches(repo_path("src/lib.rs")));
+        assert!(!matcher.matches(repo_path("src/testdata/a")));
+        assert!(matcher.matches(repo_path("docs/index.md")));
+        assert!(!matcher.matches(repo_path("docs/sub/index.md")));
+
+        // Exclusions alone select nothing
+        let matcher = sparse_matcher(&[parse("~src")]);
+        assert!(!matcher.matches(repo_path("foo")));
+    }
+}
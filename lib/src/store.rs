@@ -39,9 +39,11 @@ use crate::backend::SigningFn;
 use crate::backend::SymlinkId;
 use crate::backend::TreeId;
 use crate::commit::Commit;
+use crate::files::MergeOptions;
 use crate::index::Index;
 use crate::merge::Merge;
 use crate::merge::MergedTreeValue;
+use crate::merge_driver::MergeDrivers;
 use crate::merged_tree::MergedTree;
 use crate::repo_path::RepoPath;
 use crate::repo_path::RepoPathBuf;
@@ -59,6 +61,8 @@ const TREE_CACHE_CAPACITY: usize = 1000;
 pub struct Store {
     backend: Box<dyn Backend>,
     signer: Signer,
+    merge_drivers: MergeDrivers,
+    merge_options: MergeOptions,
     commit_cache: Mutex<CLruCache<CommitId, Arc<backend::Commit>>>,
     tree_cache: Mutex<CLruCache<(RepoPathBuf, TreeId), Arc<backend::Tree>>>,
 }
@@ -72,10 +76,17 @@ impl Debug for Store {
 }
 
 impl Store {
-    pub fn new(backend: Box<dyn Backend>, signer: Signer) -> Arc<Self> {
+    pub fn new(
+        backend: Box<dyn Backend>,
+        signer: Signer,
+        merge_drivers: MergeDrivers,
+        merge_options: MergeOptions,
+    ) -> Arc<Self> {
         Arc::new(Store {
             backend,
             signer,
+            merge_drivers,
+            merge_options,
             commit_cache: Mutex::new(CLruCache::new(COMMIT_CACHE_CAPACITY.try_into().unwrap())),
             tree_cache: Mutex::new(CLruCache::new(TREE_CACHE_CAPACITY.try_into().unwrap())),
         })
@@ -89,6 +100,16 @@ impl Store {
         &self.signer
     }
 
+    /// Merge drivers used when resolving file conflicts.
+    pub fn merge_drivers(&self) -> &MergeDrivers {
+        &self.merge_drivers
+    }
+
+    /// Options used when merging file contents.
+    pub fn merge_options(&self) -> &MergeOptions {
+        &self.merge_options
+    }
+
     pub fn get_copy_records(
         &self,
         paths: Option<&[RepoPathBuf]>,
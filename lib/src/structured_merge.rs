@@ -0,0 +1,1024 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Merges structured data files like JSON, TOML, and YAML key by key.
+
+use std::hash::Hash;
+use std::str;
+
+use bstr::BString;
+use indexmap::IndexMap;
+use indexmap::IndexSet;
+use yaml_rust2::Yaml;
+use yaml_rust2::YamlLoader;
+
+use crate::files;
+use crate::files::MergeResult;
+use crate::merge::Merge;
+use crate::repo_path::RepoPath;
+
+/// Format of a structured data file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StructuredFormat {
+    /// JSON file.
+    Json,
+    /// TOML file.
+    Toml,
+    /// YAML file.
+    Yaml,
+}
+
+impl StructuredFormat {
+    /// Determines the format from the file extension of the `path`.
+    pub fn from_path(path: &RepoPath) -> Option<Self> {
+        let name = path.components().next_back()?.as_internal_str();
+        let (_, extension) = name.rsplit_once('.')?;
+        match extension.to_ascii_lowercase().as_str() {
+            "json" => Some(StructuredFormat::Json),
+            "toml" => Some(StructuredFormat::Toml),
+            "yaml" | "yml" => Some(StructuredFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    fn is_valid(self, content: &[u8]) -> bool {
+        match self {
+            StructuredFormat::Json => parse_json(content).is_some(),
+            StructuredFormat::Toml => parse_toml(content).is_some(),
+            StructuredFormat::Yaml => {
+                str::from_utf8(content).is_ok_and(|text| YamlLoader::load_from_str(text).is_ok())
+            }
+        }
+    }
+}
+
+/// Merges the file `contents` of the `path`.
+///
+/// The contents are merged line by line first. If that fails, or doesn't
+/// produce valid data, the contents are parsed and merged key by key, so
+/// changes to different keys of the same table don't conflict even if they
+/// are on adjacent lines. The merged data is written in the formatting of the
+/// first side as far as possible. Returns `None` if the path isn't a
+/// supported format, or if the same value was changed on multiple sides.
+pub fn merge<T: AsRef<[u8]>>(path: &RepoPath, contents: &Merge<T>) -> Option<BString> {
+    let format = StructuredFormat::from_path(path)?;
+    let line_merged = match files::merge(contents) {
+        MergeResult::Resolved(content) if format.is_valid(&content) => return Some(content),
+        MergeResult::Resolved(content) => Some(content),
+        MergeResult::Conflict(_) => None,
+    };
+    let contents = contents.map(|content| content.as_ref());
+    let merged = match format {
+        StructuredFormat::Json => merge_json(&contents),
+        StructuredFormat::Toml => merge_toml(&contents),
+        StructuredFormat::Yaml => merge_yaml(&contents),
+    };
+    merged.or(line_merged)
+}
+
+/// JSON value parsed from a file, keeping the original text of the value.
+struct JsonNode<'a> {
+    /// Text of the value without the surrounding whitespace.
+    text: &'a str,
+    /// Members of the value if it's an object.
+    object: Option<JsonObject<'a>>,
+}
+
+/// JSON object whose members can be reassembled in its original formatting.
+struct JsonObject<'a> {
+    /// Text from the opening brace to the first member, or to the closing
+    /// brace if the object is empty.
+    open: &'a str,
+    /// Text between the first and the second member.
+    separator: Option<&'a str>,
+    /// Text from the end of the last member to the closing brace inclusive.
+    close: &'a str,
+    members: IndexMap<String, JsonMember<'a>>,
+}
+
+struct JsonMember<'a> {
+    /// Text between the previous member and this member, if any.
+    leading: Option<&'a str>,
+    /// Text of the key up to the value, e.g. `"key": `.
+    key: &'a str,
+    value: JsonNode<'a>,
+}
+
+/// JSON document with the whitespace around the top-level value.
+struct JsonDocument<'a> {
+    prefix: &'a str,
+    root: JsonNode<'a>,
+    suffix: &'a str,
+}
+
+fn parse_json(content: &[u8]) -> Option<JsonDocument<'_>> {
+    let text = str::from_utf8(content).ok()?;
+    // Validate the syntax so that the parser below can be lenient.
+    serde_json::from_str::<serde::de::IgnoredAny>(text).ok()?;
+    let mut parser = JsonParser { text, pos: 0 };
+    parser.skip_whitespace();
+    let prefix = &text[..parser.pos];
+    let root = parser.parse_value()?;
+    Some(JsonDocument {
+        prefix,
+        root,
+        suffix: &text[parser.pos..],
+    })
+}
+
+/// Parser of syntactically valid JSON which records the text of the values.
+struct JsonParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_string(&mut self) -> Option<()> {
+        self.pos += 1;
+        loop {
+            match self.peek()? {
+                b'"' => break,
+                b'\\' => self.pos += 2,
+                _ => self.pos += 1,
+            }
+        }
+        self.pos += 1;
+        Some(())
+    }
+
+    fn parse_value(&mut self) -> Option<JsonNode<'a>> {
+        let start = self.pos;
+        let mut object = None;
+        match self.peek()? {
+            b'{' => object = Some(self.parse_object()?),
+            b'[' => {
+                self.pos += 1;
+                self.skip_whitespace();
+                while self.peek()? != b']' {
+                    self.parse_value()?;
+                    self.skip_whitespace();
+                    if self.peek()? == b',' {
+                        self.pos += 1;
+                        self.skip_whitespace();
+                    }
+                }
+                self.pos += 1;
+            }
+            b'"' => self.skip_string()?,
+            _ => {
+                // Number or literal
+                while !matches!(
+                    self.peek(),
+                    None | Some(b',' | b']' | b'}' | b' ' | b'\t' | b'\n' | b'\r')
+                ) {
+                    self.pos += 1;
+                }
+            }
+        }
+        Some(JsonNode {
+            text: &self.text[start..self.pos],
+            object,
+        })
+    }
+
+    fn parse_object(&mut self) -> Option<JsonObject<'a>> {
+        let start = self.pos;
+        self.pos += 1;
+        self.skip_whitespace();
+        if self.peek()? == b'}' {
+            self.pos += 1;
+            return Some(JsonObject {
+                open: &self.text[start..start + 1],
+                separator: None,
+                close: &self.text[start + 1..self.pos],
+                members: IndexMap::new(),
+            });
+        }
+        let open = &self.text[start..self.pos];
+        let mut separator = None;
+        let mut leading = None;
+        let mut members = IndexMap::new();
+        loop {
+            let key_start = self.pos;
+            self.skip_string()?;
+            let name: String = serde_json::from_str(&self.text[key_start..self.pos]).ok()?;
+            self.skip_whitespace();
+            self.pos += 1; // ':'
+            self.skip_whitespace();
+            let key = &self.text[key_start..self.pos];
+            let value = self.parse_value()?;
+            if members
+                .insert(
+                    name,
+                    JsonMember {
+                        leading,
+                        key,
+                        value,
+                    },
+                )
+                .is_some()
+            {
+                // Duplicate keys can't be merged unambiguously
+                return None;
+            }
+            let value_end = self.pos;
+            self.skip_whitespace();
+            if self.peek()? == b'}' {
+                self.pos += 1;
+                return Some(JsonObject {
+                    open,
+                    separator,
+                    close: &self.text[value_end..self.pos],
+                    members,
+                });
+            }
+            self.pos += 1; // ','
+            self.skip_whitespace();
+            leading = Some(&self.text[value_end..self.pos]);
+            separator.get_or_insert(leading.unwrap());
+        }
+    }
+}
+
+fn merge_json(contents: &Merge<&[u8]>) -> Option<BString> {
+    let documents = contents.maybe_map(|content| parse_json(content))?;
+    let merged = merge_json_nodes(&documents.map(|document| Some(&document.root)))??;
+    let first = documents.first();
+    Some(format!("{}{merged}{}", first.prefix, first.suffix).into())
+}
+
+/// Merges JSON values. Returns the text of the merged value, which keeps the
+/// formatting of the first side as far as possible. Returns `None` if the
+/// values conflict, or `Some(None)` if the merged value is absent.
+fn merge_json_nodes(nodes: &Merge<Option<&JsonNode>>) -> Option<Option<String>> {
+    // Values which differ only in formatting are equal
+    let values = nodes
+        .map(|node| node.map(|node| serde_json::from_str::<serde_json::Value>(node.text).unwrap()));
+    if let Some(value) = values.resolve_trivial() {
+        let index = values.iter().position(|v| v == value).unwrap();
+        return Some(nodes.as_slice()[index].map(|node| node.text.to_owned()));
+    }
+    // Only objects can be merged key by key. An object missing in the base is
+    // treated as empty.
+    let objects = nodes.maybe_map(|node| match node {
+        Some(node) => node.object.as_ref().map(Some),
+        None => Some(None),
+    })?;
+    if objects.adds().any(Option::is_none) {
+        return None;
+    }
+
+    let keys = union_keys(objects.iter().map(|object| {
+        object
+            .iter()
+            .flat_map(|object| object.members.keys().map(String::as_str))
+    }));
+    let mut merged_members = vec![];
+    for key in keys {
+        let members = objects.map(|object| object.and_then(|object| object.members.get(key)));
+        if let Some(value) = merge_json_nodes(&members.map(|member| member.map(|m| &m.value)))? {
+            let key = members.iter().flatten().next().unwrap().key;
+            let leading = members.iter().flatten().find_map(|member| member.leading);
+            merged_members.push((leading, format!("{key}{value}")));
+        }
+    }
+    if merged_members.is_empty() {
+        return Some(Some("{}".to_owned()));
+    }
+    // Lay out the members like the first side, or like another side if the
+    // first side has no members to copy the layout from. Members keep the
+    // text which preceded them if they weren't first in their object.
+    let layout = objects
+        .adds()
+        .flatten()
+        .find(|object| !object.members.is_empty())
+        .unwrap_or_else(|| objects.first().as_ref().unwrap());
+    let separator = match layout.separator {
+        Some(separator) => separator.to_owned(),
+        None => format!(",{}", &layout.open[1..]),
+    };
+    let mut merged = layout.open.to_owned();
+    for (i, (leading, member)) in merged_members.iter().enumerate() {
+        if i > 0 {
+            merged.push_str(leading.unwrap_or(&separator));
+        }
+        merged.push_str(member);
+    }
+    merged.push_str(layout.close);
+    Some(Some(merged))
+}
+
+fn parse_toml(content: &[u8]) -> Option<toml_edit::DocumentMut> {
+    str::from_utf8(content).ok()?.parse().ok()
+}
+
+fn merge_toml(contents: &Merge<&[u8]>) -> Option<BString> {
+    let documents = contents.maybe_map(|content| parse_toml(content))?;
+    let items = documents.map(|document| Some(document.as_item()));
+    let toml_edit::Item::Table(merged) = merge_toml_items(&items)?? else {
+        return None;
+    };
+    // Keep the trailing comments of the first side
+    let mut document = documents.first().clone();
+    *document = merged;
+    Some(document.to_string().into())
+}
+
+/// Merges TOML items. Returns `None` if the items conflict, or `Some(None)`
+/// if the merged item is absent.
+fn merge_toml_items(items: &Merge<Option<&toml_edit::Item>>) -> Option<Option<toml_edit::Item>> {
+    let texts = items.map(|item| item.map(toml_item_to_string));
+    if let Some(text) = texts.resolve_trivial() {
+        let index = texts.iter().position(|t| t == text).unwrap();
+        return Some(items.as_slice()[index].cloned());
+    }
+    // Only tables of the same kind can be merged key by key. A table missing in
+    // the base is treated as empty.
+    let first = (*items.first())?;
+    if items
+        .iter()
+        .flatten()
+        .any(|item| item.is_table() != first.is_table())
+    {
+        return None;
+    }
+    let tables = items.maybe_map(|item| match item {
+        Some(item) => item.as_table_like().map(Some),
+        None => Some(None),
+    })?;
+    if tables.adds().any(Option::is_none) {
+        return None;
+    }
+
+    let mut merged = first.clone();
+    let merged_table = merged.as_table_like_mut().unwrap();
+    let mut inserted = false;
+    let keys = union_keys(tables.iter().map(|table| {
+        table
+            .iter()
+            .flat_map(|table| table.iter().map(|(key, _)| key))
+    }));
+    for key in keys {
+        let key_items = tables.map(|table| table.and_then(|table| table.get(key)));
+        match merge_toml_items(&key_items)? {
+            Some(item) => {
+                if let Some(merged_item) = merged_table.get_mut(key) {
+                    // Keep the formatting of the key and the value
+                    let decor = merged_item.as_value().map(|value| value.decor().clone());
+                    *merged_item = item;
+                    if let (Some(value), Some(decor)) = (merged_item.as_value_mut(), decor) {
+                        *value.decor_mut() = decor;
+                    }
+                } else {
+                    merged_table.insert(key, item);
+                    inserted = true;
+                }
+            }
+            None => {
+                merged_table.remove(key);
+            }
+        }
+    }
+    if let (true, Some(inline_table)) = (inserted, merged.as_inline_table_mut()) {
+        // Inline tables can't be formatted like the other sides key by key
+        inline_table.fmt();
+    }
+    Some(Some(merged))
+}
+
+/// Formats the `item` including its nested tables, but without the whitespace
+/// and comments around it.
+fn toml_item_to_string(item: &toml_edit::Item) -> String {
+    let mut item = item.clone();
+    if let Some(value) = item.as_value_mut() {
+        value.decor_mut().clear();
+    }
+    let mut document = toml_edit::DocumentMut::new();
+    document.insert("_", item);
+    document.to_string()
+}
+
+/// YAML document whose top-level value is a block mapping.
+struct YamlDocument<'a> {
+    /// Text up to the document start marker (`---`) inclusive, if any.
+    prefix: &'a str,
+    root: YamlMapping<'a>,
+}
+
+/// YAML block mapping whose entries can be reassembled in its original
+/// formatting.
+struct YamlMapping<'a> {
+    entries: IndexMap<Yaml, YamlEntry<'a>>,
+    /// Comments and blank lines after the last entry.
+    close: &'a str,
+}
+
+struct YamlEntry<'a> {
+    /// Comments and blank lines before the entry.
+    leading: &'a str,
+    /// Lines of the key and the value.
+    text: &'a str,
+    value: Yaml,
+    /// Line of the key and the entries of the value if the value is a block
+    /// mapping.
+    mapping: Option<(&'a str, YamlMapping<'a>)>,
+}
+
+fn parse_yaml(content: &[u8]) -> Option<YamlDocument<'_>> {
+    let text = str::from_utf8(content).ok()?;
+    let [value] = <[Yaml; 1]>::try_from(YamlLoader::load_from_str(text).ok()?).ok()?;
+    let mut prefix_len = 0;
+    let mut pos = 0;
+    for line in text.split_inclusive('\n') {
+        pos += line.len();
+        if line.starts_with('%') || line.trim_end() == "---" {
+            prefix_len = pos;
+        } else if !is_yaml_trivia(line) {
+            break;
+        }
+    }
+    let root = parse_yaml_mapping(&text[prefix_len..], &value)?;
+    Some(YamlDocument {
+        prefix: &text[..prefix_len],
+        root,
+    })
+}
+
+/// Returns true if the `line` is blank or a comment.
+fn is_yaml_trivia(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || line.starts_with('#')
+}
+
+/// Splits the `text` of the block mapping `value` into its entries.
+///
+/// An entry starts at each line with the indentation of the mapping, and
+/// includes the more indented lines which follow it. The YAML syntax isn't
+/// parsed further. Instead, each entry is parsed on its own and compared with
+/// the `value`, so mappings which can't be split like this, for example
+/// because they contain aliases to other entries, aren't merged.
+fn parse_yaml_mapping<'a>(text: &'a str, value: &Yaml) -> Option<YamlMapping<'a>> {
+    let Yaml::Hash(hash) = value else {
+        return None;
+    };
+    let mut indent = None;
+    // Ranges of the leading text and the text of each entry
+    let mut ranges: Vec<(usize, usize, usize)> = vec![];
+    let mut trivia_start = None;
+    let mut pos = 0;
+    for line in text.split_inclusive('\n') {
+        let (start, end) = (pos, pos + line.len());
+        pos = end;
+        if is_yaml_trivia(line) {
+            trivia_start.get_or_insert(start);
+            continue;
+        }
+        let content = line.trim_start_matches(' ');
+        let line_indent = line.len() - content.len();
+        let indent = *indent.get_or_insert(line_indent);
+        let content = content.trim_end();
+        let is_sequence_item = content == "-" || content.starts_with("- ");
+        if line_indent < indent {
+            return None;
+        } else if line_indent == indent && !is_sequence_item {
+            ranges.push((trivia_start.unwrap_or(start), start, end));
+        } else {
+            // Sequence items may be indented like the key of the sequence
+            ranges.last_mut()?.2 = end;
+        }
+        trivia_start = None;
+    }
+    if ranges.len() != hash.len() {
+        return None;
+    }
+    let indent = indent?;
+    let mut entries = IndexMap::new();
+    for ((leading_start, start, end), (key, value)) in ranges.into_iter().zip(hash) {
+        let entry_text = &text[start..end];
+        let dedented: String = entry_text
+            .split_inclusive('\n')
+            .map(|line| {
+                let spaces = line.len() - line.trim_start_matches(' ').len();
+                &line[spaces.min(indent)..]
+            })
+            .collect();
+        let [Yaml::Hash(entry_hash)] = &YamlLoader::load_from_str(&dedented).ok()?[..] else {
+            return None;
+        };
+        if entry_hash.len() != 1 || entry_hash.front() != Some((key, value)) {
+            return None;
+        }
+        let head_len = entry_text.find('\n').map_or(entry_text.len(), |i| i + 1);
+        let (head, body) = entry_text.split_at(head_len);
+        let mapping = parse_yaml_mapping(body, value).map(|mapping| (head, mapping));
+        let entry = YamlEntry {
+            leading: &text[leading_start..start],
+            text: entry_text,
+            value: value.clone(),
+            mapping,
+        };
+        entries.insert(key.clone(), entry);
+    }
+    Some(YamlMapping {
+        entries,
+        close: &text[trivia_start.unwrap_or(text.len())..],
+    })
+}
+
+fn merge_yaml(contents: &Merge<&[u8]>) -> Option<BString> {
+    let documents = contents.maybe_map(|content| parse_yaml(content))?;
+    let (merged, value) = merge_yaml_mappings(&documents.map(|document| Some(&document.root)))?;
+    let merged = format!("{}{merged}", documents.first().prefix);
+    // Entries taken from different sides may not fit together, for example if
+    // the sides are indented differently.
+    let merged_values = YamlLoader::load_from_str(&merged).ok()?;
+    (merged_values == [value]).then(|| merged.into())
+}
+
+/// Merges YAML block mappings. Returns the merged text, which keeps the
+/// formatting of the first side as far as possible, and the merged value.
+/// Returns `None` if the mappings conflict, or if all entries were removed.
+fn merge_yaml_mappings(mappings: &Merge<Option<&YamlMapping>>) -> Option<(String, Yaml)> {
+    let keys = union_keys(
+        mappings
+            .iter()
+            .map(|mapping| mapping.iter().flat_map(|mapping| mapping.entries.keys())),
+    );
+    let mut merged = String::new();
+    let mut merged_hash = yaml_rust2::yaml::Hash::new();
+    for key in keys {
+        let entries = mappings.map(|mapping| mapping.and_then(|mapping| mapping.entries.get(key)));
+        if let Some((text, value)) = merge_yaml_entries(&entries)? {
+            // Keep the comments of the entry unless they were changed on
+            // multiple sides
+            let leadings = entries.map(|entry| entry.map(|entry| entry.leading));
+            let leading = leadings
+                .resolve_trivial()
+                .copied()
+                .flatten()
+                .unwrap_or_else(|| entries.iter().flatten().next().unwrap().leading);
+            push_yaml_lines(&mut merged, leading);
+            push_yaml_lines(&mut merged, &text);
+            merged_hash.insert(key.clone(), value);
+        }
+    }
+    if merged_hash.is_empty() {
+        return None;
+    }
+    let layout = mappings.adds().flatten().next()?;
+    push_yaml_lines(&mut merged, layout.close);
+    Some((merged, Yaml::Hash(merged_hash)))
+}
+
+/// Merges YAML mapping entries. Returns `None` if the entries conflict, or
+/// `Some(None)` if the merged entry is absent.
+fn merge_yaml_entries(entries: &Merge<Option<&YamlEntry>>) -> Option<Option<(String, Yaml)>> {
+    let values = entries.map(|entry| entry.map(|entry| &entry.value));
+    if let Some(value) = values.resolve_trivial() {
+        let index = values.iter().position(|v| v == value).unwrap();
+        let entry = entries.as_slice()[index];
+        return Some(entry.map(|entry| (entry.text.to_owned(), entry.value.clone())));
+    }
+    // Only block mappings can be merged key by key. A mapping missing in the
+    // base is treated as empty.
+    let mappings = entries.maybe_map(|entry| match entry {
+        Some(entry) => entry.mapping.as_ref().map(Some),
+        None => Some(None),
+    })?;
+    if mappings.adds().any(Option::is_none) {
+        return None;
+    }
+    let (head, _) = mappings.iter().flatten().next().unwrap();
+    let (body, value) =
+        merge_yaml_mappings(&mappings.map(|mapping| mapping.as_ref().map(|(_, mapping)| mapping)))?;
+    Some(Some((format!("{head}{body}"), value)))
+}
+
+/// Appends the `lines` to the `text`, starting a new line if the `text` has an
+/// unterminated last line.
+fn push_yaml_lines(text: &mut String, lines: &str) {
+    if !lines.is_empty() && !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text.push_str(lines);
+}
+
+/// Returns the keys of all tables in order of first appearance.
+fn union_keys<'a, K: Eq + Hash + ?Sized + 'a>(
+    key_lists: impl IntoIterator<Item = impl IntoIterator<Item = &'a K>>,
+) -> IndexSet<&'a K> {
+    key_lists.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    fn merge(path: &str, removes: &[&str], adds: &[&str]) -> Option<String> {
+        let contents = Merge::from_removes_adds(removes, adds);
+        let merged = super::merge(RepoPath::from_internal_string(path), &contents)?;
+        Some(String::from_utf8(merged.into()).unwrap())
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        let format = |path| StructuredFormat::from_path(RepoPath::from_internal_string(path));
+        assert_eq!(format("dir/package.json"), Some(StructuredFormat::Json));
+        assert_eq!(format("Cargo.TOML"), Some(StructuredFormat::Toml));
+        assert_eq!(format("ci.yml"), Some(StructuredFormat::Yaml));
+        assert_eq!(format("config.yaml"), Some(StructuredFormat::Yaml));
+        assert_eq!(format("json"), None);
+        assert_eq!(format("file.txt"), None);
+        assert_eq!(format(""), None);
+    }
+
+    #[test]
+    fn test_merge_json() {
+        let base = indoc! {r#"
+            {
+                "name": "foo",
+                "dependencies": {
+                    "a": "1.0"
+                }
+            }
+        "#};
+        let side1 = indoc! {r#"
+            {
+                "name": "foo",
+                "dependencies": {
+                    "a": "1.0",
+                    "b": "2.0"
+                }
+            }
+        "#};
+        let side2 = indoc! {r#"
+            {
+                "name": "foo",
+                "dependencies": {
+                    "a": "1.0",
+                    "c": "3.0"
+                }
+            }
+        "#};
+        let side3 = indoc! {r#"
+            {
+                "name": "bar",
+                "dependencies": {
+                    "a": "1.0"
+                }
+            }
+        "#};
+
+        // Keys added on adjacent lines are merged, keeping the key order and
+        // indentation
+        insta::assert_snapshot!(merge("f.json", &[base], &[side1, side2]).unwrap(), @r#"
+        {
+            "name": "foo",
+            "dependencies": {
+                "a": "1.0",
+                "b": "2.0",
+                "c": "3.0"
+            }
+        }
+        "#);
+
+        // The line merge is used if it produces valid JSON
+        let merged = merge("f.json", &[base], &[side1, side3]).unwrap();
+        assert_eq!(merged, side1.replace("foo", "bar"));
+
+        // n-way merge
+        insta::assert_snapshot!(
+            merge("f.json", &[base, base], &[side1, side2, side3]).unwrap(), @r#"
+        {
+            "name": "bar",
+            "dependencies": {
+                "a": "1.0",
+                "b": "2.0",
+                "c": "3.0"
+            }
+        }
+        "#);
+
+        // Removed and changed keys
+        let side4 = side1.replace("\"a\": \"1.0\",\n        ", "");
+        let side5 = side2.replace("1.0", "1.1");
+        insta::assert_snapshot!(merge("f.json", &[base], &[&side4, side2]).unwrap(), @r#"
+        {
+            "name": "foo",
+            "dependencies": {
+                "b": "2.0",
+                "c": "3.0"
+            }
+        }
+        "#);
+        assert_eq!(merge("f.json", &[base], &[&side4, &side5]), None);
+
+        // Values of different types, or conflicting values, aren't merged
+        let side6 = side1.replace(r#""b": "2.0""#, r#""b": ["2.0"]"#);
+        assert_eq!(merge("f.json", &[base], &[&side6, side1]), None);
+
+        // Invalid JSON isn't merged
+        assert_eq!(merge("f.json", &[base], &[side1, "{"]), None);
+        // Objects with duplicate keys aren't merged
+        let side7 = side1.replace(r#""b": "2.0""#, r#""a": "2.0""#);
+        assert_eq!(merge("f.json", &[base], &[&side7, side2]), None);
+        // Other files aren't merged
+        assert_eq!(merge("f.txt", &[base], &[side1, side2]), None);
+    }
+
+    #[test]
+    fn test_merge_json_formatting() {
+        let base = indoc! {r#"
+            {"version": 1.0, "items": [1, 2],
+              "a": {"x": 1e3}}
+        "#};
+        let side1 = indoc! {r#"
+            {"version": 1.0, "items": [1, 2],
+              "a": {"x": 1e3, "y": 2}}
+        "#};
+        let side2 = indoc! {r#"
+            {"version": 1.0, "items": [1, 2],
+              "a": {"x": 1e3, "z": 3}}
+        "#};
+
+        // The formatting of values and the layout of objects are kept
+        insta::assert_snapshot!(merge("f.json", &[base], &[side1, side2]).unwrap(), @r#"
+        {"version": 1.0, "items": [1, 2],
+          "a": {"x": 1e3, "y": 2, "z": 3}}
+        "#);
+
+        // Values which differ only in formatting aren't conflicts
+        let side3 = side2.replace("1e3", "1000.0").replace(", \"z\": 3", "");
+        insta::assert_snapshot!(merge("f.json", &[base], &[side1, &side3]).unwrap(), @r#"
+        {"version": 1.0, "items": [1, 2],
+          "a": {"x": 1e3, "y": 2}}
+        "#);
+
+        // Members added to an empty object are laid out like the other side
+        let base = "{}\n";
+        let side1 = "{\n  \"a\": 1\n}\n";
+        let side2 = "{}\n";
+        let side3 = "{\n  \"b\": 2\n}\n";
+        insta::assert_snapshot!(merge("f.json", &[base], &[side2, side1]).unwrap(), @r#"
+        {
+          "a": 1
+        }
+        "#);
+        insta::assert_snapshot!(
+            merge("f.json", &[base, base], &[side2, side1, side3]).unwrap(), @r#"
+        {
+          "a": 1,
+          "b": 2
+        }
+        "#);
+    }
+
+    #[test]
+    fn test_merge_toml() {
+        let base = indoc! {r#"
+            # Package
+            [package]
+            name = "foo"
+
+            [dependencies]
+            a = "1.0" # comment
+            # trailing comment
+        "#};
+        let side1 = indoc! {r#"
+            # Package
+            [package]
+            name = "foo"
+
+            [dependencies]
+            a = "1.0" # comment
+            b = { version = "2.0", features = ["x"] }
+            # trailing comment
+        "#};
+        let side2 = indoc! {r#"
+            # Package
+            [package]
+            name = "foo"
+
+            [dependencies]
+            a = "1.0" # comment
+            c = "3.0"
+            # trailing comment
+        "#};
+
+        // Keys added on adjacent lines are merged, keeping the formatting
+        insta::assert_snapshot!(merge("Cargo.toml", &[base], &[side1, side2]).unwrap(), @r#"
+        # Package
+        [package]
+        name = "foo"
+
+        [dependencies]
+        a = "1.0" # comment
+        b = { version = "2.0", features = ["x"] }
+        c = "3.0"
+        # trailing comment
+        "#);
+
+        // Inline tables are merged too
+        let side3 = side1.replace(r#"features = ["x"]"#, r#"features = ["y"]"#);
+        let side4 = side1.replace(r#"version = "2.0""#, r#"version = "2.1""#);
+        let side5 = side1.replace(r#" }"#, r#", optional = true }"#);
+        insta::assert_snapshot!(merge("Cargo.toml", &[side1], &[&side4, &side5]).unwrap(), @r#"
+        # Package
+        [package]
+        name = "foo"
+
+        [dependencies]
+        a = "1.0" # comment
+        b = { version = "2.1", features = ["x"], optional = true }
+        # trailing comment
+        "#);
+        assert_eq!(
+            merge("Cargo.toml", &[side1], &[&side3, side1]).unwrap(),
+            side3
+        );
+
+        // Conflicting values aren't merged
+        let side6 = side2.replace("3.0", "3.1");
+        assert_eq!(merge("Cargo.toml", &[base], &[side2, &side6]), None);
+        let side7 = side1.replace(r#"features = ["x"]"#, r#"features = ["z"]"#);
+        assert_eq!(merge("Cargo.toml", &[side1], &[&side3, &side7]), None);
+    }
+
+    #[test]
+    fn test_merge_yaml() {
+        let base = indoc! {"
+            # Service
+            name: foo
+            dependencies:
+              a: 1.0
+            # trailing comment
+        "};
+        let side1 = indoc! {"
+            # Service
+            name: foo
+            dependencies:
+              a: 1.0
+              b:
+                version: 2.0
+                features:
+                - x
+            # trailing comment
+        "};
+        let side2 = indoc! {"
+            # Service
+            name: foo
+            dependencies:
+              a: 1.0
+              # comment
+              c: 3.0
+            # trailing comment
+        "};
+
+        // Keys added on adjacent lines are merged, keeping the formatting and
+        // the comments
+        insta::assert_snapshot!(merge("f.yaml", &[base], &[side1, side2]).unwrap(), @r"
+        # Service
+        name: foo
+        dependencies:
+          a: 1.0
+          b:
+            version: 2.0
+            features:
+            - x
+          # comment
+          c: 3.0
+        # trailing comment
+        ");
+
+        // Nested mappings are merged too
+        let side3 = side1.replace("2.0", "2.1");
+        let side4 = side1.replace("- x", "- x\n    optional: true");
+        insta::assert_snapshot!(merge("f.yaml", &[side1], &[&side3, &side4]).unwrap(), @r"
+        # Service
+        name: foo
+        dependencies:
+          a: 1.0
+          b:
+            version: 2.1
+            features:
+            - x
+            optional: true
+        # trailing comment
+        ");
+
+        // Removed keys
+        let side5 = side2.replace("  a: 1.0\n", "");
+        insta::assert_snapshot!(merge("f.yaml", &[base], &[&side5, side1]).unwrap(), @r"
+        # Service
+        name: foo
+        dependencies:
+          # comment
+          c: 3.0
+          b:
+            version: 2.0
+            features:
+            - x
+        # trailing comment
+        ");
+
+        // Conflicting values aren't merged
+        let side6 = side2.replace("3.0", "3.1");
+        assert_eq!(merge("f.yaml", &[base], &[side2, &side6]), None);
+        let side7 = side1.replace("2.0", "2.2");
+        assert_eq!(merge("f.yaml", &[side1], &[&side3, &side7]), None);
+        // Mappings whose entries are all removed aren't merged key by key
+        let contents = Merge::from_removes_adds(
+            [b"a:\n  b: 1\n  c: 2\n".as_slice()],
+            [b"a:\n  b: 1\n".as_slice(), b"a:\n  c: 2\n".as_slice()],
+        );
+        assert_eq!(merge_yaml(&contents), None);
+    }
+
+    #[test]
+    fn test_merge_yaml_formatting() {
+        // The document start marker is kept, and strings are compared by value
+        let base = indoc! {"
+            ---
+            a: 'x'
+            b:
+              c: 1
+        "};
+        let side1 = indoc! {"
+            ---
+            a: 'x'
+            b:
+              c: 1
+              d: 2
+        "};
+        let side2 = indoc! {r#"
+            ---
+            a: "x"
+            b:
+              c: 1
+              e: 3
+        "#};
+        insta::assert_snapshot!(merge("f.yml", &[base], &[side1, side2]).unwrap(), @r"
+        ---
+        a: 'x'
+        b:
+          c: 1
+          d: 2
+          e: 3
+        ");
+
+        // Flow mappings aren't merged key by key
+        let base = "a: {b: 1}\nc: 1\n";
+        let side1 = "a: {b: 1, c: 2}\nc: 1\n";
+        let side2 = "a: {b: 1, d: 3}\nc: 1\n";
+        assert_eq!(merge("f.yml", &[base], &[side1, side2]), None);
+
+        // Mappings with aliases to other entries aren't merged
+        let base = "a: &x\n  b: 1\nc: *x\n";
+        let side1 = "a: &x\n  b: 1\n  d: 2\nc: *x\n";
+        let side2 = "a: &x\n  b: 1\n  e: 3\nc: *x\n";
+        assert_eq!(merge("f.yml", &[base], &[side1, side2]), None);
+
+        // Sides which are indented differently aren't merged
+        let base = "a:\n  b: 1\n";
+        let side1 = "a:\n  b: 1\n  c: 2\n";
+        let side2 = "a:\n    b: 1\n    d:\n      e: 3\n";
+        assert_eq!(merge("f.yml", &[base], &[side1, side2]), None);
+
+        // Invalid YAML and other documents than mappings aren't merged
+        let base = "- a\n";
+        let side1 = "- a\n- b\n";
+        let side2 = "- c\n- a\n";
+        assert_eq!(merge("f.yml", &[base], &[side1, "a: ["]), None);
+        assert_eq!(
+            merge("f.yml", &[base], &[side1, side2]).unwrap(),
+            "- c\n- a\n- b\n"
+        );
+        assert_eq!(
+            merge("f.yml", &["a: 1\n"], &["a: 2\n---\nb: 1\n", "a: 3\n"]),
+            None
+        );
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright 2025 The Jujutsu Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing trailers from commit descriptions.
+
+use thiserror::Error;
+
+/// A key-value pair of the form `Key: value` found at the end of a commit
+/// description.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Trailer {
+    /// Trailer key, e.g. `Signed-off-by`.
+    pub key: String,
+    /// Trailer value. Continuation lines are unfolded into a single line.
+    pub value: String,
+}
+
+/// Error occurred while parsing trailers.
+#[derive(Debug, Error)]
+pub enum TrailerParseError {
+    /// The line is neither a trailer nor a continuation of the previous one.
+    #[error("The line {line:?} is not a trailer")]
+    NonTrailerLine {
+        /// The offending line.
+        line: String,
+    },
+}
+
+/// Parses the trailers from the last paragraph of the `description`.
+///
+/// The trailer paragraph must be separated from the preceding text by a blank
+/// line. The first paragraph (i.e. the subject) is never considered a trailer
+/// paragraph. If the last paragraph contains a line that isn't a trailer, no
+/// trailers are returned.
+pub fn parse_description_trailers(description: &str) -> Vec<Trailer> {
+    let lines = description.trim_end().lines().collect::<Vec<_>>();
+    let Some(blank_pos) = lines.iter().rposition(|line| line.trim().is_empty()) else {
+        return vec![];
+    };
+    if lines[..blank_pos].iter().all(|line| line.trim().is_empty()) {
+        return vec![];
+    }
+    parse_trailer_lines(&lines[blank_pos + 1..]).unwrap_or_default()
+}
+
+/// Parses the whole `text` as trailer lines.
+///
+/// Unlike [`parse_description_trailers()`], this fails if the `text` contains
+/// a line that isn't a trailer. Blank lines are ignored.
+pub fn parse_trailers(text: &str) -> Result<Vec<Trailer>, TrailerParseError> {
+    let lines = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+    parse_trailer_lines(&lines)
+}
+
+fn parse_trailer_lines(lines: &[&str]) -> Result<Vec<Trailer>, TrailerParseError> {
+    let mut trailers: Vec<Trailer> = vec![];
+    for &line in lines {
+        let non_trailer_line = || TrailerParseError::NonTrailerLine {
+            line: line.to_owned(),
+        };
+        if line.starts_with(char::is_whitespace) {
+            let trailer = trailers.last_mut().ok_or_else(non_trailer_line)?;
+            if !trailer.value.is_empty() {
+                trailer.value.push(' ');
+            }
+            trailer.value.push_str(line.trim());
+        } else {
+            let (key, value) = line.split_once(':').ok_or_else(non_trailer_line)?;
+            // Whitespace is allowed between the key and the separator.
+            let key = key.trim_end();
+            if !is_valid_key(key) {
+                return Err(non_trailer_line());
+            }
+            trailers.push(Trailer {
+                key: key.to_owned(),
+                value: value.trim().to_owned(),
+            });
+        }
+    }
+    Ok(trailers)
+}
+
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use indoc::indoc;
+
+    use super::*;
+
+    fn trailer(key: &str, value: &str) -> Trailer {
+        Trailer {
+            key: key.to_owned(),
+            value: value.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_parse_description_trailers() {
+        let description = indoc! {"
+            subject
+
+            body
+
+            Signed-off-by: Alice <alice@example.com>
+            Changelog : Fixed a bug
+              in the parser
+            Reviewed-by:
+        "};
+        assert_eq!(
+            parse_description_trailers(description),
+            vec![
+                trailer("Signed-off-by", "Alice <alice@example.com>"),
+                trailer("Changelog", "Fixed a bug in the parser"),
+                trailer("Reviewed-by", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_description_trailers_subject_only() {
+        assert_eq!(parse_description_trailers(""), vec![]);
+        assert_eq!(parse_description_trailers("Key: value\n"), vec![]);
+        assert_eq!(parse_description_trailers("\nKey: value\n"), vec![]);
+    }
+
+    #[test]
+    fn test_parse_description_trailers_non_trailer_paragraph() {
+        let description = indoc! {"
+            subject
+
+            Key: value
+            not a trailer
+        "};
+        assert_eq!(parse_description_trailers(description), vec![]);
+
+        let description = indoc! {"
+            subject
+
+            Key: value
+
+            Key with spaces: not a trailer
+        "};
+        assert_eq!(parse_description_trailers(description), vec![]);
+
+        let description = indoc! {"
+            subject
+
+              continuation without trailer
+        "};
+        assert_eq!(parse_description_trailers(description), vec![]);
+    }
+
+    #[test]
+    fn test_parse_description_trailers_trailing_blank_lines() {
+        let description = "subject\n\nKey: value\n\n\n";
+        assert_eq!(
+            parse_description_trailers(description),
+            vec![trailer("Key", "value")]
+        );
+    }
+
+    #[test]
+    fn test_parse_trailers() {
+        assert_eq!(
+            parse_trailers("A: 1\n\nB: 2\n").unwrap(),
+            vec![trailer("A", "1"), trailer("B", "2")]
+        );
+        assert_matches!(
+            parse_trailers("A: 1\nfoo\n"),
+            Err(TrailerParseError::NonTrailerLine { line }) if line == "foo"
+        );
+    }
+}
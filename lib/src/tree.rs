@@ -42,6 +42,7 @@ use crate::matchers::Matcher;
 use crate::merge::trivial_merge;
 use crate::merge::Merge;
 use crate::merge::MergedTreeVal;
+use crate::merge_driver::MergeDrivers;
 use crate::object_id::ObjectId;
 use crate::repo_path::RepoPath;
 use crate::repo_path::RepoPathBuf;
@@ -398,7 +399,7 @@ fn merge_tree_value(
                 Err(conflict) => {
                     let conflict_borrowed = conflict.map(|value| value.as_ref());
                     if let Some(tree_value) =
-                        try_resolve_file_conflict(store, &filename, &conflict_borrowed)
+                        try_resolve_file_conflict(store, &filename, &conflict_borrowed, None)
                             .block_on()?
                     {
                         Some(tree_value)
@@ -412,7 +413,8 @@ fn merge_tree_value(
     })
 }
 
-/// Resolves file-level conflict by merging content hunks.
+/// Resolves file-level conflict by merging content hunks, or by the driver in
+/// `merge_drivers` which applies to the file.
 ///
 /// The input `conflict` is supposed to be simplified. It shouldn't contain
 /// non-file values that cancel each other.
@@ -420,6 +422,7 @@ pub async fn try_resolve_file_conflict(
     store: &Store,
     filename: &RepoPath,
     conflict: &MergedTreeVal<'_>,
+    merge_drivers: Option<&MergeDrivers>,
 ) -> BackendResult<Option<TreeValue>> {
     // If there are any non-file or any missing parts in the conflict, we can't
     // merge it. We check early so we don't waste time reading file contents if
@@ -471,14 +474,22 @@ pub async fn try_resolve_file_conflict(
         BackendResult::Ok(content)
     });
     let contents = Merge::from_vec(try_join_all(content_futures).await?);
-    let merge_result = files::merge(&contents);
-    match merge_result {
-        MergeResult::Resolved(merged_content) => {
+    let driver = merge_drivers.and_then(|drivers| drivers.find(filename));
+    let merged_content = if let Some(driver) = driver {
+        driver.merge(filename, &contents).await
+    } else {
+        match files::merge_with_options(&contents, store.merge_options()) {
+            MergeResult::Resolved(merged_content) => Some(merged_content),
+            MergeResult::Conflict(_) => None,
+        }
+    };
+    match merged_content {
+        Some(merged_content) => {
             let id = store
                 .write_file(filename, &mut merged_content.as_slice())
                 .await?;
             Ok(Some(TreeValue::File { id, executable }))
         }
-        MergeResult::Conflict(_) => Ok(None),
+        None => Ok(None),
     }
 }
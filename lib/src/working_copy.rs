@@ -27,10 +27,13 @@ use tracing::instrument;
 
 use crate::backend::BackendError;
 use crate::backend::MergedTreeId;
+use crate::case_collisions::CaseCollision;
 use crate::commit::Commit;
 use crate::conflicts::ConflictMarkerStyle;
+use crate::content_filter::ContentFilters;
 use crate::dag_walk;
 use crate::fsmonitor::FsmonitorSettings;
+use crate::gitattributes::GitAttributesError;
 use crate::gitignore::GitIgnoreError;
 use crate::gitignore::GitIgnoreFile;
 use crate::matchers::EverythingMatcher;
@@ -46,6 +49,7 @@ use crate::repo::RewriteRootCommit;
 use crate::repo_path::InvalidRepoPathError;
 use crate::repo_path::RepoPath;
 use crate::repo_path::RepoPathBuf;
+use crate::sparse::SparsePattern;
 use crate::store::Store;
 
 /// The trait all working-copy implementations must implement.
@@ -68,9 +72,9 @@ pub trait WorkingCopy: Send {
 
     /// Patterns that decide which paths from the current tree should be checked
     /// out in the working copy. An empty list means that no paths should be
-    /// checked out in the working copy. A single `RepoPath::root()` entry means
-    /// that all files should be checked out.
-    fn sparse_patterns(&self) -> Result<&[RepoPathBuf], WorkingCopyStateError>;
+    /// checked out in the working copy. A single `SparsePattern::root()` entry
+    /// means that all files should be checked out.
+    fn sparse_patterns(&self) -> Result<&[SparsePattern], WorkingCopyStateError>;
 
     /// Locks the working copy and returns an instance with methods for updating
     /// the working copy files and state.
@@ -136,7 +140,7 @@ pub trait LockedWorkingCopy {
     fn recover(&mut self, commit: &Commit) -> Result<(), ResetError>;
 
     /// See `WorkingCopy::sparse_patterns()`
-    fn sparse_patterns(&self) -> Result<&[RepoPathBuf], WorkingCopyStateError>;
+    fn sparse_patterns(&self) -> Result<&[SparsePattern], WorkingCopyStateError>;
 
     /// Updates the patterns that decide which paths from the current tree
     /// should be checked out in the working copy.
@@ -146,7 +150,7 @@ pub trait LockedWorkingCopy {
     // to use sparse).
     fn set_sparse_patterns(
         &mut self,
-        new_sparse_patterns: Vec<RepoPathBuf>,
+        new_sparse_patterns: Vec<SparsePattern>,
         options: &CheckoutOptions,
     ) -> Result<CheckoutStats, CheckoutError>;
 
@@ -183,6 +187,9 @@ pub enum SnapshotError {
     /// Checking path with ignore patterns failed.
     #[error(transparent)]
     GitIgnoreError(#[from] GitIgnoreError),
+    /// Loading `.gitattributes` files failed.
+    #[error(transparent)]
+    GitAttributesError(#[from] GitAttributesError),
     /// Some other error happened while snapshotting the working copy.
     #[error("{message}")]
     Other {
@@ -221,6 +228,9 @@ pub struct SnapshotOptions<'a> {
     pub max_new_file_size: u64,
     /// Expected conflict marker style for checking for changed files.
     pub conflict_marker_style: ConflictMarkerStyle,
+    /// Filters to run on the contents of new and modified files before they
+    /// are stored.
+    pub content_filters: ContentFilters,
 }
 
 impl SnapshotOptions<'_> {
@@ -233,6 +243,7 @@ impl SnapshotOptions<'_> {
             start_tracking_matcher: &EverythingMatcher,
             max_new_file_size: u64::MAX,
             conflict_marker_style: ConflictMarkerStyle::default(),
+            content_filters: ContentFilters::default(),
         }
     }
 }
@@ -245,6 +256,9 @@ pub type SnapshotProgress<'a> = dyn Fn(&RepoPath) + 'a + Sync;
 pub struct SnapshotStats {
     /// List of new (previously untracked) files which are still untracked.
     pub untracked_paths: BTreeMap<RepoPathBuf, UntrackedReason>,
+    /// Newly tracked files which collide with other files if the names are
+    /// compared case-insensitively.
+    pub case_collisions: Vec<CaseCollision>,
 }
 
 /// Reason why the new path isn't tracked.
@@ -266,6 +280,9 @@ pub enum UntrackedReason {
 pub struct CheckoutOptions {
     /// Conflict marker style to use when materializing files
     pub conflict_marker_style: ConflictMarkerStyle,
+    /// Filters to run on the contents of files before they are written to
+    /// the working copy.
+    pub content_filters: ContentFilters,
 }
 
 impl CheckoutOptions {
@@ -273,6 +290,7 @@ impl CheckoutOptions {
     pub fn empty_for_test() -> Self {
         CheckoutOptions {
             conflict_marker_style: ConflictMarkerStyle::default(),
+            content_filters: ContentFilters::default(),
         }
     }
 }
@@ -319,6 +337,16 @@ pub enum CheckoutError {
         /// The reserved path component.
         name: &'static str,
     },
+    /// Paths in the commit only differ in case, and the working copy is on a
+    /// case-insensitive filesystem.
+    #[error(
+        "Paths {} only differ in case and can't be checked out on a case-insensitive filesystem",
+        paths.iter().map(|path| path.as_internal_file_string()).join(", ")
+    )]
+    CaseCollision {
+        /// The colliding paths.
+        paths: Vec<RepoPathBuf>,
+    },
     /// Reading or writing from the commit backend failed.
     #[error("Internal backend error")]
     InternalBackendError(#[from] BackendError),
@@ -28,11 +28,13 @@ use thiserror::Error;
 use crate::backend::BackendInitError;
 use crate::backend::MergedTreeId;
 use crate::commit::Commit;
+use crate::config::ConfigGetError;
 use crate::file_util::IoResultExt as _;
 use crate::file_util::PathError;
 use crate::local_backend::LocalBackend;
 use crate::local_working_copy::LocalWorkingCopy;
 use crate::local_working_copy::LocalWorkingCopyFactory;
+use crate::merge_driver::MergeDriverConfigError;
 use crate::op_heads_store::OpHeadsStoreError;
 use crate::op_store::OperationId;
 use crate::op_store::WorkspaceId;
@@ -79,6 +81,10 @@ pub enum WorkspaceInitError {
     Backend(#[from] BackendInitError),
     #[error(transparent)]
     SignInit(#[from] SignInitError),
+    #[error(transparent)]
+    MergeDrivers(#[from] MergeDriverConfigError),
+    #[error(transparent)]
+    MergeOptions(ConfigGetError),
 }
 
 #[derive(Error, Debug)]
@@ -307,6 +313,8 @@ impl Workspace {
                 RepoInitError::Backend(err) => WorkspaceInitError::Backend(err),
                 RepoInitError::OpHeadsStore(err) => WorkspaceInitError::OpHeadsStore(err),
                 RepoInitError::Path(err) => WorkspaceInitError::Path(err),
+                RepoInitError::MergeDrivers(err) => WorkspaceInitError::MergeDrivers(err),
+                RepoInitError::MergeOptions(err) => WorkspaceInitError::MergeOptions(err),
             })?;
             let (working_copy, repo) = init_working_copy(
                 &repo,
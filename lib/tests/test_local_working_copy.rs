@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::io::Write as _;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 #[cfg(unix)]
@@ -26,11 +27,22 @@ use itertools::Itertools;
 use jj_lib::backend::MergedTreeId;
 use jj_lib::backend::TreeId;
 use jj_lib::backend::TreeValue;
+use jj_lib::case_collisions::find_case_collisions;
+use jj_lib::content_filter::ContentFilter;
+use jj_lib::content_filter::ContentFilters;
 use jj_lib::file_util::check_symlink_support;
 use jj_lib::file_util::try_symlink;
+use jj_lib::fileset::FilesetExpression;
 use jj_lib::fsmonitor::FsmonitorSettings;
+use jj_lib::git;
+use jj_lib::git_lfs::LfsPointer;
 use jj_lib::gitignore::GitIgnoreFile;
 use jj_lib::local_working_copy::LocalWorkingCopy;
+use jj_lib::local_working_copy::LockedLocalWorkingCopy;
+use jj_lib::local_working_copy::TreeStateFormat;
+use jj_lib::matchers::EverythingMatcher;
+use jj_lib::matchers::Matcher;
+use jj_lib::matchers::PrefixMatcher;
 use jj_lib::merge::Merge;
 use jj_lib::merge::MergedTreeValue;
 use jj_lib::merged_tree::MergedTree;
@@ -43,9 +55,11 @@ use jj_lib::repo_path::RepoPath;
 use jj_lib::repo_path::RepoPathBuf;
 use jj_lib::repo_path::RepoPathComponent;
 use jj_lib::secret_backend::SecretBackend;
+use jj_lib::sparse::SparsePattern;
 use jj_lib::working_copy::CheckoutError;
 use jj_lib::working_copy::CheckoutOptions;
 use jj_lib::working_copy::CheckoutStats;
+use jj_lib::working_copy::SnapshotError;
 use jj_lib::working_copy::SnapshotOptions;
 use jj_lib::working_copy::UntrackedReason;
 use jj_lib::workspace::default_working_copy_factories;
@@ -56,6 +70,7 @@ use test_case::test_case;
 use testutils::commit_with_tree;
 use testutils::create_tree;
 use testutils::write_random_commit;
+use testutils::TestRepo;
 use testutils::TestRepoBackend;
 use testutils::TestWorkspace;
 
@@ -109,7 +124,7 @@ fn test_root() {
     let mut test_workspace = TestWorkspace::init();
 
     let wc = test_workspace.workspace.working_copy();
-    assert_eq!(wc.sparse_patterns().unwrap(), vec![RepoPathBuf::root()]);
+    assert_eq!(wc.sparse_patterns().unwrap(), vec![SparsePattern::root()]);
     let new_tree = test_workspace.snapshot().unwrap();
     let repo = &test_workspace.repo;
     let wc_commit_id = repo
@@ -123,6 +138,7 @@ fn test_root() {
 
 #[test_case(TestRepoBackend::Local ; "local backend")]
 #[test_case(TestRepoBackend::Git ; "git backend")]
+#[test_case(TestRepoBackend::Test ; "test backend")]
 fn test_checkout_file_transitions(backend: TestRepoBackend) {
     // Tests switching between commits where a certain path is of one type in one
     // commit and another type in the other. Includes a "missing" type, so we cover
@@ -543,6 +559,70 @@ fn test_tree_builder_file_directory_transition() {
     assert!(!child_path.to_fs_path_unchecked(&workspace_root).exists());
 }
 
+/// Checks out more files than are written in one batch. The test backend reads
+/// them concurrently.
+#[test_case(TestRepoBackend::Local ; "local backend")]
+#[test_case(TestRepoBackend::Git ; "git backend")]
+#[test_case(TestRepoBackend::Test ; "test backend")]
+fn test_checkout_many_files_concurrently(backend: TestRepoBackend) {
+    let mut test_workspace = TestWorkspace::init_with_backend(backend);
+    let repo = test_workspace.repo.clone();
+    let workspace_root = test_workspace.workspace.workspace_root().to_owned();
+
+    // Files are replaced by directories and vice versa between the trees
+    let paths1 = (0..500)
+        .map(|i| RepoPathBuf::from_internal_string(format!("dir{}/file{i}", i % 7)))
+        .chain((0..10).map(|i| RepoPathBuf::from_internal_string(format!("replaced{i}"))))
+        .collect_vec();
+    let paths2 = (0..500)
+        .map(|i| RepoPathBuf::from_internal_string(format!("dir{}/file{i}", i % 5)))
+        .chain((0..10).map(|i| RepoPathBuf::from_internal_string(format!("replaced{i}/file"))))
+        .collect_vec();
+    let create = |paths: &[RepoPathBuf], contents: &str| {
+        let entries = paths
+            .iter()
+            .map(|path| (path.as_ref(), contents))
+            .collect_vec();
+        create_tree(&repo, &entries)
+    };
+    let commit1 = commit_with_tree(repo.store(), create(&paths1, "contents1").id());
+    let commit2 = commit_with_tree(repo.store(), create(&paths2, "contents2").id());
+
+    let ws = &mut test_workspace.workspace;
+    let stats = ws
+        .check_out(
+            repo.op_id().clone(),
+            None,
+            &commit1,
+            &CheckoutOptions::empty_for_test(),
+        )
+        .unwrap();
+    assert_eq!(stats.added_files, 510);
+    let stats = ws
+        .check_out(
+            repo.op_id().clone(),
+            Some(commit1.tree_id()),
+            &commit2,
+            &CheckoutOptions::empty_for_test(),
+        )
+        .unwrap();
+    assert_eq!(stats.skipped_files, 0);
+    for path in &paths2 {
+        let disk_path = path.to_fs_path_unchecked(&workspace_root);
+        assert_eq!(std::fs::read_to_string(disk_path).unwrap(), "contents2");
+    }
+    for i in 0..10 {
+        let disk_path = workspace_root.join(format!("replaced{i}"));
+        assert!(disk_path.is_dir());
+    }
+    // Emptied directories are removed
+    assert!(!workspace_root.join("dir6").exists());
+
+    // The recorded file states should match the files on disk
+    let new_tree = test_workspace.snapshot().unwrap();
+    assert_eq!(new_tree.id(), *commit2.tree_id());
+}
+
 #[test]
 fn test_conflicting_changes_on_disk() {
     let test_workspace = TestWorkspace::init();
@@ -1054,6 +1134,280 @@ fn test_gitignores() {
     );
 }
 
+#[test]
+fn test_gitattributes_eol_snapshot() {
+    // Tests that line endings are normalized on snapshot per .gitattributes.
+
+    let mut test_workspace = TestWorkspace::init();
+    let repo = test_workspace.repo.clone();
+    let workspace_root = test_workspace.workspace.workspace_root().to_owned();
+
+    let gitattributes_path = RepoPath::from_internal_string(".gitattributes");
+    let text_path = RepoPath::from_internal_string("file.txt");
+    let binary_path = RepoPath::from_internal_string("file.bin");
+    let auto_path = RepoPath::from_internal_string("dir/auto.txt");
+    let auto_binary_path = RepoPath::from_internal_string("dir/auto.dat");
+    let other_path = RepoPath::from_internal_string("other");
+
+    testutils::write_working_copy_file(
+        &workspace_root,
+        gitattributes_path,
+        "*.txt text
+*.bin -text
+",
+    );
+    std::fs::create_dir(workspace_root.join("dir")).unwrap();
+    testutils::write_working_copy_file(
+        &workspace_root,
+        RepoPath::from_internal_string("dir/.gitattributes"),
+        "* text=auto
+",
+    );
+    testutils::write_working_copy_file(&workspace_root, text_path, "a\r\nb\r\n");
+    testutils::write_working_copy_file(&workspace_root, binary_path, "a\r\nb\r\n");
+    testutils::write_working_copy_file(&workspace_root, auto_path, "a\r\nb\r\n");
+    testutils::write_working_copy_file(&workspace_root, auto_binary_path, "a\r\n\0\r\n");
+    testutils::write_working_copy_file(&workspace_root, other_path, "a\r\nb\r\n");
+
+    let tree = test_workspace.snapshot().unwrap();
+    let read_content = |path: &RepoPath| {
+        let value = tree.path_value(path).unwrap();
+        let Some(Some(TreeValue::File { id, .. })) = value.as_resolved() else {
+            panic!("unexpected value at {path:?}: {value:?}");
+        };
+        testutils::read_file(repo.store(), path, id)
+    };
+    assert_eq!(read_content(text_path), b"a\nb\n");
+    assert_eq!(read_content(binary_path), b"a\r\nb\r\n");
+    assert_eq!(read_content(auto_path), b"a\nb\n");
+    assert_eq!(read_content(auto_binary_path), b"a\r\n\0\r\n");
+    assert_eq!(read_content(other_path), b"a\r\nb\r\n");
+}
+
+#[test]
+fn test_gitattributes_eol_checkout() {
+    // Tests that line endings are converted on checkout per .gitattributes
+    // found in the target tree.
+
+    let mut test_workspace = TestWorkspace::init();
+    let repo = &test_workspace.repo;
+    let workspace_root = test_workspace.workspace.workspace_root().to_owned();
+
+    let gitattributes_path = RepoPath::from_internal_string(".gitattributes");
+    let crlf_path = RepoPath::from_internal_string("crlf.txt");
+    let lf_path = RepoPath::from_internal_string("lf.txt");
+    let binary_path = RepoPath::from_internal_string("file.bin");
+
+    let tree = create_tree(
+        repo,
+        &[
+            (
+                gitattributes_path,
+                "*.txt text eol=crlf\nlf.txt eol=lf\n*.bin binary\n",
+            ),
+            (crlf_path, "a\nb\n"),
+            (lf_path, "a\nb\n"),
+            (binary_path, "a\nb\n"),
+        ],
+    );
+    let commit = commit_with_tree(repo.store(), tree.id());
+    let ws = &mut test_workspace.workspace;
+    ws.check_out(
+        repo.op_id().clone(),
+        None,
+        &commit,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
+
+    let read_disk =
+        |path: &RepoPath| std::fs::read(path.to_fs_path_unchecked(&workspace_root)).unwrap();
+    assert_eq!(read_disk(crlf_path), b"a\r\nb\r\n");
+    assert_eq!(read_disk(lf_path), b"a\nb\n");
+    assert_eq!(read_disk(binary_path), b"a\nb\n");
+
+    // Converted files are clean, and normalized back when modified
+    let new_tree = test_workspace.snapshot().unwrap();
+    assert_eq!(new_tree.id(), tree.id());
+    testutils::write_working_copy_file(&workspace_root, crlf_path, "a\r\nb\r\nc\r\n");
+    let new_tree = test_workspace.snapshot().unwrap();
+    let expected_tree = create_tree(
+        &test_workspace.repo,
+        &[
+            (
+                gitattributes_path,
+                "*.txt text eol=crlf\nlf.txt eol=lf\n*.bin binary\n",
+            ),
+            (crlf_path, "a\nb\nc\n"),
+            (lf_path, "a\nb\n"),
+            (binary_path, "a\nb\n"),
+        ],
+    );
+    assert_eq!(new_tree.id(), expected_tree.id());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_content_filters() {
+    // Tests that clean filters are run on snapshot, and smudge filters on
+    // checkout.
+
+    let mut test_workspace = TestWorkspace::init();
+    let repo = test_workspace.repo.clone();
+    let workspace_root = test_workspace.workspace.workspace_root().to_owned();
+
+    let filtered_path = RepoPath::from_internal_string("filtered/file");
+    let other_path = RepoPath::from_internal_string("other");
+    let content_filters = ContentFilters::new(vec![
+        ContentFilter {
+            name: "upper".to_owned(),
+            files: FilesetExpression::prefix_path(RepoPathBuf::from_internal_string("filtered")),
+            clean: Some(vec!["tr".to_owned(), "a-z".to_owned(), "A-Z".to_owned()]),
+            smudge: Some(vec!["tr".to_owned(), "A-Z".to_owned(), "a-z".to_owned()]),
+        },
+        ContentFilter {
+            name: "path".to_owned(),
+            files: FilesetExpression::all(),
+            clean: None,
+            smudge: Some(vec![
+                "sh".to_owned(),
+                "-c".to_owned(),
+                "cat; echo \"$0\"".to_owned(),
+                "$path".to_owned(),
+            ]),
+        },
+    ]);
+
+    std::fs::create_dir(workspace_root.join("filtered")).unwrap();
+    testutils::write_working_copy_file(&workspace_root, filtered_path, "abc\n");
+    testutils::write_working_copy_file(&workspace_root, other_path, "abc\n");
+    let snapshot_options = SnapshotOptions {
+        content_filters: content_filters.clone(),
+        ..SnapshotOptions::empty_for_test()
+    };
+    let (tree, _stats) = test_workspace
+        .snapshot_with_options(&snapshot_options)
+        .unwrap();
+    let expected_tree = create_tree(&repo, &[(filtered_path, "ABC\n"), (other_path, "abc\n")]);
+    assert_eq!(tree.id(), expected_tree.id());
+
+    // Smudge filters are run in reverse order
+    let commit = commit_with_tree(repo.store(), tree.id());
+    let empty_tree = create_tree(&repo, &[]);
+    let empty_commit = commit_with_tree(repo.store(), empty_tree.id());
+    let checkout_options = CheckoutOptions {
+        content_filters: content_filters.clone(),
+        ..CheckoutOptions::empty_for_test()
+    };
+    let ws = &mut test_workspace.workspace;
+    ws.check_out(repo.op_id().clone(), None, &empty_commit, &checkout_options)
+        .unwrap();
+    ws.check_out(repo.op_id().clone(), None, &commit, &checkout_options)
+        .unwrap();
+    let read_disk =
+        |path: &RepoPath| std::fs::read(path.to_fs_path_unchecked(&workspace_root)).unwrap();
+    assert_eq!(read_disk(filtered_path), b"abc\nfiltered/file\n");
+    assert_eq!(read_disk(other_path), b"abc\nother\n");
+
+    // A failing filter aborts the snapshot
+    testutils::write_working_copy_file(&workspace_root, filtered_path, "def\n");
+    let snapshot_options = SnapshotOptions {
+        content_filters: ContentFilters::new(vec![ContentFilter {
+            name: "fail".to_owned(),
+            files: FilesetExpression::all(),
+            clean: Some(vec!["false".to_owned()]),
+            smudge: None,
+        }]),
+        ..SnapshotOptions::empty_for_test()
+    };
+    assert_matches!(
+        test_workspace.snapshot_with_options(&snapshot_options),
+        Err(SnapshotError::Other { .. })
+    );
+}
+
+#[test]
+fn test_git_lfs_snapshot_and_checkout() {
+    // Tests that files with filter=lfs are stored as LFS pointers, and checked
+    // out from the LFS object store.
+
+    let mut test_workspace = TestWorkspace::init_with_backend(TestRepoBackend::Git);
+    let repo = test_workspace.repo.clone();
+    let workspace_root = test_workspace.workspace.workspace_root().to_owned();
+    let lfs_store = git::get_lfs_object_store(repo.store()).unwrap();
+
+    let gitattributes_path = RepoPath::from_internal_string(".gitattributes");
+    let large_path = RepoPath::from_internal_string("large.bin");
+    let pointer_path = RepoPath::from_internal_string("pointer.bin");
+    let missing_path = RepoPath::from_internal_string("missing.bin");
+    let normal_path = RepoPath::from_internal_string("normal");
+
+    let existing_pointer = lfs_store.write_object(&mut &b"existing"[..]).unwrap();
+    let missing_pointer_content = indoc! {"
+        version https://git-lfs.github.com/spec/v1
+        oid sha256:0000000000000000000000000000000000000000000000000000000000000000
+        size 3
+    "};
+    testutils::write_working_copy_file(&workspace_root, gitattributes_path, "*.bin filter=lfs\n");
+    testutils::write_working_copy_file(&workspace_root, large_path, "large content");
+    testutils::write_working_copy_file(
+        &workspace_root,
+        pointer_path,
+        std::str::from_utf8(&existing_pointer.to_bytes()).unwrap(),
+    );
+    testutils::write_working_copy_file(&workspace_root, missing_path, missing_pointer_content);
+    testutils::write_working_copy_file(&workspace_root, normal_path, "normal content");
+
+    // Content is replaced with pointers, and pointers are stored as is
+    let tree = test_workspace.snapshot().unwrap();
+    let read_content = |path: &RepoPath| {
+        let value = tree.path_value(path).unwrap();
+        let Some(Some(TreeValue::File { id, .. })) = value.as_resolved() else {
+            panic!("unexpected value at {path:?}: {value:?}");
+        };
+        testutils::read_file(repo.store(), path, id)
+    };
+    let large_pointer = LfsPointer::parse(&read_content(large_path)).unwrap();
+    assert_eq!(large_pointer.size(), 13);
+    assert!(lfs_store.has_object(&large_pointer));
+    assert_eq!(read_content(pointer_path), existing_pointer.to_bytes());
+    assert_eq!(
+        read_content(missing_path),
+        missing_pointer_content.as_bytes()
+    );
+    assert_eq!(read_content(normal_path), b"normal content");
+
+    // Objects are checked out in place of pointers if available
+    let commit = commit_with_tree(repo.store(), tree.id());
+    let empty_tree = create_tree(&repo, &[]);
+    let empty_commit = commit_with_tree(repo.store(), empty_tree.id());
+    let ws = &mut test_workspace.workspace;
+    ws.check_out(
+        repo.op_id().clone(),
+        None,
+        &empty_commit,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
+    ws.check_out(
+        repo.op_id().clone(),
+        None,
+        &commit,
+        &CheckoutOptions::empty_for_test(),
+    )
+    .unwrap();
+    let read_disk =
+        |path: &RepoPath| std::fs::read(path.to_fs_path_unchecked(&workspace_root)).unwrap();
+    assert_eq!(read_disk(large_path), b"large content");
+    assert_eq!(read_disk(pointer_path), b"existing");
+    assert_eq!(read_disk(missing_path), missing_pointer_content.as_bytes());
+    assert_eq!(read_disk(normal_path), b"normal content");
+
+    // The checked-out files are clean
+    let new_tree = test_workspace.snapshot().unwrap();
+    assert_eq!(new_tree.id(), tree.id());
+}
+
 #[test]
 fn test_gitignores_in_ignored_dir() {
     // Tests that .gitignore files in an ignored directory are ignored, i.e. that
@@ -1558,6 +1912,149 @@ fn test_check_out_existing_directory_symlink_icase_fs() {
     assert!(!workspace_root.parent().unwrap().join("escaped").exists());
 }
 
+#[test]
+fn test_check_out_case_collision_icase_fs() {
+    let mut test_workspace = TestWorkspace::init();
+    let repo = &test_workspace.repo;
+    let workspace_root = test_workspace.workspace.workspace_root().to_owned();
+    let is_icase_fs = check_icase_fs(&workspace_root);
+
+    let lower_path = RepoPath::from_internal_string("dir/readme");
+    let upper_path = RepoPath::from_internal_string("dir/README");
+    let other_path = RepoPath::from_internal_string("other");
+    let tree = create_tree(
+        repo,
+        &[
+            (lower_path, "lower"),
+            (upper_path, "upper"),
+            (other_path, "other"),
+        ],
+    );
+    let commit = commit_with_tree(repo.store(), tree.id());
+
+    // The checkout is refused on icase fs before any file is written.
+    let ws = &mut test_workspace.workspace;
+    let result = ws.check_out(
+        repo.op_id().clone(),
+        None,
+        &commit,
+        &CheckoutOptions::empty_for_test(),
+    );
+    if is_icase_fs {
+        assert_matches!(
+            result,
+            Err(CheckoutError::CaseCollision { paths })
+                if paths == [upper_path.to_owned(), lower_path.to_owned()]
+        );
+        assert!(!other_path.to_fs_path_unchecked(&workspace_root).exists());
+    } else {
+        assert_eq!(result.unwrap().added_files, 3);
+        let read = |path: &RepoPath| {
+            std::fs::read_to_string(path.to_fs_path_unchecked(&workspace_root)).unwrap()
+        };
+        assert_eq!(read(lower_path), "lower");
+        assert_eq!(read(upper_path), "upper");
+    }
+}
+
+#[test]
+fn test_find_case_collisions() {
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    let tree1 = create_tree(
+        repo,
+        &[
+            (RepoPath::from_internal_string("docs/a"), ""),
+            (RepoPath::from_internal_string("Docs/b"), ""),
+            (RepoPath::from_internal_string("src/main"), ""),
+        ],
+    );
+    let tree2 = create_tree(
+        repo,
+        &[
+            (RepoPath::from_internal_string("docs/a"), ""),
+            (RepoPath::from_internal_string("Docs/b"), ""),
+            (RepoPath::from_internal_string("src/main"), ""),
+            (RepoPath::from_internal_string("src/Main"), ""),
+            (RepoPath::from_internal_string("src/MAIN"), ""),
+            (RepoPath::from_internal_string("SRC/lib"), ""),
+            (RepoPath::from_internal_string("file"), ""),
+        ],
+    );
+    let find = |old_tree: &MergedTree, new_tree: &MergedTree, matcher: &dyn Matcher| {
+        find_case_collisions(old_tree, new_tree, matcher)
+            .block_on()
+            .unwrap()
+            .into_iter()
+            .map(|paths| {
+                paths
+                    .iter()
+                    .map(|path| path.as_internal_file_string().to_owned())
+                    .collect_vec()
+            })
+            .collect_vec()
+    };
+
+    // Pre-existing collision in the root directory isn't reported unless
+    // entries are added there.
+    let empty_tree = repo
+        .store()
+        .get_root_tree(&repo.store().empty_merged_tree_id())
+        .unwrap();
+    assert_eq!(
+        find(&tree1, &tree1, &EverythingMatcher),
+        Vec::<Vec<String>>::new()
+    );
+    assert_eq!(
+        find(&empty_tree, &tree1, &EverythingMatcher),
+        [["Docs", "docs"]]
+    );
+    assert_eq!(
+        find(&tree1, &tree2, &EverythingMatcher),
+        [vec!["SRC", "src"], vec!["src/MAIN", "src/Main", "src/main"],]
+    );
+
+    // Paths outside of the matcher don't collide.
+    let matcher = PrefixMatcher::new([RepoPath::from_internal_string("src")]);
+    assert_eq!(
+        find(&tree1, &tree2, &matcher),
+        [["src/MAIN", "src/Main", "src/main"]]
+    );
+}
+
+#[test]
+fn test_snapshot_case_collisions() {
+    let mut test_workspace = TestWorkspace::init();
+    let workspace_root = test_workspace.workspace.workspace_root().to_owned();
+    if check_icase_fs(&workspace_root) {
+        return; // colliding files can't be created
+    }
+
+    let options = SnapshotOptions::empty_for_test();
+    let lower_path = RepoPath::from_internal_string("dir/readme");
+    let upper_path = RepoPath::from_internal_string("dir/README");
+    let other_path = RepoPath::from_internal_string("dir/other");
+    testutils::write_working_copy_file(&workspace_root, lower_path, "lower");
+    let (_tree, stats) = test_workspace.snapshot_with_options(&options).unwrap();
+    assert!(stats.case_collisions.is_empty());
+
+    // A newly tracked file colliding with an existing one is reported
+    testutils::write_working_copy_file(&workspace_root, upper_path, "upper");
+    let (_tree, stats) = test_workspace.snapshot_with_options(&options).unwrap();
+    assert_eq!(
+        stats.case_collisions,
+        [vec![upper_path.to_owned(), lower_path.to_owned()]]
+    );
+
+    // Existing collisions aren't reported again unless a colliding file is
+    // added
+    testutils::write_working_copy_file(&workspace_root, lower_path, "modified");
+    testutils::write_working_copy_file(&workspace_root, other_path, "other");
+    let (_tree, stats) = test_workspace.snapshot_with_options(&options).unwrap();
+    assert!(stats.case_collisions.is_empty());
+}
+
 #[test_case(false; "symlink target does not exist")]
 #[test_case(true; "symlink target exists")]
 fn test_check_out_existing_file_symlink_icase_fs(victim_exists: bool) {
@@ -2016,7 +2513,7 @@ fn test_fsmonitor() {
     let ws = &mut test_workspace.workspace;
     assert_eq!(
         ws.working_copy().sparse_patterns().unwrap(),
-        vec![RepoPathBuf::root()]
+        vec![SparsePattern::root()]
     );
 
     let foo_path = RepoPath::from_internal_string("foo");
@@ -2104,6 +2601,153 @@ fn test_fsmonitor() {
     }
 }
 
+#[cfg(all(unix, feature = "fsmonitor-daemon"))]
+#[test]
+fn test_fsmonitor_builtin() {
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use jj_lib::fsmonitor::builtin;
+    use jj_lib::fsmonitor::BuiltinConfig;
+    use jj_lib::local_working_copy::TreeState;
+
+    let mut test_workspace = TestWorkspace::init();
+    let repo = &test_workspace.repo;
+    let workspace_root = test_workspace.workspace.workspace_root().to_owned();
+    let ws = &mut test_workspace.workspace;
+    let state_path = ws
+        .working_copy()
+        .as_any()
+        .downcast_ref::<LocalWorkingCopy>()
+        .unwrap()
+        .state_path()
+        .to_owned();
+
+    let foo_path = RepoPath::from_internal_string("foo");
+    let bar_path = RepoPath::from_internal_string("bar");
+    let untouched_path = RepoPath::from_internal_string("untouched");
+    let nested_path = RepoPath::from_internal_string("dir/nested");
+    testutils::write_working_copy_file(&workspace_root, foo_path, "foo\n");
+    testutils::write_working_copy_file(&workspace_root, untouched_path, "untouched\n");
+
+    let daemon = {
+        let workspace_root = workspace_root.clone();
+        let state_path = state_path.clone();
+        std::thread::spawn(move || builtin::run(&workspace_root, &state_path))
+    };
+    let socket_path = builtin::socket_path(&state_path);
+    let connect = || {
+        let deadline = Instant::now() + Duration::from_secs(30);
+        loop {
+            match builtin::Client::connect(&socket_path) {
+                Ok(client) => return client,
+                Err(err) if Instant::now() > deadline => {
+                    panic!("failed to connect to the daemon: {err}")
+                }
+                Err(_) => std::thread::sleep(Duration::from_millis(10)),
+            }
+        }
+    };
+    connect();
+
+    let config = BuiltinConfig {
+        auto_start: false,
+        daemon_command: vec![],
+    };
+    let snapshot = |locked_ws: &mut LockedWorkspace| {
+        let (tree_id, _stats) = locked_ws
+            .locked_wc()
+            .snapshot(&SnapshotOptions {
+                fsmonitor_settings: FsmonitorSettings::Builtin(config.clone()),
+                ..SnapshotOptions::empty_for_test()
+            })
+            .unwrap();
+        tree_id
+    };
+    // Paths the daemon reports as changed since the token saved by the last
+    // snapshot, or None if the whole working copy would have to be crawled.
+    let query_changed_paths = || {
+        let tree_state = TreeState::load(
+            repo.store().clone(),
+            workspace_root.clone(),
+            state_path.clone(),
+        )
+        .unwrap();
+        let (_token, changed_paths) = tree_state.query_builtin_fsmonitor(&config).unwrap();
+        changed_paths.map(|paths| {
+            paths
+                .iter()
+                .map(|path| path.to_str().unwrap().to_owned())
+                .sorted()
+                .collect_vec()
+        })
+    };
+
+    // Without a token, the whole working copy is scanned
+    {
+        assert_eq!(query_changed_paths(), None);
+        let mut locked_ws = ws.start_working_copy_mutation().unwrap();
+        let tree_id = snapshot(&mut locked_ws);
+        insta::assert_snapshot!(testutils::dump_tree(repo.store(), &tree_id), @r#"
+        tree 1a6ea303d7d29cf563a2
+          file "foo" (e99c2057c15160add351): "foo\n"
+          file "untouched" (cc6d97eb7a41a6d9eb44): "untouched\n"
+        "#);
+        locked_ws.finish(repo.op_id().clone()).unwrap();
+    }
+    // The snapshot saved the token issued by the daemon
+    assert_eq!(query_changed_paths(), Some(vec![]));
+
+    // New, modified, and nested files are reported by the daemon
+    {
+        testutils::write_working_copy_file(&workspace_root, foo_path, "updated foo\n");
+        testutils::write_working_copy_file(&workspace_root, bar_path, "bar\n");
+        testutils::write_working_copy_file(&workspace_root, nested_path, "nested\n");
+        let changed_paths = query_changed_paths().unwrap();
+        assert!(changed_paths.contains(&"foo".to_owned()));
+        assert!(changed_paths.contains(&"bar".to_owned()));
+        assert!(changed_paths.contains(&"dir".to_owned()));
+        assert!(!changed_paths.contains(&"untouched".to_owned()));
+        let mut locked_ws = ws.start_working_copy_mutation().unwrap();
+        let tree_id = snapshot(&mut locked_ws);
+        insta::assert_snapshot!(testutils::dump_tree(repo.store(), &tree_id), @r#"
+        tree 8f4883ea8b1518c0f087
+          file "bar" (94cc973e7e1aefb7eff6): "bar\n"
+          file "dir/nested" (6209060941cd770c8d46): "nested\n"
+          file "foo" (e0fbd106147cc04ccd05): "updated foo\n"
+          file "untouched" (cc6d97eb7a41a6d9eb44): "untouched\n"
+        "#);
+        locked_ws.finish(repo.op_id().clone()).unwrap();
+    }
+    assert_eq!(query_changed_paths(), Some(vec![]));
+
+    // Removed files and renamed directories are reported too
+    {
+        std::fs::remove_file(foo_path.to_fs_path_unchecked(&workspace_root)).unwrap();
+        std::fs::rename(workspace_root.join("dir"), workspace_root.join("moved")).unwrap();
+        assert_eq!(
+            query_changed_paths(),
+            Some(vec!["dir".to_owned(), "foo".to_owned(), "moved".to_owned()])
+        );
+        let mut locked_ws = ws.start_working_copy_mutation().unwrap();
+        let tree_id = snapshot(&mut locked_ws);
+        insta::assert_snapshot!(testutils::dump_tree(repo.store(), &tree_id), @r#"
+        tree cf30fc5fb9d0a9a7e8e8
+          file "bar" (94cc973e7e1aefb7eff6): "bar\n"
+          file "moved/nested" (6209060941cd770c8d46): "nested\n"
+          file "untouched" (cc6d97eb7a41a6d9eb44): "untouched\n"
+        "#);
+        locked_ws.finish(repo.op_id().clone()).unwrap();
+    }
+
+    let status = connect().status().unwrap();
+    assert_eq!(status.pid, std::process::id());
+    assert_eq!(status.watch_error, None);
+    connect().stop().unwrap();
+    daemon.join().unwrap().unwrap();
+    assert!(!socket_path.exists());
+}
+
 #[test]
 fn test_snapshot_max_new_file_size() {
     let mut test_workspace = TestWorkspace::init();
@@ -2186,3 +2830,168 @@ fn test_snapshot_max_new_file_size() {
         UntrackedReason::FileTooLarge { .. }
     );
 }
+
+#[test]
+fn test_tree_state_format() {
+    let mut test_workspace = TestWorkspace::init();
+    let repo = test_workspace.repo.clone();
+    let workspace_root = test_workspace.workspace.workspace_root().to_owned();
+    let wc: &LocalWorkingCopy = test_workspace
+        .workspace
+        .working_copy()
+        .as_any()
+        .downcast_ref()
+        .unwrap();
+    let state_path = wc.state_path().to_path_buf();
+    let proto_path = state_path.join("tree_state");
+    let segmented_path = state_path.join("segmented_tree_state");
+    let file_paths = (0..16)
+        .map(|i| RepoPathBuf::from_internal_string(format!("dir/file{i}")))
+        .collect_vec();
+    for path in &file_paths {
+        testutils::write_working_copy_file(&workspace_root, path, "contents");
+    }
+
+    let mut snapshot = |format: Option<TreeStateFormat>| {
+        let ws = &mut test_workspace.workspace;
+        let mut locked_ws = ws.start_working_copy_mutation().unwrap();
+        if let Some(format) = format {
+            let locked_wc: &mut LockedLocalWorkingCopy =
+                locked_ws.locked_wc().as_any_mut().downcast_mut().unwrap();
+            locked_wc.set_tree_state_format(format).unwrap();
+        }
+        let (tree_id, _stats) = locked_ws
+            .locked_wc()
+            .snapshot(&SnapshotOptions::empty_for_test())
+            .unwrap();
+        locked_ws.finish(repo.op_id().clone()).unwrap();
+        tree_id
+    };
+    let load_file_states = || {
+        let wc = LocalWorkingCopy::load(
+            repo.store().clone(),
+            workspace_root.clone(),
+            state_path.clone(),
+        );
+        wc.file_states()
+            .unwrap()
+            .iter()
+            .map(|(path, state)| (path.to_owned(), state))
+            .collect_vec()
+    };
+
+    // Migrate to the segmented format
+    let tree_id = snapshot(None);
+    let file_states = load_file_states();
+    assert_eq!(file_states.len(), 16);
+    assert_eq!(snapshot(Some(TreeStateFormat::Segmented)), tree_id);
+    assert!(!proto_path.exists());
+    assert!(segmented_path.exists());
+    assert_eq!(load_file_states(), file_states);
+
+    // Changes are appended to the file
+    let len = std::fs::metadata(&segmented_path).unwrap().len();
+    let content = std::fs::read(&segmented_path).unwrap();
+    testutils::write_working_copy_file(&workspace_root, &file_paths[0], "modified contents");
+    std::fs::remove_file(file_paths[1].to_fs_path_unchecked(&workspace_root)).unwrap();
+    let tree_id = snapshot(None);
+    assert_ne!(load_file_states(), file_states);
+    let file_states = load_file_states();
+    assert_eq!(file_states.len(), 15);
+    assert_eq!(file_states[0].1.size, 17);
+    let new_content = std::fs::read(&segmented_path).unwrap();
+    assert!(new_content.len() as u64 > len);
+    assert_eq!(new_content[..content.len()], content);
+
+    // Incompletely written segment is ignored
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&segmented_path)
+        .unwrap();
+    file.write_all(&new_content[content.len()..new_content.len() - 1])
+        .unwrap();
+    drop(file);
+    assert_eq!(load_file_states(), file_states);
+
+    // The file is rewritten if it has an incomplete segment or the deltas
+    // would get large
+    for path in &file_paths[2..] {
+        testutils::write_working_copy_file(&workspace_root, path, "modified contents");
+    }
+    snapshot(None);
+    let file_states = load_file_states();
+    assert!(file_states.iter().all(|(_, state)| state.size == 17));
+    let new_len = std::fs::metadata(&segmented_path).unwrap().len();
+    assert!(new_len < new_content.len() as u64);
+
+    // Migrate back to the proto format
+    assert_ne!(snapshot(Some(TreeStateFormat::Proto)), tree_id);
+    assert!(proto_path.exists());
+    assert!(!segmented_path.exists());
+    assert_eq!(load_file_states(), file_states);
+}
+
+#[test]
+fn test_segmented_tree_state_growth() {
+    let mut test_workspace = TestWorkspace::init();
+    let repo = test_workspace.repo.clone();
+    let workspace_root = test_workspace.workspace.workspace_root().to_owned();
+    let wc: &LocalWorkingCopy = test_workspace
+        .workspace
+        .working_copy()
+        .as_any()
+        .downcast_ref()
+        .unwrap();
+    let segmented_path = wc.state_path().join("segmented_tree_state");
+    let file_paths = (0..200)
+        .map(|i| RepoPathBuf::from_internal_string(format!("dir/file{i}")))
+        .collect_vec();
+    for path in &file_paths {
+        testutils::write_working_copy_file(&workspace_root, path, "contents");
+    }
+
+    let mut snapshot = |fsmonitor_settings: FsmonitorSettings| {
+        let ws = &mut test_workspace.workspace;
+        let mut locked_ws = ws.start_working_copy_mutation().unwrap();
+        let locked_wc: &mut LockedLocalWorkingCopy =
+            locked_ws.locked_wc().as_any_mut().downcast_mut().unwrap();
+        locked_wc
+            .set_tree_state_format(TreeStateFormat::Segmented)
+            .unwrap();
+        locked_ws
+            .locked_wc()
+            .snapshot(&SnapshotOptions {
+                fsmonitor_settings,
+                ..SnapshotOptions::empty_for_test()
+            })
+            .unwrap();
+        locked_ws.finish(repo.op_id().clone()).unwrap();
+    };
+    let file_len = || std::fs::metadata(&segmented_path).unwrap().len();
+    snapshot(FsmonitorSettings::None);
+    let full_len = file_len();
+
+    // Snapshots without changes don't append segments even though the
+    // filesystem monitor makes them save the tree state
+    for _ in 0..10 {
+        snapshot(FsmonitorSettings::Test {
+            changed_files: vec![],
+        });
+        assert_eq!(file_len(), full_len);
+    }
+
+    // Small changes are appended, but the file is compacted before the deltas
+    // get as large as the full segment
+    let mut max_len = full_len;
+    for i in 0..100 {
+        testutils::write_working_copy_file(
+            &workspace_root,
+            &file_paths[0],
+            &format!("contents {i}"),
+        );
+        snapshot(FsmonitorSettings::None);
+        max_len = max_len.max(file_len());
+        assert!(file_len() <= full_len * 2);
+    }
+    assert!(max_len > full_len);
+}
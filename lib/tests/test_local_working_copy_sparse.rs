@@ -18,17 +18,21 @@ use jj_lib::local_working_copy::LocalWorkingCopy;
 use jj_lib::matchers::EverythingMatcher;
 use jj_lib::repo::Repo;
 use jj_lib::repo_path::RepoPath;
-use jj_lib::repo_path::RepoPathBuf;
+use jj_lib::sparse::SparsePattern;
 use jj_lib::working_copy::CheckoutOptions;
 use jj_lib::working_copy::CheckoutStats;
+use jj_lib::working_copy::SnapshotOptions;
 use jj_lib::working_copy::WorkingCopy;
 use pollster::FutureExt as _;
 use testutils::commit_with_tree;
 use testutils::create_tree;
 use testutils::TestWorkspace;
 
-fn to_owned_path_vec(paths: &[&RepoPath]) -> Vec<RepoPathBuf> {
-    paths.iter().map(|&path| path.to_owned()).collect()
+fn to_prefix_patterns(paths: &[&RepoPath]) -> Vec<SparsePattern> {
+    paths
+        .iter()
+        .map(|&path| SparsePattern::from_prefix(path.to_owned()))
+        .collect()
 }
 
 #[test]
@@ -73,7 +77,7 @@ fn test_sparse_checkout() {
 
     // Set sparse patterns to only dir1/
     let mut locked_ws = ws.start_working_copy_mutation().unwrap();
-    let sparse_patterns = to_owned_path_vec(&[dir1_path]);
+    let sparse_patterns = to_prefix_patterns(&[dir1_path]);
     let stats = locked_ws
         .locked_wc()
         .set_sparse_patterns(sparse_patterns.clone(), &CheckoutOptions::empty_for_test())
@@ -133,7 +137,7 @@ fn test_sparse_checkout() {
 
     // Set sparse patterns to file2, dir1/subdir1/ and dir2/
     let mut locked_wc = wc.start_mutation().unwrap();
-    let sparse_patterns = to_owned_path_vec(&[root_file1_path, dir1_subdir1_path, dir2_path]);
+    let sparse_patterns = to_prefix_patterns(&[root_file1_path, dir1_subdir1_path, dir2_path]);
     let stats = locked_wc
         .set_sparse_patterns(sparse_patterns.clone(), &CheckoutOptions::empty_for_test())
         .unwrap();
@@ -174,6 +178,125 @@ fn test_sparse_checkout() {
 }
 
 /// Test that sparse patterns are respected on commit
+#[test]
+fn test_sparse_checkout_fileset_patterns() {
+    let mut test_workspace = TestWorkspace::init();
+    let repo = &test_workspace.repo;
+    let working_copy_path = test_workspace.workspace.workspace_root().to_owned();
+
+    let readme_path = RepoPath::from_internal_string("README.md");
+    let license_path = RepoPath::from_internal_string("LICENSE");
+    let src_file_path = RepoPath::from_internal_string("src/lib.rs");
+    let src_data_file_path = RepoPath::from_internal_string("src/testdata/big.bin");
+    let docs_file_path = RepoPath::from_internal_string("docs/index.md");
+
+    let tree = create_tree(
+        repo,
+        &[
+            (readme_path, "contents"),
+            (license_path, "contents"),
+            (src_file_path, "contents"),
+            (src_data_file_path, "contents"),
+            (docs_file_path, "contents"),
+        ],
+    );
+    let commit = commit_with_tree(repo.store(), tree.id());
+    test_workspace
+        .workspace
+        .check_out(
+            repo.op_id().clone(),
+            None,
+            &commit,
+            &CheckoutOptions::empty_for_test(),
+        )
+        .unwrap();
+    let ws = &mut test_workspace.workspace;
+
+    // Check out src/ without its test data, and top-level markdown files
+    let mut locked_ws = ws.start_working_copy_mutation().unwrap();
+    let sparse_patterns = ["~src/testdata", "glob:*.md", "src"]
+        .map(|text| SparsePattern::parse(text).unwrap())
+        .to_vec();
+    let stats = locked_ws
+        .locked_wc()
+        .set_sparse_patterns(sparse_patterns.clone(), &CheckoutOptions::empty_for_test())
+        .unwrap();
+    assert_eq!(
+        stats,
+        CheckoutStats {
+            updated_files: 0,
+            added_files: 0,
+            removed_files: 3,
+            skipped_files: 0,
+        }
+    );
+    locked_ws.finish(repo.op_id().clone()).unwrap();
+    for (path, exists) in [
+        (readme_path, true),
+        (license_path, false),
+        (src_file_path, true),
+        (src_data_file_path, false),
+        (docs_file_path, false),
+    ] {
+        assert_eq!(
+            path.to_fs_path_unchecked(&working_copy_path).exists(),
+            exists,
+            "{path:?}"
+        );
+    }
+
+    // Reload the state to check that it was persisted
+    let wc: &LocalWorkingCopy = ws.working_copy().as_any().downcast_ref().unwrap();
+    let wc = LocalWorkingCopy::load(
+        repo.store().clone(),
+        ws.workspace_root().to_path_buf(),
+        wc.state_path().to_path_buf(),
+    );
+    assert_eq!(
+        wc.file_states().unwrap().paths().collect_vec(),
+        vec![readme_path, src_file_path]
+    );
+    assert_eq!(wc.sparse_patterns().unwrap(), sparse_patterns);
+
+    // Paths outside the sparse patterns are kept in the snapshot
+    std::fs::write(
+        src_file_path.to_fs_path_unchecked(&working_copy_path),
+        "new",
+    )
+    .unwrap();
+    let mut locked_wc = wc.start_mutation().unwrap();
+    let (new_tree, _stats) = locked_wc
+        .snapshot(&SnapshotOptions::empty_for_test())
+        .unwrap();
+    let expected_tree = create_tree(
+        repo,
+        &[
+            (readme_path, "contents"),
+            (license_path, "contents"),
+            (src_file_path, "new"),
+            (src_data_file_path, "contents"),
+            (docs_file_path, "contents"),
+        ],
+    );
+    assert_eq!(new_tree, expected_tree.id());
+
+    // Removing the exclusion checks out the test data
+    let stats = locked_wc
+        .set_sparse_patterns(
+            vec![SparsePattern::parse("src").unwrap()],
+            &CheckoutOptions::empty_for_test(),
+        )
+        .unwrap();
+    assert_eq!(stats.added_files, 1);
+    assert_eq!(stats.removed_files, 1);
+    assert!(src_data_file_path
+        .to_fs_path_unchecked(&working_copy_path)
+        .exists());
+    assert!(!readme_path
+        .to_fs_path_unchecked(&working_copy_path)
+        .exists());
+}
+
 #[test]
 fn test_sparse_commit() {
     let mut test_workspace = TestWorkspace::init();
@@ -212,7 +335,7 @@ fn test_sparse_commit() {
         .workspace
         .start_working_copy_mutation()
         .unwrap();
-    let sparse_patterns = to_owned_path_vec(&[dir1_path]);
+    let sparse_patterns = to_prefix_patterns(&[dir1_path]);
     locked_ws
         .locked_wc()
         .set_sparse_patterns(sparse_patterns, &CheckoutOptions::empty_for_test())
@@ -253,7 +376,7 @@ fn test_sparse_commit() {
         .workspace
         .start_working_copy_mutation()
         .unwrap();
-    let sparse_patterns = to_owned_path_vec(&[dir1_path, dir2_path]);
+    let sparse_patterns = to_prefix_patterns(&[dir1_path, dir2_path]);
     locked_ws
         .locked_wc()
         .set_sparse_patterns(sparse_patterns, &CheckoutOptions::empty_for_test())
@@ -288,7 +411,7 @@ fn test_sparse_commit_gitignore() {
         .workspace
         .start_working_copy_mutation()
         .unwrap();
-    let sparse_patterns = to_owned_path_vec(&[dir1_path]);
+    let sparse_patterns = to_prefix_patterns(&[dir1_path]);
     locked_ws
         .locked_wc()
         .set_sparse_patterns(sparse_patterns, &CheckoutOptions::empty_for_test())
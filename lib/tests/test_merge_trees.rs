@@ -15,12 +15,15 @@
 use itertools::Itertools;
 use jj_lib::backend::MergedTreeId;
 use jj_lib::backend::TreeValue;
+use jj_lib::config::ConfigLayer;
+use jj_lib::config::ConfigSource;
 use jj_lib::merge::Merge;
 use jj_lib::repo::Repo;
 use jj_lib::repo_path::RepoPath;
 use jj_lib::repo_path::RepoPathBuf;
 use jj_lib::repo_path::RepoPathComponent;
 use jj_lib::rewrite::rebase_commit;
+use jj_lib::settings::UserSettings;
 use jj_lib::tree::merge_trees;
 use jj_lib::tree::Tree;
 use testutils::create_single_tree;
@@ -745,3 +748,214 @@ fn test_rebase_on_lossy_merge() {
     .flatten();
     assert_eq!(*commit_d2.tree_id(), MergedTreeId::Merge(expected_tree_id));
 }
+
+fn merge_driver_settings(config_text: &str) -> UserSettings {
+    let mut config = testutils::base_user_config();
+    config.add_layer(ConfigLayer::parse(ConfigSource::User, config_text).unwrap());
+    UserSettings::from_config(config).unwrap()
+}
+
+#[test]
+fn test_merge_drivers() {
+    let settings = merge_driver_settings(
+        r#"
+        [merge-drivers.a-union]
+        files = "glob:'*.md'"
+        command = ":union"
+
+        [merge-drivers.b-ours]
+        files = "ours.txt | CHANGELOG.md"
+        command = ":ours"
+
+        [merge-drivers.c-theirs]
+        files = "theirs.txt"
+        command = ":theirs"
+        "#,
+    );
+    let test_repo = TestRepo::init_with_settings(&settings);
+    let repo = &test_repo.repo;
+
+    let union_path = RepoPath::from_internal_string("CHANGELOG.md");
+    let ours_path = RepoPath::from_internal_string("ours.txt");
+    let theirs_path = RepoPath::from_internal_string("theirs.txt");
+    let other_path = RepoPath::from_internal_string("other.txt");
+    let write_tree = |content: &str| {
+        let content = format!("head\n{content}tail\n");
+        create_tree(
+            repo,
+            &[
+                (union_path, &content),
+                (ours_path, &content),
+                (theirs_path, &content),
+                (other_path, &content),
+            ],
+        )
+    };
+    let base_tree = write_tree("");
+    let side1_tree = write_tree("one\n");
+    let side2_tree = write_tree("two\n");
+
+    // Drivers aren't run by plain merges, which are also used for display
+    let merged_tree = side1_tree.merge(&base_tree, &side2_tree).unwrap();
+    assert!(!merged_tree.path_value(union_path).unwrap().is_resolved());
+
+    let merged_tree = side1_tree
+        .merge_with_drivers(&base_tree, &side2_tree)
+        .unwrap();
+    let read_resolved = |path: &RepoPath| {
+        let value = merged_tree.path_value(path).unwrap();
+        match value.into_resolved() {
+            Ok(Some(TreeValue::File { id, .. })) => {
+                String::from_utf8(testutils::read_file(repo.store(), path, &id)).unwrap()
+            }
+            value => panic!("unexpected value at {path:?}: {value:?}"),
+        }
+    };
+    // The first driver in order of the names applies
+    assert_eq!(read_resolved(union_path), "head\none\ntwo\ntail\n");
+    assert_eq!(read_resolved(ours_path), "head\none\ntail\n");
+    assert_eq!(read_resolved(theirs_path), "head\ntwo\ntail\n");
+    assert!(!merged_tree.path_value(other_path).unwrap().is_resolved());
+}
+
+#[test]
+fn test_merge_driver_structured() {
+    let settings = merge_driver_settings(
+        r#"
+        [merge-drivers.manifests]
+        files = "glob:'*.json'"
+        command = ":structured"
+        "#,
+    );
+    let test_repo = TestRepo::init_with_settings(&settings);
+    let repo = &test_repo.repo;
+
+    let json_path = RepoPath::from_internal_string("package.json");
+    let conflict_path = RepoPath::from_internal_string("conflict.json");
+    let write_tree = |deps: &str, version: &str| {
+        create_tree(
+            repo,
+            &[
+                (json_path, &format!("{{\n  \"a\": 1{deps}\n}}\n")),
+                (conflict_path, &format!("{{\"version\": {version}}}\n")),
+            ],
+        )
+    };
+    let base_tree = write_tree("", "1");
+    let side1_tree = write_tree(",\n  \"b\": 2", "2");
+    let side2_tree = write_tree(",\n  \"c\": 3", "3");
+
+    // Keys added on adjacent lines are merged
+    let merged_tree = side1_tree
+        .merge_with_drivers(&base_tree, &side2_tree)
+        .unwrap();
+    let value = merged_tree.path_value(json_path).unwrap();
+    let Ok(Some(TreeValue::File { id, .. })) = value.into_resolved() else {
+        panic!("package.json should be resolved");
+    };
+    assert_eq!(
+        testutils::read_file(repo.store(), json_path, &id),
+        b"{\n  \"a\": 1,\n  \"b\": 2,\n  \"c\": 3\n}\n"
+    );
+    // The same value changed differently on both sides is left conflicted
+    assert!(!merged_tree.path_value(conflict_path).unwrap().is_resolved());
+}
+
+#[test]
+fn test_merge_hunk_level_word() {
+    let settings = merge_driver_settings(r#"merge.hunk-level = "word""#);
+    let test_repo = TestRepo::init_with_settings(&settings);
+    let repo = &test_repo.repo;
+
+    let word_path = RepoPath::from_internal_string("word.txt");
+    let overlap_path = RepoPath::from_internal_string("overlap.txt");
+    let base_tree = create_tree(
+        repo,
+        &[(word_path, "one two three\n"), (overlap_path, "one two\n")],
+    );
+    let side1_tree = create_tree(
+        repo,
+        &[(word_path, "ONE two three\n"), (overlap_path, "one TWO\n")],
+    );
+    let side2_tree = create_tree(
+        repo,
+        &[(word_path, "one two THREE\n"), (overlap_path, "one 2\n")],
+    );
+
+    let merged_tree = side1_tree.merge(&base_tree, &side2_tree).unwrap();
+    match merged_tree.path_value(word_path).unwrap().into_resolved() {
+        Ok(Some(TreeValue::File { id, .. })) => {
+            assert_eq!(
+                testutils::read_file(repo.store(), word_path, &id),
+                b"ONE two THREE\n"
+            );
+        }
+        value => panic!("unexpected value: {value:?}"),
+    }
+    assert!(!merged_tree.path_value(overlap_path).unwrap().is_resolved());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_merge_driver_external() {
+    let settings = merge_driver_settings(
+        r#"
+        [merge-drivers.concat]
+        files = "resolved.txt"
+        command = ["sh", "-c", "cat $base $left $right > $output && echo $path >> $output"]
+
+        [merge-drivers.fail]
+        files = "failed.txt"
+        command = ["sh", "-c", "echo junk > $output; echo error >&2; exit 1"]
+
+        [merge-drivers.missing]
+        files = "missing.txt"
+        command = ["jj-test-nonexistent-merge-driver", "$output"]
+
+        [merge-drivers.quoted]
+        files = "quoted.txt"
+        command = "sh -c 'cat $right $left > $output'"
+        "#,
+    );
+    let test_repo = TestRepo::init_with_settings(&settings);
+    let repo = &test_repo.repo;
+
+    let resolved_path = RepoPath::from_internal_string("resolved.txt");
+    let failed_path = RepoPath::from_internal_string("failed.txt");
+    let missing_path = RepoPath::from_internal_string("missing.txt");
+    let quoted_path = RepoPath::from_internal_string("quoted.txt");
+    let write_tree = |content: &str| {
+        create_tree(
+            repo,
+            &[
+                (resolved_path, content),
+                (failed_path, content),
+                (missing_path, content),
+                (quoted_path, content),
+            ],
+        )
+    };
+    let base_tree = write_tree("base\n");
+    let side1_tree = write_tree("left\n");
+    let side2_tree = write_tree("right\n");
+
+    let merged_tree = side1_tree
+        .merge_with_drivers(&base_tree, &side2_tree)
+        .unwrap();
+    let read_resolved = |path: &RepoPath| {
+        let value = merged_tree.path_value(path).unwrap();
+        let Ok(Some(TreeValue::File { id, .. })) = value.into_resolved() else {
+            panic!("{path:?} should be resolved");
+        };
+        testutils::read_file(repo.store(), path, &id)
+    };
+    assert_eq!(
+        read_resolved(resolved_path),
+        b"base\nleft\nright\nresolved.txt\n"
+    );
+    // String commands are split into arguments like a shell would
+    assert_eq!(read_resolved(quoted_path), b"right\nleft\n");
+    // The conflict is kept if the driver fails or can't be run
+    assert!(!merged_tree.path_value(failed_path).unwrap().is_resolved());
+    assert!(!merged_tree.path_value(missing_path).unwrap().is_resolved());
+}
@@ -41,9 +41,12 @@ use jj_lib::repo_path::RepoPathBuf;
 use jj_lib::repo_path::RepoPathComponent;
 use pollster::FutureExt as _;
 use pretty_assertions::assert_eq;
+use test_case::test_case;
 use testutils::create_single_tree;
+use testutils::create_tree;
 use testutils::write_file;
 use testutils::TestRepo;
+use testutils::TestRepoBackend;
 
 fn file_value(file_id: &FileId) -> TreeValue {
     TreeValue::File {
@@ -1342,6 +1345,66 @@ fn test_merge_partial_resolution() {
     assert_eq!(merged, expected_merged);
 }
 
+/// Merge 3 resolved trees with many directories, some of which need their
+/// files to be merged, and some of which can't be resolved. The result
+/// shouldn't depend on the backend's concurrency.
+#[test_case(TestRepoBackend::Local ; "local backend")]
+#[test_case(TestRepoBackend::Git ; "git backend")]
+#[test_case(TestRepoBackend::Test ; "test backend")]
+fn test_merge_many_directories(backend: TestRepoBackend) {
+    let test_repo = TestRepo::init_with_backend(backend);
+    let repo = &test_repo.repo;
+
+    let paths = (0..100)
+        .map(|i| RepoPathBuf::from_internal_string(format!("dir{i}/sub/file")))
+        .collect_vec();
+    let contents = |f: &dyn Fn(usize) -> String| {
+        paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| (path.as_ref(), f(i)))
+            .collect_vec()
+    };
+    let create = |contents: &[(&RepoPath, String)]| {
+        let contents = contents
+            .iter()
+            .map(|(path, text)| (*path, text.as_str()))
+            .collect_vec();
+        create_single_tree(repo, &contents)
+    };
+    // Every 10th file is changed on the same line on both sides
+    let base = create(&contents(&|_| "a\nb\nc\n".to_owned()));
+    let side1 = create(&contents(&|i| format!("a{i}\nb\nc\n")));
+    let side2 = create(&contents(&|i| {
+        if i % 10 == 0 {
+            "a2\nb\nc\n".to_owned()
+        } else {
+            "a\nb\nc2\n".to_owned()
+        }
+    }));
+    let expected_contents = |conflict_side: &dyn Fn(usize) -> String| {
+        contents(&|i| {
+            if i % 10 == 0 {
+                conflict_side(i)
+            } else {
+                format!("a{i}\nb\nc2\n")
+            }
+        })
+    };
+    let expected = Merge::from_removes_adds(
+        vec![create(&expected_contents(&|_| "a\nb\nc\n".to_owned()))],
+        vec![
+            create(&expected_contents(&|i| format!("a{i}\nb\nc\n"))),
+            create(&expected_contents(&|_| "a2\nb\nc\n".to_owned())),
+        ],
+    );
+
+    let merged = MergedTree::resolved(side1)
+        .merge(&MergedTree::resolved(base), &MergedTree::resolved(side2))
+        .unwrap();
+    assert_eq!(merged, MergedTree::new(expected));
+}
+
 /// Merge 3 trees where each one is a 3-way conflict and the result is arrived
 /// at by only simplifying the conflict (no need to recurse)
 #[test]
@@ -1568,3 +1631,86 @@ fn test_merge_simplify_file_conflict_with_absent() {
     let merged = child1_merged.merge(&parent_merged, &child2_merged).unwrap();
     assert_eq!(merged, expected_merged);
 }
+
+#[test]
+fn test_merge_with_copies() {
+    let test_repo = TestRepo::init();
+    let repo = &test_repo.repo;
+
+    let old_path = RepoPath::from_internal_string("dir/old");
+    let new_path = RepoPath::from_internal_string("new");
+    let other_path = RepoPath::from_internal_string("other");
+    let base = create_tree(repo, &[(old_path, "a\nb\nc\n"), (other_path, "1\n")]);
+    let renamed = create_tree(repo, &[(new_path, "a\nb\nc\n"), (other_path, "1\n")]);
+    let modified = create_tree(repo, &[(old_path, "a\nB\nc\n"), (other_path, "2\n")]);
+    let copy_records = create_copy_records(&[(old_path, new_path)]);
+    let no_copy_records = CopyRecords::default();
+
+    // Without copy records, the modification conflicts with the removal
+    let merged = renamed.merge(&base, &modified).unwrap();
+    assert_eq!(
+        merged.conflicts().map(|(path, _)| path).collect_vec(),
+        [old_path.to_owned()]
+    );
+
+    // The modification is carried over to the renamed file
+    let expected = create_tree(repo, &[(new_path, "a\nB\nc\n"), (other_path, "2\n")]);
+    let merged = renamed
+        .merge_with_copies(&base, &modified, &copy_records, &no_copy_records)
+        .unwrap();
+    assert_eq!(merged, expected);
+    let merged = modified
+        .merge_with_copies(&base, &renamed, &no_copy_records, &copy_records)
+        .unwrap();
+    assert_eq!(merged, expected);
+
+    // Conflicts are reported at the new path
+    let renamed_modified = create_tree(repo, &[(new_path, "a\nX\nc\n"), (other_path, "1\n")]);
+    let merged = renamed_modified
+        .merge_with_copies(&base, &modified, &copy_records, &no_copy_records)
+        .unwrap();
+    assert_eq!(
+        merged.conflicts().map(|(path, _)| path).collect_vec(),
+        [new_path.to_owned()]
+    );
+    assert!(merged.path_value(old_path).unwrap().is_absent());
+
+    // Copies aren't followed
+    let copied = create_tree(
+        repo,
+        &[
+            (old_path, "a\nb\nc\n"),
+            (new_path, "a\nb\nc\n"),
+            (other_path, "1\n"),
+        ],
+    );
+    let expected = create_tree(
+        repo,
+        &[
+            (old_path, "a\nB\nc\n"),
+            (new_path, "a\nb\nc\n"),
+            (other_path, "2\n"),
+        ],
+    );
+    let merged = copied
+        .merge_with_copies(&base, &modified, &copy_records, &no_copy_records)
+        .unwrap();
+    assert_eq!(merged, expected);
+
+    // Renames conflicting with files added on the other side are ignored
+    let modified_added = create_tree(
+        repo,
+        &[
+            (old_path, "a\nB\nc\n"),
+            (new_path, "d\n"),
+            (other_path, "1\n"),
+        ],
+    );
+    let merged = renamed
+        .merge_with_copies(&base, &modified_added, &copy_records, &no_copy_records)
+        .unwrap();
+    assert_eq!(
+        merged.conflicts().map(|(path, _)| path).collect_vec(),
+        [old_path.to_owned(), new_path.to_owned()]
+    );
+}
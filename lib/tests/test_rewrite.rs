@@ -24,6 +24,7 @@ use jj_lib::op_store::RemoteRefState;
 use jj_lib::op_store::WorkspaceId;
 use jj_lib::repo::Repo;
 use jj_lib::repo_path::RepoPath;
+use jj_lib::rewrite::rebase_commit;
 use jj_lib::rewrite::rebase_commit_with_options;
 use jj_lib::rewrite::restore_tree;
 use jj_lib::rewrite::CommitRewriter;
@@ -41,6 +42,7 @@ use testutils::rebase_descendants_with_options_return_map;
 use testutils::write_random_commit;
 use testutils::CommitGraphBuilder;
 use testutils::TestRepo;
+use testutils::TestRepoBackend;
 
 #[test]
 fn test_restore_tree() {
@@ -1844,3 +1846,115 @@ fn test_rebase_abandoning_empty() {
         hashset! {new_commit_f.id().clone(), new_wc_commit_id.clone()}
     );
 }
+
+#[test]
+fn test_rebase_follows_renames() {
+    let test_repo = TestRepo::init_with_backend(TestRepoBackend::Git);
+    let repo = &test_repo.repo;
+
+    // Commit B renames a file which commit C modifies. Rebasing either of them
+    // onto the other should apply the modification to the renamed file.
+    //
+    // B C
+    // |/
+    // A
+    let old_path = RepoPath::from_internal_string("dir/old");
+    let new_path = RepoPath::from_internal_string("new");
+    let content = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+    let modified_content = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+    let tree_a = create_tree(repo, &[(old_path, content)]);
+    let tree_b = create_tree(repo, &[(new_path, content)]);
+    let tree_c = create_tree(repo, &[(old_path, modified_content)]);
+    let expected_tree = create_tree(repo, &[(new_path, modified_content)]);
+
+    let mut tx = repo.start_transaction();
+    let root_commit_id = repo.store().root_commit_id().clone();
+    let commit_a = tx
+        .repo_mut()
+        .new_commit(vec![root_commit_id], tree_a.id())
+        .write()
+        .unwrap();
+    let commit_b = tx
+        .repo_mut()
+        .new_commit(vec![commit_a.id().clone()], tree_b.id())
+        .write()
+        .unwrap();
+    let commit_c = tx
+        .repo_mut()
+        .new_commit(vec![commit_a.id().clone()], tree_c.id())
+        .write()
+        .unwrap();
+
+    let new_commit_c =
+        rebase_commit(tx.repo_mut(), commit_c.clone(), vec![commit_b.id().clone()]).unwrap();
+    assert_eq!(*new_commit_c.tree_id(), expected_tree.id());
+    let new_commit_b =
+        rebase_commit(tx.repo_mut(), commit_b.clone(), vec![commit_c.id().clone()]).unwrap();
+    assert_eq!(*new_commit_b.tree_id(), expected_tree.id());
+
+    // The conflict is reported at the new path if both sides modified the file
+    let tree_b2 = create_tree(repo, &[(new_path, "1\n2\n3\n4\nFIVE\n6\n7\n8\n9\n")]);
+    let commit_b2 = tx
+        .repo_mut()
+        .new_commit(vec![commit_a.id().clone()], tree_b2.id())
+        .write()
+        .unwrap();
+    let new_commit_c = rebase_commit(
+        tx.repo_mut(),
+        commit_c.clone(),
+        vec![commit_b2.id().clone()],
+    )
+    .unwrap();
+    let new_tree_c = new_commit_c.tree().unwrap();
+    assert_eq!(
+        new_tree_c.conflicts().map(|(path, _)| path).collect_vec(),
+        [new_path.to_owned()]
+    );
+    assert!(new_tree_c.path_value(old_path).unwrap().is_absent());
+}
+
+#[test]
+fn test_rebase_siblings_follow_renames() {
+    let test_repo = TestRepo::init_with_backend(TestRepoBackend::Git);
+    let repo = &test_repo.repo;
+
+    // Commit B renames a file which commits C and D modify in different
+    // places. Rebasing C and D onto B should apply both modifications to the
+    // renamed file, reusing the renames between A and B.
+    //
+    // B C D
+    // |/ /
+    // A-'
+    let old_path = RepoPath::from_internal_string("old");
+    let new_path = RepoPath::from_internal_string("new");
+    let content = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+    let content_c = "one\n2\n3\n4\n5\n6\n7\n8\n9\n";
+    let content_d = "1\n2\n3\n4\n5\n6\n7\n8\nnine\n";
+    let tree_a = create_tree(repo, &[(old_path, content)]);
+    let tree_b = create_tree(repo, &[(new_path, content)]);
+    let tree_c = create_tree(repo, &[(old_path, content_c)]);
+    let tree_d = create_tree(repo, &[(old_path, content_d)]);
+
+    let mut tx = repo.start_transaction();
+    let root_commit_id = repo.store().root_commit_id().clone();
+    let commit_a = tx
+        .repo_mut()
+        .new_commit(vec![root_commit_id], tree_a.id())
+        .write()
+        .unwrap();
+    let mut new_child = |tree: &MergedTree| {
+        tx.repo_mut()
+            .new_commit(vec![commit_a.id().clone()], tree.id())
+            .write()
+            .unwrap()
+    };
+    let commit_b = new_child(&tree_b);
+    let commit_c = new_child(&tree_c);
+    let commit_d = new_child(&tree_d);
+
+    for (commit, content) in [(commit_c, content_c), (commit_d, content_d)] {
+        let new_commit = rebase_commit(tx.repo_mut(), commit, vec![commit_b.id().clone()]).unwrap();
+        let expected_tree = create_tree(repo, &[(new_path, content)]);
+        assert_eq!(*new_commit.tree_id(), expected_tree.id());
+    }
+}